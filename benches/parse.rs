@@ -0,0 +1,46 @@
+//! How long the pages take to parse, on a train list much longer than the site's.
+//! Run with `cargo bench`.
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use scraper::Html;
+
+use std::hint::black_box;
+
+use thsr::confirm_train_flow::{parse_alerts, parse_trains};
+use thsr::result::parse_result;
+
+const TRAIN_LIST: &str = include_str!("../tests/fixtures/train_list.html");
+const RESULT: &str = include_str!("../tests/fixtures/result.html");
+
+/// The fixture train list with its trains and alerts repeated `times` times.
+fn long_train_list(times: usize) -> Html {
+    let repeat = |page: &str, start: &str, end: &str| {
+        let from = page.find(start).unwrap();
+        let to = page.rfind(end).unwrap() + end.len();
+        format!(
+            "{}{}{}",
+            &page[..from],
+            page[from..to].repeat(times),
+            &page[to..]
+        )
+    };
+    let page = repeat(TRAIN_LIST, "<label class=\"result-item\">", "</label>");
+    Html::parse_document(&repeat(&page, "<li>", "</li>"))
+}
+
+fn parse(c: &mut Criterion) {
+    let train_list = long_train_list(100);
+    c.bench_function("parse_trains", |b| {
+        b.iter(|| parse_trains(black_box(&train_list)))
+    });
+    c.bench_function("parse_alerts", |b| {
+        b.iter(|| parse_alerts(black_box(&train_list)))
+    });
+    let result = Html::parse_document(RESULT);
+    c.bench_function("parse_result", |b| {
+        b.iter(|| parse_result(black_box(&result)))
+    });
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
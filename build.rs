@@ -0,0 +1,13 @@
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    #[cfg(feature = "grpc")]
+    {
+        println!("cargo:rerun-if-changed=proto/thsr.proto");
+        // Bring the protobuf compiler along instead of requiring one on the system.
+        // SAFETY: build scripts run on a single thread
+        unsafe {
+            std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path().unwrap());
+        }
+        tonic_build::compile_protos("proto/thsr.proto").unwrap();
+    }
+}
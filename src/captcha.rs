@@ -0,0 +1,348 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use serde::Deserialize;
+use tempfile::NamedTempFile;
+use tracing::{info, warn};
+
+use std::env;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+use crate::config::CaptchaConfig;
+use crate::events;
+use crate::i18n::tr;
+use crate::output;
+use crate::prompt::Prompter;
+
+#[cfg(feature = "captcha-ml")]
+pub mod model;
+#[cfg(feature = "ocr")]
+pub mod ocr;
+#[cfg(feature = "blocking")]
+pub mod service;
+pub mod web;
+
+/// Characters appearing in the security code images.
+pub const CHARSET: &str = "2345679ACFGHKMNPQRSTYZ";
+
+/// Width of the security code in terminal cells when drawn inline.
+const INLINE_WIDTH: u32 = 32;
+/// Width of the block-art fallback in terminal cells.
+const BLOCK_WIDTH: u32 = 48;
+/// How often the file given by --captcha-in is checked for the code.
+const CODE_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(clap::ValueEnum, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SolverKind {
+    /// Show the image and ask
+    #[default]
+    Manual,
+    /// Recognize the code with Tesseract (requires the `ocr` feature)
+    Ocr,
+    /// Run the ONNX model given by --captcha-model, none is bundled (requires the
+    /// `captcha-ml` feature)
+    Model,
+    /// Send the image to a 2captcha-style solving service (needs --captcha-api-key)
+    Service,
+}
+
+/// An automatic guess of the security code.
+pub struct Guess {
+    pub code: String,
+    /// 0-100
+    pub confidence: f32,
+}
+
+/// Solve the security code in `img_data` with the configured solver,
+/// falling back to asking the user when the guess is unreliable.
+/// Returns `None` when the user asked for a new image instead.
+pub fn solve(img_data: &[u8], config: &CaptchaConfig, prompter: &dyn Prompter) -> Option<String> {
+    let guess = match config.solver {
+        SolverKind::Manual => None,
+        SolverKind::Ocr => Some(guess_ocr(img_data)),
+        SolverKind::Model => Some(guess_model(img_data, config)),
+        SolverKind::Service => Some(guess_service(img_data, config)),
+    };
+
+    if let Some(guess) = guess {
+        match guess {
+            Ok(guess) if guess.confidence >= config.min_confidence => {
+                info!(
+                    "Security code recognized as {} ({:.0}% confidence)",
+                    guess.code, guess.confidence
+                );
+                return Some(guess.code);
+            }
+            Ok(guess) => output::warning(tr!(
+                "Recognized {:?} with only {:.0}% confidence, please enter it yourself.",
+                "辨識為 {:?}，但信心只有 {:.0}%，請自行輸入。",
+                guess.code,
+                guess.confidence
+            )),
+            Err(err_msg) => warn!("{}", err_msg),
+        }
+    }
+    prompter.security_code(img_data)
+}
+
+#[cfg(feature = "ocr")]
+fn guess_ocr(img_data: &[u8]) -> Result<Guess, String> {
+    ocr::guess(img_data)
+}
+
+#[cfg(not(feature = "ocr"))]
+fn guess_ocr(_img_data: &[u8]) -> Result<Guess, String> {
+    Err("OCR is not available, thsr was built without the `ocr` feature".to_string())
+}
+
+#[cfg(feature = "captcha-ml")]
+fn guess_model(img_data: &[u8], config: &CaptchaConfig) -> Result<Guess, String> {
+    let path = config
+        .model
+        .as_deref()
+        .ok_or("No captcha model configured, none is bundled with thsr, use --captcha-model")?;
+    model::guess(img_data, path)
+}
+
+#[cfg(not(feature = "captcha-ml"))]
+fn guess_model(_img_data: &[u8], _config: &CaptchaConfig) -> Result<Guess, String> {
+    Err(
+        "The captcha model is not available, thsr was built without the `captcha-ml` feature"
+            .to_string(),
+    )
+}
+
+#[cfg(feature = "blocking")]
+fn guess_service(img_data: &[u8], config: &CaptchaConfig) -> Result<Guess, String> {
+    let api_key = config
+        .api_key
+        .as_deref()
+        .ok_or("No captcha service API key configured, use --captcha-api-key")?;
+    service::guess(img_data, &config.service_url, api_key)
+}
+
+#[cfg(not(feature = "blocking"))]
+fn guess_service(_img_data: &[u8], _config: &CaptchaConfig) -> Result<Guess, String> {
+    Err(
+        "The captcha service is not available, thsr was built without the `blocking` feature"
+            .to_string(),
+    )
+}
+
+/// Ask the user for the code in `img_data`, through the channel the config picks.
+pub fn ask(img_data: &[u8], config: &CaptchaConfig) -> Option<String> {
+    if let Some(addr) = config.http {
+        match web::ask(img_data, addr) {
+            Ok(input) => return parse_answer(&input),
+            Err(err_msg) => warn!("{}", err_msg),
+        }
+    }
+
+    // A leftover answer belongs to an older image
+    if let Some(path) = &config.input
+        && path != Path::new("-")
+    {
+        let _ = fs::remove_file(path);
+    }
+    // Keep the viewer's copy of the image until the code is entered
+    let _image_file = show(img_data, config);
+
+    let input = match &config.input {
+        Some(path) if path != Path::new("-") => wait_for_code(path),
+        // Read the security code from the user
+        _ => {
+            let mut input = String::new();
+            std::io::stdin()
+                .read_line(&mut input)
+                .expect("Failed to read input");
+            input
+        }
+    };
+    parse_answer(&input)
+}
+
+/// Ask for the code in `img_data`, showing the image or handing it over as
+/// `--captcha-out` says. The returned file is the viewer's copy of the image.
+pub(crate) fn show(img_data: &[u8], config: &CaptchaConfig) -> Option<NamedTempFile> {
+    output::line(tr!(
+        "Input security code (or r for a new image):",
+        "請輸入驗證碼（輸入 r 換一張）："
+    ));
+    match &config.out {
+        Some(path) => {
+            write_image(img_data, path);
+            None
+        }
+        None => show_image(img_data),
+    }
+}
+
+/// The entered code, or `None` if a new image was asked for. Codes are
+/// 4 characters long, so a lone `r` can't be one.
+pub(crate) fn parse_answer(input: &str) -> Option<String> {
+    match input.trim() {
+        "r" | "R" => None,
+        code => Some(code.to_string()),
+    }
+}
+
+/// Hand the image over to whoever answers the prompt, as a file at `path`
+/// or as a base64 line on stdout for `-`.
+fn write_image(img_data: &[u8], path: &Path) {
+    if path == Path::new("-") {
+        output::line(STANDARD.encode(img_data));
+        return;
+    }
+    match fs::write(path, img_data) {
+        Ok(()) => info!("Security code image written to {}", path.display()),
+        Err(err) => warn!("Failed to write {}: {}", path.display(), err),
+    }
+}
+
+/// Wait for the code to be written to the file at `path`. The file is removed
+/// after reading, so the next prompt waits for a new answer.
+fn wait_for_code(path: &Path) -> String {
+    output::line(tr!(
+        "Waiting for the security code in {}...",
+        "等待 {} 中的驗證碼…",
+        path.display()
+    ));
+    loop {
+        if let Ok(code) = fs::read_to_string(path)
+            && !code.trim().is_empty()
+        {
+            if let Err(err) = fs::remove_file(path) {
+                warn!("Failed to remove {}: {}", path.display(), err);
+            }
+            return code;
+        }
+        thread::sleep(CODE_POLL_INTERVAL);
+    }
+}
+
+/// Show the image in the terminal or an image viewer. The latter gets a
+/// temporary copy, which is deleted once the returned file is dropped.
+fn show_image(img_data: &[u8]) -> Option<NamedTempFile> {
+    // The image is drawn on stdout, which the events keep to themselves
+    let draw = !events::enabled();
+    if draw {
+        match show_inline(img_data) {
+            Ok(true) => return None,
+            Ok(false) => (),
+            Err(err_msg) => warn!("{}", err_msg),
+        }
+    }
+
+    let drawn = draw
+        && io::stdout().is_terminal()
+        && match show_blocks(img_data) {
+            Ok(()) => true,
+            Err(err_msg) => {
+                warn!("{}", err_msg);
+                false
+            }
+        };
+    // Without a display there is no viewer to open, e.g. over SSH
+    if drawn && cfg!(target_os = "linux") && !has_display() {
+        return None;
+    }
+
+    // Save the image to a file only this run uses
+    let file = tempfile::Builder::new()
+        .prefix("thsr-code-")
+        .suffix(".jpg")
+        .tempfile()
+        .and_then(|mut file| file.write_all(img_data).map(|_| file))
+        .expect("Failed to write image file");
+    let path = file.path();
+
+    // Open the image using the default image viewer
+    let mut child = if cfg!(target_os = "windows") {
+        Command::new("cmd")
+            .arg("/C")
+            .arg(path)
+            .spawn()
+            .expect("Failed to open image")
+    } else if cfg!(target_os = "macos") {
+        Command::new("open")
+            .arg(path)
+            .spawn()
+            .expect("Failed to open image")
+    } else if cfg!(target_os = "linux") {
+        Command::new("xdg-open")
+            .arg(path)
+            .spawn()
+            .expect("Failed to open image")
+    } else {
+        output::line(tr!(
+            "Please open the image manually: {}",
+            "請自行開啟圖片：{}",
+            path.display()
+        ));
+        return Some(file);
+    };
+
+    // Reap the viewer launcher in the background so it doesn't linger as a zombie
+    std::thread::spawn(move || child.wait());
+    Some(file)
+}
+
+/// Draw the image right in the terminal if it speaks the Kitty, iTerm2 or Sixel
+/// graphics protocol, which also works over SSH. Returns whether it was shown.
+fn show_inline(img_data: &[u8]) -> Result<bool, String> {
+    let supported = viuer::get_kitty_support() != viuer::KittySupport::None
+        || viuer::is_iterm_supported()
+        || sixel_supported();
+    if !supported {
+        return Ok(false);
+    }
+
+    let img = image::load_from_memory(img_data)
+        .map_err(|err| format!("Failed to decode the security code image: {}", err))?;
+    let config = viuer::Config {
+        absolute_offset: false,
+        width: Some(INLINE_WIDTH),
+        ..Default::default()
+    };
+    viuer::print(&img, &config)
+        .map_err(|err| format!("Failed to show the security code: {}", err))?;
+    Ok(true)
+}
+
+/// Downscale the image and draw it with colored half-block characters, two pixels
+/// per cell. Crude, but enough to read the code in most terminals.
+fn show_blocks(img_data: &[u8]) -> Result<(), String> {
+    let img = image::load_from_memory(img_data)
+        .map_err(|err| format!("Failed to decode the security code image: {}", err))?;
+    let config = viuer::Config {
+        absolute_offset: false,
+        width: Some(BLOCK_WIDTH),
+        use_kitty: false,
+        use_iterm: false,
+        #[cfg(feature = "sixel")]
+        use_sixel: false,
+        ..Default::default()
+    };
+    viuer::print(&img, &config)
+        .map_err(|err| format!("Failed to show the security code: {}", err))?;
+    Ok(())
+}
+
+pub(crate) fn has_display() -> bool {
+    env::var_os("DISPLAY").is_some() || env::var_os("WAYLAND_DISPLAY").is_some()
+}
+
+#[cfg(feature = "sixel")]
+fn sixel_supported() -> bool {
+    viuer::is_sixel_supported()
+}
+
+#[cfg(not(feature = "sixel"))]
+fn sixel_supported() -> bool {
+    false
+}
@@ -0,0 +1,82 @@
+use tract_onnx::prelude::*;
+
+use std::path::Path;
+
+use super::{CHARSET, Guess};
+
+/// Security codes always have this many characters.
+const CODE_LEN: usize = 4;
+
+/// Run the ONNX model at `path` on the image. No model ships with thsr, `path` is one
+/// the user trained or obtained.
+///
+/// The model takes a `1x1xHxW` grayscale image scaled to 0..1, with a fixed H and W,
+/// and returns `1x4xN` scores, one row per character over the N characters of [`CHARSET`].
+pub fn guess(img_data: &[u8], path: &Path) -> Result<Guess, String> {
+    let load_err = |err: TractError| format!("Failed to load {}: {}", path.display(), err);
+    let model = tract_onnx::onnx()
+        .model_for_path(path)
+        .and_then(|model| model.into_optimized())
+        .map_err(load_err)?;
+
+    let shape = model
+        .input_fact(0)
+        .ok()
+        .and_then(|fact| fact.shape.as_concrete().map(|shape| shape.to_vec()))
+        .filter(|shape| shape.len() == 4)
+        .ok_or("The captcha model must take a fixed 1x1xHxW input")?;
+    let (height, width) = (shape[2] as u32, shape[3] as u32);
+
+    let img = image::load_from_memory(img_data)
+        .map_err(|err| format!("Failed to decode the security code image: {}", err))?
+        .to_luma8();
+    let img = image::imageops::resize(&img, width, height, image::imageops::FilterType::Triangle);
+    let input: Tensor = tract_ndarray::Array4::from_shape_fn(
+        (1, 1, height as usize, width as usize),
+        |(_, _, y, x)| f32::from(img.get_pixel(x as u32, y as u32)[0]) / 255.0,
+    )
+    .into();
+
+    let outputs = model
+        .into_runnable()
+        .and_then(|runnable| runnable.run(tvec!(input.into())))
+        .map_err(|err| format!("Failed to run the captcha model: {}", err))?;
+    let scores = outputs[0]
+        .to_array_view::<f32>()
+        .map_err(|err| format!("Unexpected captcha model output: {}", err))?;
+
+    let charset: Vec<char> = CHARSET.chars().collect();
+    if scores.shape() != [1, CODE_LEN, charset.len()] {
+        return Err(format!(
+            "Unexpected captcha model output shape {:?}, expected [1, {}, {}]",
+            scores.shape(),
+            CODE_LEN,
+            charset.len()
+        ));
+    }
+
+    let mut code = String::new();
+    let mut confidence = 1.0f32;
+    for position in 0..CODE_LEN {
+        let row: Vec<f32> = (0..charset.len())
+            .map(|idx| scores[[0, position, idx]])
+            .collect();
+        // Softmax, so the score of the best character reads as a probability
+        let max = row.iter().copied().fold(f32::MIN, f32::max);
+        let exp: Vec<f32> = row.iter().map(|score| (score - max).exp()).collect();
+        let total: f32 = exp.iter().sum();
+        let (best, prob) = exp
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(idx, value)| (idx, value / total))
+            .unwrap();
+        code.push(charset[best]);
+        confidence = confidence.min(prob);
+    }
+
+    Ok(Guess {
+        code,
+        confidence: confidence * 100.0,
+    })
+}
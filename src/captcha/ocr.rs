@@ -0,0 +1,175 @@
+use image::imageops::FilterType;
+use image::{GrayImage, Luma};
+
+use std::process::Command;
+
+use super::{CHARSET, Guess};
+
+/// Security codes always have this many characters.
+const CODE_LEN: usize = 4;
+
+/// Clean up the image and let the `tesseract` executable read it.
+pub fn guess(img_data: &[u8]) -> Result<Guess, String> {
+    let img = preprocess(img_data)?;
+
+    // Removed again when `file` is dropped
+    let file = tempfile::Builder::new()
+        .prefix("thsr-ocr-")
+        .suffix(".png")
+        .tempfile()
+        .map_err(|err| format!("Failed to create a temporary file: {}", err))?;
+    let path = file.path();
+    img.save(path)
+        .map_err(|err| format!("Failed to write {}: {}", path.display(), err))?;
+
+    let output = Command::new("tesseract")
+        .arg(path)
+        .arg("stdout")
+        .args(["--psm", "8"])
+        .arg("-c")
+        .arg(format!("tessedit_char_whitelist={}", CHARSET))
+        .arg("tsv")
+        .output();
+
+    let output = output.map_err(|err| format!("Failed to run tesseract: {}", err))?;
+    if !output.status.success() {
+        return Err(format!(
+            "tesseract failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    parse_tsv(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Collect the recognized words of tesseract's TSV output. The confidence of the
+/// guess is the one of its least certain word.
+fn parse_tsv(tsv: &str) -> Result<Guess, String> {
+    let mut code = String::new();
+    let mut confidence = 100.0f32;
+    for line in tsv.lines().skip(1) {
+        // level page_num block_num par_num line_num word_num left top width height conf text
+        let cols: Vec<&str> = line.split('\t').collect();
+        if cols.len() < 12 || cols[0] != "5" || cols[11].trim().is_empty() {
+            continue;
+        }
+        code.push_str(cols[11].trim());
+        confidence = confidence.min(cols[10].parse().unwrap_or(0.0));
+    }
+
+    if code.is_empty() {
+        return Err("tesseract found no text in the security code".to_string());
+    }
+    if code.chars().count() != CODE_LEN {
+        confidence = 0.0;
+    }
+    Ok(Guess { code, confidence })
+}
+
+/// Grayscale, upscale, binarize and deskew the image.
+fn preprocess(img_data: &[u8]) -> Result<GrayImage, String> {
+    let gray = image::load_from_memory(img_data)
+        .map_err(|err| format!("Failed to decode the security code image: {}", err))?
+        .to_luma8();
+    let gray = image::imageops::resize(
+        &gray,
+        gray.width() * 2,
+        gray.height() * 2,
+        FilterType::Triangle,
+    );
+
+    let threshold = otsu_threshold(&gray);
+    let mut binary = GrayImage::from_fn(gray.width(), gray.height(), |x, y| {
+        if gray.get_pixel(x, y)[0] > threshold {
+            Luma([255])
+        } else {
+            Luma([0])
+        }
+    });
+
+    // Text should be dark on a light background
+    let dark = binary.pixels().filter(|pixel| pixel[0] == 0).count();
+    if dark * 2 > (binary.width() * binary.height()) as usize {
+        image::imageops::invert(&mut binary);
+    }
+
+    Ok(deskew(&binary))
+}
+
+/// Threshold maximizing the between-class variance of the histogram.
+fn otsu_threshold(img: &GrayImage) -> u8 {
+    let mut histogram = [0u64; 256];
+    for pixel in img.pixels() {
+        histogram[pixel[0] as usize] += 1;
+    }
+
+    let total: u64 = histogram.iter().sum();
+    let sum_all: f64 = histogram
+        .iter()
+        .enumerate()
+        .map(|(value, count)| value as f64 * *count as f64)
+        .sum();
+
+    let (mut weight_bg, mut sum_bg) = (0u64, 0f64);
+    let (mut best, mut best_variance) = (0u8, 0f64);
+    for (value, count) in histogram.iter().enumerate() {
+        weight_bg += count;
+        if weight_bg == 0 {
+            continue;
+        }
+        let weight_fg = total - weight_bg;
+        if weight_fg == 0 {
+            break;
+        }
+        sum_bg += value as f64 * *count as f64;
+
+        let mean_bg = sum_bg / weight_bg as f64;
+        let mean_fg = (sum_all - sum_bg) / weight_fg as f64;
+        let variance = weight_bg as f64 * weight_fg as f64 * (mean_bg - mean_fg).powi(2);
+        if variance > best_variance {
+            best_variance = variance;
+            best = value as u8;
+        }
+    }
+    best
+}
+
+/// Rotate by the angle within ±15° that packs the dark pixels into the fewest
+/// rows, which is when the line of text is level.
+fn deskew(img: &GrayImage) -> GrayImage {
+    let best_angle = (-15..=15)
+        .map(|degrees| degrees as f32)
+        .map(|degrees| (degrees, row_variance(&rotate(img, degrees))))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(degrees, _)| degrees)
+        .unwrap_or(0.0);
+    rotate(img, best_angle)
+}
+
+/// Nearest-neighbour rotation around the center, filling the corners with white.
+fn rotate(img: &GrayImage, degrees: f32) -> GrayImage {
+    if degrees == 0.0 {
+        return img.clone();
+    }
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let (cx, cy) = (img.width() as f32 / 2.0, img.height() as f32 / 2.0);
+    GrayImage::from_fn(img.width(), img.height(), |x, y| {
+        let (dx, dy) = (x as f32 - cx, y as f32 - cy);
+        let src_x = (cos * dx + sin * dy + cx).round();
+        let src_y = (-sin * dx + cos * dy + cy).round();
+        if src_x < 0.0 || src_y < 0.0 || src_x >= img.width() as f32 || src_y >= img.height() as f32
+        {
+            Luma([255])
+        } else {
+            *img.get_pixel(src_x as u32, src_y as u32)
+        }
+    })
+}
+
+fn row_variance(img: &GrayImage) -> f64 {
+    let rows: Vec<f64> = img
+        .rows()
+        .map(|row| row.filter(|pixel| pixel[0] == 0).count() as f64)
+        .collect();
+    let mean = rows.iter().sum::<f64>() / rows.len() as f64;
+    rows.iter().map(|count| (count - mean).powi(2)).sum::<f64>() / rows.len() as f64
+}
@@ -0,0 +1,114 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use reqwest::blocking::Client;
+use serde::Deserialize;
+use tracing::info;
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::Guess;
+
+/// Time given to the workers before the first poll, and between polls.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// Give up on a captcha that isn't solved by then.
+const SOLVE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Reply of the 2captcha-style `in.php`/`res.php` endpoints with `json=1`.
+#[derive(Deserialize)]
+struct Reply {
+    status: u8,
+    request: String,
+}
+
+/// Upload the image to a 2captcha-compatible service and wait for a worker to solve it.
+///
+/// The answer is printed together with how long it took and what it cost, the latter
+/// derived from the account balance before and after.
+pub fn guess(img_data: &[u8], server: &str, api_key: &str) -> Result<Guess, String> {
+    let server = server.trim_end_matches('/');
+    let client = Client::builder()
+        .timeout(Duration::from_secs(30))
+        .build()
+        .unwrap();
+    let started = Instant::now();
+    let balance_before = balance(&client, server, api_key).ok();
+
+    let image = STANDARD.encode(img_data);
+    let uploaded = call(client.post(format!("{}/in.php", server)).form(&[
+        ("key", api_key),
+        ("method", "base64"),
+        ("body", &image),
+        ("json", "1"),
+    ]))?;
+    let id = match uploaded {
+        Reply { status: 1, request } => request,
+        Reply { request, .. } => {
+            return Err(format!("Captcha service refused the image: {}", request));
+        }
+    };
+
+    let code = loop {
+        thread::sleep(POLL_INTERVAL);
+        let reply = call(client.get(format!("{}/res.php", server)).query(&[
+            ("key", api_key),
+            ("action", "get"),
+            ("id", &id),
+            ("json", "1"),
+        ]))?;
+        match reply {
+            Reply { status: 1, request } => break request,
+            Reply { request, .. } if request == "CAPCHA_NOT_READY" => {
+                if started.elapsed() >= SOLVE_TIMEOUT {
+                    return Err(format!(
+                        "Captcha service did not answer within {}",
+                        humantime::format_duration(SOLVE_TIMEOUT)
+                    ));
+                }
+            }
+            Reply { request, .. } => return Err(format!("Captcha service failed: {}", request)),
+        }
+    };
+
+    let elapsed = started.elapsed().as_secs_f32();
+    match (balance_before, balance(&client, server, api_key).ok()) {
+        (Some(before), Some(after)) => info!(
+            "Captcha service answered in {:.1}s, cost {:.5} (balance {:.5})",
+            elapsed,
+            before - after,
+            after
+        ),
+        _ => info!("Captcha service answered in {:.1}s", elapsed),
+    }
+
+    // Nothing tells how sure the worker was, trust the answer
+    Ok(Guess {
+        code: code.trim().to_uppercase(),
+        confidence: 100.0,
+    })
+}
+
+fn balance(client: &Client, server: &str, api_key: &str) -> Result<f64, String> {
+    let reply = call(client.get(format!("{}/res.php", server)).query(&[
+        ("key", api_key),
+        ("action", "getbalance"),
+        ("json", "1"),
+    ]))?;
+    reply.request.parse().map_err(|_| {
+        format!(
+            "Unexpected balance from the captcha service: {}",
+            reply.request
+        )
+    })
+}
+
+fn call(request: reqwest::blocking::RequestBuilder) -> Result<Reply, String> {
+    let resp = request
+        .send()
+        .map_err(|err| format!("Failed to reach the captcha service: {}", err))?;
+    let body = resp
+        .text()
+        .map_err(|err| format!("Failed to read the captcha service reply: {}", err))?;
+    serde_json::from_str(&body)
+        .map_err(|_| format!("Unexpected reply from the captcha service: {}", body.trim()))
+}
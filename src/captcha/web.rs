@@ -0,0 +1,83 @@
+use tiny_http::{Header, Method, Response, Server};
+use tracing::warn;
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+
+use crate::output;
+
+const PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>THSR security code</title>
+</head>
+<body style="font-family: sans-serif; text-align: center; margin-top: 3em">
+<img src="/captcha.jpg" alt="security code" style="width: 280px; image-rendering: pixelated">
+<form method="post" action="/">
+<p><input name="code" autofocus autocomplete="off" maxlength="4" size="6" style="font-size: 2em; text-transform: uppercase"></p>
+<p><button type="submit">Submit</button> <button type="submit" name="code" value="r">New image</button></p>
+</form>
+</body>
+</html>
+"#;
+
+const DONE_PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head><meta charset="utf-8"><title>THSR security code</title></head>
+<body style="font-family: sans-serif; text-align: center; margin-top: 3em">
+<p>Received, you can close this page.</p>
+</body>
+</html>
+"#;
+
+/// Serve a page showing the image with a text box on `addr`, and wait until
+/// something is submitted through it.
+pub fn ask(img_data: &[u8], addr: SocketAddr) -> Result<String, String> {
+    let server =
+        Server::http(addr).map_err(|err| format!("Failed to listen on {}: {}", addr, err))?;
+    output::line(format_args!("Enter the security code at http://{}/", addr));
+
+    loop {
+        let mut request = server
+            .recv()
+            .map_err(|err| format!("Failed to receive a request: {}", err))?;
+
+        let answer = match (request.method(), request.url()) {
+            (Method::Get, "/") => request.respond(html(PAGE)),
+            (Method::Get, "/captcha.jpg") => request.respond(
+                Response::from_data(img_data)
+                    .with_header(header("Content-Type", "image/jpeg"))
+                    .with_header(header("Cache-Control", "no-store")),
+            ),
+            (Method::Post, "/") => {
+                let mut body = String::new();
+                let _ = request.as_reader().read_to_string(&mut body);
+                let form: HashMap<String, String> =
+                    serde_urlencoded::from_str(&body).unwrap_or_default();
+                match form.get("code").filter(|code| !code.trim().is_empty()) {
+                    Some(code) => {
+                        let code = code.clone();
+                        let _ = request.respond(html(DONE_PAGE));
+                        return Ok(code);
+                    }
+                    // Nothing entered, show the form again
+                    None => request.respond(html(PAGE)),
+                }
+            }
+            _ => request.respond(Response::from_string("Not found").with_status_code(404)),
+        };
+        if let Err(err) = answer {
+            warn!("Failed to answer the browser: {}", err);
+        }
+    }
+}
+
+fn html(page: &str) -> Response<std::io::Cursor<Vec<u8>>> {
+    Response::from_string(page).with_header(header("Content-Type", "text/html; charset=utf-8"))
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name, value).unwrap()
+}
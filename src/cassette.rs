@@ -0,0 +1,225 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use bytes::Bytes;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::error::Error;
+use crate::http::{HttpResponse, Transport};
+use crate::redact::Redactor;
+
+/// Whether to record the session of a booking, or to replay a recorded one.
+pub enum CassetteMode {
+    Record(PathBuf),
+    Replay(PathBuf),
+}
+
+/// A recorded session: every request of a booking and the response it got, in order.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Interaction {
+    pub method: String,
+    pub url: String,
+
+    /// The posted form
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub form: Option<String>,
+
+    pub status: u16,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cookies: Vec<(String, String)>,
+
+    /// The response body, if it is text
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+
+    /// The response body otherwise, e.g. the security code image
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_base64: Option<String>,
+}
+
+impl Cassette {
+    pub fn load(path: &Path) -> Result<Cassette, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read cassette {}: {}", path.display(), err))?;
+        serde_json::from_str(&content)
+            .map_err(|err| format!("Invalid cassette {}: {}", path.display(), err))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(self).unwrap();
+        fs::write(path, content)
+            .map_err(|err| format!("Failed to write cassette {}: {}", path.display(), err))
+    }
+}
+
+/// Passes requests on to `inner` and keeps a copy of every exchange.
+///
+/// The session ID, personal data and the values given to [`Recorder::redact`] are
+/// replaced by tokens everywhere in the recording, see [`crate::redact`].
+/// The live session is left alone.
+pub struct Recorder<T: Transport> {
+    inner: T,
+    redactor: Mutex<Redactor>,
+    cassette: Mutex<Cassette>,
+}
+
+impl<T: Transport> Recorder<T> {
+    pub fn new(inner: T) -> Self {
+        Recorder {
+            inner,
+            redactor: Mutex::default(),
+            cassette: Mutex::default(),
+        }
+    }
+
+    /// Keep `secret` out of the recording.
+    pub fn redact(self, secret: &str) -> Self {
+        self.redactor.lock().unwrap().add(secret);
+        self
+    }
+
+    /// Write the exchanges recorded so far to `path`.
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        self.cassette.lock().unwrap().save(path)
+    }
+
+    pub fn into_cassette(self) -> Cassette {
+        self.cassette.into_inner().unwrap()
+    }
+
+    fn scrub(&self, text: &str) -> String {
+        self.redactor.lock().unwrap().text(text)
+    }
+
+    fn record(
+        &self,
+        method: &str,
+        url: &str,
+        form: Option<&str>,
+        result: Result<HttpResponse, Error>,
+    ) -> Result<HttpResponse, Error> {
+        let Ok(resp) = result else {
+            return result;
+        };
+
+        // Session IDs are handed out in cookies and reused in the URLs afterwards
+        if let Some(session) = resp.cookie("JSESSIONID") {
+            self.redactor.lock().unwrap().add(session);
+        }
+
+        let (body, body_base64) = match std::str::from_utf8(&resp.body) {
+            Ok(text) => (Some(self.scrub(text)), None),
+            Err(_) => (None, Some(STANDARD.encode(&resp.body))),
+        };
+        self.cassette
+            .lock()
+            .unwrap()
+            .interactions
+            .push(Interaction {
+                method: method.to_string(),
+                url: self.scrub(url),
+                form: form.map(|form| self.redactor.lock().unwrap().form(form)),
+                status: resp.status.as_u16(),
+                cookies: resp
+                    .cookies
+                    .iter()
+                    .map(|(name, value)| (name.clone(), self.scrub(value)))
+                    .collect(),
+                body,
+                body_base64,
+            });
+        Ok(resp)
+    }
+}
+
+impl<T: Transport> Transport for Recorder<T> {
+    fn get(&self, url: &str) -> Result<HttpResponse, Error> {
+        self.record("GET", url, None, self.inner.get(url))
+    }
+
+    fn post_form(&self, url: &str, body: String) -> Result<HttpResponse, Error> {
+        let result = self.inner.post_form(url, body.clone());
+        self.record("POST", url, Some(&body), result)
+    }
+
+    fn cookie(&self, url: &str, name: &str) -> Option<String> {
+        self.inner.cookie(url, name)
+    }
+}
+
+/// Answers requests from a cassette, in the recorded order.
+///
+/// Only the method and URL of a request are checked, the posted forms may differ.
+pub struct Replayer {
+    interactions: Vec<Interaction>,
+    next: Mutex<usize>,
+}
+
+impl Replayer {
+    pub fn new(cassette: Cassette) -> Self {
+        Replayer {
+            interactions: cassette.interactions,
+            next: Mutex::new(0),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        Cassette::load(path).map(Replayer::new)
+    }
+
+    /// Whether every recorded exchange has been replayed.
+    pub fn is_done(&self) -> bool {
+        *self.next.lock().unwrap() == self.interactions.len()
+    }
+
+    fn replay(&self, method: &str, url: &str) -> Result<HttpResponse, Error> {
+        let mut next = self.next.lock().unwrap();
+        let index = *next;
+        let interaction = self.interactions.get(index).ok_or_else(|| {
+            Error::Network(format!(
+                "Cassette has no response left for {} {}",
+                method, url
+            ))
+        })?;
+        if interaction.method != method || interaction.url != url {
+            return Err(Error::Network(format!(
+                "Cassette expected {} {}, got {} {}",
+                interaction.method, interaction.url, method, url
+            )));
+        }
+        *next = index + 1;
+
+        let body = match (&interaction.body, &interaction.body_base64) {
+            (_, Some(encoded)) => STANDARD
+                .decode(encoded)
+                .map_err(|err| Error::Network(format!("Invalid body in cassette: {}", err)))?,
+            (Some(text), None) => text.clone().into_bytes(),
+            (None, None) => Vec::new(),
+        };
+        Ok(HttpResponse {
+            status: StatusCode::from_u16(interaction.status).unwrap_or(StatusCode::OK),
+            cookies: interaction.cookies.clone(),
+            body: Bytes::from(body),
+        })
+    }
+}
+
+impl Transport for Replayer {
+    fn get(&self, url: &str) -> Result<HttpResponse, Error> {
+        self.replay("GET", url)
+    }
+
+    fn post_form(&self, url: &str, _body: String) -> Result<HttpResponse, Error> {
+        self.replay("POST", url)
+    }
+}
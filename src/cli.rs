@@ -1,35 +1,459 @@
-use clap::Parser;
+use chrono::NaiveTime;
 use clap::builder::TypedValueParser;
+use clap::{Parser, Subcommand};
+use reqwest::Proxy;
+use tracing::Level;
+
+use std::ffi::OsString;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::backend::BackendKind;
+use crate::booking::BookingOptions;
+use crate::booking_flow;
+use crate::captcha::SolverKind;
+use crate::cassette::CassetteMode;
+use crate::config::Config;
+use crate::events::EventFormat;
+use crate::http::parse_proxy;
+use crate::i18n::Lang;
+use crate::passenger::{self, Passenger, parse_email, parse_passport, parse_phone};
+use crate::pick::{self, TrainPick};
+use crate::preset::Preset;
+use crate::schema::{SlotRange, Station, TimeSlot};
+use crate::select::Selection;
+use crate::site::SiteLocale;
 
 /// A CLI tool for booking Taiwan High Speed Rail tickets.
 /// Run the program without flags will guide you through the booking process.
 #[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+#[command(
+    name = "thsr",
+    version,
+    about,
+    long_about = None,
+    args_conflicts_with_subcommands = true,
+    after_help = "Any other COMMAND runs the plugin thsr-COMMAND found on the PATH, see the README"
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[command(flatten)]
+    pub args: Args,
+
+    /// List available stations
+    #[arg(long)]
+    pub list_station: bool,
+
+    /// List available times
+    #[arg(long)]
+    pub list_time_table: bool,
+
+    /// Print the lists of --list-station and --list-time-table as JSON
+    #[arg(long)]
+    pub json: bool,
+
+    /// Record the requests and responses of the booking to a cassette file,
+    /// with the personal and session IDs redacted
+    #[arg(long, value_name = "PATH", conflicts_with = "replay")]
+    pub record: Option<PathBuf>,
+
+    /// Replay a recorded cassette instead of talking to the booking site
+    #[arg(long, value_name = "PATH")]
+    pub replay: Option<PathBuf>,
+
+    /// Capture the HTTP traffic of the booking to a HAR file, with headers, redirects
+    /// and timings, for opening in a browser's network tools. Cookies and personal IDs are redacted
+    #[arg(long, value_name = "PATH", conflicts_with = "replay")]
+    pub har: Option<PathBuf>,
+
+    /// Answer the questions with key=value lines read from this file, or from stdin for `-`:
+    /// the booking options up to an empty line, then answer=... and captcha=... as they are asked
+    #[arg(long, value_name = "PATH")]
+    pub answers: Option<PathBuf>,
+
+    /// How much to log to stderr: error, warn, info, debug or trace.
+    /// Defaults to info, or to RUST_LOG if set
+    #[arg(long, value_name = "LEVEL", global = true)]
+    pub log_level: Option<Level>,
+
+    /// Print without colors. Setting NO_COLOR does the same
+    #[arg(long, global = true)]
+    pub no_color: bool,
+
+    /// Print the progress of the booking on stdout as events, one JSON object per line, for
+    /// programs running thsr. The usual output goes to stderr instead
+    #[arg(long, value_name = "FORMAT", global = true)]
+    pub events: Option<EventFormat>,
+
+    /// Language of the prompts and of what is printed.
+    /// Defaults to Chinese for a Chinese locale, to English otherwise
+    #[arg(long, value_name = "LANG", global = true)]
+    pub lang: Option<Lang>,
+}
+
+impl Cli {
+    /// The booking options, of the subcommand if one is given.
+    pub fn booking_args(&self) -> &Args {
+        match &self.command {
+            Some(Command::Watch(watch_args)) => &watch_args.args,
+            Some(Command::Trains(trains_args)) => &trains_args.args,
+            Some(Command::Schedule(schedule_args)) => &schedule_args.args,
+            Some(Command::Resume(resume_args)) => &resume_args.args,
+            Some(Command::Serve(serve_args)) => &serve_args.args,
+            Some(Command::Daemon(daemon_args)) => &daemon_args.args,
+            #[cfg(feature = "grpc")]
+            Some(Command::Grpc(grpc_args)) => &grpc_args.args,
+            #[cfg(feature = "tui")]
+            Some(Command::Tui(tui_args)) => &tui_args.args,
+            #[cfg(feature = "gui")]
+            Some(Command::Gui(gui_args)) => &gui_args.args,
+            Some(
+                Command::Man(_)
+                | Command::Preset(_)
+                | Command::Station(_)
+                | Command::Remind(_)
+                | Command::Doctor(_)
+                | Command::External(_),
+            )
+            | None => &self.args,
+        }
+    }
+
+    pub fn cassette(&self) -> Option<CassetteMode> {
+        match (&self.record, &self.replay) {
+            (Some(path), _) => Some(CassetteMode::Record(path.clone())),
+            (None, Some(path)) => Some(CassetteMode::Replay(path.clone())),
+            (None, None) => None,
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Re-run the train query on an interval and book as soon as a matching train shows up.
+    /// Route, date and time must be given as options.
+    Watch(WatchArgs),
+
+    /// List the trains of the route and date without booking, from one time slot or,
+    /// with --sweep, from every slot of a range merged into one list.
+    Trains(TrainsArgs),
+
+    /// Wait for the booking window of the target date to open (28 days ahead, 00:00 Taipei time)
+    /// and book right at the opening instant. All prompts are answered beforehand.
+    Schedule(ScheduleArgs),
+
+    /// Pick up an interrupted booking at the last step it completed.
+    /// Every booking saves its progress after each step until it is done.
+    Resume(ResumeArgs),
+
+    /// Serve a JSON API for booking, e.g. for a web front-end. The booking options
+    /// given here are the defaults of the bookings made through it.
+    Serve(ServeArgs),
+
+    /// Run queued booking jobs at their scheduled times, taken from a job file and over HTTP.
+    /// The booking options given here are the defaults of the jobs.
+    Daemon(DaemonArgs),
+
+    /// Serve the bookings as a gRPC service streaming their progress, see proto/thsr.proto.
+    /// The booking options given here are the defaults of the bookings made through it.
+    #[cfg(feature = "grpc")]
+    Grpc(GrpcArgs),
+
+    /// Book in a full-screen terminal interface: pick the route in a form, then follow
+    /// the booking with the trains and the security code on screen.
+    /// The booking options given here fill in the form.
+    #[cfg(feature = "tui")]
+    Tui(TuiArgs),
+
+    /// Book in a desktop window: pick the route, then the train, and type in the
+    /// security code. The booking options given here fill in the form.
+    #[cfg(feature = "gui")]
+    Gui(GuiArgs),
+
+    /// Print the manual page, or write the pages of all subcommands to a directory
+    /// for packaging.
+    Man(ManArgs),
+
+    /// Add, list or remove the trips of the config file booked with --preset.
+    Preset(PresetArgs),
+
+    /// Tell about a station: where it is, the lines to change to and its first and last trains.
+    Station(StationArgs),
+
+    /// Remind of the booked tickets still to be paid, from the history of the bookings,
+    /// before their payment deadline passes.
+    Remind(RemindArgs),
+
+    /// Check what booking depends on: reaching the booking site over DNS and TLS, its
+    /// session cookie, a way to show the security code, the config file and the clock.
+    Doctor(DoctorArgs),
+
+    /// Any other subcommand runs the plugin thsr-<NAME> found on the PATH
+    #[command(external_subcommand)]
+    External(Vec<OsString>),
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct WatchArgs {
+    #[command(flatten)]
+    pub args: Args,
+
+    /// Interval between queries, e.g. 90s, 5m
+    #[arg(long, value_name = "DURATION", default_value = "90s", value_parser = humantime::parse_duration)]
+    pub every: Duration,
+
+    /// Serve Prometheus metrics at http://ADDR/metrics while watching
+    #[arg(long, value_name = "ADDR")]
+    pub metrics: Option<SocketAddr>,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct TrainsArgs {
+    #[command(flatten)]
+    pub args: Args,
+
+    /// Search from every time slot of this range, e.g. 08:00-12:00, instead of --time.
+    /// Each search asks for a security code
+    #[arg(long, value_name = "START-END", conflicts_with = "time")]
+    pub sweep: Option<SlotRange>,
+
+    /// Print the trains as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ScheduleArgs {
+    #[command(flatten)]
+    pub args: Args,
+
+    /// Travel date to book once its booking window opens
+    #[arg(long, value_name = "DATE")]
+    pub target_date: String,
+
+    /// How long before the opening to open the session and enter the security code
+    #[arg(long, value_name = "DURATION", default_value = "60s", value_parser = humantime::parse_duration)]
+    pub warmup: Duration,
+
+    /// NTP server used to correct the local clock
+    #[arg(long, value_name = "HOST", default_value = "pool.ntp.org")]
+    pub ntp_server: String,
+
+    /// Trust the local clock instead of querying an NTP server
+    #[arg(long)]
+    pub no_ntp: bool,
+
+    /// Serve Prometheus metrics at http://ADDR/metrics while waiting and booking
+    #[arg(long, value_name = "ADDR")]
+    pub metrics: Option<SocketAddr>,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ResumeArgs {
+    #[command(flatten)]
+    pub args: Args,
+
+    /// State file of the booking.
+    /// Defaults to thsr/booking.json under the user's local data directory
+    #[arg(long, value_name = "PATH")]
+    pub state: Option<PathBuf>,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ServeArgs {
+    #[command(flatten)]
+    pub args: Args,
+
+    /// Address to listen on. There is no authentication, keep it private
+    #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:8080")]
+    pub listen: SocketAddr,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct DaemonArgs {
+    #[command(flatten)]
+    pub args: Args,
+
+    /// TOML file of jobs to queue at startup
+    #[arg(long, value_name = "PATH")]
+    pub jobs: Option<PathBuf>,
+
+    /// Also take jobs over HTTP on this address. There is no authentication, keep it private
+    #[arg(long, value_name = "ADDR")]
+    pub listen: Option<SocketAddr>,
+
+    /// File recording the finished jobs. Defaults to thsr/history.jsonl under the local data directory
+    #[arg(long, value_name = "PATH")]
+    pub history: Option<PathBuf>,
+}
+
+#[cfg(feature = "grpc")]
+#[derive(clap::Args, Debug, Clone)]
+pub struct GrpcArgs {
+    #[command(flatten)]
+    pub args: Args,
+
+    /// Address to listen on. There is no authentication, keep it private
+    #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:50051")]
+    pub listen: SocketAddr,
+}
+
+#[cfg(feature = "tui")]
+#[derive(clap::Args, Debug, Clone)]
+pub struct TuiArgs {
+    #[command(flatten)]
+    pub args: Args,
+}
+
+#[cfg(feature = "gui")]
+#[derive(clap::Args, Debug, Clone)]
+pub struct GuiArgs {
+    #[command(flatten)]
+    pub args: Args,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ManArgs {
+    /// Directory to write thsr.1 and a page for every subcommand to, instead of
+    /// printing thsr.1
+    #[arg(long, value_name = "DIR")]
+    pub out: Option<PathBuf>,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct PresetArgs {
+    #[command(subcommand)]
+    pub action: PresetAction,
+
+    /// Path to the config file holding the presets.
+    /// Defaults to thsr/config.toml under the user's config directory
+    #[arg(long, value_name = "PATH", global = true)]
+    pub config: Option<PathBuf>,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum PresetAction {
+    /// Save a trip under a name, replacing the one of that name
+    Add {
+        /// Name of the preset, e.g. weekend-home
+        name: String,
+
+        #[command(flatten)]
+        preset: Preset,
+    },
+
+    /// List the presets
+    List,
+
+    /// Remove a preset
+    Remove {
+        /// Name of the preset
+        name: String,
+    },
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct StationArgs {
+    #[command(subcommand)]
+    pub action: StationAction,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum StationAction {
+    /// Print the address, location, transfers and first and last trains of a station
+    Info {
+        /// ID, code, English or Chinese name of the station, e.g. 2, TPE, taipei or 台北
+        name: String,
+    },
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct RemindArgs {
+    /// Remind of the bookings to be paid within this time, e.g. 12h, 2days
+    #[arg(long, value_name = "DURATION", default_value = "24h", value_parser = humantime::parse_duration)]
+    pub before: Duration,
+
+    /// Keep running, looking for bookings to remind of on this interval, e.g. 30m.
+    /// Every booking is reminded of once
+    #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration)]
+    pub every: Option<Duration>,
+
+    /// File of the bookings. Defaults to thsr/history.jsonl under the local data directory
+    #[arg(long, value_name = "PATH")]
+    pub history: Option<PathBuf>,
+
+    /// Path to the config file with the notifiers to remind through.
+    /// Defaults to thsr/config.toml under the user's config directory
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct DoctorArgs {
+    /// Path to the config file to check.
+    /// Defaults to thsr/config.toml under the user's config directory
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// NTP server the local clock is checked against
+    #[arg(long, value_name = "HOST", default_value = "pool.ntp.org")]
+    pub ntp_server: String,
+
+    /// Leave the clock unchecked, e.g. where NTP is blocked
+    #[arg(long)]
+    pub no_ntp: bool,
+}
+
+/// Booking options shared by all modes.
+#[derive(clap::Args, Debug, Clone)]
 pub struct Args {
     /// Personal ID (Default is ryan's. Can be overridden.)
     #[arg(long, short = 'i', value_name = "ID", default_value = "S125544509")]
     pub personal_id: Option<String>,
 
-    /// Departure date
+    /// Passport number, for visitors booking without a Taiwan ID. Takes the place of
+    /// --personal-id, without membership
+    #[arg(long, value_name = "NUMBER", value_parser = parse_passport)]
+    pub passport: Option<String>,
+
+    /// Mobile number for THSR's text messages about the booking, e.g. when the train
+    /// is disrupted. Overrides the config file
+    #[arg(long, value_name = "NUMBER", value_parser = parse_phone)]
+    pub phone: Option<String>,
+
+    /// Email address THSR sends its confirmation mail to. Overrides the config file
+    #[arg(long, value_name = "ADDRESS", value_parser = parse_email)]
+    pub email: Option<String>,
+
+    /// Departure date, or a range of dates like 2025/06/01..2025/06/03 to book on the
+    /// first one with a train to book
     #[arg(long, short = 'd', value_name = "DATE")]
     pub date: Option<String>,
 
-    /// Time ID of the departure time.
+    /// Departure time, by its ID or as HH:MM.
     /// To see available times, use the --list-time-table option.
-    #[arg(long, short = 'T', value_name = "TIME_ID")]
-    pub time: Option<usize>,
+    #[arg(long, short = 'T', value_name = "TIME")]
+    pub time: Option<TimeSlot>,
 
-    /// Departure station ID.
+    /// Departure station ID, code or name.
     /// To see available stations, use the --list-station option.
-    #[arg(long, short = 'f', value_name = "STATION_ID")]
-    pub from: Option<usize>,
+    #[arg(long, short = 'f', value_name = "STATION")]
+    pub from: Option<Station>,
 
-    /// Arrival station ID.
+    /// Arrival station ID, code or name.
     /// To see available stations, use the --list-station option.
-    #[arg(long, short = 't', value_name = "STATION_ID")]
-    pub to: Option<usize>,
+    #[arg(long, short = 't', value_name = "STATION")]
+    pub to: Option<Station>,
+
+    /// Train number to book, skipping the train selection prompt. Several, e.g. 803,805,809,
+    /// are tried in order, booking the first one listed with seats
+    #[arg(long, value_name = "TRAIN_NO", value_delimiter = ',')]
+    pub train: Vec<u32>,
 
-    /// Number of adults
+    /// Number of adults. Groups of more than 10 are booked as several bookings on the same train
     #[arg(long, short = 'a', value_name = "NUMBER")]
     pub adult_cnt: Option<u8>,
 
@@ -37,6 +461,33 @@ pub struct Args {
     #[arg(long, short = 's', value_name = "NUMBER")]
     pub student_cnt: Option<u8>,
 
+    /// Book for these passenger profiles of the config file, e.g. mom,dad,me, instead of
+    /// --adult-cnt and --student-cnt. The first one books, with their ID and membership
+    #[arg(long, value_name = "NAMES", value_delimiter = ',')]
+    pub passengers: Vec<String>,
+
+    /// The profiles of `passengers`, see [`crate::passenger::pick`]
+    #[arg(skip)]
+    pub party: Vec<Passenger>,
+
+    /// IDs or passport numbers of the passengers the ticket form asks for, as for early
+    /// bird, elder and disabled tickets, in the order of the tickets, e.g. A123456789,B223456789
+    #[arg(
+        long,
+        value_name = "IDS",
+        value_delimiter = ',',
+        conflicts_with = "passenger_file"
+    )]
+    pub passenger_ids: Vec<String>,
+
+    /// File of the passenger IDs, one per line, instead of --passenger-ids
+    #[arg(long, value_name = "PATH")]
+    pub passenger_file: Option<PathBuf>,
+
+    /// Show the names and IDs filled in on the ticket form and ask before submitting them
+    #[arg(long)]
+    pub review_passengers: bool,
+
     /// Seat preference. 0: None, 1: Window, 2: Aisle
     #[arg(
         long,
@@ -60,14 +511,205 @@ pub struct Args {
     pub class_type: Option<usize>,
 
     /// Whether to use personal ID as membership (Default: true/y)
-    #[arg(long, short = 'm', value_name = "TO_USE_MEMBERSHIP", default_value_t = true)]
+    #[arg(
+        long,
+        short = 'm',
+        value_name = "TO_USE_MEMBERSHIP",
+        default_value = "true"
+    )]
     pub use_membership: Option<bool>,
 
-    /// List available stations
+    /// How to solve the security code. Overrides the config file
+    #[arg(long, value_name = "SOLVER")]
+    pub captcha_solver: Option<SolverKind>,
+
+    /// ONNX model for the `model` security code solver. Overrides the config file
+    #[arg(long, value_name = "PATH")]
+    pub captcha_model: Option<PathBuf>,
+
+    /// API key for the `service` security code solver. Overrides the config file
+    #[arg(long, value_name = "KEY")]
+    pub captcha_api_key: Option<String>,
+
+    /// Write the security code image to this file instead of showing it,
+    /// or print it base64-encoded for `-`. Overrides the config file
+    #[arg(long, value_name = "PATH")]
+    pub captcha_out: Option<PathBuf>,
+
+    /// Wait for the security code to be written to this file instead of
+    /// prompting, or read it from stdin for `-`. Overrides the config file
+    #[arg(long, value_name = "PATH")]
+    pub captcha_in: Option<PathBuf>,
+
+    /// Serve a page for entering the security code on this address,
+    /// e.g. 127.0.0.1:8787. Overrides the config file
+    #[arg(long, value_name = "ADDR")]
+    pub captcha_http: Option<SocketAddr>,
+
+    /// Send all requests through this proxy, e.g. http://host:3128 or socks5h://127.0.0.1:1080.
+    /// Overrides the config file and the HTTPS_PROXY/ALL_PROXY environment variables
+    #[arg(long, value_name = "URL", value_parser = parse_proxy)]
+    pub proxy: Option<Proxy>,
+
+    /// Keep the cookies in this file between runs, so a follow-up run goes on with
+    /// the same server session. Overrides the config file
+    #[arg(long, value_name = "PATH")]
+    pub cookie_jar: Option<PathBuf>,
+
+    /// How to talk to the booking system. Overrides the config file
+    #[arg(long, value_name = "BACKEND")]
+    pub backend: Option<BackendKind>,
+
+    /// Language of the booking site, for its alerts, errors and station names.
+    /// Overrides the config file
+    #[arg(long, value_name = "LOCALE")]
+    pub site_locale: Option<SiteLocale>,
+
+    /// Search and pick the train, then print what would be booked instead of booking it
     #[arg(long)]
-    pub list_station: bool,
+    pub dry_run: bool,
 
-    /// List available times
+    /// Write the trains found to this file, as CSV if it ends with .csv and as JSON otherwise
+    #[arg(long, value_name = "PATH")]
+    pub trains_out: Option<PathBuf>,
+
+    /// Book without asking to confirm the summary shown before the tickets are confirmed
+    #[arg(long, short = 'y')]
+    pub yes: bool,
+
+    /// Take the stations, time, tickets, seat and class not given from the last booking
     #[arg(long)]
-    pub list_time_table: bool,
-}
\ No newline at end of file
+    pub last: bool,
+
+    /// Book the trip saved under this name in the config file, see `thsr preset`.
+    /// Options given take precedence
+    #[arg(long, value_name = "NAME")]
+    pub preset: Option<String>,
+
+    /// How to pick the train without asking, unless --train is given
+    #[arg(long, value_name = "PICK")]
+    pub pick: Option<TrainPick>,
+
+    /// Latest departure of the train picked with --pick, e.g. 19:00
+    #[arg(long, value_name = "HH:MM", value_parser = pick::parse_clock)]
+    pub depart_before: Option<NaiveTime>,
+
+    /// Pick the train with this Rhai script, evaluating to the index of the train to book
+    /// in `trains`. Requires the `scripting` feature
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["pick", "depart_before"])]
+    pub select_script: Option<PathBuf>,
+
+    /// Pick the train with an expression of its depart, arrive, duration, early_bird,
+    /// student, seats and train number, e.g. 'duration<100 && depart>=18:00 ? earliest : fastest'
+    #[arg(long, value_name = "EXPR", conflicts_with_all = ["pick", "depart_before", "select_script"])]
+    pub select: Option<Selection>,
+
+    /// When no train can be booked in the time slot, search up to this many slots
+    /// earlier and later too, the nearest first
+    #[arg(long, value_name = "N")]
+    pub nearby_slots: Option<usize>,
+
+    /// Shell command to run after a booking, with its details in environment variables
+    /// such as THSR_PNR, THSR_PRICE and THSR_TRAIN. Overrides the config file
+    #[arg(long, value_name = "COMMAND")]
+    pub on_success: Option<String>,
+
+    /// Shell command to approve the booking right before it is made, with its summary in
+    /// environment variables such as THSR_SUMMARY. Failing calls the booking off.
+    /// Overrides the config file
+    #[arg(long, value_name = "COMMAND")]
+    pub on_confirm: Option<String>,
+
+    /// Path to the config file.
+    /// Defaults to thsr/config.toml under the user's config directory.
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Key file with the age identities decrypting the encrypted section of the config file.
+    /// A section encrypted with a passphrase asks for it instead
+    #[arg(long, value_name = "PATH")]
+    pub config_key: Option<PathBuf>,
+}
+
+impl Args {
+    /// The options of the booking as the flows take them, booking on the site and
+    /// through the backend of `config`.
+    pub fn options(&self, config: &Config) -> BookingOptions {
+        BookingOptions {
+            site: config.site.clone(),
+            backend: config.backend,
+            date: self.date.clone(),
+            time: self.time,
+            from: self.from,
+            to: self.to,
+            train: self.train.clone(),
+            nearby_slots: self.nearby_slots,
+            adult_cnt: self.adult_cnt,
+            student_cnt: self.student_cnt,
+            party: self.party.clone(),
+            passengers: self.passengers.clone(),
+            passenger_ids: self.passenger_ids.clone(),
+            review_passengers: self.review_passengers,
+            seat_prefer: self.seat_prefer,
+            class_type: self.class_type,
+            personal_id: self.personal_id.clone(),
+            passport: self.passport.clone(),
+            phone: self.phone.clone(),
+            email: self.email.clone(),
+            use_membership: self.use_membership,
+            dry_run: self.dry_run,
+            yes: self.yes,
+        }
+    }
+
+    /// Check the options given, so a mistake is reported before anything is sent
+    /// to the booking site rather than by it.
+    pub fn validate(&self) -> Result<(), String> {
+        if let (Some(from), Some(to)) = (self.from, self.to)
+            && from == to
+        {
+            return Err(format!(
+                "The departure and arrival stations are both {}, pick two different ones",
+                from
+            ));
+        }
+        if let Some(date) = &self.date {
+            match booking_flow::parse_dates(date) {
+                None => {
+                    return Err(format!(
+                        "{} is not a date like 2025/06/29, nor a range of them like \
+                         2025/06/29..2025/07/01",
+                        date
+                    ));
+                }
+                Some(dates) if dates.is_empty() => {
+                    return Err(format!("The dates {} end before they start", date));
+                }
+                Some(_) => {}
+            }
+        }
+        let tickets = [self.adult_cnt, self.student_cnt];
+        if tickets.iter().any(Option::is_some) && tickets.iter().flatten().all(|&count| count == 0)
+        {
+            return Err("At least one ticket has to be booked".to_string());
+        }
+        if let Some(id) = (self.passenger_ids.iter())
+            .find(|id| !passenger::is_national_id(id) && parse_passport(id).is_err())
+        {
+            return Err(format!(
+                "{} is neither a Taiwan ID nor a passport number",
+                id
+            ));
+        }
+        if self.passport.is_none()
+            && let Some(id) = &self.personal_id
+            && !passenger::is_valid_national_id(id)
+        {
+            return Err(format!(
+                "{} is not a valid Taiwan ID, visitors book with --passport",
+                id
+            ));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,328 @@
+use age::armor::ArmoredReader;
+use age::secrecy::SecretString;
+use age::{Decryptor, Identity, IdentityFile};
+use reqwest::Proxy;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, USER_AGENT};
+use serde::Deserialize;
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+use crate::backend::BackendKind;
+use crate::captcha::SolverKind;
+use crate::http::{RateLimit, RetryPolicy, deserialize_proxy};
+use crate::passenger::{Passenger, deserialize_email, deserialize_phone};
+use crate::preset::Preset;
+use crate::site::Site;
+
+/// Settings read from the TOML config file.
+///
+/// Every section is optional, a missing file behaves like an empty one.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub email: Option<EmailConfig>,
+    pub ntfy: Option<NtfyConfig>,
+    pub retry: RetryPolicy,
+    /// Pace of the requests of `thsr watch` and `thsr trains --sweep`
+    pub rate_limit: RateLimit,
+    pub captcha: CaptchaConfig,
+    pub site: Site,
+    pub headers: HeadersConfig,
+
+    /// How to talk to the booking system
+    pub backend: BackendKind,
+
+    /// Proxy for all requests to the booking site, e.g. `socks5h://127.0.0.1:1080`
+    #[serde(deserialize_with = "deserialize_proxy")]
+    pub proxy: Option<Proxy>,
+
+    /// File keeping the cookies between runs, so a later run goes on with the same session
+    pub cookie_jar: Option<PathBuf>,
+
+    /// Passenger profiles by name, for `--passengers`
+    pub passengers: BTreeMap<String, Passenger>,
+
+    /// Trips by name, for `--preset`
+    pub presets: BTreeMap<String, Preset>,
+
+    /// Contact details put on the bookings
+    pub contact: ContactConfig,
+
+    /// Shell command run after a booking, with its details in `THSR_*` environment variables
+    pub on_success: Option<String>,
+
+    /// Shell command approving a booking before it is made, with its summary in `THSR_*`
+    /// environment variables. The booking is called off when it fails
+    pub on_confirm: Option<String>,
+}
+
+/// How THSR reaches you about a booking.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ContactConfig {
+    /// Mobile number for THSR's text messages, e.g. when the train is disrupted
+    #[serde(deserialize_with = "deserialize_phone")]
+    pub phone: Option<String>,
+
+    /// Address for THSR's own confirmation mail
+    #[serde(deserialize_with = "deserialize_email")]
+    pub email: Option<String>,
+}
+
+/// SMTP settings for mailing the booking summary to yourself.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct EmailConfig {
+    pub smtp_host: String,
+
+    #[serde(default = "default_smtp_port")]
+    pub smtp_port: u16,
+
+    #[serde(default)]
+    pub tls: SmtpTls,
+
+    pub username: Option<String>,
+    pub password: Option<String>,
+
+    pub from: String,
+    pub to: Vec<String>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpTls {
+    /// Plain connection upgraded with STARTTLS (usually port 587)
+    #[default]
+    Starttls,
+    /// TLS from the first byte (usually port 465)
+    Tls,
+    /// No encryption at all, only meant for local relays
+    None,
+}
+
+/// How the security code is solved.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct CaptchaConfig {
+    pub solver: SolverKind,
+
+    /// Automatic guesses below this confidence (0-100) are confirmed by hand
+    pub min_confidence: f32,
+
+    /// ONNX model used by the `model` solver
+    pub model: Option<PathBuf>,
+
+    /// Base URL of the 2captcha-compatible API used by the `service` solver
+    pub service_url: String,
+
+    /// API key for the solving service
+    pub api_key: Option<String>,
+
+    /// Where to write the image for manual solving instead of showing it, `-` for base64 on stdout
+    pub out: Option<PathBuf>,
+
+    /// File to read the manually solved code from instead of the terminal, `-` for stdin
+    #[serde(rename = "in")]
+    pub input: Option<PathBuf>,
+
+    /// Serve a page for entering the code on this address instead
+    pub http: Option<SocketAddr>,
+}
+
+impl Default for CaptchaConfig {
+    fn default() -> Self {
+        CaptchaConfig {
+            solver: SolverKind::Manual,
+            min_confidence: 70.0,
+            model: None,
+            service_url: "https://2captcha.com".to_string(),
+            api_key: None,
+            out: None,
+            input: None,
+            http: None,
+        }
+    }
+}
+
+/// Changes to the browser-like headers sent with every request, for when the
+/// built-in ones trip the site's bot detection.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct HeadersConfig {
+    /// Sent instead of the built-in Firefox User-Agent
+    #[serde(deserialize_with = "deserialize_header_value")]
+    pub user_agent: Option<HeaderValue>,
+
+    /// Built-in headers to leave out, e.g. `Sec-Fetch-Mode`
+    #[serde(deserialize_with = "deserialize_header_names")]
+    pub remove: Vec<HeaderName>,
+
+    /// Headers to add, or to send with another value
+    #[serde(deserialize_with = "deserialize_header_map")]
+    pub set: HeaderMap,
+}
+
+impl HeadersConfig {
+    /// Apply the changes to the built-in `headers`.
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        for name in &self.remove {
+            headers.remove(name);
+        }
+        if let Some(user_agent) = &self.user_agent {
+            headers.insert(USER_AGENT, user_agent.clone());
+        }
+        for (name, value) in &self.set {
+            headers.insert(name, value.clone());
+        }
+    }
+}
+
+fn deserialize_header_value<'de, D: serde::Deserializer<'de>>(
+    de: D,
+) -> Result<Option<HeaderValue>, D::Error> {
+    let value = String::deserialize(de)?;
+    HeaderValue::from_str(&value)
+        .map(Some)
+        .map_err(|_| serde::de::Error::custom(format!("invalid header value {:?}", value)))
+}
+
+fn deserialize_header_names<'de, D: serde::Deserializer<'de>>(
+    de: D,
+) -> Result<Vec<HeaderName>, D::Error> {
+    Vec::<String>::deserialize(de)?
+        .iter()
+        .map(|name| {
+            HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| serde::de::Error::custom(format!("invalid header name {:?}", name)))
+        })
+        .collect()
+}
+
+fn deserialize_header_map<'de, D: serde::Deserializer<'de>>(de: D) -> Result<HeaderMap, D::Error> {
+    let mut headers = HeaderMap::new();
+    for (name, value) in BTreeMap::<String, String>::deserialize(de)? {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| serde::de::Error::custom(format!("invalid header name {:?}", name)))?;
+        let value = HeaderValue::from_str(&value)
+            .map_err(|_| serde::de::Error::custom(format!("invalid header value {:?}", value)))?;
+        headers.insert(name, value);
+    }
+    Ok(headers)
+}
+
+/// Push notifications through an ntfy server.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct NtfyConfig {
+    #[serde(default = "default_ntfy_server")]
+    pub server: String,
+
+    pub topic: String,
+
+    /// Access token for protected topics
+    pub token: Option<String>,
+}
+
+fn default_ntfy_server() -> String {
+    "https://ntfy.sh".to_string()
+}
+
+fn default_smtp_port() -> u16 {
+    587
+}
+
+impl Config {
+    /// `<config dir>/thsr/config.toml`, e.g. `~/.config/thsr/config.toml` on Linux.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("thsr").join("config.toml"))
+    }
+
+    /// Load the config from `path`, or from the default location if none is given.
+    /// An explicitly given path must exist, the default one may be absent.
+    ///
+    /// The `encrypted` section is decrypted with the age identities of the `key` file,
+    /// or with a passphrase from `THSR_CONFIG_PASSPHRASE` or the terminal.
+    pub fn load(path: Option<&Path>, key: Option<&Path>) -> Result<Config, String> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match Self::default_path() {
+                Some(path) if path.exists() => path,
+                _ => return Ok(Config::default()),
+            },
+        };
+
+        let content = fs::read_to_string(&path)
+            .map_err(|err| format!("Failed to read config {}: {}", path.display(), err))?;
+        let invalid = |err: toml::de::Error| format!("Invalid config {}: {}", path.display(), err);
+        let mut table: toml::Table = toml::from_str(&content).map_err(invalid)?;
+        let Some(encrypted) = table.remove("encrypted") else {
+            return toml::from_str(&content).map_err(invalid);
+        };
+        let Some(encrypted) = encrypted.as_str() else {
+            return Err(format!(
+                "Invalid config {}: encrypted must be an age armored string",
+                path.display()
+            ));
+        };
+        let secrets = decrypt(encrypted, key).map_err(|err_msg| {
+            format!("Failed to decrypt config {}: {}", path.display(), err_msg)
+        })?;
+        let secrets: toml::Table = toml::from_str(&secrets).map_err(|err| {
+            format!(
+                "Invalid encrypted section of config {}: {}",
+                path.display(),
+                err
+            )
+        })?;
+        merge(&mut table, secrets);
+        table.try_into().map_err(invalid)
+    }
+}
+
+/// Decrypt the age armored `armored` with the identities of the `key` file, or with a
+/// passphrase when it was encrypted with one.
+fn decrypt(armored: &str, key: Option<&Path>) -> Result<String, String> {
+    let decryptor = Decryptor::new(ArmoredReader::new(armored.trim().as_bytes()))
+        .map_err(|err| err.to_string())?;
+
+    let identities: Vec<Box<dyn Identity>> = if decryptor.is_scrypt() {
+        let passphrase = match std::env::var("THSR_CONFIG_PASSPHRASE") {
+            Ok(passphrase) => passphrase,
+            Err(_) => rpassword::prompt_password("Passphrase of the config file: ")
+                .map_err(|err| format!("Failed to read the passphrase: {}", err))?,
+        };
+        vec![Box::new(age::scrypt::Identity::new(SecretString::from(
+            passphrase,
+        )))]
+    } else {
+        let key = key.ok_or("it is encrypted to a key, give the key file with --config-key")?;
+        IdentityFile::from_file(key.display().to_string())
+            .and_then(|file| file.into_identities().map_err(std::io::Error::other))
+            .map_err(|err| format!("Failed to read the key file {}: {}", key.display(), err))?
+    };
+
+    let mut reader = decryptor
+        .decrypt(identities.iter().map(|identity| identity.as_ref()))
+        .map_err(|err| err.to_string())?;
+    let mut secrets = String::new();
+    reader
+        .read_to_string(&mut secrets)
+        .map_err(|err| err.to_string())?;
+    Ok(secrets)
+}
+
+/// Add the decrypted `secrets` to `table`, section by section.
+fn merge(table: &mut toml::Table, secrets: toml::Table) {
+    for (name, value) in secrets {
+        match (table.get_mut(&name), value) {
+            (Some(toml::Value::Table(section)), toml::Value::Table(value)) => merge(section, value),
+            (_, value) => {
+                table.insert(name, value);
+            }
+        }
+    }
+}
@@ -0,0 +1,345 @@
+//! `thsr daemon`: a queue of bookings to make at given times. Jobs come from a job
+//! file and over HTTP, are tried again according to their retry policy while the
+//! failure is temporary, and end up in the [`history`].
+
+use chrono::{DateTime, FixedOffset, NaiveDateTime, TimeZone};
+use serde::{Deserialize, Serialize};
+use tiny_http::{Method, Request, Response, Server};
+use tracing::{info, info_span, warn};
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::cli::{Args, DaemonArgs};
+use crate::config::Config;
+use crate::exit;
+use crate::history::{self, Entry};
+use crate::http::deserialize_duration;
+use crate::output;
+use crate::progress;
+use crate::prompt::{Prompter, StdinPrompter};
+use crate::remote::{self, BookingRequest};
+use crate::serve::{error, json};
+use crate::time::{taipei, taipei_now};
+use crate::{book, build_client, load_config, metrics, report};
+
+/// A booking to make, as written in the job file or posted to `/jobs`.
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct JobSpec {
+    /// When to start, `YYYY/MM/DD HH:MM` Taipei time. Right away if left out
+    pub at: Option<String>,
+    pub booking: BookingRequest,
+    pub retry: JobRetry,
+}
+
+/// How often a job is tried before it is given up.
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(default, deny_unknown_fields)]
+pub struct JobRetry {
+    /// Tries in total, the first one included
+    pub attempts: u32,
+
+    /// Wait between two tries
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub delay: Duration,
+}
+
+impl Default for JobRetry {
+    fn default() -> Self {
+        JobRetry {
+            attempts: 3,
+            delay: Duration::from_secs(60),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JobFile {
+    #[serde(default)]
+    job: Vec<JobSpec>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    /// Waiting for its time, or for the next try
+    Queued,
+    Running,
+}
+
+/// A job in the queue, as shown by the API.
+#[derive(Serialize, Debug, Clone)]
+pub struct Job {
+    pub id: String,
+    pub status: JobStatus,
+    /// When the next try starts
+    pub at: String,
+    /// Tries made so far
+    pub attempts: u32,
+    pub last_error: Option<String>,
+
+    #[serde(skip)]
+    due: DateTime<FixedOffset>,
+    #[serde(skip)]
+    args: Args,
+    #[serde(skip)]
+    retry: JobRetry,
+}
+
+impl Job {
+    fn schedule(&mut self, due: DateTime<FixedOffset>) {
+        self.due = due;
+        self.at = due.format("%Y/%m/%d %H:%M:%S").to_string();
+        self.status = JobStatus::Queued;
+    }
+}
+
+/// The jobs waiting or running, shared between the API and the worker.
+#[derive(Default)]
+pub struct Queue {
+    jobs: Mutex<Vec<Job>>,
+    changed: Condvar,
+}
+
+impl Queue {
+    /// Queue `spec`, with `args` for the options it leaves out.
+    pub fn push(&self, spec: JobSpec, args: &Args) -> Result<Job, String> {
+        let due = match &spec.at {
+            Some(at) => parse_time(at)?,
+            None => taipei_now(),
+        };
+        let mut args = spec.booking.apply(args);
+        if args.from.is_none() || args.to.is_none() || args.date.is_none() || args.time.is_none() {
+            return Err("A job needs from, to, date and time".to_string());
+        }
+        // Nobody is there to answer, like in watch mode
+        if args.adult_cnt.is_none() && args.student_cnt.is_none() {
+            args.adult_cnt = Some(1);
+        }
+        args.seat_prefer.get_or_insert(0);
+        args.class_type.get_or_insert(0);
+
+        let mut job = Job {
+            id: remote::new_id(),
+            status: JobStatus::Queued,
+            at: String::new(),
+            attempts: 0,
+            last_error: None,
+            due,
+            args,
+            retry: spec.retry,
+        };
+        job.schedule(due);
+        info!("Job {} queued for {}", job.id, job.at);
+        self.jobs.lock().unwrap().push(job.clone());
+        self.changed.notify_all();
+        Ok(job)
+    }
+
+    /// The jobs in the queue, in the order they were added.
+    pub fn jobs(&self) -> Vec<Job> {
+        self.jobs.lock().unwrap().clone()
+    }
+
+    /// Wait for the next job to be due, and mark it running.
+    fn next(&self) -> Job {
+        let mut jobs = self.jobs.lock().unwrap();
+        loop {
+            let now = taipei_now();
+            let next = (jobs.iter().enumerate())
+                .filter(|(_, job)| job.status == JobStatus::Queued)
+                .min_by_key(|(_, job)| job.due)
+                .map(|(idx, job)| (idx, job.due));
+            jobs = match next {
+                Some((idx, due)) if due <= now => {
+                    jobs[idx].status = JobStatus::Running;
+                    jobs[idx].attempts += 1;
+                    return jobs[idx].clone();
+                }
+                Some((_, due)) => {
+                    let wait = (due - now).to_std().unwrap_or_default();
+                    self.changed.wait_timeout(jobs, wait).unwrap().0
+                }
+                None => self.changed.wait(jobs).unwrap(),
+            };
+        }
+    }
+
+    fn retry(&self, id: &str, err_msg: String, due: DateTime<FixedOffset>) {
+        let mut jobs = self.jobs.lock().unwrap();
+        if let Some(job) = jobs.iter_mut().find(|job| job.id == id) {
+            job.last_error = Some(err_msg);
+            job.schedule(due);
+        }
+    }
+
+    fn remove(&self, id: &str) {
+        self.jobs.lock().unwrap().retain(|job| job.id != id);
+    }
+}
+
+/// A job time, `YYYY/MM/DD HH:MM` in Taipei time or RFC 3339.
+fn parse_time(text: &str) -> Result<DateTime<FixedOffset>, String> {
+    NaiveDateTime::parse_from_str(text, "%Y/%m/%d %H:%M")
+        .ok()
+        .and_then(|time| taipei().from_local_datetime(&time).single())
+        .or_else(|| DateTime::parse_from_rfc3339(text).ok())
+        .ok_or_else(|| format!("Invalid job time {}: use YYYY/MM/DD HH:MM", text))
+}
+
+/// Queue the jobs of the TOML file at `path`.
+pub fn load_jobs(queue: &Queue, path: &Path, args: &Args) -> Result<usize, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|err| format!("Failed to read jobs from {}: {}", path.display(), err))?;
+    let file: JobFile = toml::from_str(&content)
+        .map_err(|err| format!("Invalid job file {}: {}", path.display(), err))?;
+    let count = file.job.len();
+    for spec in file.job {
+        queue.push(spec, args)?;
+    }
+    Ok(count)
+}
+
+/// Run the jobs of `queue` as they become due, forever, recording the finished
+/// ones to the history file `history`.
+pub fn work(queue: &Queue, config: &Config, history: Option<&Path>, prompter: &dyn Prompter) {
+    loop {
+        let job = queue.next();
+        let _span = info_span!("job", id = job.id.as_str(), attempt = job.attempts).entered();
+        info!("Starting job {}", job.id);
+
+        let client = build_client(config);
+        // Queuing the job was the confirmation, nobody is there to answer
+        let args = Args {
+            yes: true,
+            ..job.args.clone()
+        };
+        let outcome = book(&client, &args, config, prompter);
+        match outcome {
+            Err(err) if err.is_temporary() && job.attempts < job.retry.attempts => {
+                warn!(
+                    "{}, trying again in {}",
+                    err,
+                    humantime::format_duration(job.retry.delay)
+                );
+                let due = taipei_now() + job.retry.delay;
+                queue.retry(&job.id, err.to_string(), due);
+            }
+            outcome => {
+                let entry = Entry::new(
+                    &job.id,
+                    job.attempts,
+                    outcome.as_ref().map_err(ToString::to_string),
+                );
+                if let Some(path) = history
+                    && let Err(err_msg) = history::append(path, &entry)
+                {
+                    warn!("{}", err_msg);
+                }
+                queue.remove(&job.id);
+                report(config, outcome);
+            }
+        }
+    }
+}
+
+/// Take jobs over HTTP on `server`, until killed.
+pub fn serve(server: &Server, queue: &Queue, args: &Args, history: Option<&Path>) {
+    for request in server.incoming_requests() {
+        handle(request, queue, args, history);
+    }
+}
+
+fn handle(mut request: Request, queue: &Queue, args: &Args, history: Option<&Path>) {
+    let mut body = String::new();
+    let _ = request.as_reader().read_to_string(&mut body);
+    let url = request.url().to_string();
+    let (path, _query) = url.split_once('?').unwrap_or((&url, ""));
+
+    let response = match (request.method(), path.trim_end_matches('/')) {
+        (Method::Post, "/jobs") => match serde_json::from_str::<JobSpec>(&body) {
+            Ok(spec) => match queue.push(spec, args) {
+                Ok(job) => json(201, &job),
+                Err(err_msg) => error(400, &err_msg),
+            },
+            Err(err) => error(400, &format!("Invalid job: {}", err)),
+        },
+        (Method::Get, "/jobs") => json(200, &queue.jobs()),
+        (Method::Get, "/history") => match history.map(history::load).transpose() {
+            Ok(entries) => json(200, &entries.unwrap_or_default()),
+            Err(err_msg) => error(500, &err_msg),
+        },
+        (Method::Get, "/metrics") => {
+            Response::from_string(metrics::render()).with_header(metrics::content_type())
+        }
+        _ => error(404, "Not found"),
+    };
+    if let Err(err) = request.respond(response) {
+        warn!("Failed to answer the API caller: {}", err);
+    }
+}
+
+/// Run the jobs of the job file, and those posted to the API, until killed.
+pub fn run(daemon_args: DaemonArgs) {
+    let DaemonArgs {
+        mut args,
+        jobs,
+        listen,
+        history,
+    } = daemon_args;
+    // Jobs book on their own, possibly several at a time
+    progress::disable();
+    let config = match load_config(&mut args) {
+        Ok(config) => config,
+        Err(err_msg) => {
+            output::error(err_msg);
+            exit::fail(exit::Status::Invalid);
+            return;
+        }
+    };
+    let history: Option<PathBuf> = history.or_else(history::default_path);
+
+    let queue = Arc::new(Queue::default());
+    if let Some(path) = &jobs {
+        match load_jobs(&queue, path, &args) {
+            Ok(count) => output::line(format_args!(
+                "Queued {} jobs from {}",
+                count,
+                path.display()
+            )),
+            Err(err_msg) => {
+                output::error(err_msg);
+                return;
+            }
+        }
+    }
+    if let Some(listen) = listen {
+        let server = match Server::http(listen) {
+            Ok(server) => server,
+            Err(err) => {
+                output::error(format!("Failed to listen on {}: {}", listen, err));
+                return;
+            }
+        };
+        output::line(format_args!("Taking jobs at http://{}/jobs", listen));
+        let queue = Arc::clone(&queue);
+        let args = args.clone();
+        let history = history.clone();
+        thread::spawn(move || serve(&server, &queue, &args, history.as_deref()));
+    } else if jobs.is_none() {
+        output::error("Give a job file with --jobs, or take jobs over HTTP with --listen");
+        return;
+    }
+
+    work(
+        &queue,
+        &config,
+        history.as_deref(),
+        &StdinPrompter::new(&config.captcha),
+    );
+}
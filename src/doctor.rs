@@ -0,0 +1,218 @@
+//! `thsr doctor`, checking what a booking depends on: the booking site reached over
+//! DNS and TLS, its session cookie kept, a way to show the security code, the config
+//! file and the local clock. The first thing to run when booking doesn't work.
+
+use console::style;
+use reqwest::Url;
+use scraper::Html;
+
+use std::env;
+use std::io::{self, IsTerminal};
+use std::net::ToSocketAddrs;
+
+use crate::captcha;
+use crate::cli::DoctorArgs;
+use crate::config::{CaptchaConfig, Config};
+use crate::exit;
+use crate::http::{HttpClient, Transport};
+use crate::i18n::tr;
+use crate::ntp;
+use crate::output;
+use crate::site::Site;
+use crate::{get_header, parse_maintenance, selectors};
+
+/// The most the local clock may be off and pass, in milliseconds.
+const MAX_SKEW: i64 = 2000;
+
+/// One line of the report: what was found when the check passed, or what went wrong.
+pub struct Check {
+    pub name: String,
+    pub outcome: Result<String, String>,
+}
+
+impl Check {
+    fn new(name: String, outcome: Result<String, String>) -> Check {
+        Check { name, outcome }
+    }
+
+    pub fn passed(&self) -> bool {
+        self.outcome.is_ok()
+    }
+}
+
+/// The host of the booking site resolved, as the requests will resolve it.
+fn dns(site: &Site) -> Result<String, String> {
+    let url = Url::parse(&site.base_url)
+        .map_err(|err| format!("Invalid address {}: {}", site.base_url, err))?;
+    let host = (url.host_str()).ok_or_else(|| format!("No host in {}", site.base_url))?;
+    let port = url.port_or_known_default().unwrap_or(443);
+    let addr = ((host, port).to_socket_addrs())
+        .map_err(|err| format!("{} doesn't resolve: {}", host, err))?
+        .next()
+        .ok_or_else(|| format!("{} resolves to no address", host))?;
+    Ok(format!("{} resolves to {}", host, addr.ip()))
+}
+
+/// The booking page fetched with its search form, over TLS for an `https` site.
+fn reach(client: &dyn Transport, site: &Site) -> Result<String, String> {
+    let response = (client.get(&site.booking_page())).map_err(|err| err.to_string())?;
+    let document = Html::parse_document(&response.text());
+    if document.select(&selectors::DATE_INPUT).next().is_none() {
+        return Err(parse_maintenance(&document).map_or_else(
+            || "The booking page has no search form".to_string(),
+            |err| err.to_string(),
+        ));
+    }
+    let over = match site.base_url.starts_with("https:") {
+        true => "TLS",
+        false => "plain HTTP",
+    };
+    Ok(format!(
+        "The booking page opens over {}, {}",
+        over, response.status
+    ))
+}
+
+/// The session cookie of the booking page kept, after loading the cookie jar if one
+/// is configured.
+fn cookies(client: &dyn Transport, site: &Site, jar: Result<(), String>) -> Result<String, String> {
+    jar?;
+    match client.cookie(&site.booking_page(), "JSESSIONID") {
+        Some(_) => Ok("The session cookie of the booking site is kept".to_string()),
+        None => Err("The booking site set no session cookie, or it wasn't kept".to_string()),
+    }
+}
+
+/// The checks of the booking site with `client`: DNS, the booking page and its cookie.
+/// The cookie jar of `config` is loaded into `client` first.
+pub fn site_checks(client: &HttpClient, config: &Config) -> Vec<Check> {
+    let site = &config.site;
+    let jar = match &config.cookie_jar {
+        Some(path) => client.load_cookies(path),
+        None => Ok(()),
+    };
+    let reached = reach(client, site);
+    let cookies = match &reached {
+        Ok(_) => cookies(client, site, jar),
+        Err(_) => Err("Not checked, the booking page didn't open".to_string()),
+    };
+    vec![
+        Check::new("DNS".to_string(), dns(site)),
+        Check::new(tr!("Booking site", "訂票網站"), reached),
+        Check::new(tr!("Cookies", "Cookie"), cookies),
+    ]
+}
+
+fn on_path(program: &str) -> bool {
+    let path = env::var_os("PATH").unwrap_or_default();
+    let file = format!("{}{}", program, env::consts::EXE_SUFFIX);
+    env::split_paths(&path).any(|dir| dir.join(&file).is_file())
+}
+
+/// How the security code would be shown: handed over as configured, opened in the
+/// image viewer of the desktop, or drawn in the terminal.
+fn viewer(config: &CaptchaConfig) -> Result<String, String> {
+    if config.out.is_some() || config.http.is_some() {
+        return Ok("Handed over as --captcha-out or --captcha-http says".to_string());
+    }
+    let viewer = if cfg!(target_os = "windows") {
+        Some("cmd")
+    } else if cfg!(target_os = "macos") {
+        Some("open")
+    } else if cfg!(target_os = "linux") && captcha::has_display() {
+        Some("xdg-open")
+    } else {
+        None
+    };
+    match viewer {
+        Some(viewer) if on_path(viewer) => Ok(format!("Opened in the image viewer by {}", viewer)),
+        _ if io::stdout().is_terminal() => Ok("Drawn in the terminal".to_string()),
+        _ => Err(
+            "No image viewer and no terminal to draw the security code in, see --captcha-out and --captcha-http"
+                .to_string(),
+        ),
+    }
+}
+
+/// The local clock against the NTP `server`, off by at most [`MAX_SKEW`] to pass.
+fn clock(server: &str) -> Result<String, String> {
+    let offset = (ntp::clock_offset(server))
+        .map_err(|err_msg| format!("Failed to ask {} the time: {}", server, err_msg))?;
+    let millis = offset.num_milliseconds();
+    // A positive offset means the local clock is behind
+    let off = format!(
+        "{:.1}s {}",
+        millis.abs() as f64 / 1000.0,
+        if millis > 0 { "behind" } else { "ahead of" }
+    );
+    match millis.abs() <= MAX_SKEW {
+        true => Ok(format!("{} {}", off, server)),
+        false => Err(format!(
+            "The clock is {} {}, set it right to book when the booking window opens",
+            off, server
+        )),
+    }
+}
+
+fn report(checks: &[Check]) {
+    for check in checks {
+        let (mark, found) = match &check.outcome {
+            Ok(found) => (style("PASS").green().bold(), found),
+            Err(err_msg) => (style("FAIL").red().bold(), err_msg),
+        };
+        println!("{} {}: {}", mark, check.name, found);
+    }
+}
+
+pub fn run(doctor_args: DoctorArgs) {
+    let (mut config, loaded) = match Config::load(doctor_args.config.as_deref(), None) {
+        Ok(config) => {
+            let path = doctor_args.config.clone().or_else(Config::default_path);
+            let found = match path {
+                Some(path) if path.exists() => format!("{} is valid", path.display()),
+                _ => "No config file, the defaults are used".to_string(),
+            };
+            (config, Ok(found))
+        }
+        Err(err_msg) => (Config::default(), Err(err_msg)),
+    };
+    if let Ok(base_url) = env::var("THSR_BASE_URL") {
+        config.site.base_url = Site::new(base_url).base_url;
+    }
+
+    let client = HttpClient::new(
+        get_header(&config.site, &config.headers),
+        config.retry.clone(),
+    );
+    let client = match &config.proxy {
+        Some(proxy) => client.proxy(proxy.clone()),
+        None => client,
+    };
+    let mut checks = site_checks(&client, &config);
+    checks.push(Check::new(
+        tr!("Security code", "驗證碼"),
+        viewer(&config.captcha),
+    ));
+    checks.push(Check::new(tr!("Config", "設定檔"), loaded));
+    if !doctor_args.no_ntp {
+        checks.push(Check::new(
+            tr!("Clock", "時鐘"),
+            clock(&doctor_args.ntp_server),
+        ));
+    }
+    report(&checks);
+
+    let failed = checks.iter().filter(|check| !check.passed()).count();
+    match failed {
+        0 => output::success(tr!("All checks passed", "全部檢查通過")),
+        _ => {
+            output::error(tr!(
+                "{} of {} checks failed",
+                "{} 項檢查未通過，共 {} 項",
+                failed,
+                checks.len()
+            ));
+            exit::fail(exit::Status::Failed);
+        }
+    }
+}
@@ -0,0 +1,91 @@
+//! Progress of the booking as machine-readable events on stdout, one JSON object
+//! per line with `--events ndjson`, for programs wrapping `thsr`.
+
+use chrono::{Local, SecondsFormat};
+use serde::Serialize;
+
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::alert::Alert;
+use crate::confirm_train_flow::Train;
+use crate::error::Error;
+use crate::exit::Status;
+use crate::result::BookingResult;
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum EventFormat {
+    Ndjson,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event<'a> {
+    /// A new session was opened on the booking page
+    PageLoaded,
+    /// The security code is waiting to be solved
+    CaptchaRequired,
+    /// An alert of the booking site above the trains
+    Alert {
+        #[serde(flatten)]
+        alert: &'a Alert,
+    },
+    TrainsFound {
+        trains: &'a [Train],
+    },
+    TrainSelected {
+        train: u32,
+    },
+    Booked {
+        #[serde(flatten)]
+        result: &'a BookingResult,
+    },
+    Error {
+        message: String,
+        /// The exit status the error ends `thsr` with
+        status: u8,
+    },
+}
+
+impl Event<'_> {
+    pub fn error(err: &Error) -> Event<'static> {
+        Event::Error {
+            message: err.to_string(),
+            status: Status::of(err).code(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Line<'a> {
+    time: String,
+    #[serde(flatten)]
+    event: &'a Event<'a>,
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+pub fn init(format: Option<EventFormat>) {
+    ENABLED.store(format.is_some(), Ordering::Relaxed);
+}
+
+/// Whether events were asked for, leaving stdout to them.
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Print `event` as a line of JSON, if events were asked for.
+pub fn emit(event: &Event) {
+    if !enabled() {
+        return;
+    }
+    let line = Line {
+        time: Local::now().to_rfc3339_opts(SecondsFormat::Millis, false),
+        event,
+    };
+    let mut stdout = io::stdout().lock();
+    if let Ok(json) = serde_json::to_string(&line) {
+        let _ = writeln!(stdout, "{}", json);
+        let _ = stdout.flush();
+    }
+}
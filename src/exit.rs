@@ -0,0 +1,99 @@
+//! The exit status of `thsr`, telling scripts around it how the booking went.
+
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use crate::error::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(u8)]
+pub enum Status {
+    Success = 0,
+    Failed = 1,
+    Invalid = 2,
+    Captcha = 3,
+    SoldOut = 4,
+    Network = 5,
+    SessionExpired = 6,
+    Cancelled = 7,
+}
+
+impl Status {
+    /// All statuses, in the order of their codes.
+    pub const ALL: [Status; 8] = [
+        Status::Success,
+        Status::Failed,
+        Status::Invalid,
+        Status::Captcha,
+        Status::SoldOut,
+        Status::Network,
+        Status::SessionExpired,
+        Status::Cancelled,
+    ];
+
+    /// The status of a booking failing with `err`.
+    pub fn of(err: &Error) -> Status {
+        match err {
+            Error::WrongSecurityCode(_) => Status::Captcha,
+            Error::SoldOut(_) | Error::TrainUnavailable(_) => Status::SoldOut,
+            Error::InvalidId(_) | Error::TooManyTickets(_) | Error::Script(_) => Status::Invalid,
+            Error::Network(_) | Error::Throttled(_) => Status::Network,
+            Error::SessionExpired => Status::SessionExpired,
+            Error::Cancelled => Status::Cancelled,
+            Error::DryRun => Status::Success,
+            Error::Maintenance(_) | Error::Rejected(_) | Error::UnexpectedPage(_) => Status::Failed,
+        }
+    }
+
+    pub fn code(self) -> u8 {
+        self as u8
+    }
+
+    /// What the status means, for the documentation.
+    pub fn description(self) -> &'static str {
+        match self {
+            Status::Success => "Booked, or nothing to book, e.g. a dry run",
+            Status::Failed => "Any other failure, e.g. a message of the booking site",
+            Status::Invalid => "Invalid options or config file, or an ID refused by the site",
+            Status::Captcha => "The security code was rejected too many times",
+            Status::SoldOut => "No seats left, or the train asked for isn't listed",
+            Status::Network => {
+                "The booking site couldn't be reached, even after retrying, or turned the requests away"
+            }
+            Status::SessionExpired => "The session expired and couldn't be started over",
+            Status::Cancelled => "The booking summary wasn't confirmed",
+        }
+    }
+}
+
+static STATUS: AtomicU8 = AtomicU8::new(Status::Success as u8);
+
+/// Exit with `status`, unless a later failure replaces it.
+pub fn fail(status: Status) {
+    if status != Status::Success {
+        STATUS.store(status as u8, Ordering::Relaxed);
+    }
+}
+
+/// Exit with [`Status::Failed`], unless a more telling status was set.
+pub(crate) fn failed() {
+    let _ = STATUS.compare_exchange(
+        Status::Success as u8,
+        Status::Failed as u8,
+        Ordering::Relaxed,
+        Ordering::Relaxed,
+    );
+}
+
+pub fn status() -> Status {
+    let code = STATUS.load(Ordering::Relaxed);
+    (Status::ALL.into_iter())
+        .find(|status| status.code() == code)
+        .unwrap_or(Status::Failed)
+}
+
+impl From<Status> for ExitCode {
+    fn from(status: Status) -> ExitCode {
+        ExitCode::from(status.code())
+    }
+}
@@ -0,0 +1,43 @@
+//! The train list written to a file with `--trains-out`, as JSON or as CSV for a
+//! spreadsheet, to compare the trains offline.
+
+use std::fs;
+use std::path::Path;
+
+use crate::confirm_train_flow::Train;
+
+/// The trains as CSV with a header line, the times as `HH:MM`.
+pub fn csv(trains: &[Train]) -> String {
+    let mut out = String::from("id,depart,arrive,travel_minutes,discount_info,seats\n");
+    for train in trains {
+        out.push_str(&format!(
+            "{},{},{},{},{},{}\n",
+            train.id(),
+            train.depart().format("%H:%M"),
+            train.arrive().format("%H:%M"),
+            train.travel_minutes(),
+            csv_field(train.discount_info()),
+            train.seats().as_str()
+        ));
+    }
+    out
+}
+
+/// `text` quoted if it holds a comma, a quote or a line break.
+fn csv_field(text: &str) -> String {
+    match text.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", text.replace('"', "\"\"")),
+        false => text.to_string(),
+    }
+}
+
+/// Write `trains` to `path`, as CSV if it ends with `.csv` and as JSON otherwise.
+pub fn write_trains(path: &Path, trains: &[Train]) -> Result<(), String> {
+    let is_csv = (path.extension()).is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+    let content = match is_csv {
+        true => csv(trains),
+        false => serde_json::to_string_pretty(trains).unwrap(),
+    };
+    fs::write(path, content)
+        .map_err(|err| format!("Failed to write the trains to {}: {}", path.display(), err))
+}
@@ -0,0 +1,109 @@
+//! The command line side of a booking. The flows of `thsr_core` ask the prompter
+//! picked, the terminal, a stream of answers or a remote caller, and what they tell
+//! is printed, counted in the metrics, emitted as events and notified about as the
+//! options and config say.
+
+use tracing::info;
+
+use crate::captcha;
+use crate::cli::Args;
+use crate::config::Config;
+use crate::confirm_train_flow::Train;
+use crate::error::Error;
+use crate::events::{self, Event};
+use crate::export;
+use crate::metrics;
+use crate::notify;
+use crate::pick;
+use crate::progress;
+use crate::prompt::{Check, Notice, Prompter};
+use crate::summary::Summary;
+
+/// Books through `prompter` with the options of `args` and the config `config`.
+pub struct Frontend<'a> {
+    prompter: &'a dyn Prompter,
+    args: &'a Args,
+    config: &'a Config,
+}
+
+impl<'a> Frontend<'a> {
+    pub fn new(prompter: &'a dyn Prompter, args: &'a Args, config: &'a Config) -> Self {
+        Frontend {
+            prompter,
+            args,
+            config,
+        }
+    }
+}
+
+impl Prompter for Frontend<'_> {
+    fn ask(&self, hint: &str) -> String {
+        self.prompter.ask(hint)
+    }
+
+    // Guessed by the configured solver first
+    fn security_code(&self, img_data: &[u8]) -> Option<String> {
+        captcha::solve(img_data, &self.config.captcha, self.prompter)
+    }
+
+    fn select(&self, hint: &str, items: &[String], default: usize) -> usize {
+        self.prompter.select(hint, items, default)
+    }
+
+    fn search(&self, hint: &str, items: &[String], default: usize) -> usize {
+        self.prompter.search(hint, items, default)
+    }
+
+    fn input(&self, hint: &str, default: &str, check: Check) -> String {
+        self.prompter.input(hint, default, check)
+    }
+
+    fn secret(&self, hint: &str, default: &str, check: Check) -> String {
+        self.prompter.secret(hint, default, check)
+    }
+
+    fn confirm(&self, hint: &str) -> bool {
+        self.prompter.confirm(hint)
+    }
+
+    fn tell(&self, notice: Notice) {
+        match notice {
+            Notice::PageLoaded => events::emit(&Event::PageLoaded),
+            Notice::CaptchaRequired => {
+                notify::notify(self.config, &notify::Event::CaptchaRequired);
+                events::emit(&Event::CaptchaRequired);
+            }
+            Notice::Alert(alert) => events::emit(&Event::Alert { alert }),
+            Notice::Searched(error) => metrics::searched(error),
+            Notice::StepDone { step, took, error } => metrics::step(step, took, error),
+            Notice::TrainsFound(trains) => {
+                events::emit(&Event::TrainsFound { trains });
+                if let Some(path) = &self.args.trains_out
+                    && let Err(err_msg) = export::write_trains(path, trains)
+                {
+                    self.prompter.tell(Notice::Warning(&err_msg));
+                }
+            }
+            Notice::TrainSelected(train) => events::emit(&Event::TrainSelected { train }),
+            Notice::Line(_) | Notice::Status(_) | Notice::Warning(_) => {}
+        }
+        self.prompter.tell(notice);
+    }
+
+    fn wait(&self, step: &str, work: &mut dyn FnMut()) {
+        progress::spin(step, work)
+    }
+
+    fn pick_train(&self, trains: &[Train]) -> Result<Option<u32>, Error> {
+        pick::select(trains, self.args)
+    }
+
+    // The `on_confirm` command approves by exiting successfully
+    fn approve(&self, summary: &Summary) -> Result<(), String> {
+        let Some(command) = &self.config.on_confirm else {
+            return Ok(());
+        };
+        info!("Asking {} to approve the booking", command);
+        notify::run_hook(command, summary.hook_env())
+    }
+}
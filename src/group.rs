@@ -0,0 +1,82 @@
+//! Groups larger than the booking site takes at once, booked as consecutive
+//! bookings on the same train, each with its own security code.
+
+use tracing::info;
+
+use crate::cli::Args;
+use crate::config::Config;
+use crate::error::Error;
+use crate::http::Transport;
+use crate::prompt::Prompter;
+use crate::result::BookingResult;
+use crate::{Checkpoint, book_with_journal};
+
+/// The most tickets of a single booking.
+pub const MAX_TICKETS: u8 = 10;
+
+/// `args` split into bookings of at most [`MAX_TICKETS`] tickets, adults first, the
+/// passenger IDs going along with their tickets. A small enough group is booked as is.
+pub fn split(args: &Args) -> Vec<Args> {
+    let (mut adults, mut students) = (args.adult_cnt.unwrap_or(0), args.student_cnt.unwrap_or(0));
+    if u32::from(adults) + u32::from(students) <= u32::from(MAX_TICKETS) {
+        return vec![args.clone()];
+    }
+
+    let mut parts = Vec::new();
+    let mut ids = args.passenger_ids.iter();
+    while adults > 0 || students > 0 {
+        let part_adults = adults.min(MAX_TICKETS);
+        let part_students = students.min(MAX_TICKETS - part_adults);
+        adults -= part_adults;
+        students -= part_students;
+        parts.push(Args {
+            adult_cnt: Some(part_adults),
+            student_cnt: Some(part_students),
+            // The IDs of the tickets of this part
+            passenger_ids: (ids.by_ref())
+                .take(usize::from(part_adults + part_students))
+                .cloned()
+                .collect(),
+            ..args.clone()
+        });
+    }
+    parts
+}
+
+/// Book the parts of `args` one after the other, on the train picked for the first
+/// one, stopping at the first failure. Returns the outcome of every part tried.
+pub fn book(
+    client: &dyn Transport,
+    args: &Args,
+    config: &Config,
+    prompter: &dyn Prompter,
+    journal: &mut dyn FnMut(Checkpoint),
+) -> Vec<Result<BookingResult, Error>> {
+    let parts = split(args);
+    let count = parts.len();
+    let mut train = None;
+    let mut outcomes = Vec::new();
+    for (idx, part) in parts.into_iter().enumerate() {
+        if count > 1 {
+            info!("Booking {} of {}", idx + 1, count);
+        }
+        // The later parts book the train picked for the first
+        let part = match train {
+            Some(train) => Args {
+                train: vec![train],
+                ..part
+            },
+            None => part,
+        };
+        let outcome = book_with_journal(client, &part, config, prompter, &mut |checkpoint| {
+            train = checkpoint.train.or(train);
+            journal(checkpoint);
+        });
+        let failed = outcome.is_err();
+        outcomes.push(outcome);
+        if failed {
+            break;
+        }
+    }
+    outcomes
+}
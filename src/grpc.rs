@@ -0,0 +1,220 @@
+//! `thsr grpc`: the bookings of [`crate::remote`] as a gRPC service, see `proto/thsr.proto`.
+//! Each booking streams its progress, and its questions are answered with a separate call.
+
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::{ReceiverStream, TcpListenerStream};
+use tonic::{Request, Response, Status};
+
+use std::collections::HashMap;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+
+use crate::cli::{Args, GrpcArgs};
+use crate::config::Config;
+use crate::exit;
+use crate::load_config;
+use crate::output;
+use crate::passenger::parse_passport;
+use crate::progress;
+use crate::remote::{self, BookingRequest, Update};
+use crate::schema::{Station, TimeSlot};
+use crate::stage::Progress;
+
+#[allow(clippy::large_enum_variant)]
+pub mod proto {
+    tonic::include_proto!("thsr");
+}
+
+use proto::booking_server::{Booking, BookingServer};
+use proto::{AnswerReply, AnswerRequest, BookRequest, Event, Step, TrainList, event};
+
+/// Events waiting to be streamed before a booking waits for the caller to catch up.
+const EVENT_BUFFER: usize = 16;
+
+pub struct Service {
+    args: Args,
+    config: Arc<Config>,
+    /// Where the answers to the running bookings go, by booking ID
+    bookings: Arc<Mutex<HashMap<String, Sender<String>>>>,
+}
+
+impl Service {
+    /// Book with `args` and `config`, unless a request says otherwise.
+    pub fn new(args: Args, config: Config) -> Service {
+        Service {
+            args,
+            config: Arc::new(config),
+            bookings: Arc::default(),
+        }
+    }
+}
+
+impl TryFrom<BookRequest> for BookingRequest {
+    type Error = Status;
+
+    fn try_from(request: BookRequest) -> Result<Self, Status> {
+        let count = |value: Option<u32>, name: &str| {
+            value
+                .map(u8::try_from)
+                .transpose()
+                .map_err(|_| format!("Too many tickets in {}", name))
+        };
+        let station =
+            |id: Option<u32>| (id.map(|id| id.to_string().parse::<Station>())).transpose();
+        Ok(BookingRequest {
+            personal_id: request.personal_id,
+            passport: (request.passport.as_deref())
+                .map(parse_passport)
+                .transpose()
+                .map_err(Status::invalid_argument)?,
+            from: station(request.from).map_err(Status::invalid_argument)?,
+            to: station(request.to).map_err(Status::invalid_argument)?,
+            date: request.date,
+            time: (request.time.map(|id| id.to_string().parse::<TimeSlot>()))
+                .transpose()
+                .map_err(Status::invalid_argument)?,
+            train: request.train,
+            adult_cnt: count(request.adult_cnt, "adult_cnt").map_err(Status::invalid_argument)?,
+            student_cnt: count(request.student_cnt, "student_cnt")
+                .map_err(Status::invalid_argument)?,
+            seat_prefer: request.seat_prefer.map(|prefer| prefer as usize),
+            class_type: request.class_type.map(|class_type| class_type as usize),
+            use_membership: request.use_membership,
+        })
+    }
+}
+
+fn event(update: Update) -> event::Kind {
+    match update {
+        Update::Reached(progress) => event::Kind::Step(
+            match progress {
+                Progress::Train(_) => Step::Train,
+                Progress::Ticket(_) => Step::Ticket,
+                Progress::Booked(_) => Step::Booked,
+            }
+            .into(),
+        ),
+        Update::Trains(trains) => event::Kind::Trains(TrainList {
+            trains: trains
+                .iter()
+                .map(|train| proto::Train {
+                    id: train.id(),
+                    depart: train.depart().format("%H:%M").to_string(),
+                    arrive: train.arrive().format("%H:%M").to_string(),
+                    travel_time: train.travel_time(),
+                    discount_info: train.discount_info().to_string(),
+                })
+                .collect(),
+        }),
+        Update::Question(hint) => event::Kind::Question(hint.to_string()),
+        Update::Captcha(img_data) => event::Kind::Captcha(img_data.to_vec()),
+        Update::Done(Ok(result)) => event::Kind::Result(proto::Result {
+            pnr: result.pnr.clone(),
+            price: result.price.clone(),
+            payment_deadline: result.payment_deadline.clone(),
+            date: result.date.clone(),
+            depart_time: result.depart_time.clone(),
+            arrive_time: result.arrive_time.clone(),
+            from: result.from.clone(),
+            to: result.to.clone(),
+            class: result.class.clone(),
+            passenger_count: result.passenger_count.clone(),
+            seats: result.seats.iter().map(ToString::to_string).collect(),
+            tgo_points: result.tgo_points,
+        }),
+        Update::Done(Err(err_msg)) => event::Kind::Error(err_msg.clone()),
+    }
+}
+
+#[tonic::async_trait]
+impl Booking for Service {
+    type BookStream = ReceiverStream<Result<Event, Status>>;
+
+    async fn book(
+        &self,
+        request: Request<BookRequest>,
+    ) -> Result<Response<Self::BookStream>, Status> {
+        let request = BookingRequest::try_from(request.into_inner())?;
+        let id = remote::new_id();
+        let (events, stream) = mpsc::channel(EVENT_BUFFER);
+
+        // Registered before the first question can be asked
+        let mut bookings = self.bookings.lock().unwrap();
+        let notify = {
+            let id = id.clone();
+            let bookings = Arc::clone(&self.bookings);
+            move |update: Update| {
+                let done = matches!(update, Update::Done(_));
+                let event = Event {
+                    booking_id: id.clone(),
+                    kind: Some(event(update)),
+                };
+                // Nobody to answer once the caller is gone, which ends the booking
+                if events.blocking_send(Ok(event)).is_err() || done {
+                    bookings.lock().unwrap().remove(&id);
+                }
+            }
+        };
+        let answers = remote::spawn(
+            id.clone(),
+            request.apply(&self.args),
+            Arc::clone(&self.config),
+            notify,
+        );
+        bookings.insert(id, answers);
+        Ok(Response::new(ReceiverStream::new(stream)))
+    }
+
+    async fn answer(
+        &self,
+        request: Request<AnswerRequest>,
+    ) -> Result<Response<AnswerReply>, Status> {
+        let AnswerRequest { booking_id, answer } = request.into_inner();
+        let bookings = self.bookings.lock().unwrap();
+        let answers = bookings
+            .get(&booking_id)
+            .ok_or_else(|| Status::not_found("No such booking"))?;
+        answers
+            .send(answer)
+            .map_err(|_| Status::failed_precondition("The booking has ended"))?;
+        Ok(Response::new(AnswerReply {}))
+    }
+}
+
+/// Serve the bookings on `listener` until killed.
+pub async fn serve(listener: TcpListener, service: Service) -> Result<(), String> {
+    tonic::transport::Server::builder()
+        .add_service(BookingServer::new(service))
+        .serve_with_incoming(TcpListenerStream::new(listener))
+        .await
+        .map_err(|err| format!("The gRPC server stopped: {}", err))
+}
+
+/// Serve the bookings on the address of `grpc_args` until killed.
+pub fn run(grpc_args: GrpcArgs) {
+    let GrpcArgs { mut args, listen } = grpc_args;
+    progress::disable();
+    let config = match load_config(&mut args) {
+        Ok(config) => config,
+        Err(err_msg) => {
+            output::error(err_msg);
+            exit::fail(exit::Status::Invalid);
+            return;
+        }
+    };
+    let runtime = tokio::runtime::Runtime::new().expect("Failed to start the async runtime");
+    let outcome = runtime.block_on(async {
+        let listener = TcpListener::bind(listen)
+            .await
+            .map_err(|err| format!("Failed to listen on {}: {}", listen, err))?;
+        output::line(format_args!(
+            "Serving the booking gRPC service at {}",
+            listen
+        ));
+        serve(listener, Service::new(args, config)).await
+    });
+    if let Err(err_msg) = outcome {
+        output::error(err_msg);
+    }
+}
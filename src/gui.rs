@@ -0,0 +1,441 @@
+//! `thsr gui`: booking in a desktop window, for those who'd rather not use a terminal.
+//!
+//! Like `thsr tui`, the booking runs on its own thread through [`remote::spawn`];
+//! the window fills in the route, shows the trains and the security code, and
+//! sends the answers back.
+
+use eframe::egui::{self, Color32, ColorImage, RichText, TextureHandle, TextureOptions};
+
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+
+use crate::booking_flow::normalize_date;
+use crate::cli::{Args, GuiArgs};
+use crate::config::Config;
+use crate::confirm_train_flow::Train;
+use crate::exit;
+use crate::load_config;
+use crate::notify::{self, Event};
+use crate::output;
+use crate::progress;
+use crate::remote::{self, Update};
+use crate::result::BookingResult;
+use crate::schema::{STATIONS, Station, TIME_SLOTS};
+use crate::stage::Progress;
+
+/// Book in a window, with `gui_args` filling in the form.
+pub fn run(gui_args: GuiArgs) {
+    let GuiArgs { mut args } = gui_args;
+    progress::disable();
+    let config = match load_config(&mut args) {
+        Ok(config) => Arc::new(config),
+        Err(err_msg) => {
+            output::error(err_msg);
+            exit::fail(exit::Status::Invalid);
+            return;
+        }
+    };
+    let options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default().with_inner_size([560.0, 640.0]),
+        ..Default::default()
+    };
+    let app = App::new(args, config);
+    if let Err(err) = eframe::run_native("THSR", options, Box::new(|_| Ok(Box::new(app)))) {
+        output::error(err);
+    }
+}
+
+const CLASSES: [&str; 2] = ["Standard", "Business"];
+const SEATS: [&str; 3] = ["Any", "Window", "Aisle"];
+
+/// The choices of the form, as 0-based indexes into the lists.
+struct Form {
+    from: usize,
+    to: usize,
+    /// Empty for the latest bookable date
+    date: String,
+    time: usize,
+    adults: u8,
+    students: u8,
+    class_type: usize,
+    seat_prefer: usize,
+}
+
+impl Form {
+    fn new(args: &Args) -> Form {
+        Form {
+            from: args.from.unwrap_or(Station::Taipei).index(),
+            to: args.to.unwrap_or(Station::Zuoying).index(),
+            date: args.date.clone().unwrap_or_default(),
+            time: args.time.unwrap_or_default().index(),
+            adults: (args.adult_cnt)
+                .unwrap_or(if args.student_cnt.is_some() { 0 } else { 1 })
+                .min(10),
+            students: args.student_cnt.unwrap_or(0).min(10),
+            class_type: (args.class_type)
+                .filter(|&idx| idx < CLASSES.len())
+                .unwrap_or(0),
+            seat_prefer: (args.seat_prefer)
+                .filter(|&idx| idx < SEATS.len())
+                .unwrap_or(0),
+        }
+    }
+
+    /// `args` with the choices of the form, or what is wrong with them.
+    fn args(&self, args: &Args) -> Result<Args, String> {
+        if self.from == self.to {
+            return Err("The departure and arrival stations are the same".to_string());
+        }
+        let date = match self.date.trim() {
+            "" => None,
+            date => Some(
+                normalize_date(date)
+                    .ok_or_else(|| "Please enter a date like 2025/06/29".to_string())?,
+            ),
+        };
+        if self.adults + self.students == 0 && args.party.is_empty() {
+            return Err("Please book at least one ticket".to_string());
+        }
+        Ok(Args {
+            from: Station::from_index(self.from),
+            to: Station::from_index(self.to),
+            date,
+            time: TIME_SLOTS.get(self.time).copied(),
+            adult_cnt: Some(self.adults),
+            student_cnt: Some(self.students),
+            class_type: Some(self.class_type),
+            seat_prefer: Some(self.seat_prefer),
+            ..args.clone()
+        })
+    }
+}
+
+/// What the booking thread has told so far.
+#[derive(Default)]
+struct Status {
+    step: &'static str,
+    trains: Vec<Train>,
+    question: Option<String>,
+    captcha: Option<Vec<u8>>,
+    /// Counts the security code images, to tell a new one
+    captchas: usize,
+    outcome: Option<Result<BookingResult, String>>,
+}
+
+/// A booking running on its own thread.
+struct Booking {
+    status: Arc<Mutex<Status>>,
+    answers: Sender<String>,
+    answer: String,
+    texture: Option<(usize, TextureHandle)>,
+    notified: bool,
+}
+
+impl Booking {
+    fn start(args: Args, config: Arc<Config>, ctx: &egui::Context) -> Booking {
+        let status = Arc::new(Mutex::new(Status {
+            step: "search",
+            ..Default::default()
+        }));
+        let state = Arc::clone(&status);
+        let ctx = ctx.clone();
+        let answers = remote::spawn(remote::new_id(), args, config, move |update| {
+            let mut status = state.lock().unwrap();
+            match update {
+                Update::Reached(progress) => {
+                    status.step = match progress {
+                        Progress::Train(_) => "train",
+                        Progress::Ticket(_) => "ticket",
+                        Progress::Booked(_) => "booked",
+                    }
+                }
+                Update::Trains(trains) => status.trains = trains,
+                Update::Question(hint) => status.question = Some(hint.to_string()),
+                Update::Captcha(img_data) => {
+                    status.captcha = Some(img_data.to_vec());
+                    status.captchas += 1;
+                }
+                Update::Done(outcome) => status.outcome = Some(outcome.clone()),
+            }
+            ctx.request_repaint();
+        });
+        Booking {
+            status,
+            answers,
+            answer: String::new(),
+            texture: None,
+            notified: false,
+        }
+    }
+
+    fn send(&mut self, answer: String) {
+        let mut status = self.status.lock().unwrap();
+        status.question = None;
+        status.captcha = None;
+        self.answer.clear();
+        // A booking gone quiet has already told how it ended
+        let _ = self.answers.send(answer);
+    }
+}
+
+struct App {
+    args: Args,
+    config: Arc<Config>,
+    form: Form,
+    error: Option<String>,
+    booking: Option<Booking>,
+}
+
+impl App {
+    fn new(args: Args, config: Arc<Config>) -> App {
+        App {
+            form: Form::new(&args),
+            args,
+            config,
+            error: None,
+            booking: None,
+        }
+    }
+
+    fn form(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Book a ticket");
+        ui.add_space(8.0);
+        let form = &mut self.form;
+        egui::Grid::new("form")
+            .num_columns(2)
+            .spacing([16.0, 8.0])
+            .show(ui, |ui| {
+                ui.label("From");
+                egui::ComboBox::from_id_salt("from").show_index(
+                    ui,
+                    &mut form.from,
+                    STATIONS.len(),
+                    |idx| STATIONS[idx].name,
+                );
+                ui.end_row();
+
+                ui.label("To");
+                egui::ComboBox::from_id_salt("to").show_index(
+                    ui,
+                    &mut form.to,
+                    STATIONS.len(),
+                    |idx| STATIONS[idx].name,
+                );
+                ui.end_row();
+
+                ui.label("Date");
+                ui.add(egui::TextEdit::singleline(&mut form.date).hint_text("latest bookable"));
+                ui.end_row();
+
+                ui.label("Departure after");
+                egui::ComboBox::from_id_salt("time").show_index(
+                    ui,
+                    &mut form.time,
+                    TIME_SLOTS.len(),
+                    |idx| TIME_SLOTS[idx].to_string(),
+                );
+                ui.end_row();
+
+                if self.args.party.is_empty() {
+                    ui.label("Adults");
+                    ui.add(egui::DragValue::new(&mut form.adults).range(0..=10));
+                    ui.end_row();
+
+                    ui.label("Students");
+                    ui.add(egui::DragValue::new(&mut form.students).range(0..=10));
+                    ui.end_row();
+                } else {
+                    ui.label("Passengers");
+                    ui.label(self.args.passengers.join(", "));
+                    ui.end_row();
+                }
+
+                ui.label("Class");
+                ui.horizontal(|ui| {
+                    for (idx, class) in CLASSES.iter().enumerate() {
+                        ui.radio_value(&mut form.class_type, idx, *class);
+                    }
+                });
+                ui.end_row();
+
+                ui.label("Seat");
+                ui.horizontal(|ui| {
+                    for (idx, seat) in SEATS.iter().enumerate() {
+                        ui.radio_value(&mut form.seat_prefer, idx, *seat);
+                    }
+                });
+                ui.end_row();
+            });
+
+        ui.add_space(12.0);
+        if ui.button("Book").clicked() {
+            match self.form.args(&self.args) {
+                Ok(args) => {
+                    self.error = None;
+                    self.booking = Some(Booking::start(args, Arc::clone(&self.config), ui.ctx()));
+                }
+                Err(err_msg) => self.error = Some(err_msg),
+            }
+        }
+        if let Some(err_msg) = &self.error {
+            ui.colored_label(Color32::RED, err_msg);
+        }
+    }
+
+    fn booking(&mut self, ui: &mut egui::Ui) {
+        let Some(booking) = &mut self.booking else {
+            return;
+        };
+        let status = Arc::clone(&booking.status);
+        let status = status.lock().unwrap();
+
+        if let Some(outcome) = &status.outcome {
+            if !booking.notified {
+                booking.notified = true;
+                match outcome {
+                    Ok(result) => notify::notify(&self.config, &Event::Booked(result)),
+                    Err(err_msg) => notify::notify(&self.config, &Event::Failed(err_msg)),
+                }
+            }
+            match outcome {
+                Ok(result) => {
+                    ui.heading(format!("Booked, PNR {}", result.pnr));
+                    ui.add_space(8.0);
+                    ui.monospace(result.to_string());
+                }
+                Err(err_msg) => {
+                    ui.heading("The booking failed");
+                    ui.colored_label(Color32::RED, err_msg);
+                }
+            }
+            ui.add_space(12.0);
+            if ui.button("Book another").clicked() {
+                drop(status);
+                self.booking = None;
+            }
+            return;
+        }
+
+        ui.horizontal(|ui| {
+            ui.spinner();
+            ui.heading(match status.step {
+                "train" => "Picking the train",
+                "ticket" => "Entering the passengers",
+                "booked" => "Booked",
+                _ => "Searching",
+            });
+        });
+        ui.add_space(8.0);
+
+        let mut answer = None;
+        if let Some(img_data) = &status.captcha {
+            let texture = match &booking.texture {
+                Some((count, texture)) if *count == status.captchas => texture.clone(),
+                _ => {
+                    let texture = ui.ctx().load_texture(
+                        "captcha",
+                        captcha_image(img_data),
+                        TextureOptions::default(),
+                    );
+                    booking.texture = Some((status.captchas, texture.clone()));
+                    texture
+                }
+            };
+            ui.label("Type the security code in the image:");
+            ui.add(egui::Image::new(&texture).fit_to_exact_size(texture.size_vec2() * 2.0));
+            ui.horizontal(|ui| {
+                let input = ui.text_edit_singleline(&mut booking.answer);
+                input.request_focus();
+                let entered = input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                if ui.button("Send").clicked() || entered {
+                    answer = Some(booking.answer.clone());
+                }
+                if ui.button("New image").clicked() {
+                    answer = Some("r".to_string());
+                }
+            });
+        } else if let Some(hint) = &status.question {
+            if status.step == "train" && !status.trains.is_empty() {
+                ui.label("Pick a train:");
+                answer = trains(ui, &status.trains);
+            } else {
+                ui.label(hint);
+                ui.horizontal(|ui| {
+                    let input = ui.text_edit_singleline(&mut booking.answer);
+                    let entered =
+                        input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    if ui.button("Send").clicked() || entered {
+                        answer = Some(booking.answer.clone());
+                    }
+                });
+            }
+        } else if !status.trains.is_empty() {
+            trains(ui, &status.trains);
+        }
+
+        drop(status);
+        if let Some(answer) = answer {
+            booking.send(answer);
+        }
+    }
+}
+
+/// The train list, returning the answer picking a train when its button is clicked.
+fn trains(ui: &mut egui::Ui, trains: &[Train]) -> Option<String> {
+    let mut picked = None;
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        egui::Grid::new("trains")
+            .striped(true)
+            .spacing([12.0, 6.0])
+            .show(ui, |ui| {
+                for header in [
+                    "Train",
+                    "Depart",
+                    "Arrive",
+                    "Time",
+                    "Seats",
+                    "Discounts",
+                    "",
+                ] {
+                    ui.label(RichText::new(header).strong());
+                }
+                ui.end_row();
+                for (idx, train) in trains.iter().enumerate() {
+                    ui.label(train.id().to_string());
+                    ui.label(train.depart().format("%H:%M").to_string());
+                    ui.label(train.arrive().format("%H:%M").to_string());
+                    ui.label(train.travel_time());
+                    ui.label(train.seats().hint().unwrap_or_default());
+                    ui.label(train.discount_info());
+                    if ui.button("Book").clicked() {
+                        picked = Some((idx + 1).to_string());
+                    }
+                    ui.end_row();
+                }
+            });
+    });
+    picked
+}
+
+/// The security code image for a texture, or a blank one if it can't be read.
+fn captcha_image(img_data: &[u8]) -> ColorImage {
+    match image::load_from_memory(img_data) {
+        Ok(image) => {
+            let image = image.to_rgba8();
+            ColorImage::from_rgba_unmultiplied(
+                [image.width() as usize, image.height() as usize],
+                image.as_raw(),
+            )
+        }
+        Err(_) => ColorImage::new([1, 1], vec![Color32::WHITE]),
+    }
+}
+
+impl eframe::App for App {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        egui::CentralPanel::default().show(ctx, |ui| match self.booking {
+            Some(_) => self.booking(ui),
+            None => self.form(ui),
+        });
+    }
+}
@@ -0,0 +1,74 @@
+//! The bookings finished by `thsr daemon` and those made on the command line, one JSON
+//! line each, kept for looking back at what was booked and what was given up, and for
+//! `thsr remind`.
+
+use chrono::{Local, SecondsFormat};
+use serde::{Deserialize, Serialize};
+
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use crate::result::BookingResult;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Entry {
+    /// The job that made the booking
+    pub job: String,
+    pub finished_at: String,
+    /// Tries made, the last one included
+    pub attempts: u32,
+    pub result: Option<BookingResult>,
+    pub error: Option<String>,
+}
+
+impl Entry {
+    pub fn new(job: &str, attempts: u32, outcome: Result<&BookingResult, String>) -> Entry {
+        let (result, error) = match outcome {
+            Ok(result) => (Some(result.clone()), None),
+            Err(err_msg) => (None, Some(err_msg)),
+        };
+        Entry {
+            job: job.to_string(),
+            finished_at: Local::now().to_rfc3339_opts(SecondsFormat::Secs, false),
+            attempts,
+            result,
+            error,
+        }
+    }
+}
+
+/// `<local data dir>/thsr/history.jsonl`, e.g. `~/.local/share/thsr/history.jsonl` on Linux.
+pub fn default_path() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|dir| dir.join("thsr").join("history.jsonl"))
+}
+
+pub fn append(path: &Path, entry: &Entry) -> Result<(), String> {
+    let failed = |err: std::io::Error| format!("Failed to record to {}: {}", path.display(), err);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(failed)?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(failed)?;
+    writeln!(file, "{}", serde_json::to_string(entry).unwrap()).map_err(failed)
+}
+
+/// The entries recorded in `path`, oldest first. A missing file has none.
+pub fn load(path: &Path) -> Result<Vec<Entry>, String> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(format!("Failed to read {}: {}", path.display(), err)),
+    };
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            serde_json::from_str(line)
+                .map_err(|err| format!("Invalid history entry in {}: {}", path.display(), err))
+        })
+        .collect()
+}
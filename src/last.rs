@@ -0,0 +1,102 @@
+//! The options of the last booking, remembered so `--last` books the same trip
+//! again without answering everything anew.
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::booking_flow::BookingPayload;
+use crate::cli::Args;
+use crate::schema::{Station, TicketType, TimeSlot};
+
+/// The route, time, tickets and seat of a booking, as saved to the last booking file.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LastBooking {
+    pub from: Station,
+    pub to: Station,
+    pub time: Option<TimeSlot>,
+    pub adult_cnt: u8,
+    pub student_cnt: u8,
+    /// The passenger profiles booked for, instead of the ticket counts
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub passengers: Vec<String>,
+    pub seat_prefer: usize,
+    pub class_type: usize,
+}
+
+impl LastBooking {
+    /// `<local data dir>/thsr/last.json`, e.g. `~/.local/share/thsr/last.json` on Linux.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_local_dir().map(|dir| dir.join("thsr").join("last.json"))
+    }
+
+    /// The options `payload` searched with, for the passengers of `args`.
+    pub fn new(payload: &BookingPayload, args: &Args) -> LastBooking {
+        let count = |ticket_type: TicketType| {
+            (payload.ticket_counts().into_iter())
+                .find(|(counted, _)| *counted == ticket_type)
+                .map_or(0, |(_, count)| count)
+        };
+        LastBooking {
+            from: payload.start_station,
+            to: payload.dest_station,
+            time: Some(payload.outbound_time),
+            // A group split into several bookings searches for part of it at a time
+            adult_cnt: args.adult_cnt.unwrap_or_else(|| count(TicketType::Adult)),
+            student_cnt: args
+                .student_cnt
+                .unwrap_or_else(|| count(TicketType::College)),
+            passengers: args.passengers.clone(),
+            seat_prefer: payload.seat_prefer,
+            class_type: payload.class_type.into(),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<LastBooking, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|err| format!("No last booking in {}: {}", path.display(), err))?;
+        serde_json::from_str(&content)
+            .map_err(|err| format!("Invalid last booking {}: {}", path.display(), err))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|err| format!("Failed to create {}: {}", dir.display(), err))?;
+        }
+        let content = serde_json::to_string_pretty(self).unwrap();
+        fs::write(path, content)
+            .map_err(|err| format!("Failed to write last booking {}: {}", path.display(), err))
+    }
+
+    /// Fill in the options not given in `args`. Tickets are taken as a whole, so
+    /// giving any of the counts or passengers leaves out the remembered ones.
+    pub fn apply(&self, args: &mut Args) {
+        args.from = args.from.or(Some(self.from));
+        args.to = args.to.or(Some(self.to));
+        args.time = args.time.or(self.time);
+        args.seat_prefer = args.seat_prefer.or(Some(self.seat_prefer));
+        args.class_type = args.class_type.or(Some(self.class_type));
+        if args.adult_cnt.is_none() && args.student_cnt.is_none() && args.passengers.is_empty() {
+            match self.passengers.is_empty() {
+                true => {
+                    args.adult_cnt = Some(self.adult_cnt);
+                    args.student_cnt = Some(self.student_cnt);
+                }
+                false => args.passengers = self.passengers.clone(),
+            }
+        }
+    }
+}
+
+/// Remember the options `payload` searched with to the last booking file, for `--last`.
+pub fn remember(payload: &BookingPayload, args: &Args) {
+    let Some(path) = LastBooking::default_path() else {
+        return;
+    };
+    if let Err(err_msg) = LastBooking::new(payload, args).save(&path) {
+        warn!("{}", err_msg);
+    }
+}
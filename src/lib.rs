@@ -1,32 +1,100 @@
+#[cfg(not(any(feature = "blocking", feature = "async")))]
+compile_error!("thsr needs a client to book with, enable the `blocking` or the `async` feature");
+
+// The site, its sessions and pages come from thsr-core, under their old paths
+#[cfg(feature = "blocking")]
+pub use thsr_core::har;
+pub use thsr_core::page::{is_session_expired, maintenance_end, parse_error, parse_maintenance};
+pub use thsr_core::{error, http, i18n, redact, selectors, site};
+// And so do the booking flows, which thsr only wires up to the command line
+pub use thsr_core::booking::Checkpoint;
+pub use thsr_core::{
+    alert, backend, booking, booking_flow, confirm_ticket_flow, confirm_train_flow, fare, schema,
+    stage, summary, tgo, time,
+};
+
+pub mod captcha;
+pub mod cassette;
 pub mod cli;
-pub mod schema;
+pub mod config;
+#[cfg(feature = "blocking")]
+pub mod daemon;
+#[cfg(feature = "blocking")]
+pub mod doctor;
+pub mod events;
+pub mod exit;
+pub mod export;
+pub mod frontend;
+pub mod group;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+#[cfg(feature = "gui")]
+pub mod gui;
+pub mod history;
+pub mod last;
+pub mod log;
+pub mod man;
+pub mod metrics;
+pub mod notify;
+pub mod ntp;
+pub mod output;
+pub mod passenger;
+pub mod pick;
+pub mod plugin;
+pub mod preset;
+pub mod progress;
+pub mod prompt;
+pub mod remind;
+pub mod remote;
+pub mod result;
+#[cfg(feature = "blocking")]
+pub mod resume;
+#[cfg(feature = "blocking")]
+pub mod schedule;
+pub mod script;
+pub mod select;
+#[cfg(feature = "blocking")]
+pub mod serve;
+pub mod station;
+#[cfg(feature = "blocking")]
+pub mod trains;
+#[cfg(feature = "tui")]
+pub mod tui;
+#[cfg(feature = "blocking")]
+pub mod watch;
 
-use bytes::Bytes;
-use reqwest::blocking::Client;
 use reqwest::header::{HeaderMap, HeaderValue};
-use scraper::{Html, Selector};
-use serde::{Deserialize, Serialize};
-
-use std::collections::HashMap;
-use std::fmt::Debug;
-use std::fs;
-use std::process::Command;
-use std::str::FromStr;
 
 use crate::cli::Args;
-use crate::schema::{STATION_MAP, TIME_TABLE, TicketType};
-
-static BASE_URL: &str = "https://irs.thsrc.com.tw";
-static BOOKING_PAGE_URL: &str = "https://irs.thsrc.com.tw/IMINT/?locale=tw";
-static SUBMIT_FORM_URL: &str = "https://irs.thsrc.com.tw/IMINT/;jsessionid={}?wicket:interface=:0:BookingS1Form::IFormSubmitListener";
-static CONFIRM_TRAIN_URL: &str =
-    "https://irs.thsrc.com.tw/IMINT/?wicket:interface=:1:BookingS2Form::IFormSubmitListener";
-static CONFIRM_TICKET_URL: &str =
-    "https://irs.thsrc.com.tw/IMINT/?wicket:interface=:2:BookingS3Form::IFormSubmitListener";
-
-fn get_header() -> HeaderMap {
+use crate::config::{Config, HeadersConfig};
+use crate::error::Error;
+use crate::frontend::Frontend;
+use crate::http::Transport;
+use crate::last::LastBooking;
+use crate::prompt::Prompter;
+use crate::result::BookingResult;
+use crate::site::Site;
+
+// Booking from the command line, resuming and the frontends use the blocking client
+#[cfg(feature = "blocking")]
+use {
+    crate::booking_flow::BookingPayload,
+    crate::cassette::{CassetteMode, Recorder, Replayer},
+    crate::history::Entry,
+    crate::http::HttpClient,
+    crate::i18n::tr,
+    crate::notify::Event,
+    crate::prompt::{StdinPrompter, StreamPrompter},
+    crate::result::show_result,
+    crate::resume::BookingState,
+    crate::stage::Progress,
+    std::path::PathBuf,
+    tracing::{info, warn},
+};
+
+/// The headers of a Firefox visit to `site`, with the changes from the config applied.
+fn get_header(site: &Site, changes: &HeadersConfig) -> HeaderMap {
     let mut headers = HeaderMap::new();
-    headers.insert("Host", HeaderValue::from_static("irs.thsrc.com.tw"));
     headers.insert(
         "User-Agent",
         HeaderValue::from_static(
@@ -41,994 +109,338 @@ fn get_header() -> HeaderMap {
     );
     headers.insert(
         "Accept-Language",
-        HeaderValue::from_static("zh-TW,zh;q=0.8,en-US;q=0.5,en;q=0.3"),
+        HeaderValue::from_static(site.locale.accept_language()),
     );
     headers.insert("Accept-Encoding", HeaderValue::from_static("deflate, br"));
     headers.insert("Connection", HeaderValue::from_static("keep-alive"));
     headers.insert("Upgrade-Insecure-Requests", HeaderValue::from_static("1"));
-    headers.insert(
-        "Referer",
-        HeaderValue::from_static("https://irs.thsrc.com.tw/IMINT/"),
-    );
+    if let Ok(referer) = HeaderValue::from_str(&site.url("/IMINT/")) {
+        headers.insert("Referer", referer);
+    }
     headers.insert("Sec-Fetch-Site", HeaderValue::from_static("same-origin"));
     headers.insert("Sec-Fetch-Mode", HeaderValue::from_static("no-cors"));
+    changes.apply(&mut headers);
     headers
 }
 
-fn get_input<T: FromStr>(hint: &str, default: T) -> T {
-    println!("{hint}");
-    let mut input = String::new();
-    std::io::stdin().read_line(&mut input).unwrap_or_default();
-    let input = input.trim().to_string();
-    if input.is_empty() {
-        return default;
+#[cfg(feature = "blocking")]
+fn build_client(config: &Config) -> HttpClient {
+    let client = HttpClient::new(
+        get_header(&config.site, &config.headers),
+        config.retry.clone(),
+    );
+    let client = match &config.proxy {
+        Some(proxy) => client.proxy(proxy.clone()),
+        None => client,
+    };
+    if let Some(path) = &config.cookie_jar
+        && let Err(err_msg) = client.load_cookies(path)
+    {
+        warn!("{}, starting without cookies", err_msg);
     }
-    input.parse().unwrap_or(default)
+    client
 }
 
-pub fn run(args: Args) {
-    let policy = reqwest::redirect::Policy::limited(20);
-    let client = Client::builder()
-        .redirect(policy)
-        .default_headers(get_header())
-        .cookie_store(true)
-        .timeout(std::time::Duration::from_secs(60))
-        .build()
-        .unwrap();
-
-    // First page
-    let resp = match booking_flow::run_flow(&client, &args) {
-        Ok(resp) => resp,
-        Err(err_msg) => {
-            println!("Error: {}", err_msg);
-            return;
-        }
-    };
-
-    // Second Page
-    let resp = match confirm_train_flow::run_flow(resp, &client) {
-        Ok(resp) => resp,
-        Err(err_msg) => {
-            println!("Error: {}", err_msg);
-            return;
-        }
-    };
-
-    // Final page
-    let resp = match confirm_ticket_flow::run_flow(&resp, &client, &args) {
-        Ok(resp) => resp,
-        Err(err_msg) => {
-            println!("Error: {}", err_msg);
-            return;
-        }
-    };
-
-    // Show the final booking result
-    show_result(&resp);
+/// An async client sending what [`HttpClient`](http::HttpClient) would, through the
+/// proxy of the config. The cookie jar is left to the caller.
+#[cfg(feature = "async")]
+pub fn build_async_client(config: &Config) -> http::AsyncHttpClient {
+    http::AsyncHttpClient::new(
+        get_header(&config.site, &config.headers),
+        config.retry.clone(),
+        config.proxy.clone(),
+    )
 }
 
-pub fn parse_error(page: &Html) -> Option<String> {
-    let err_selector = Selector::parse("span.feedbackPanelERROR").unwrap();
-    let errors: Vec<String> = page
-        .select(&err_selector)
-        .filter_map(|element| element.text().next().map(|text| text.trim().to_string()))
-        .collect();
-    if errors.is_empty() {
-        None
-    } else {
-        Some(errors.join("\n"))
+/// Keep the cookies of `client` in the cookie jar of the config, if there is one.
+#[cfg(feature = "blocking")]
+fn save_cookies(client: &HttpClient, config: &Config) {
+    if let Some(path) = &config.cookie_jar
+        && let Err(err_msg) = client.save_cookies(path)
+    {
+        warn!("{}", err_msg);
     }
 }
 
-// First page: Booking Flow
-pub mod booking_flow {
-    use super::*;
-
-    pub fn run_flow(client: &Client, args: &Args) -> Result<Html, String> {
-        println!("Requesting booking page...");
-        let response = client.get(BOOKING_PAGE_URL).send().unwrap();
-
-        // Parse jsession id
-        let jid = response
-            .cookies()
-            .find(|cookie| cookie.name() == "JSESSIONID")
-            .map(|cookie| cookie.value().to_string())
-            .unwrap();
-
-        // Parse to HTML object
-        let body = response.text().unwrap(); // Get the response body as a string
-        let document = Html::parse_document(&body);
-
-        // Request security code image
-        let sec_code_img_url = parse_security_code_img_url(&document);
-        let img_resp = client.get(&sec_code_img_url).send().unwrap();
-
-        // Making selections
-        let mut payload = BookingPayload::default();
-        payload.search_by = parse_search_by(&document);
-        payload.types_of_trip = parse_types_of_trip_value(&document);
-        payload.select_start_station(&args.from);
-        payload.select_dest_station(&args.to);
-        
-        let (start_date, end_date) = parse_avail_start_end_date(&document);
-
-        // MODIFIED: If no date is provided via CLI, set the default to the latest possible date (end_date).
-        if args.date.is_none() {
-            payload.outbound_date = end_date.clone();
-        }
-        
-        payload.select_date(&start_date, &end_date, &args.date);
-
-        payload.select_time(&args.time);
-        if args.adult_cnt.is_none() && args.student_cnt.is_none() {
-            payload.select_ticket_num(TicketType::Adult, &None);
-        }
-        if args.adult_cnt.is_some() {
-            payload.select_ticket_num(TicketType::Adult, &args.adult_cnt);
-        }
-        if args.student_cnt.is_some() {
-            payload.select_ticket_num(TicketType::College, &args.student_cnt);
-        }
-        payload.select_seat_prefer(&args.seat_prefer);
-        payload.select_class_type(&args.class_type);
-        payload.input_security_code(img_resp.bytes().unwrap());
-
-        // Make the booking request
-        let resp = client
-            .post(SUBMIT_FORM_URL.replace("{}", &jid))
-            .header("Content-Type", "application/x-www-form-urlencoded")
-            .body(serde_urlencoded::to_string(&payload).unwrap())
-            .send()
-            .unwrap();
-
-        // Parse to HTML object
-        let resp_html = Html::parse_document(&resp.text().unwrap());
-        if let Some(err_msg) = parse_error(&resp_html) {
-            return Err(err_msg);
-        }
-        Ok(resp_html)
+/// Load the config file, with the command line options taking precedence.
+/// The passengers picked with `--passengers` are looked up into `args`, and the
+/// contact details of the config file fill in those not given. The options are
+/// then checked, see [`Args::validate`].
+pub fn load_config(args: &mut Args) -> Result<Config, String> {
+    let mut config = Config::load(args.config.as_deref(), args.config_key.as_deref())?;
+    if args.phone.is_none() {
+        args.phone = config.contact.phone.clone();
     }
-
-    fn parse_avail_start_end_date(page: &Html) -> (String, String) {
-        let selector = Selector::parse("#toTimeInputField").unwrap();
-        let elem = page.select(&selector).next().unwrap();
-        let end_date = elem.attr("limit").unwrap();
-        let start_date = elem.attr("date").unwrap();
-        (start_date.to_string(), end_date.to_string())
+    if args.email.is_none() {
+        args.email = config.contact.email.clone();
     }
-
-    fn parse_types_of_trip_value(page: &Html) -> u8 {
-        let selector = Selector::parse("#BookingS1Form_tripCon_typesoftrip").unwrap();
-        let elem = page.select(&selector).next().unwrap();
-        let selected_selector = Selector::parse("[selected='selected']").unwrap();
-        let trip_type = elem.select(&selected_selector).next().unwrap();
-        trip_type.attr("value").unwrap().parse().unwrap()
+    if let Some(name) = args.preset.clone() {
+        preset::apply(&name, args, &config)?;
     }
-
-    fn parse_search_by(page: &Html) -> String {
-        let candidates_selector = Selector::parse("input[name='bookingMethod']").unwrap();
-        let candidates = page.select(&candidates_selector);
-        let tag = candidates
-            .filter(|cand| cand.value().attr("checked").is_some())
-            .next()
-            .unwrap();
-        tag.value().attr("value").unwrap().to_string()
+    if args.last {
+        let path = LastBooking::default_path()
+            .ok_or("No data directory for the last booking, give the options instead")?;
+        LastBooking::load(&path)?.apply(args);
     }
-
-    fn parse_security_code_img_url(page: &Html) -> String {
-        let selector = Selector::parse("#BookingS1Form_homeCaptcha_passCode").unwrap();
-        let elem = page.select(&selector).next().unwrap();
-        let img_url = elem.attr("src").unwrap();
-        format!("{}{}", BASE_URL, img_url)
-    }
-
-    #[derive(Serialize, Deserialize, Debug)]
-    pub struct BookingPayload {
-        #[serde(rename(serialize = "selectStartStation"))]
-        pub start_station: u8,
-
-        #[serde(rename(serialize = "selectDestinationStation"))]
-        pub dest_station: u8,
-
-        #[serde(rename(serialize = "bookingMethod"))]
-        pub search_by: String,
-
-        #[serde(rename(serialize = "tripCon:typesoftrip"), default)]
-        pub types_of_trip: u8, // 0: one way, 1: round trip
-
-        #[serde(rename(serialize = "toTimeInputField"))]
-        pub outbound_date: String,
-
-        #[serde(rename(serialize = "toTimeTable"))]
-        pub outbound_time: String,
-
-        #[serde(rename(serialize = "homeCaptcha:securityCode"))]
-        pub security_code: String,
-
-        #[serde(rename(serialize = "seatCon:seatRadioGroup"))]
-        pub seat_prefer: usize, // 0: any, 1: window, 2: aisle
-
-        #[serde(rename(serialize = "BookingS1Form:hf:0"), default)]
-        pub form_mark: String,
-
-        #[serde(rename(serialize = "trainCon:trainRadioGroup"), default)]
-        pub class_type: u8, // 0: standard, 1: business
-
-        #[serde(rename(serialize = "backTimeInputField"))]
-        pub inbound_date: Option<String>,
-
-        #[serde(rename(serialize = "backTimeTable"))]
-        pub inbound_time: Option<String>,
-
-        #[serde(rename(serialize = "toTrainIDInputField"), default)]
-        pub to_train_id: Option<u8>,
-
-        #[serde(rename(serialize = "backTrainIDInputField"), default)]
-        pub back_train_id: Option<u8>,
-
-        #[serde(
-            rename(serialize = "ticketPanel:rows:0:ticketAmount"),
-            default = "default_adult_ticket_num"
-        )]
-        pub adult_ticket_num: String,
-
-        #[serde(
-            rename(serialize = "ticketPanel:rows:1:ticketAmount"),
-            default = "default_child_ticket_num"
-        )]
-        pub child_ticket_num: String,
-
-        #[serde(
-            rename(serialize = "ticketPanel:rows:2:ticketAmount"),
-            default = "default_disabled_ticket_num"
-        )]
-        pub disabled_ticket_num: String,
-
-        #[serde(
-            rename(serialize = "ticketPanel:rows:3:ticketAmount"),
-            default = "default_elder_ticket_num"
-        )]
-        pub elder_ticket_num: String,
-
-        #[serde(
-            rename(serialize = "ticketPanel:rows:4:ticketAmount"),
-            default = "default_college_ticket_num"
-        )]
-        pub college_ticket_num: String,
+    passenger::pick(args, &config)?;
+    passenger::read_ids(args)?;
+    args.validate()?;
+    if let Some(solver) = args.captcha_solver {
+        config.captcha.solver = solver;
     }
-
-    pub fn default_adult_ticket_num() -> String {
-        "1F".to_string()
+    if let Some(model) = &args.captcha_model {
+        config.captcha.model = Some(model.clone());
     }
-
-    pub fn default_child_ticket_num() -> String {
-        "0H".to_string()
+    if let Some(api_key) = &args.captcha_api_key {
+        config.captcha.api_key = Some(api_key.clone());
     }
-
-    pub fn default_disabled_ticket_num() -> String {
-        "0W".to_string()
+    if let Some(out) = &args.captcha_out {
+        config.captcha.out = Some(out.clone());
     }
-
-    pub fn default_elder_ticket_num() -> String {
-        "0E".to_string()
+    if let Some(input) = &args.captcha_in {
+        config.captcha.input = Some(input.clone());
     }
-
-    pub fn default_college_ticket_num() -> String {
-        "0P".to_string()
+    if let Some(addr) = args.captcha_http {
+        config.captcha.http = Some(addr);
     }
-
-    impl Default for BookingPayload {
-        fn default() -> Self {
-            BookingPayload {
-                // MODIFIED: Default start station to Taipei (2)
-                start_station: 2,
-                // MODIFIED: Default destination station to Zuoying (12)
-                dest_station: 12,
-                search_by: "1".to_string(),
-                types_of_trip: 0,
-                // NOTE: This date is a temporary placeholder before scraping the real end_date in run_flow
-                outbound_date: "2023/10/01".to_string(), 
-                outbound_time: "08:00".to_string(),
-                security_code: "1234".to_string(),
-                seat_prefer: 0,
-                form_mark: "".to_string(),
-                class_type: 0,
-                inbound_date: None,
-                inbound_time: None,
-                to_train_id: None,
-                back_train_id: None,
-                adult_ticket_num: default_adult_ticket_num(),
-                child_ticket_num: default_child_ticket_num(),
-                disabled_ticket_num: default_disabled_ticket_num(),
-                elder_ticket_num: default_elder_ticket_num(),
-                college_ticket_num: default_college_ticket_num(),
-            }
-        }
+    if let Some(proxy) = &args.proxy {
+        config.proxy = Some(proxy.clone());
     }
-
-    impl BookingPayload {
-        pub fn select_start_station(&mut self, from: &Option<usize>) {
-            if let Some(from) = from {
-                self.start_station = from.clone() as u8;
-                return;
-            }
-
-            for (i, station) in STATION_MAP.iter().enumerate() {
-                println!("{}: {:?}", i + 1, station);
-            }
-            // MODIFIED: Interactive default to 2 (Taipei)
-            let input = get_input("Please select start station (default: 2):", 2);
-            if input > 0 && input <= STATION_MAP.len() {
-                self.start_station = input as u8;
-            } else {
-                println!("Invalid input, defaulting to Taipei (2).");
-                self.start_station = 2;
-            }
-        }
-
-        pub fn select_dest_station(&mut self, to: &Option<usize>) {
-            if let Some(to) = to {
-                self.dest_station = to.clone() as u8;
-                return;
-            }
-
-            for (i, station) in STATION_MAP.iter().enumerate() {
-                println!("{}: {:?}", i + 1, station);
-            }
-            // MODIFIED: Interactive default to 12 (Zuoying)
-            let input = get_input("Please select destination station (default: 12):", 12);
-            if input > 0 && input <= STATION_MAP.len() {
-                self.dest_station = input as u8;
-            } else {
-                println!("Invalid input, defaulting to Zuoying (12).");
-                self.dest_station = 12;
-            }
-        }
-
-        pub fn input_security_code(&mut self, img_data: Bytes) {
-            println!("Input security code:");
-            show_image(&img_data);
-            // Read the security code from the user
-            let mut input = String::new();
-            std::io::stdin()
-                .read_line(&mut input)
-                .expect("Failed to read input");
-            self.security_code = input.trim().to_string();
-        }
-
-        pub fn select_date(
-            &mut self,
-            start_date: &String,
-            end_date: &String,
-            date: &Option<String>,
-        ) {
-            let input = match date.clone() {
-                Some(date) => date,
-                None => get_input(
-                    // MODIFIED: Prompt suggests and uses end_date as the default value.
-                    &format!(
-                        "Please select a date between {} and {} (default to latest: {}):",
-                        start_date, end_date, end_date 
-                    ),
-                    end_date.clone(), // This is the new default value passed to get_input
-                ),
-            };
-
-            let input = match normalize_date(&input) {
-                Some(date) => date,
-                None => {
-                    // MODIFIED: Default to end_date on format error
-                    println!("Invalid date format, defaulting to latest date: {}", end_date);
-                    end_date.clone() 
-                }
-            };
-
-            if input.is_empty() {
-                // MODIFIED: Ensure input defaults to end_date if empty
-                self.outbound_date = end_date.clone(); 
-                return;
-            }
-
-            if input.ge(start_date) && input.le(end_date) {
-                self.outbound_date = input;
-            } else {
-                // MODIFIED: Default to end_date on range error
-                println!("Invalid date or outside booking range, defaulting to latest date: {}", end_date);
-                self.outbound_date = end_date.to_string(); 
-            }
-        }
-
-        pub fn select_time(&mut self, time: &Option<usize>) {
-            let opt = match time.clone() {
-                Some(time) => time,
-                None => {
-                    for (idx, &t_str) in TIME_TABLE.iter().enumerate() {
-                        let mut t_int = t_str[..t_str.len() - 1].parse::<u16>().unwrap();
-                        if t_str.ends_with('A') && (t_int / 100) == 12 {
-                            t_int %= 1200;
-                        } else if t_int != 1230 && t_str.ends_with('P') {
-                            t_int += 1200;
-                        }
-                        let formatted_time = format!("{:04}", t_int);
-                        println!(
-                            "{}. {}:{}",
-                            idx + 1,
-                            &formatted_time[..formatted_time.len() - 2],
-                            &formatted_time[formatted_time.len() - 2..]
-                        );
-                    }
-                    get_input("Select departure time (default: 10):", 10)
-                }
-            };
-
-            if opt > TIME_TABLE.len() {
-                println!("Invalid input, defaulting to 10.");
-                self.outbound_time = TIME_TABLE[9].to_string();
-                return;
-            }
-
-            self.outbound_time = TIME_TABLE[opt - 1].to_string();
-        }
-
-        pub fn select_ticket_num(&mut self, ticket_type: TicketType, val: &Option<u8>) {
-            let mut val = match val.clone() {
-                Some(val) => val,
-                None => get_input(
-                    &format!(
-                        "Please select the number (0~10) of tickets for {:?} (default: 1)",
-                        ticket_type
-                    ),
-                    1,
-                ),
-            };
-
-            if val > 10 {
-                println!("Invalid input, defaulting to 1.");
-                val = 1;
-            }
-
-            let val = format!("{}{}", val, (ticket_type.clone() as u8) as char);
-            match ticket_type {
-                TicketType::Adult => self.adult_ticket_num = val,
-                TicketType::Child => self.child_ticket_num = val,
-                TicketType::Disabled => self.disabled_ticket_num = val,
-                TicketType::Elder => self.elder_ticket_num = val,
-                TicketType::College => self.college_ticket_num = val,
-            }
-        }
-
-        pub fn select_seat_prefer(&mut self, prefer: &Option<usize>) {
-            let input = match prefer.clone() {
-                Some(prefer) => prefer,
-                None => get_input(
-                    "Please select seat preference (0: any, 1: window, 2: aisle) (default: 0):",
-                    0,
-                ),
-            };
-
-            if input > 2 {
-                println!("Invalid input, defaulting to any.");
-                self.seat_prefer = 0;
-            } else {
-                self.seat_prefer = input;
-            }
-        }
-
-        pub fn select_class_type(&mut self, class_type: &Option<usize>) {
-            let input = match class_type.clone() {
-                Some(class_type) => class_type,
-                None => get_input(
-                    "Please select class type (0: standard, 1: business) (default: 0):",
-                    0,
-                ),
-            };
-
-            if input > 1 {
-                println!("Invalid input, defaulting to standard.");
-                self.class_type = 0;
-            } else {
-                self.class_type = input as u8;
-            }
-        }
+    if let Some(backend) = args.backend {
+        config.backend = backend;
     }
-
-    fn normalize_date(input: &str) -> Option<String> {
-        let parts: Vec<&str> = input.split('/').collect();
-        if parts.len() != 3 {
-            return None;
-        }
-
-        let year = parts[0].parse::<u16>().ok()?;
-        let month = parts[1].parse::<u8>().ok()?;
-        let day = parts[2].parse::<u8>().ok()?;
-
-        if year >= 1000 && month >= 1 && month <= 12 && day >= 1 && day <= 31 {
-            Some(format!("{:04}/{:02}/{:02}", year, month, day))
-        } else {
-            None
-        }
+    if let Some(path) = &args.cookie_jar {
+        config.cookie_jar = Some(path.clone());
     }
-
-    fn show_image(img_data: &[u8]) {
-        // Save the image to a file
-        let file_name = "tmp_code.jpg";
-        fs::write(file_name, img_data).expect("Failed to write image file");
-
-        // Open the image using the default image viewer
-        if cfg!(target_os = "windows") {
-            Command::new("cmd")
-                .args(&["/C", file_name])
-                .spawn()
-                .expect("Failed to open image");
-        } else if cfg!(target_os = "macos") {
-            Command::new("open")
-                .arg(file_name)
-                .spawn()
-                .expect("Failed to open image");
-        } else if cfg!(target_os = "linux") {
-            Command::new("xdg-open")
-                .arg(file_name)
-                .spawn()
-                .expect("Failed to open image");
-        } else {
-            println!("Please open the image manually: {}", file_name);
-        }
+    if let Some(command) = &args.on_success {
+        config.on_success = Some(command.clone());
     }
-}
-
-// Second page: Confirm Train Flow
-pub mod confirm_train_flow {
-    use super::*;
-
-    pub fn run_flow(document: Html, client: &Client) -> Result<Html, String> {
-        // Parse alerts
-        let alerts = parse_alert_body(&document);
-        println!("{}", alerts.join("\n"));
-
-        // Parse available trains
-        let trains = parse_trains(&document);
-        let mut payload = ConfirmTrainPayload::default();
-        payload.select_available_trains(trains.as_slice());
-
-        let resp = client
-            .post(CONFIRM_TRAIN_URL)
-            .header("Content-Type", "application/x-www-form-urlencoded")
-            .body(serde_urlencoded::to_string(&payload).unwrap())
-            .send()
-            .unwrap();
-
-        // Parse to HTML object
-        let resp_html = Html::parse_document(&resp.text().unwrap());
-        if let Some(err_msg) = parse_error(&resp_html) {
-            return Err(err_msg);
-        }
-        Ok(resp_html)
+    if let Some(command) = &args.on_confirm {
+        config.on_confirm = Some(command.clone());
     }
-
-    fn parse_alert_body(document: &Html) -> Vec<String> {
-        let li_selector = Selector::parse("ul.alert-body > li").unwrap();
-        document
-            .select(&li_selector)
-            .map(|tag| tag.text().collect::<Vec<_>>().join("").trim().to_string())
-            .collect()
+    if let Ok(base_url) = std::env::var("THSR_BASE_URL") {
+        config.site.base_url = Site::new(base_url).base_url;
     }
-
-    fn parse_trains(document: &Html) -> Vec<Train> {
-        let selector = Selector::parse("label.result-item").unwrap(); // Adjust the selector based on `self.cond.from_html`
-        let avail = document.select(&selector);
-
-        avail
-            .map(|element| {
-                let tag_selector = Selector::parse("input").unwrap();
-                let elem = element.select(&tag_selector).next().unwrap();
-
-                let id = elem.attr("querycode").unwrap().parse().unwrap();
-                let depart = elem.attr("querydeparture").unwrap().to_string();
-                let arrive = elem.attr("queryarrival").unwrap().to_string();
-                let travel_time = elem.attr("queryestimatedtime").unwrap().to_string();
-                let form_value = elem.attr("value").unwrap().to_string();
-                let discount_info = parse_discount(&element);
-
-                Train {
-                    id,
-                    depart,
-                    arrive,
-                    travel_time,
-                    discount_info,
-                    form_value,
-                }
-            })
-            .collect()
+    if let Some(locale) = args.site_locale {
+        config.site.locale = locale;
     }
+    Ok(config)
+}
 
-    fn parse_discount(item: &scraper::ElementRef) -> String {
-        let mut discounts = Vec::new();
-
-        if let Some(tag) = item
-            .select(&Selector::parse("p.early-bird span").unwrap())
-            .next()
-        {
-            discounts.push(tag.text().next().unwrap().to_string());
+/// Book with the options from the command line, recording or replaying the
+/// session as a cassette, capturing the traffic to the HAR file `har`, and taking
+/// the answers from the stream at `answers`, see [`StreamPrompter`].
+#[cfg(feature = "blocking")]
+pub fn run(
+    mut args: Args,
+    cassette: Option<CassetteMode>,
+    har: Option<PathBuf>,
+    answers: Option<PathBuf>,
+) {
+    let stream = match answers.as_deref().map(StreamPrompter::open).transpose() {
+        Ok(stream) => stream,
+        Err(err_msg) => {
+            output::error(err_msg);
+            exit::fail(exit::Status::Invalid);
+            return;
         }
-
-        if let Some(tag) = item
-            .select(&Selector::parse("p.student span").unwrap())
-            .next()
-        {
-            discounts.push(tag.text().next().unwrap().to_string());
+    };
+    if let Some(stream) = &stream {
+        match stream.options() {
+            Ok(request) => args = request.apply(&args),
+            Err(err_msg) => {
+                output::error(err_msg);
+                exit::fail(exit::Status::Invalid);
+                return;
+            }
         }
+    }
 
-        if !discounts.is_empty() {
-            format!("({})", discounts.join(", "))
-        } else {
-            String::new()
+    let config = match load_config(&mut args) {
+        Ok(config) => config,
+        Err(err_msg) => {
+            output::error(err_msg);
+            exit::fail(exit::Status::Invalid);
+            return;
         }
-    }
+    };
+    let stream = stream.map(|stream| stream.with_captcha(&config.captcha));
+    let stdin = StdinPrompter::new(&config.captcha);
+    let prompter: &dyn Prompter = match &stream {
+        Some(stream) => stream,
+        None => &stdin,
+    };
 
-    #[derive(Debug)]
-    pub struct Train {
-        id: u32,
-        depart: String,
-        arrive: String,
-        travel_time: String,
-        discount_info: String,
-        form_value: String,
+    let personal_id = args.personal_id.as_deref().unwrap_or_default();
+    let passport = args.passport.as_deref().unwrap_or_default();
+    let mut client = build_client(&config);
+    if har.is_some() {
+        client = client.capture_har();
     }
-
-    #[derive(Serialize, Deserialize, Debug)]
-    pub struct ConfirmTrainPayload {
-        #[serde(rename(serialize = "TrainQueryDataViewPanel:TrainGroup"), default)]
-        pub selected_train: String,
-
-        #[serde(rename(serialize = "BookingS2Form:hf:0"), default)]
-        pub form_mark: String,
+    if let Some(capture) = client.har() {
+        capture.redact(personal_id);
+        capture.redact(passport);
     }
 
-    impl Default for ConfirmTrainPayload {
-        fn default() -> Self {
-            ConfirmTrainPayload {
-                selected_train: "".to_string(),
-                form_mark: "".to_string(),
+    // A replayed booking has nothing to resume
+    let state_path = match cassette {
+        Some(CassetteMode::Replay(_)) => None,
+        _ => BookingState::default_path(),
+    };
+    let mut journal = resume::journal(state_path.as_deref(), &client, &args);
+    // Once the search goes through, its options are kept for the next --last
+    let mut remembered = false;
+    let mut journal = |checkpoint: Checkpoint| {
+        if !remembered {
+            last::remember(checkpoint.payload, &args);
+            remembered = true;
+        }
+        journal(checkpoint)
+    };
+    let replaying = matches!(cassette, Some(CassetteMode::Replay(_)));
+    let outcomes = match cassette {
+        None => group::book(&client, &args, &config, prompter, &mut journal),
+        Some(CassetteMode::Record(path)) => {
+            let recorder = Recorder::new(&client).redact(personal_id).redact(passport);
+            let outcomes = group::book(&recorder, &args, &config, prompter, &mut journal);
+            match recorder.save(&path) {
+                Ok(()) => info!("Session recorded to {}", path.display()),
+                Err(err_msg) => output::error(err_msg),
             }
+            outcomes
         }
-    }
-
-    impl ConfirmTrainPayload {
-        pub fn select_available_trains(&mut self, trains: &[Train]) {
-            for (idx, train) in trains.iter().enumerate() {
-                println!(
-                    "{:>2}. {:>4} {:>3}~{} {:>3} {}",
-                    idx + 1,
-                    train.id,
-                    train.depart,
-                    train.arrive,
-                    train.travel_time,
-                    train.discount_info
-                );
+        Some(CassetteMode::Replay(path)) => match Replayer::load(&path) {
+            Ok(replayer) => group::book(&replayer, &args, &config, prompter, &mut |_| {}),
+            Err(err_msg) => {
+                output::error(err_msg);
+                return;
             }
-
-            let selection = get_input("Select a train (default: 1):", 1);
-            self.selected_train = trains[selection - 1].form_value.clone();
+        },
+    };
+    save_cookies(&client, &config);
+    if let (Some(path), Some(capture)) = (&har, client.har()) {
+        match capture.save(path) {
+            Ok(()) => info!("Traffic captured to {}", path.display()),
+            Err(err_msg) => output::error(err_msg),
         }
     }
-}
-
-// Final page: Confirm Ticket Flow
-pub mod confirm_ticket_flow {
-    use super::*;
-
-    pub fn run_flow(document: &Html, client: &Client, args: &Args) -> Result<Html, String> {
-        // let body = fs::read_to_string("confirm_response.html").unwrap();
-        // let body = std::fs::read_to_string("confirm_ticket_super_early_bird.html").unwrap();
-
-        let mut payload = ConfirmTicketPayload::default();
-
-        // Input personal ID
-        let personal_id = payload.input_personal_id(&args.personal_id);
-
-        // Parse membership radio
-        let (radio_value, add_payload) =
-            process_membership(&document, &personal_id, &args.use_membership);
-        payload.member_radio = radio_value;
-
-        // Additional flow for early bird
-        let mut payload = serde_urlencoded::to_string(&payload).unwrap();
-        if let Some(additional_payload) = process_early_bird(&document, &personal_id) {
-            let additional_payload = serde_urlencoded::to_string(&additional_payload).unwrap();
-            payload = format!("{}&{}", payload, additional_payload);
-        }
-
-        if let Some(add_payload) = add_payload {
-            payload = format!("{}&{}", payload, add_payload);
-        }
-
-        println!("Booking...");
-        let resp = client
-            .post(CONFIRM_TICKET_URL)
-            .header("Content-Type", "application/x-www-form-urlencoded")
-            .body(payload)
-            .send()
-            .unwrap();
 
-        let html = Html::parse_document(&resp.text().unwrap());
-        if let Some(err_msg) = parse_error(&html) {
-            return Err(err_msg);
+    // The bookings made are kept in the history for `thsr remind`
+    if let Some(path) = history::default_path().filter(|_| !replaying) {
+        for result in outcomes.iter().flatten() {
+            if let Err(err_msg) = history::append(&path, &Entry::new("thsr", 1, Ok(result))) {
+                warn!("{}", err_msg);
+            }
         }
-        Ok(html)
     }
 
-    #[derive(Serialize, Deserialize, Debug)]
-    struct ConfirmTicketPayload {
-        #[serde(rename(serialize = "dummyId"))]
-        pub personal_id: String,
-
-        #[serde(rename(serialize = "dummyPhone"))]
-        pub phone_num: String,
-
-        #[serde(rename(
-            serialize = "TicketMemberSystemInputPanel:TakerMemberSystemDataView:memberSystemRadioGroup"
-        ))]
-        pub member_radio: String, // 非高鐵會員, 企業會員 / 高鐵會員 / 企業會員統編
-
-        #[serde(rename(serialize = "BookingS3FormSP:hf:0"), default)]
-        form_mark: String,
-
-        #[serde(rename(serialize = "idInputRadio"), default)]
-        id_input_radio: u8, // 0: 身份證字號 / 1: 護照號碼
-
-        #[serde(rename(serialize = "diffOver"), default = "default_diff_over")]
-        diff_over: u8,
-
-        #[serde(rename(serialize = "email"), default)]
-        email: String,
-
-        #[serde(rename(serialize = "agree"), default = "default_agree")]
-        agree: String,
-
-        #[serde(rename(serialize = "isGoBackM"), default)]
-        go_back_m: String,
+    // A group split into several bookings gets a summary of all of them
+    let count = group::split(&args).len();
+    let pnrs: Vec<String> = (outcomes.iter().flatten())
+        .map(|result| result.pnr.clone())
+        .collect();
+    for outcome in outcomes {
+        report(&config, outcome);
+    }
+    if count > 1 {
+        output::success(tr!(
+            "Booked {} of {} bookings for the group: {}",
+            "團體的 {1} 筆訂位完成了 {0} 筆：{2}",
+            pnrs.len(),
+            count,
+            pnrs.join(", ")
+        ));
+    }
+    resume::hint(state_path.as_deref());
+}
 
-        #[serde(rename(serialize = "backHome"), default)]
-        back_home: String,
+/// Go through all three steps of a booking, asking `prompter` for what `args` leaves open.
+pub fn book(
+    client: &dyn Transport,
+    args: &Args,
+    config: &Config,
+    prompter: &dyn Prompter,
+) -> Result<BookingResult, Error> {
+    book_with_journal(client, args, config, prompter, &mut |_| {})
+}
 
-        #[serde(rename(serialize = "TgoError"), default)]
-        tgo_error: u8,
-    }
+/// Like [`book`], handing every step reached to `journal`, e.g. [`resume::journal`].
+pub fn book_with_journal(
+    client: &dyn Transport,
+    args: &Args,
+    config: &Config,
+    prompter: &dyn Prompter,
+    journal: &mut dyn FnMut(Checkpoint),
+) -> Result<BookingResult, Error> {
+    let frontend = Frontend::new(prompter, args, config);
+    booking::book_with_journal(client, &args.options(config), &frontend, journal)
+}
 
-    fn default_diff_over() -> u8 {
-        1
-    }
+/// Pick the train and confirm the tickets, starting from `progress`, see
+/// [`booking::complete_booking`].
+#[cfg(feature = "blocking")]
+fn complete_booking(
+    client: &dyn Transport,
+    progress: Progress,
+    payload: &mut BookingPayload,
+    args: &Args,
+    config: &Config,
+    prompter: &dyn Prompter,
+    journal: &mut dyn FnMut(Checkpoint),
+) -> Result<BookingResult, Error> {
+    let frontend = Frontend::new(prompter, args, config);
+    booking::complete_booking(
+        client,
+        progress,
+        payload,
+        &args.options(config),
+        &frontend,
+        journal,
+    )
+}
 
-    fn default_agree() -> String {
-        "on".to_string()
+/// Tell what the Chinese message of the booking site in `err` means, in English,
+/// along with the message itself. Those reading Chinese need no explanation.
+#[cfg(feature = "blocking")]
+fn explain(err: &Error) {
+    if i18n::lang() == i18n::Lang::En
+        && let Some(explanation) = err.explanation()
+    {
+        output::status(explanation);
     }
+}
 
-    impl Default for ConfirmTicketPayload {
-        fn default() -> Self {
-            ConfirmTicketPayload {
-                personal_id: "".to_string(), // The actual ID is loaded from args.personal_id
-                phone_num: "".to_string(),
-                member_radio: "0".to_string(),
-                form_mark: "".to_string(),
-                id_input_radio: 0,
-                diff_over: default_diff_over(),
-                email: "".to_string(),
-                agree: default_agree(),
-                go_back_m: "".to_string(),
-                back_home: "".to_string(),
-                tgo_error: 1,
-            }
-        }
+#[cfg(feature = "blocking")]
+fn report(config: &Config, outcome: Result<BookingResult, Error>) {
+    match &outcome {
+        Ok(result) => events::emit(&events::Event::Booked { result }),
+        Err(Error::DryRun) => {}
+        Err(err) => events::emit(&events::Event::error(err)),
     }
-
-    impl ConfirmTicketPayload {
-        // MODIFIED: Simplified to use the default/CLI argument directly, skipping interactive prompt.
-        pub fn input_personal_id(&mut self, personal_id: &Option<String>) -> String {
-            let id_to_use = personal_id
-                .as_ref()
-                .cloned()
-                .unwrap_or_else(|| {
-                    // This is the emergency fallback if the CLI default failed.
-                    println!("Error: Personal ID was empty, using placeholder default A123456789.");
-                    "A123456789".to_string()
-                });
-
-            println!("Using Personal ID: {}", id_to_use); // Provide feedback
-            self.personal_id = id_to_use.trim().to_string();
-            self.personal_id.clone()
+    match outcome {
+        Err(Error::DryRun) => {
+            output::status(tr!("Dry run, nothing was booked", "試跑，未實際訂位"));
+            return;
         }
-    }
-
-    fn process_membership(
-        page: &Html,
-        membership_id: &String,
-        to_use_membership: &Option<bool>,
-    ) -> (String, Option<String>) {
-        
-        // MODIFIED: Now defaults to 'true' if the value is missing from the command line, 
-        // relying on the cli.rs default and eliminating interactive prompt need.
-        let use_membership = match to_use_membership {
-            Some(v) => *v,
-            None => true, // Default to true if no flag is provided (cleanup change)
-        };
-
-        let sel_str = match use_membership {
-            true => "#memberSystemRadio1",
-            false => "#memberSystemRadio3",
-        };
-
-        let membership_selector = Selector::parse(sel_str).unwrap();
-        let elem = page.select(&membership_selector).next().unwrap();
-        let membership_radio = elem.attr("value").unwrap();
-
-        if use_membership {
-            let payload = vec![
-                (
-                    "TicketMemberSystemInputPanel:TakerMemberSystemDataView:memberSystemRadioGroup:memberShipNumber",
-                    membership_id.clone(),
-                ),
-                (
-                    "TicketMemberSystemInputPanel:TakerMemberSystemDataView:memberSystemRadioGroup:memberSystemShipCheckBox",
-                    "on".to_string(),
-                ),
-            ];
-            let encoded_payload = serde_urlencoded::to_string(&payload).unwrap();
-            return (membership_radio.to_string(), Some(encoded_payload));
+        Err(Error::Cancelled) => {
+            output::status(tr!("Cancelled, nothing was booked", "已取消，未訂位"));
+            exit::fail(exit::Status::Cancelled);
+            return;
         }
-
-        (membership_radio.to_string(), None)
+        _ => {}
     }
-
-    fn process_early_bird(page: &Html, personal_id: &str) -> Option<HashMap<String, String>> {
-        let selector = Selector::parse(".superEarlyBird").unwrap();
-        let elem: Vec<String> = page
-            .select(&selector)
-            .filter_map(|tag| tag.text().next().map(|text| text.to_string()))
-            .collect();
-
-        if elem.is_empty() {
-            return None;
+    metrics::finished(outcome.is_ok());
+    match outcome {
+        Ok(result) => {
+            // Show the final booking result
+            show_result(&result);
+            notify::notify(config, &Event::Booked(&result));
         }
-
-        let personal_id = get_input(
-            &format!("Passenger's ID number (default: {}):", personal_id),
-            personal_id.to_string(),
-        );
-
-        let early_type_selector = Selector::parse(
-            "input[name='TicketPassengerInfoInputPanel:passengerDataView:0:passengerDataView2:passengerDataTypeName']").unwrap();
-        let early_type_elem = page.select(&early_type_selector).next().unwrap();
-        let early_type = early_type_elem.attr("value").unwrap().to_string();
-
-        let mut additional_payload = HashMap::from([
-            (
-                "TicketPassengerInfoInputPanel:passengerDataView:0:passengerDataView2:passengerDataLastName".to_string(),
-                "".to_string(),
-            ),
-            (
-                "TicketPassengerInfoInputPanel:passengerDataView:0:passengerDataView2:passengerDataFirstName".to_string(),
-                "".to_string(),
-            ),
-            (
-                "TicketPassengerInfoInputPanel:passengerDataView:0:passengerDataView2:passengerDataTypeName".to_string(),
-                early_type.clone(),
-            ),
-            (
-                "TicketPassengerInfoInputPanel:passengerDataView:0:passengerDataView2:passengerDataIdNumber".to_string(),
-                personal_id,
-            ),
-            (
-                "TicketPassengerInfoInputPanel:passengerDataView:0:passengerDataView2:passengerDataInputChoice".to_string(),
-                "0".to_string(), // 0 for ID, 1 for passport
-            ),
-        ]);
-
-        for i in 1..elem.len() {
-            let inp_id = loop {
-                let inp_id = get_input(
-                    &format!(
-                        "Input passenger's ID number for passenger {}\n(ID change is not allowed after input!):",
-                        i + 1
-                    ),
-                    "".to_string(),
-                );
-                if inp_id.is_empty() {
-                    println!("ID should not be empty!");
-                } else {
-                    break inp_id;
-                }
-            };
-
-            additional_payload.insert(
-                format!("TicketPassengerInfoInputPanel:passengerDataView:{i}:passengerDataView2:passengerDataLastName"),
-                "".to_string(),
-            );
-            additional_payload.insert(
-                format!("TicketPassengerInfoInputPanel:passengerDataView:{i}:passengerDataView2:passengerDataFirstName"),
-                "".to_string(),
-            );
-            additional_payload.insert(
-                format!("TicketPassengerInfoInputPanel:passengerDataView:{i}:passengerDataView2:passengerDataTypeName"),
-                early_type.clone(),
-            );
-            additional_payload.insert(
-                format!("TicketPassengerInfoInputPanel:passengerDataView:{i}:passengerDataView2:passengerDataIdNumber"),
-                inp_id.trim().to_string(),
-            );
-            additional_payload.insert(
-                format!("TicketPassengerInfoInputPanel:passengerDataView:{i}:passengerDataView2:passengerDataInputChoice"),
-                "0".to_string(), // 0 for ID, 1 for passport
-            );
+        Err(err) => {
+            output::error(&err);
+            explain(&err);
+            exit::fail(exit::Status::of(&err));
+            notify::notify(config, &Event::Failed(&err.to_string()));
         }
-        Some(additional_payload)
     }
 }
-
-fn show_result(page: &Html) {
-    let pnr_code_selector = Selector::parse("p.pnr-code span").unwrap();
-    let pnr_code_span_tag = page.select(&pnr_code_selector).next().unwrap();
-    let pnr_code = pnr_code_span_tag.text().next().unwrap();
-
-    println!("\nPlease use the following PNR code for payment and picking up the ticket:");
-    println!("PNR Code: {}", pnr_code);
-
-    // Price
-    let price_selector = Selector::parse("#setTrainTotalPriceValue").unwrap();
-    let price_tag = page.select(&price_selector).next().unwrap();
-    let price = price_tag.text().next().unwrap();
-
-    let payment_status_selector = Selector::parse("span.status-unpaid span:nth-child(3)").unwrap();
-    let payment_status_tag = page.select(&payment_status_selector).next().unwrap();
-    let payment_exp_date = payment_status_tag.text().next().unwrap();
-    println!("Price: {}. Please pay before {}", price, payment_exp_date);
-    println!("-------(Ticket Information)-------");
-
-    // Departure date
-    let depart_date_selector = Selector::parse("span.date span").unwrap();
-    let depart_date_tag = page.select(&depart_date_selector).next().unwrap();
-    let depart_date = depart_date_tag.text().next().unwrap();
-    println!("{:>7}{}", "Date: ", depart_date);
-
-    // Departure and arrival time
-    let depart_time_selector = Selector::parse("#setTrainDeparture0").unwrap();
-    let depart_time_tag = page.select(&depart_time_selector).next().unwrap();
-    let depart_time = depart_time_tag.text().next().unwrap();
-
-    let arrive_time_selector = Selector::parse("#setTrainArrival0").unwrap();
-    let arrive_time_tag = page.select(&arrive_time_selector).next().unwrap();
-    let arrive_time = arrive_time_tag.text().next().unwrap();
-
-    println!(
-        "{:>7}{}",
-        "Time: ",
-        format!("{}~{}", depart_time, arrive_time)
-    );
-
-    // Station
-    let depart_from_selector = Selector::parse("p.departure-stn span").unwrap();
-    let depart_from_tag = page.select(&depart_from_selector).next().unwrap();
-    let depart_from = depart_from_tag.text().next().unwrap();
-    println!("{:>7}{}", "From: ", depart_from);
-
-    let arrive_to_selector = Selector::parse("p.arrival-stn span").unwrap();
-    let arrive_to_tag = page.select(&arrive_to_selector).next().unwrap();
-    let arrive_to = arrive_to_tag.text().next().unwrap();
-    println!("{:>7}{}", "To: ", arrive_to);
-
-    // Seat info
-    let seats_selector = Selector::parse("div.seat-label span").unwrap();
-    let seats: Vec<String> = page
-        .select(&seats_selector)
-        .filter_map(|tag| tag.text().next().map(|text| text.to_string()))
-        .collect();
-
-    let passenger_count_selector = Selector::parse("div.uk-accordion-content span").unwrap();
-    let passenger_count_tag = page.select(&passenger_count_selector).next().unwrap();
-    let passenger_count = passenger_count_tag.text().next().unwrap();
-
-    let seat_type_selector = Selector::parse("p.info-data span").unwrap();
-    let seat_type_tag = page.select(&seat_type_selector).next().unwrap();
-    let seat_type = seat_type_tag.text().next().unwrap();
-    println!("Class: {}{}", seat_type, passenger_count);
-    println!("Seats: {}", seats.join(", "));
-}
\ No newline at end of file
@@ -0,0 +1,76 @@
+use tracing::Level;
+use tracing_subscriber::EnvFilter;
+
+use std::io::{self, Write};
+use std::sync::Arc;
+
+use crate::output;
+use crate::progress;
+use crate::redact::Redactor;
+
+/// Where the diagnostics go unless `RUST_LOG` or `--log-level` says otherwise:
+/// the status of each step, and only the warnings of the libraries used.
+const DEFAULT_FILTER: &str = "warn,thsr=info";
+
+/// Send diagnostics to stderr, keeping stdout for prompts and results.
+/// `level` applies to thsr itself and takes precedence over `RUST_LOG`.
+/// Every line goes through `redactor` first, logs tend to end up in bug reports.
+pub fn init(level: Option<Level>, redactor: Redactor) {
+    let redactor = Arc::new(redactor);
+    tracing_subscriber::fmt()
+        .with_env_filter(filter(level))
+        .with_target(false)
+        .with_ansi(output::log_colors())
+        .with_writer(move || RedactingWriter {
+            redactor: Arc::clone(&redactor),
+            inner: io::stderr(),
+        })
+        .init();
+}
+
+/// Like [`init`], sending the diagnostics to the writers made by `make_writer`
+/// instead of stderr, without colors.
+pub fn init_with<W: Write + 'static>(
+    level: Option<Level>,
+    redactor: Redactor,
+    make_writer: fn() -> W,
+) {
+    let redactor = Arc::new(redactor);
+    tracing_subscriber::fmt()
+        .with_env_filter(filter(level))
+        .with_target(false)
+        .with_ansi(false)
+        .with_writer(move || RedactingWriter {
+            redactor: Arc::clone(&redactor),
+            inner: make_writer(),
+        })
+        .init();
+}
+
+fn filter(level: Option<Level>) -> EnvFilter {
+    match level {
+        Some(level) => EnvFilter::new(format!("warn,thsr={}", level)),
+        None => {
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER))
+        }
+    }
+}
+
+/// Redacts what is written to `inner`. Log lines are written in one piece,
+/// so nothing to redact is split across writes.
+struct RedactingWriter<W: Write> {
+    redactor: Arc<Redactor>,
+    inner: W,
+}
+
+impl<W: Write> Write for RedactingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let text = self.redactor.text(&String::from_utf8_lossy(buf));
+        progress::suspend(|| self.inner.write_all(text.as_bytes()))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
@@ -1,45 +1,102 @@
 use clap::Parser;
+use serde_json::json;
 
-use thsr::cli::Args;
-use thsr::run;
-use thsr::schema::{STATION_MAP, TIME_TABLE};
+use std::process::ExitCode;
 
-fn show_station() {
-    for (i, station) in STATION_MAP.iter().enumerate() {
-        println!("{}: {:?}", i + 1, station);
-    }
-}
+use thsr::cli::{Cli, Command};
+use thsr::redact::{self, Redactor};
+use thsr::schema::{STATIONS, TIME_SLOTS};
+use thsr::{
+    daemon, doctor, events, exit, i18n, log, man, output, plugin, preset, remind, resume, run,
+    schedule, selectors, serve, station, trains, watch,
+};
 
-fn show_time_table() {
-    for (idx, &t_str) in TIME_TABLE.iter().enumerate() {
-        let mut t_int = t_str[..t_str.len() - 1].parse::<u16>().unwrap();
-        if t_str.ends_with('A') && (t_int / 100) == 12 {
-            t_int %= 1200;
-        } else if t_int != 1230 && t_str.ends_with('P') {
-            t_int += 1200;
-        }
-        let formatted_time = format!("{:04}", t_int);
+fn show_station(json: bool) {
+    if json {
+        println!("{}", serde_json::to_string_pretty(&STATIONS).unwrap());
+        return;
+    }
+    println!("ID  Code  Name       中文");
+    for station in &STATIONS {
         println!(
-            "{}. {}:{}",
-            idx + 1,
-            &formatted_time[..formatted_time.len() - 2],
-            &formatted_time[formatted_time.len() - 2..]
+            "{:>2}  {:<4}  {:<9}  {}",
+            station.station.id(),
+            station.code,
+            station.name,
+            station.name_zh
         );
     }
 }
 
-fn main() {
-    let args = Args::parse();
-
-    if args.list_time_table {
-        show_time_table();
+fn show_time_table(json: bool) {
+    if json {
+        let times: Vec<_> = (TIME_SLOTS.iter())
+            .map(|slot| json!({"id": slot.id(), "code": slot.code(), "time": slot}))
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&times).unwrap());
         return;
     }
+    for slot in &TIME_SLOTS {
+        println!("{}. {}", slot.id(), slot);
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    selectors::check();
+    let args = cli.booking_args();
+    let personal_id = args.personal_id.as_deref().unwrap_or_default();
+    let passport = args.passport.as_deref().unwrap_or_default();
+    output::init(cli.no_color);
+    i18n::init(cli.lang);
+    events::init(cli.events);
+    if let Ok(salt) = std::env::var("THSR_REDACT_SALT") {
+        redact::set_salt(&salt);
+    }
+    let redactor = Redactor::default().secret(personal_id).secret(passport);
+    // The full-screen interface keeps the log to itself
+    #[cfg(feature = "tui")]
+    if let Some(Command::Tui(_)) = cli.command {
+        log::init_with(cli.log_level, redactor, thsr::tui::log_writer);
+    } else {
+        log::init(cli.log_level, redactor);
+    }
+    #[cfg(not(feature = "tui"))]
+    log::init(cli.log_level, redactor);
 
-    if args.list_station {
-        show_station();
-        return;
+    if cli.list_time_table {
+        show_time_table(cli.json);
+        return ExitCode::SUCCESS;
     }
 
-    run(args);
+    if cli.list_station {
+        show_station(cli.json);
+        return ExitCode::SUCCESS;
+    }
+
+    match cli.command {
+        Some(Command::Watch(watch_args)) => watch::run(watch_args),
+        Some(Command::Trains(trains_args)) => trains::run(trains_args),
+        Some(Command::Schedule(schedule_args)) => schedule::run(schedule_args),
+        Some(Command::Resume(resume_args)) => resume::run(resume_args),
+        Some(Command::Serve(serve_args)) => serve::run(serve_args),
+        Some(Command::Daemon(daemon_args)) => daemon::run(daemon_args),
+        #[cfg(feature = "grpc")]
+        Some(Command::Grpc(grpc_args)) => thsr::grpc::run(grpc_args),
+        #[cfg(feature = "tui")]
+        Some(Command::Tui(tui_args)) => thsr::tui::run(tui_args),
+        #[cfg(feature = "gui")]
+        Some(Command::Gui(gui_args)) => thsr::gui::run(gui_args),
+        Some(Command::Man(man_args)) => man::run(man_args),
+        Some(Command::Preset(preset_args)) => preset::run(preset_args),
+        Some(Command::Station(station_args)) => station::run(station_args),
+        Some(Command::Remind(remind_args)) => remind::run(remind_args),
+        Some(Command::Doctor(doctor_args)) => doctor::run(doctor_args),
+        Some(Command::External(plugin_args)) => return plugin::run(plugin_args, cli.no_color),
+        None => {
+            let cassette = cli.cassette();
+            run(cli.args, cassette, cli.har, cli.answers)
+        }
+    }
+    exit::status().into()
 }
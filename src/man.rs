@@ -0,0 +1,177 @@
+//! Manual pages for packaging, generated from the command line definition so they
+//! never drift from `--help`.
+
+use clap::{Command, CommandFactory};
+use clap_mangen::Man;
+use roff::{Roff, bold, italic, roman};
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::cli::{Cli, ManArgs};
+use crate::exit::Status;
+use crate::output;
+
+/// What the booking asks and how to answer it, which `--help` can't tell.
+fn interactive_section(roff: &mut Roff) {
+    roff.control("SH", ["INTERACTIVE USE"]);
+    roff.text([roman(
+        "Everything not given as an option is asked for: the stations, the date, the time, \
+         the tickets, then the train, the personal ID and the security code. An empty answer \
+         takes the default shown with the question.",
+    )]);
+    roff.control("PP", []);
+    roff.text([roman(
+        "On an interactive terminal, stations are found by typing part of their English or \
+         Chinese name, times and trains are picked with the arrow keys, dates and ticket \
+         counts are checked as they are typed, and IDs are hidden. Otherwise every question \
+         is a line read from standard input, lists are numbered and answered with a number.",
+    )]);
+    roff.control("PP", []);
+    roff.text([
+        roman(
+            "The security code image is shown inline in terminals speaking the Kitty, iTerm2 \
+             or Sixel graphics protocol, as colored blocks elsewhere, and opened in the image \
+             viewer when a desktop is available. Answer ",
+        ),
+        bold("r"),
+        roman(" to get a new image. See "),
+        bold("--captcha-solver"),
+        roman(", "),
+        bold("--captcha-in"),
+        roman(" and "),
+        bold("--captcha-http"),
+        roman(" to solve it otherwise."),
+    ]);
+    roff.control("PP", []);
+    roff.text([
+        roman("The progress of a booking is saved after every step. If it is interrupted, "),
+        bold("thsr resume"),
+        roman(" goes on where it stopped."),
+    ]);
+}
+
+fn environment_section(roff: &mut Roff) {
+    roff.control("SH", ["ENVIRONMENT"]);
+    let variables = [
+        (
+            "NO_COLOR",
+            "When not empty, print without colors, like --no-color.",
+        ),
+        (
+            "LC_ALL, LC_MESSAGES, LANG",
+            "Chinese locales print in Chinese unless --lang is given.",
+        ),
+        ("RUST_LOG", "What to log when --log-level isn't given."),
+        (
+            "HTTPS_PROXY, ALL_PROXY",
+            "Proxy for the requests when none is configured.",
+        ),
+        (
+            "THSR_CONFIG_PASSPHRASE",
+            "Passphrase of the encrypted section of the config file, instead of asking for it.",
+        ),
+        (
+            "THSR_REDACT_SALT",
+            "Salt of the tokens standing in for personal data in logs, cassettes and HAR files.",
+        ),
+        (
+            "THSR_BASE_URL",
+            "Address of the booking site, e.g. a local mock for testing.",
+        ),
+    ];
+    for (name, description) in variables {
+        roff.control("TP", []);
+        roff.text([bold(name)]);
+        roff.text([roman(description)]);
+    }
+}
+
+fn exit_status_section(roff: &mut Roff) {
+    roff.control("SH", ["EXIT STATUS"]);
+    for status in Status::ALL {
+        roff.control("TP", []);
+        roff.text([bold(status.code().to_string())]);
+        roff.text([roman(status.description())]);
+    }
+}
+
+fn files_section(roff: &mut Roff) {
+    roff.control("SH", ["FILES"]);
+    let files = [
+        (
+            "~/.config/thsr/config.toml",
+            "The config file, see --config.",
+        ),
+        (
+            "~/.local/share/thsr/booking.json",
+            "The progress of the last booking, for thsr resume.",
+        ),
+        (
+            "~/.local/share/thsr/last.json",
+            "The options of the last search, for --last.",
+        ),
+        (
+            "~/.local/share/thsr/history.jsonl",
+            "The jobs finished by thsr daemon and the bookings made, for thsr remind.",
+        ),
+    ];
+    for (path, description) in files {
+        roff.control("TP", []);
+        roff.text([italic(path)]);
+        roff.text([roman(description)]);
+    }
+    roff.text([roman(
+        "The paths are those on Linux, other systems use their own config and data directories.",
+    )]);
+}
+
+/// The command line, without the `help` subcommand, which has no page.
+fn command() -> Command {
+    let mut cmd = Cli::command().disable_help_subcommand(true);
+    cmd.build();
+    cmd
+}
+
+/// Render the page of `thsr` itself: the generated sections, then those on the
+/// interactive use, the environment, the exit status and the files.
+pub fn render(w: &mut dyn Write) -> io::Result<()> {
+    let man = Man::new(command());
+    man.render_title(w)?;
+    man.render_name_section(w)?;
+    man.render_synopsis_section(w)?;
+    man.render_description_section(w)?;
+    man.render_options_section(w)?;
+    man.render_subcommands_section(w)?;
+
+    let mut roff = Roff::default();
+    interactive_section(&mut roff);
+    environment_section(&mut roff);
+    exit_status_section(&mut roff);
+    files_section(&mut roff);
+    roff.to_writer(w)?;
+
+    man.render_version_section(w)
+}
+
+/// Write `thsr.1` and a `thsr-<subcommand>.1` page for every subcommand to `dir`.
+pub fn generate_to(dir: &Path) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let cmd = command();
+    for subcommand in cmd.get_subcommands().filter(|sub| !sub.is_hide_set()) {
+        Man::new(subcommand.clone()).generate_to(dir)?;
+    }
+    render(&mut fs::File::create(dir.join("thsr.1"))?)
+}
+
+/// Print the page of `thsr`, or write all pages to the given directory.
+pub fn run(man_args: ManArgs) {
+    let outcome = match &man_args.out {
+        Some(dir) => generate_to(dir),
+        None => render(&mut io::stdout().lock()),
+    };
+    if let Err(err) = outcome {
+        output::error(format!("Failed to write the manual: {}", err));
+    }
+}
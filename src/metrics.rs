@@ -0,0 +1,138 @@
+//! Prometheus metrics of the bookings made by this process, for keeping an eye on
+//! long-running `watch`, `schedule` and `serve` deployments.
+
+use prometheus::core::Collector;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, Opts, Registry, TextEncoder,
+};
+use tiny_http::{Header, Method, Response, Server};
+use tracing::warn;
+
+use std::net::SocketAddr;
+use std::sync::LazyLock;
+use std::thread;
+use std::time::Duration;
+
+use crate::error::Error;
+
+static REGISTRY: LazyLock<Registry> = LazyLock::new(Registry::new);
+
+fn register<T: Collector + Clone + 'static>(metric: T) -> T {
+    REGISTRY.register(Box::new(metric.clone())).unwrap();
+    metric
+}
+
+static SEARCHES: LazyLock<IntCounter> = LazyLock::new(|| {
+    register(
+        IntCounter::new(
+            "thsr_search_attempts_total",
+            "Searches submitted, one per security code entered",
+        )
+        .unwrap(),
+    )
+});
+
+static CAPTCHA_FAILURES: LazyLock<IntCounter> = LazyLock::new(|| {
+    register(
+        IntCounter::new(
+            "thsr_captcha_failures_total",
+            "Security codes refused by the booking site",
+        )
+        .unwrap(),
+    )
+});
+
+static SOLD_OUT: LazyLock<IntCounter> = LazyLock::new(|| {
+    register(IntCounter::new("thsr_sold_out_total", "Steps answered with no seats left").unwrap())
+});
+
+static BOOKINGS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new("thsr_bookings_total", "Finished bookings, by outcome"),
+            &["outcome"],
+        )
+        .unwrap(),
+    )
+});
+
+static STEP_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register(
+        HistogramVec::new(
+            HistogramOpts::new(
+                "thsr_step_duration_seconds",
+                "Time taken by each booking step, answering its prompts included",
+            )
+            .buckets(vec![
+                0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0,
+            ]),
+            &["step"],
+        )
+        .unwrap(),
+    )
+});
+
+/// Record the booking step `step` taking `took`, counting a sold out answer.
+pub fn step(step: &str, took: Duration, error: Option<&Error>) {
+    STEP_DURATION
+        .with_label_values(&[step])
+        .observe(took.as_secs_f64());
+    if let Some(Error::SoldOut(_)) = error {
+        SOLD_OUT.inc();
+    }
+}
+
+/// Count a submitted search, and whether its security code was refused.
+pub fn searched(error: Option<&Error>) {
+    SEARCHES.inc();
+    if let Some(Error::WrongSecurityCode(_)) = error {
+        CAPTCHA_FAILURES.inc();
+    }
+}
+
+/// Count a finished booking.
+pub fn finished(booked: bool) {
+    let outcome = if booked { "booked" } else { "failed" };
+    BOOKINGS.with_label_values(&[outcome]).inc();
+}
+
+/// The metrics in the Prometheus text format.
+pub fn render() -> String {
+    // Listed from the start, before anything happened
+    LazyLock::force(&SEARCHES);
+    LazyLock::force(&CAPTCHA_FAILURES);
+    LazyLock::force(&SOLD_OUT);
+    LazyLock::force(&BOOKINGS);
+    LazyLock::force(&STEP_DURATION);
+
+    let mut buffer = Vec::new();
+    TextEncoder::new()
+        .encode(&REGISTRY.gather(), &mut buffer)
+        .unwrap();
+    String::from_utf8(buffer).unwrap()
+}
+
+/// The content type of [`render`].
+pub fn content_type() -> Header {
+    Header::from_bytes("Content-Type", TextEncoder::new().format_type()).unwrap()
+}
+
+/// Serve the metrics at `http://{listen}/metrics` from a background thread.
+pub fn serve(listen: SocketAddr) -> Result<(), String> {
+    let server = Server::http(listen)
+        .map_err(|err| format!("Failed to serve the metrics on {}: {}", listen, err))?;
+    thread::spawn(move || {
+        for request in server.incoming_requests() {
+            let response = match (request.method(), request.url()) {
+                (Method::Get, "/metrics") => {
+                    Response::from_string(render()).with_header(content_type())
+                }
+                _ => Response::from_string("Not found").with_status_code(404),
+            };
+            if let Err(err) = request.respond(response) {
+                warn!("Failed to answer the metrics scraper: {}", err);
+            }
+        }
+    });
+    Ok(())
+}
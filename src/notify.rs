@@ -0,0 +1,198 @@
+use lettre::message::header::ContentType;
+use lettre::message::{Attachment, Mailbox, MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use tracing::{error, info};
+
+use crate::config::{Config, EmailConfig, NtfyConfig, SmtpTls};
+use crate::result::BookingResult;
+
+use std::process::Command;
+
+/// Something worth telling the user about while they're away from the terminal.
+pub enum Event<'a> {
+    /// The flow is blocked on the security code prompt
+    CaptchaRequired,
+    Booked(&'a BookingResult),
+    Failed(&'a str),
+    /// A booking is still to be paid, see `thsr remind`
+    PaymentDue(&'a BookingResult),
+}
+
+/// Deliver `event` to every configured notifier.
+/// Delivery problems are only reported, they never abort the booking.
+pub fn notify(config: &Config, event: &Event) {
+    if let (Some(email), Event::Booked(result)) = (&config.email, event) {
+        match send_email(email, result) {
+            Ok(()) => info!("Booking summary sent to {}", email.to.join(", ")),
+            Err(err_msg) => error!("{}", err_msg),
+        }
+    }
+
+    if let Some(ntfy) = &config.ntfy
+        && let Err(err_msg) = send_ntfy(ntfy, event)
+    {
+        error!("{}", err_msg);
+    }
+
+    if let (Some(command), Event::Booked(result)) = (&config.on_success, event) {
+        match run_hook(command, hook_env(result)) {
+            Ok(()) => info!("Ran {}", command),
+            Err(err_msg) => error!("{}", err_msg),
+        }
+    }
+}
+
+/// The details of `result` as the environment of the `on_success` command.
+pub fn hook_env(result: &BookingResult) -> Vec<(&'static str, String)> {
+    let optional = |value: Option<u32>| value.map(|value| value.to_string()).unwrap_or_default();
+    vec![
+        ("THSR_PNR", result.pnr.clone()),
+        ("THSR_PRICE", result.price.clone()),
+        ("THSR_PAYMENT_DEADLINE", result.payment_deadline.clone()),
+        ("THSR_TRAIN", optional(result.train)),
+        ("THSR_DATE", result.date.clone()),
+        ("THSR_DEPART_TIME", result.depart_time.clone()),
+        ("THSR_ARRIVE_TIME", result.arrive_time.clone()),
+        ("THSR_FROM", result.from.clone()),
+        ("THSR_TO", result.to.clone()),
+        ("THSR_CLASS", result.class.clone()),
+        ("THSR_SEATS", result.seat_list()),
+        ("THSR_TGO_POINTS", optional(result.tgo_points)),
+        ("THSR_RESULT", serde_json::to_string(result).unwrap()),
+    ]
+}
+
+/// Run `command` with the shell, `env` added to its environment, and wait for it to
+/// finish, telling whether it succeeded.
+pub fn run_hook(command: &str, env: Vec<(&'static str, String)>) -> Result<(), String> {
+    let (shell, flag) = match cfg!(windows) {
+        true => ("cmd", "/C"),
+        false => ("sh", "-c"),
+    };
+    let status = (Command::new(shell).arg(flag).arg(command))
+        .envs(env)
+        .status()
+        .map_err(|err| format!("Failed to run {}: {}", command, err))?;
+    match status.success() {
+        true => Ok(()),
+        false => Err(format!("{} failed with {}", command, status)),
+    }
+}
+
+/// Mail the booking summary, with an ICS calendar attachment, through the configured SMTP server.
+pub fn send_email(config: &EmailConfig, result: &BookingResult) -> Result<(), String> {
+    let from: Mailbox = config
+        .from
+        .parse()
+        .map_err(|err| format!("Invalid sender address {}: {}", config.from, err))?;
+
+    let mut builder = Message::builder().from(from).subject(format!(
+        "THSR booking {}: {} → {} {} {}",
+        result.pnr, result.from, result.to, result.date, result.depart_time
+    ));
+    for to in &config.to {
+        let to: Mailbox = to
+            .parse()
+            .map_err(|err| format!("Invalid recipient address {}: {}", to, err))?;
+        builder = builder.to(to);
+    }
+
+    let mut body = MultiPart::mixed().singlepart(SinglePart::plain(result.to_string()));
+    if let Some(ics) = result.to_ics() {
+        let content_type =
+            ContentType::parse("text/calendar; charset=utf-8; method=PUBLISH").unwrap();
+        body = body.singlepart(
+            Attachment::new(format!("thsr-{}.ics", result.pnr)).body(ics, content_type),
+        );
+    }
+    let message = builder
+        .multipart(body)
+        .map_err(|err| format!("Failed to build email: {}", err))?;
+
+    let transport = match config.tls {
+        SmtpTls::Starttls => SmtpTransport::starttls_relay(&config.smtp_host),
+        SmtpTls::Tls => SmtpTransport::relay(&config.smtp_host),
+        SmtpTls::None => Ok(SmtpTransport::builder_dangerous(&config.smtp_host)),
+    }
+    .map_err(|err| format!("Invalid SMTP host {}: {}", config.smtp_host, err))?;
+
+    let mut transport = transport.port(config.smtp_port);
+    if let (Some(username), Some(password)) = (&config.username, &config.password) {
+        transport = transport.credentials(Credentials::new(username.clone(), password.clone()));
+    }
+
+    transport
+        .build()
+        .send(&message)
+        .map_err(|err| format!("Failed to send email: {}", err))?;
+    Ok(())
+}
+
+/// Publish `event` to an ntfy topic. Failures and captcha prompts are sent
+/// with a higher priority since they need someone to act on them.
+#[cfg(feature = "blocking")]
+pub fn send_ntfy(config: &NtfyConfig, event: &Event) -> Result<(), String> {
+    let (title, message, priority, tags) = match event {
+        Event::CaptchaRequired => (
+            "THSR: security code required".to_string(),
+            "The booking is waiting for the security code to be entered.".to_string(),
+            "high",
+            "keyboard",
+        ),
+        Event::Booked(result) => (
+            format!("THSR booked: {}", result.pnr),
+            result.to_string(),
+            "default",
+            "white_check_mark,train",
+        ),
+        Event::Failed(err_msg) => (
+            "THSR booking failed".to_string(),
+            err_msg.to_string(),
+            "urgent",
+            "warning",
+        ),
+        Event::PaymentDue(result) => (
+            format!("THSR: pay {} soon", result.pnr),
+            format!(
+                "{} → {} {} {}: pay {} before {}",
+                result.from,
+                result.to,
+                result.date,
+                result.depart_time,
+                result.price,
+                result.payment_deadline
+            ),
+            "high",
+            "hourglass,train",
+        ),
+    };
+
+    let url = format!("{}/{}", config.server.trim_end_matches('/'), config.topic);
+    let client = reqwest::blocking::Client::builder()
+        .timeout(std::time::Duration::from_secs(10))
+        .build()
+        .unwrap();
+    let mut request = client
+        .post(&url)
+        .header("Title", title)
+        .header("Priority", priority)
+        .header("Tags", tags)
+        .body(message);
+    if let Some(token) = &config.token {
+        request = request.bearer_auth(token);
+    }
+
+    let resp = request
+        .send()
+        .map_err(|err| format!("Failed to publish to {}: {}", url, err))?;
+    if !resp.status().is_success() {
+        return Err(format!("Failed to publish to {}: {}", url, resp.status()));
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "blocking"))]
+pub fn send_ntfy(_config: &NtfyConfig, _event: &Event) -> Result<(), String> {
+    Err("ntfy is not available, thsr was built without the `blocking` feature".to_string())
+}
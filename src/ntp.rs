@@ -0,0 +1,82 @@
+use chrono::Duration;
+
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::{Duration as StdDuration, SystemTime, UNIX_EPOCH};
+
+/// Seconds between the NTP epoch (1900) and the Unix epoch (1970)
+const NTP_UNIX_OFFSET: u64 = 2_208_988_800;
+
+fn to_ntp_seconds(time: SystemTime) -> f64 {
+    let since_unix = time.duration_since(UNIX_EPOCH).unwrap();
+    (since_unix.as_secs() + NTP_UNIX_OFFSET) as f64 + f64::from(since_unix.subsec_nanos()) / 1e9
+}
+
+fn read_timestamp(bytes: &[u8]) -> f64 {
+    let seconds = u32::from_be_bytes(bytes[..4].try_into().unwrap());
+    let fraction = u32::from_be_bytes(bytes[4..8].try_into().unwrap());
+    f64::from(seconds) + f64::from(fraction) / 4_294_967_296.0
+}
+
+/// The address of the NTP `server`: a host name or IP address, with an optional
+/// port after a colon, IPv6 addresses with a port in brackets, e.g. `[::1]:123`.
+pub fn server_addr(server: &str) -> Result<SocketAddr, String> {
+    if let Ok(addr) = server.parse::<SocketAddr>() {
+        return Ok(addr);
+    }
+    let bare = server.trim_start_matches('[').trim_end_matches(']');
+    if let Ok(ip) = bare.parse::<IpAddr>() {
+        return Ok(SocketAddr::new(ip, 123));
+    }
+    let (host, port) = match server.rsplit_once(':') {
+        Some((host, port)) => (
+            host,
+            (port.parse())
+                .map_err(|_| format!("Invalid port {} of NTP server {}", port, server))?,
+        ),
+        None => (server, 123),
+    };
+    ((host, port).to_socket_addrs())
+        .map_err(|err| format!("Failed to resolve NTP server {}: {}", server, err))?
+        .next()
+        .ok_or_else(|| format!("NTP server {} resolves to no address", server))
+}
+
+/// Ask an NTP server how far the local clock is off, using a single SNTP exchange.
+/// A positive offset means the local clock is behind.
+pub fn clock_offset(server: &str) -> Result<Duration, String> {
+    let addr = server_addr(server)?;
+    let local = match addr {
+        SocketAddr::V4(_) => "0.0.0.0:0",
+        SocketAddr::V6(_) => "[::]:0",
+    };
+    let socket = UdpSocket::bind(local).map_err(|err| err.to_string())?;
+    socket
+        .set_read_timeout(Some(StdDuration::from_secs(5)))
+        .map_err(|err| err.to_string())?;
+
+    // LI = 0, version 3, mode 3 (client)
+    let mut request = [0u8; 48];
+    request[0] = 0x1B;
+
+    let sent_at = SystemTime::now();
+    socket
+        .send_to(&request, addr)
+        .map_err(|err| format!("Failed to query NTP server {}: {}", server, err))?;
+
+    let mut response = [0u8; 48];
+    let (len, _) = socket
+        .recv_from(&mut response)
+        .map_err(|err| format!("No answer from NTP server {}: {}", server, err))?;
+    let received_at = SystemTime::now();
+    if len < 48 {
+        return Err(format!("Malformed answer from NTP server {}", server));
+    }
+
+    let t1 = to_ntp_seconds(sent_at);
+    let t2 = read_timestamp(&response[32..40]);
+    let t3 = read_timestamp(&response[40..48]);
+    let t4 = to_ntp_seconds(received_at);
+    let offset = ((t2 - t1) + (t3 - t4)) / 2.0;
+
+    Ok(Duration::microseconds((offset * 1e6) as i64))
+}
@@ -0,0 +1,79 @@
+//! What the program tells the user on stdout, by kind: routine status lines,
+//! warnings, successes and errors. Colored on a terminal, unless turned off with
+//! `--no-color` or `NO_COLOR`. With `--events`, it all goes to stderr instead, so
+//! that stdout carries nothing but the events.
+
+use console::StyledObject;
+
+use std::env;
+use std::fmt::Display;
+use std::io::{self, Write};
+
+use crate::events;
+use crate::exit;
+use crate::i18n::tr;
+
+/// Turn the colors off for `--no-color` or a non-empty `NO_COLOR`, on stdout and
+/// on the log alike.
+pub fn init(no_color: bool) {
+    if no_color || env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+        console::set_colors_enabled(false);
+        console::set_colors_enabled_stderr(false);
+    }
+}
+
+/// Where the lines meant for people go: stdout, or stderr while stdout carries the
+/// events.
+pub fn writer() -> Box<dyn Write> {
+    match events::enabled() {
+        true => Box::new(io::stderr()),
+        false => Box::new(io::stdout()),
+    }
+}
+
+/// Print `msg` as a line meant for people, see [`writer`].
+pub fn line(msg: impl Display) {
+    let _ = writeln!(writer(), "{}", msg);
+}
+
+/// `msg` colored as the terminal of [`writer`] allows.
+fn style<D>(msg: D) -> StyledObject<D> {
+    match events::enabled() {
+        true => console::style(msg).for_stderr(),
+        false => console::style(msg),
+    }
+}
+
+/// Whether the log on stderr is colored.
+pub fn log_colors() -> bool {
+    console::colors_enabled_stderr()
+}
+
+/// A routine step of the flow, e.g. the alerts of the booking site.
+pub fn status(msg: impl Display) {
+    line(style(msg).cyan());
+}
+
+/// Something taken differently than asked, e.g. a default for an invalid answer.
+pub fn warning(msg: impl Display) {
+    line(style(msg).yellow());
+}
+
+/// A notice of the booking site that may stop the trip, e.g. a suspended service.
+pub fn alert(msg: impl Display) {
+    line(style(msg).red().bold());
+}
+
+pub fn success(msg: impl Display) {
+    line(style(msg).green().bold());
+}
+
+/// Print `msg` as an error, making `thsr` exit with a failure status.
+pub fn error(msg: impl Display) {
+    line(format_args!(
+        "{}{}",
+        style(tr!("Error: ", "錯誤：")).red().bold(),
+        msg
+    ));
+    exit::failed();
+}
@@ -0,0 +1,68 @@
+//! Named passenger profiles from the config file, picked with `--passengers` to
+//! compose a booking without entering everyone's details, and the IDs passengers
+//! book with.
+
+use secrecy::ExposeSecret;
+
+use std::fs;
+
+use crate::cli::Args;
+use crate::config::Config;
+use crate::group::MAX_TICKETS;
+
+pub use thsr_core::passenger::*;
+
+/// Look up the profiles named in `args.passengers` into `args.party`. The first
+/// passenger books, their ID, membership, phone and email taking the place of the command line ones.
+pub fn pick(args: &mut Args, config: &Config) -> Result<(), String> {
+    if args.passengers.is_empty() {
+        return Ok(());
+    }
+    if args.passengers.len() > usize::from(MAX_TICKETS) {
+        return Err(format!(
+            "At most {} passengers can be booked together",
+            MAX_TICKETS
+        ));
+    }
+    let party = args
+        .passengers
+        .iter()
+        .map(|name| {
+            config
+                .passengers
+                .get(name)
+                .cloned()
+                .ok_or_else(|| format!("No passenger named {} in the config file", name))
+        })
+        .collect::<Result<Vec<Passenger>, String>>()?;
+
+    let taker = &party[0];
+    args.personal_id = Some(taker.id.expose_secret().to_string());
+    if let Some(phone) = &taker.phone {
+        args.phone = Some(phone.clone());
+    }
+    if let Some(email) = &taker.email {
+        args.email = Some(email.clone());
+    }
+    if let Some(membership) = taker.membership {
+        args.use_membership = Some(membership);
+    }
+    args.party = party;
+    Ok(())
+}
+
+/// Read the IDs of `--passenger-file` into `args.passenger_ids`: one per line, leaving
+/// out empty lines and those starting with `#`.
+pub fn read_ids(args: &mut Args) -> Result<(), String> {
+    let Some(path) = &args.passenger_file else {
+        return Ok(());
+    };
+    let content = fs::read_to_string(path)
+        .map_err(|err| format!("Failed to read passenger IDs {}: {}", path.display(), err))?;
+    args.passenger_ids = (content.lines())
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect();
+    Ok(())
+}
@@ -0,0 +1,101 @@
+//! Picking the train to book from the train list without asking, for unattended
+//! bookings and presets.
+
+use chrono::NaiveTime;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::cli::Args;
+use crate::confirm_train_flow::{Seats, Train};
+use crate::error::Error;
+use crate::script;
+
+#[derive(clap::ValueEnum, Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TrainPick {
+    /// Ask which train to book
+    #[default]
+    Ask,
+    /// The first train listed, departing the earliest
+    First,
+    /// The train with the shortest travel time, the earliest of them on a tie
+    Fastest,
+}
+
+/// The train `pick` books among `trains`, leaving out those departing after
+/// `depart_before`, or `None` to ask for one.
+pub fn train(
+    trains: &[Train],
+    pick: TrainPick,
+    depart_before: Option<NaiveTime>,
+) -> Result<Option<u32>, Error> {
+    if pick == TrainPick::Ask {
+        return Ok(None);
+    }
+    let in_window = trains.iter().filter(|train| match depart_before {
+        Some(before) => train.depart() <= before,
+        None => true,
+    });
+    let picked = match pick {
+        TrainPick::Ask | TrainPick::First => in_window.min_by_key(|train| train.depart()),
+        TrainPick::Fastest => {
+            in_window.min_by_key(|train| (train.travel_minutes(), train.depart()))
+        }
+    };
+    match (picked, depart_before) {
+        (Some(train), _) => Ok(Some(train.id())),
+        (None, Some(before)) => Err(Error::SoldOut(format!(
+            "No train departs by {}",
+            before.format("%H:%M")
+        ))),
+        (None, None) => Err(Error::SoldOut("No trains listed".to_string())),
+    }
+}
+
+/// The first train of `preference` listed with seats among `trains`, for `--train`
+/// given several trains.
+pub fn preferred(trains: &[Train], preference: &[u32]) -> Result<u32, Error> {
+    (preference.iter().copied())
+        .find(|&id| {
+            (trains.iter()).any(|train| train.id() == id && train.seats() != Seats::StandingOnly)
+        })
+        .ok_or_else(|| {
+            let ids: Vec<String> = preference.iter().map(u32::to_string).collect();
+            Error::SoldOut(format!(
+                "None of the trains {} is listed with seats",
+                ids.join(", ")
+            ))
+        })
+}
+
+/// The train `args` book among `trains` without a single `--train`: the first of
+/// several listed with seats, or the one picked by `--select-script`, `--select` or
+/// `--pick`, in that order. `None` to ask for one.
+pub fn select(trains: &[Train], args: &Args) -> Result<Option<u32>, Error> {
+    match (&args.select_script, &args.select) {
+        _ if args.train.len() > 1 => preferred(trains, &args.train).map(Some),
+        (Some(path), _) => script::select(path, trains),
+        (None, Some(selection)) => selection.train(trains).map(Some),
+        (None, None) => train(trains, args.pick.unwrap_or_default(), args.depart_before),
+    }
+}
+
+pub use thsr_core::page::parse_clock;
+
+/// (De)serialize an optional time of the day as `HH:MM`.
+pub mod clock {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(time: &Option<NaiveTime>, ser: S) -> Result<S::Ok, S::Error> {
+        match time {
+            Some(time) => ser.serialize_str(&time.format("%H:%M").to_string()),
+            None => ser.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(de: D) -> Result<Option<NaiveTime>, D::Error> {
+        let time = String::deserialize(de)?;
+        parse_clock(&time)
+            .map(Some)
+            .map_err(serde::de::Error::custom)
+    }
+}
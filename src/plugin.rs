@@ -0,0 +1,82 @@
+//! Plugins, the way git runs them: `thsr foo` runs a `thsr-foo` executable found on
+//! the PATH with the rest of the command line, telling it where thsr keeps its files.
+
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::path::PathBuf;
+use std::process::{Command, ExitCode};
+
+use crate::config::Config;
+use crate::exit;
+use crate::history;
+use crate::i18n::{self, Lang, tr};
+use crate::output;
+
+/// The executable of the plugin `name`, `thsr-<name>` in one of the directories of `path`.
+pub fn find(name: &str, path: &OsStr) -> Option<PathBuf> {
+    let file = format!("thsr-{}{}", name, env::consts::EXE_SUFFIX);
+    env::split_paths(path)
+        .map(|dir| dir.join(&file))
+        .find(|candidate| candidate.is_file())
+}
+
+/// What a plugin is told through its environment: where the config file and the
+/// history are, the executable of thsr and the language in use. A config path
+/// already in `THSR_CONFIG` is kept.
+pub fn env(no_color: bool) -> Vec<(&'static str, OsString)> {
+    let mut vars = Vec::new();
+    let config = env::var_os("THSR_CONFIG").or_else(|| Config::default_path().map(Into::into));
+    if let Some(config) = config {
+        vars.push(("THSR_CONFIG", config));
+    }
+    if let Some(history) = history::default_path() {
+        vars.push(("THSR_HISTORY", history.into()));
+    }
+    if let Ok(exe) = env::current_exe() {
+        vars.push(("THSR", exe.into()));
+    }
+    let lang = match i18n::lang() {
+        Lang::En => "en",
+        Lang::Zh => "zh",
+    };
+    vars.push(("THSR_LANG", lang.into()));
+    if no_color {
+        vars.push(("NO_COLOR", "1".into()));
+    }
+    vars
+}
+
+/// Run the plugin named by the first of `args` with the others, exiting as it does.
+pub fn run(args: Vec<OsString>, no_color: bool) -> ExitCode {
+    let Some((name, rest)) = args.split_first() else {
+        return ExitCode::SUCCESS;
+    };
+    let name = name.to_string_lossy();
+    let path = env::var_os("PATH").unwrap_or_default();
+    let Some(program) = find(&name, &path) else {
+        output::error(tr!(
+            "No subcommand {}, and no plugin thsr-{} on the PATH. See thsr --help",
+            "沒有 {} 指令，PATH 中也沒有 thsr-{} 外掛。請參考 thsr --help",
+            name,
+            name
+        ));
+        exit::fail(exit::Status::Invalid);
+        return exit::status().into();
+    };
+    match Command::new(&program)
+        .args(rest)
+        .envs(env(no_color))
+        .status()
+    {
+        Ok(status) => match status.code() {
+            Some(code) => ExitCode::from(code as u8),
+            // Killed by a signal
+            None => ExitCode::FAILURE,
+        },
+        Err(err) => {
+            output::error(format!("Failed to run {}: {}", program.display(), err));
+            exit::fail(exit::Status::Failed);
+            exit::status().into()
+        }
+    }
+}
@@ -0,0 +1,229 @@
+//! Named trips of the config file, booked with `--preset` and managed with
+//! `thsr preset`.
+
+use chrono::NaiveTime;
+use serde::{Deserialize, Serialize};
+use toml_edit::{DocumentMut, Item};
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::cli::{Args, PresetAction, PresetArgs};
+use crate::config::Config;
+use crate::exit;
+use crate::i18n::tr;
+use crate::output;
+use crate::pick::{self, TrainPick};
+use crate::schema::{Station, TimeSlot};
+
+/// The route, time window, tickets and train pick of a trip, as in a `[presets.NAME]`
+/// section of the config file. Whatever is left out is taken from the command line or asked.
+#[derive(clap::Args, Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Preset {
+    /// Departure station ID, code or name
+    #[arg(long, short = 'f', value_name = "STATION")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<Station>,
+
+    /// Arrival station ID, code or name
+    #[arg(long, short = 't', value_name = "STATION")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<Station>,
+
+    /// Time to search for trains from, by its ID or as HH:MM
+    #[arg(long, short = 'T', value_name = "TIME")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time: Option<TimeSlot>,
+
+    /// Latest departure of the train picked, e.g. 19:00
+    #[arg(long, value_name = "HH:MM", value_parser = pick::parse_clock)]
+    #[serde(with = "pick::clock", skip_serializing_if = "Option::is_none")]
+    pub depart_before: Option<NaiveTime>,
+
+    /// Number of adults
+    #[arg(long, short = 'a', value_name = "NUMBER")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub adult_cnt: Option<u8>,
+
+    /// Number of students
+    #[arg(long, short = 's', value_name = "NUMBER")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub student_cnt: Option<u8>,
+
+    /// Passenger profiles of the config file to book for, e.g. mom,dad,me
+    #[arg(long, value_name = "NAMES", value_delimiter = ',')]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub passengers: Vec<String>,
+
+    /// Seat preference. 0: None, 1: Window, 2: Aisle
+    #[arg(long, short = 'p', value_name = "NUMBER", value_parser = clap::value_parser!(u8).range(0..=2))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seat_prefer: Option<u8>,
+
+    /// Class type. 0: Standard, 1: Business
+    #[arg(long, short = 'c', value_name = "NUMBER", value_parser = clap::value_parser!(u8).range(0..=1))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub class_type: Option<u8>,
+
+    /// How to pick the train without asking
+    #[arg(long, value_name = "PICK")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pick: Option<TrainPick>,
+}
+
+impl Preset {
+    /// Fill in the options not given in `args`. Tickets are taken as a whole, so
+    /// giving any of the counts or passengers leaves out those of the preset.
+    pub fn apply(&self, args: &mut Args) {
+        args.from = args.from.or(self.from);
+        args.to = args.to.or(self.to);
+        args.time = args.time.or(self.time);
+        args.depart_before = args.depart_before.or(self.depart_before);
+        args.seat_prefer = args.seat_prefer.or(self.seat_prefer.map(usize::from));
+        args.class_type = args.class_type.or(self.class_type.map(usize::from));
+        args.pick = args.pick.or(self.pick);
+        if args.adult_cnt.is_none() && args.student_cnt.is_none() && args.passengers.is_empty() {
+            args.adult_cnt = self.adult_cnt;
+            args.student_cnt = self.student_cnt;
+            args.passengers = self.passengers.clone();
+        }
+    }
+}
+
+/// Look up the preset `name` of `config` into `args`.
+pub fn apply(name: &str, args: &mut Args, config: &Config) -> Result<(), String> {
+    let preset = (config.presets.get(name))
+        .ok_or_else(|| format!("No preset named {} in the config file", name))?;
+    preset.apply(args);
+    Ok(())
+}
+
+impl fmt::Display for Preset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        match (self.from, self.to) {
+            (Some(from), Some(to)) => parts.push(format!("{} → {}", from, to)),
+            (Some(from), None) => parts.push(tr!("from {}", "從 {}", from)),
+            (None, Some(to)) => parts.push(tr!("to {}", "到 {}", to)),
+            (None, None) => {}
+        }
+        if let Some(time) = self.time {
+            parts.push(tr!("trains from {}", "{} 起的車次", time));
+        }
+        if let Some(before) = self.depart_before {
+            parts.push(tr!("departing by {}", "{} 前出發", before.format("%H:%M")));
+        }
+        if !self.passengers.is_empty() {
+            parts.push(self.passengers.join(", "));
+        }
+        if let Some(count) = self.adult_cnt {
+            parts.push(tr!("{} adults", "全票 {} 張", count));
+        }
+        if let Some(count) = self.student_cnt {
+            parts.push(tr!("{} students", "學生票 {} 張", count));
+        }
+        if let Some(pick) = self.pick {
+            parts.push(match pick {
+                TrainPick::Ask => tr!("train asked", "詢問車次"),
+                TrainPick::First => tr!("first train", "最早的車次"),
+                TrainPick::Fastest => tr!("fastest train", "最快的車次"),
+            });
+        }
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+/// The config file at `path`, keeping its comments and layout. A missing file is an empty one.
+fn read(path: &Path) -> Result<DocumentMut, String> {
+    if !path.exists() {
+        return Ok(DocumentMut::new());
+    }
+    let content = fs::read_to_string(path)
+        .map_err(|err| format!("Failed to read config {}: {}", path.display(), err))?;
+    content
+        .parse()
+        .map_err(|err| format!("Invalid config {}: {}", path.display(), err))
+}
+
+fn write(path: &Path, doc: &DocumentMut) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .map_err(|err| format!("Failed to create {}: {}", dir.display(), err))?;
+    }
+    fs::write(path, doc.to_string())
+        .map_err(|err| format!("Failed to write config {}: {}", path.display(), err))
+}
+
+/// The presets of the config file at `path`, by name.
+pub fn list(path: &Path) -> Result<BTreeMap<String, Preset>, String> {
+    let mut table: toml::Table = toml::from_str(&read(path)?.to_string())
+        .map_err(|err| format!("Invalid config {}: {}", path.display(), err))?;
+    let Some(presets) = table.remove("presets") else {
+        return Ok(BTreeMap::new());
+    };
+    presets
+        .try_into()
+        .map_err(|err| format!("Invalid presets in config {}: {}", path.display(), err))
+}
+
+/// Add `preset` as `name` to the config file at `path`, replacing the one of that name.
+pub fn add(path: &Path, name: &str, preset: &Preset) -> Result<(), String> {
+    let mut doc = read(path)?;
+    let section: DocumentMut = (toml::to_string(preset).map_err(|err| err.to_string()))?
+        .parse()
+        .map_err(|err: toml_edit::TomlError| err.to_string())?;
+    let presets = doc
+        .entry("presets")
+        .or_insert(toml_edit::table())
+        .as_table_mut()
+        .ok_or_else(|| format!("Invalid config {}: presets must be a table", path.display()))?;
+    presets.set_implicit(true);
+    presets.insert(name, Item::Table(section.as_table().clone()));
+    write(path, &doc)
+}
+
+/// Remove the preset `name` from the config file at `path`, telling if there was one.
+pub fn remove(path: &Path, name: &str) -> Result<bool, String> {
+    let mut doc = read(path)?;
+    let removed = (doc.get_mut("presets"))
+        .and_then(Item::as_table_like_mut)
+        .and_then(|presets| presets.remove(name))
+        .is_some();
+    if removed {
+        write(path, &doc)?;
+    }
+    Ok(removed)
+}
+
+/// Add, list or remove the presets of the config file.
+pub fn run(preset_args: PresetArgs) {
+    let Some(path) = preset_args.config.or_else(Config::default_path) else {
+        output::error("No config directory for the presets, use --config");
+        exit::fail(exit::Status::Invalid);
+        return;
+    };
+    let outcome = match preset_args.action {
+        PresetAction::Add { name, preset } => add(&path, &name, &preset).map(|()| {
+            output::success(tr!("Preset {} saved", "已儲存預設行程 {}", name));
+        }),
+        PresetAction::List => list(&path).map(|presets| {
+            if presets.is_empty() {
+                output::line(tr!("No presets yet", "尚無預設行程"));
+            }
+            for (name, preset) in presets {
+                output::line(format_args!("{}: {}", name, preset));
+            }
+        }),
+        PresetAction::Remove { name } => remove(&path, &name).map(|removed| match removed {
+            true => output::success(tr!("Preset {} removed", "已移除預設行程 {}", name)),
+            false => output::warning(tr!("No preset named {}", "沒有名為 {} 的預設行程", name)),
+        }),
+    };
+    if let Err(err_msg) = outcome {
+        output::error(err_msg);
+        exit::fail(exit::Status::Invalid);
+    }
+}
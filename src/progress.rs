@@ -0,0 +1,43 @@
+//! Spinners on stderr while waiting for the booking site, so that a slow step
+//! doesn't look like a hang. Only drawn on a terminal.
+
+use indicatif::{ProgressBar, ProgressStyle};
+
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// The spinner being drawn, for the log to write around it.
+static CURRENT: Mutex<Option<ProgressBar>> = Mutex::new(None);
+
+/// No spinners from now on, for the modes booking in the background or full screen.
+pub fn disable() {
+    ENABLED.store(false, Ordering::Relaxed);
+}
+
+/// Run `wait` under a spinner telling `step`, e.g. `Booking`, and the time it has taken so far.
+pub fn spin<T>(step: &str, wait: impl FnOnce() -> T) -> T {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return wait();
+    }
+    let spinner = ProgressBar::new_spinner()
+        .with_style(ProgressStyle::with_template("{spinner:.cyan} {msg} {elapsed:.dim}").unwrap())
+        .with_message(format!("{}…", step));
+    spinner.enable_steady_tick(Duration::from_millis(100));
+    *CURRENT.lock().unwrap() = Some(spinner.clone());
+    let outcome = wait();
+    CURRENT.lock().unwrap().take();
+    spinner.finish_and_clear();
+    outcome
+}
+
+/// Run `write` with the spinner, if any, out of the way.
+pub(crate) fn suspend<T>(write: impl FnOnce() -> T) -> T {
+    let current = CURRENT.lock().unwrap().clone();
+    match current {
+        Some(spinner) => spinner.suspend(write),
+        None => write(),
+    }
+}
@@ -0,0 +1,277 @@
+use dialoguer::theme::ColorfulTheme;
+use dialoguer::{Confirm, FuzzySelect, Input, Password, Select};
+
+use std::cell::RefCell;
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, IsTerminal};
+use std::path::Path;
+use std::process;
+
+use crate::alert::Severity;
+use crate::captcha;
+use crate::config::CaptchaConfig;
+use crate::exit;
+use crate::i18n::tr;
+use crate::output;
+use crate::remote::BookingRequest;
+
+pub use thsr_core::prompt::*;
+
+/// Print what the booking tells on the terminal, leaving out what is only counted
+/// or emitted as an event, see [`crate::frontend::Frontend`].
+pub fn print(notice: Notice) {
+    match notice {
+        Notice::Line(text) => output::line(text),
+        Notice::Status(text) => output::status(text),
+        Notice::Warning(text) => output::warning(text),
+        // In the color of its severity
+        Notice::Alert(alert) => match alert.severity {
+            Severity::Critical => output::alert(&alert.text),
+            Severity::Warning => output::warning(&alert.text),
+            Severity::Info => output::status(&alert.text),
+        },
+        _ => {}
+    }
+}
+
+/// Print `items` as a numbered list.
+fn print_items(items: &[String]) {
+    for (idx, item) in items.iter().enumerate() {
+        output::line(format_args!("{:>2}. {}", idx + 1, item));
+    }
+}
+
+/// Asks on the terminal, the way the CLI does: with arrow-key lists and checked
+/// input on an interactive terminal, line by line otherwise. The security code is
+/// shown and asked for as `captcha` says.
+pub struct StdinPrompter {
+    captcha: CaptchaConfig,
+}
+
+impl StdinPrompter {
+    pub fn new(captcha: &CaptchaConfig) -> Self {
+        StdinPrompter {
+            captcha: captcha.clone(),
+        }
+    }
+}
+
+impl Prompter for StdinPrompter {
+    fn ask(&self, hint: &str) -> String {
+        output::line(hint);
+        let mut input = String::new();
+        std::io::stdin().read_line(&mut input).unwrap_or_default();
+        input.trim().to_string()
+    }
+
+    fn select(&self, hint: &str, items: &[String], default: usize) -> usize {
+        if !io::stdin().is_terminal() {
+            print_items(items);
+            return pick(self, hint, items, default, false);
+        }
+        Select::with_theme(&ColorfulTheme::default())
+            .with_prompt(hint)
+            .items(items)
+            .default(default)
+            .max_length(15)
+            .interact()
+            .unwrap_or(default)
+    }
+
+    fn search(&self, hint: &str, items: &[String], default: usize) -> usize {
+        if !io::stdin().is_terminal() {
+            print_items(items);
+            return pick(self, hint, items, default, true);
+        }
+        FuzzySelect::with_theme(&ColorfulTheme::default())
+            .with_prompt(tr!("{} (type to filter)", "{}（輸入文字篩選）", hint))
+            .items(items)
+            .default(default)
+            .max_length(15)
+            .interact()
+            .unwrap_or(default)
+    }
+
+    fn input(&self, hint: &str, default: &str, check: Check) -> String {
+        if !io::stdin().is_terminal() {
+            return input_checked(self, hint, default, check);
+        }
+        let theme = ColorfulTheme::default();
+        let mut input = Input::<String>::with_theme(&theme)
+            .with_prompt(hint)
+            .validate_with(|answer: &String| check(answer.trim()));
+        if !default.is_empty() {
+            input = input.default(default.to_string());
+        }
+        match input.interact_text() {
+            Ok(answer) => answer.trim().to_string(),
+            Err(_) => default.to_string(),
+        }
+    }
+
+    fn secret(&self, hint: &str, default: &str, check: Check) -> String {
+        if !io::stdin().is_terminal() {
+            return input_checked(self, hint, default, check);
+        }
+        let hint = match default {
+            "" => hint.to_string(),
+            _ => tr!(
+                "{} (Enter keeps the one given)",
+                "{}（按 Enter 沿用已提供的）",
+                hint
+            ),
+        };
+        let answer = Password::with_theme(&ColorfulTheme::default())
+            .with_prompt(hint)
+            .allow_empty_password(!default.is_empty())
+            .validate_with(|answer: &String| match answer.trim() {
+                "" => check(default),
+                answer => check(answer),
+            })
+            .interact()
+            .unwrap_or_default();
+        match answer.trim() {
+            "" => default.to_string(),
+            answer => answer.to_string(),
+        }
+    }
+
+    fn confirm(&self, hint: &str) -> bool {
+        if !io::stdin().is_terminal() {
+            return confirm_answered(self, hint);
+        }
+        Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt(hint)
+            .interact()
+            .unwrap_or(false)
+    }
+
+    fn security_code(&self, img_data: &[u8]) -> Option<String> {
+        captcha::ask(img_data, &self.captcha)
+    }
+
+    fn tell(&self, notice: Notice) {
+        print(notice)
+    }
+}
+
+/// Answers from `key=value` lines, for scripts driving the booking like expect does:
+/// the options of the booking first, up to an empty line, then an `answer=` line
+/// for every question and a `captcha=` line for every security code, in the order
+/// they are asked. Lines starting with `#` are skipped.
+pub struct StreamPrompter {
+    lines: RefCell<Box<dyn BufRead>>,
+    captcha: CaptchaConfig,
+}
+
+impl StreamPrompter {
+    pub fn new(reader: impl BufRead + 'static) -> Self {
+        StreamPrompter {
+            lines: RefCell::new(Box::new(reader)),
+            captcha: CaptchaConfig::default(),
+        }
+    }
+
+    /// Show the security code images as `captcha` says, e.g. writing them to `--captcha-out`.
+    pub fn with_captcha(self, captcha: &CaptchaConfig) -> Self {
+        StreamPrompter {
+            captcha: captcha.clone(),
+            ..self
+        }
+    }
+
+    /// Read the lines from stdin for `-`, otherwise from the file at `path`, e.g. a named pipe.
+    pub fn open(path: &Path) -> Result<Self, String> {
+        if path == Path::new("-") {
+            return Ok(StreamPrompter::new(io::stdin().lock()));
+        }
+        let file = File::open(path)
+            .map_err(|err| format!("Failed to open the answers {}: {}", path.display(), err))?;
+        Ok(StreamPrompter::new(BufReader::new(file)))
+    }
+
+    /// The options of the booking, the fields of `POST /bookings` of `thsr serve`,
+    /// read up to an empty line or the end of the stream.
+    pub fn options(&self) -> Result<BookingRequest, String> {
+        let mut request = BookingRequest::default();
+        while let Some(line) = self.line() {
+            if line.is_empty() {
+                break;
+            }
+            let (key, value) = split(&line)?;
+            request.set(key, value)?;
+        }
+        Ok(request)
+    }
+
+    /// The next line that isn't a comment, trimmed, or `None` at the end of the stream.
+    fn line(&self) -> Option<String> {
+        let mut lines = self.lines.borrow_mut();
+        loop {
+            let mut line = String::new();
+            match lines.read_line(&mut line) {
+                Ok(0) | Err(_) => return None,
+                Ok(_) if line.trim_start().starts_with('#') => continue,
+                Ok(_) => return Some(line.trim().to_string()),
+            }
+        }
+    }
+
+    /// The value of the next line, which must be a `key=` one. The booking can't go on
+    /// without it, so `thsr` exits otherwise.
+    fn expect(&self, key: &str) -> String {
+        let line = loop {
+            match self.line() {
+                Some(line) if line.is_empty() => continue,
+                Some(line) => break line,
+                None => broken(tr!(
+                    "The answers ended while waiting for {}=",
+                    "答案已讀完，仍在等待 {}=",
+                    key
+                )),
+            }
+        };
+        match split(&line) {
+            Ok((found, value)) if found == key => value.to_string(),
+            Ok((found, _)) => broken(tr!(
+                "Expected {}= in the answers, got {}=",
+                "答案應為 {}=，卻是 {}=",
+                key,
+                found
+            )),
+            Err(err_msg) => broken(err_msg),
+        }
+    }
+}
+
+/// The key and value of a `key=value` line.
+fn split(line: &str) -> Result<(&str, &str), String> {
+    match line.split_once('=') {
+        Some((key, value)) => Ok((key.trim(), value.trim())),
+        None => Err(format!("Invalid answer line, expected key=value: {}", line)),
+    }
+}
+
+/// Give up on a booking whose answers don't follow the protocol.
+fn broken(err_msg: impl Display) -> ! {
+    output::error(err_msg);
+    exit::fail(exit::Status::Invalid);
+    process::exit(exit::status().code().into())
+}
+
+impl Prompter for StreamPrompter {
+    fn ask(&self, hint: &str) -> String {
+        output::line(hint);
+        self.expect("answer")
+    }
+
+    fn security_code(&self, img_data: &[u8]) -> Option<String> {
+        let _image_file = captcha::show(img_data, &self.captcha);
+        captcha::parse_answer(&self.expect("captcha"))
+    }
+
+    fn tell(&self, notice: Notice) {
+        print(notice)
+    }
+}
@@ -0,0 +1,100 @@
+//! `thsr remind`, telling about the bookings of the history still to be paid before
+//! their PNR expires. Paying happens elsewhere, so every booking whose payment deadline
+//! hasn't passed counts as unpaid.
+
+use chrono::{DateTime, Duration, FixedOffset};
+
+use std::collections::HashSet;
+use std::thread;
+
+use crate::cli::RemindArgs;
+use crate::config::Config;
+use crate::exit;
+use crate::history::{self, Entry};
+use crate::i18n::tr;
+use crate::notify::{self, Event};
+use crate::output;
+use crate::result::BookingResult;
+use crate::time::{self, taipei_now};
+
+/// The bookings of `entries` to be paid within `before` of `now`, with the time left
+/// to pay each of them, the soonest first.
+pub fn due(
+    entries: &[Entry],
+    now: DateTime<FixedOffset>,
+    before: Duration,
+) -> Vec<(&BookingResult, Duration)> {
+    let mut due: Vec<_> = (entries.iter())
+        .filter_map(|entry| entry.result.as_ref())
+        .filter_map(|result| Some((result, result.time_to_pay(now)?)))
+        .filter(|(_, left)| *left > Duration::zero() && *left <= before)
+        .collect();
+    due.sort_by_key(|(_, left)| *left);
+    due
+}
+
+/// The reminder of `result`, e.g. `PNR 08123456, 台北 → 左營 2025/06/29 06:30: pay
+/// TWD 2,980 before 2025/06/03 23:59, within 3 hours 20 minutes`.
+pub fn reminder(result: &BookingResult, left: Duration) -> String {
+    tr!(
+        "PNR {}, {} → {} {} {}: pay {} before {}, within {}",
+        "訂位代號 {}，{} → {} {} {}：{} 請於 {} 前付款，剩餘 {}",
+        result.pnr,
+        result.from,
+        result.to,
+        result.date,
+        result.depart_time,
+        result.price,
+        result.payment_deadline,
+        time::describe_span(left)
+    )
+}
+
+/// Tell about the bookings due within `--before`, on the terminal and through the
+/// configured notifiers, once or every `--every` until interrupted.
+pub fn run(remind_args: RemindArgs) {
+    let Some(path) = remind_args.history.or_else(history::default_path) else {
+        output::error("No data directory for the history, use --history");
+        exit::fail(exit::Status::Invalid);
+        return;
+    };
+    let config = match Config::load(remind_args.config.as_deref(), None) {
+        Ok(config) => config,
+        Err(err_msg) => {
+            output::error(err_msg);
+            exit::fail(exit::Status::Invalid);
+            return;
+        }
+    };
+    let before = Duration::from_std(remind_args.before).unwrap_or(Duration::MAX);
+
+    let mut reminded = HashSet::new();
+    loop {
+        let entries = match history::load(&path) {
+            Ok(entries) => entries,
+            Err(err_msg) => {
+                output::error(err_msg);
+                exit::fail(exit::Status::Failed);
+                return;
+            }
+        };
+        let due = due(&entries, taipei_now(), before);
+        if due.is_empty() && remind_args.every.is_none() {
+            output::line(tr!(
+                "No bookings to pay within {}",
+                "{} 內沒有待付款的訂位",
+                humantime::format_duration(remind_args.before)
+            ));
+        }
+        for (result, left) in due {
+            if reminded.insert(result.pnr.clone()) {
+                output::warning(reminder(result, left));
+                notify::notify(&config, &Event::PaymentDue(result));
+            }
+        }
+        match remind_args.every {
+            Some(every) => thread::sleep(every),
+            None => return,
+        }
+    }
+}
@@ -0,0 +1,229 @@
+//! Bookings run on behalf of a remote caller, for the JSON and gRPC APIs.
+//!
+//! A booking runs the same steps as on the command line, on its own thread. Whatever
+//! would be asked at the terminal, the security code included, is handed to the
+//! caller through [`Update`]s, and the answers come back through a channel.
+
+use serde::Deserialize;
+
+use std::str::FromStr;
+
+use crate::cli::Args;
+use crate::confirm_train_flow::Train;
+use crate::passenger::parse_passport;
+use crate::result::BookingResult;
+use crate::schema::{Station, TimeSlot};
+use crate::stage::Progress;
+
+// Running the bookings needs the blocking client, the requests and updates don't
+#[cfg(feature = "blocking")]
+use {
+    crate::captcha,
+    crate::config::Config,
+    crate::metrics,
+    crate::prompt::{self, Notice, Prompter},
+    crate::{book_with_journal, build_client},
+    std::cell::Cell,
+    std::panic::{self, AssertUnwindSafe},
+    std::sync::Arc,
+    std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    std::thread,
+    std::time::Duration,
+    tracing::{info, warn},
+};
+
+/// A booking nobody answers for this long is given up.
+#[cfg(feature = "blocking")]
+const ANSWER_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// The options of a new booking. Whatever is left out is asked for like on the command line.
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct BookingRequest {
+    pub personal_id: Option<String>,
+    #[serde(deserialize_with = "deserialize_passport")]
+    pub passport: Option<String>,
+    pub from: Option<Station>,
+    pub to: Option<Station>,
+    pub date: Option<String>,
+    pub time: Option<TimeSlot>,
+    pub train: Option<u32>,
+    pub adult_cnt: Option<u8>,
+    pub student_cnt: Option<u8>,
+    pub seat_prefer: Option<usize>,
+    pub class_type: Option<usize>,
+    pub use_membership: Option<bool>,
+}
+
+impl BookingRequest {
+    /// `args` with the options of the request taking precedence.
+    pub fn apply(self, args: &Args) -> Args {
+        let args = args.clone();
+        Args {
+            personal_id: self.personal_id.or(args.personal_id),
+            passport: self.passport.or(args.passport),
+            from: self.from.or(args.from),
+            to: self.to.or(args.to),
+            date: self.date.or(args.date),
+            time: self.time.or(args.time),
+            train: self.train.map_or(args.train, |train| vec![train]),
+            adult_cnt: self.adult_cnt.or(args.adult_cnt),
+            student_cnt: self.student_cnt.or(args.student_cnt),
+            seat_prefer: self.seat_prefer.or(args.seat_prefer),
+            class_type: self.class_type.or(args.class_type),
+            use_membership: self.use_membership.or(args.use_membership),
+            ..args
+        }
+    }
+
+    /// Set the option `key` to `value`, as given by a `key=value` line of `--answers`.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        fn parse<T: FromStr>(key: &str, value: &str) -> Result<Option<T>, String> {
+            match value.parse() {
+                Ok(value) => Ok(Some(value)),
+                Err(_) => Err(format!("Invalid {}: {}", key, value)),
+            }
+        }
+        match key {
+            "personal_id" => self.personal_id = Some(value.to_string()),
+            "passport" => self.passport = Some(parse_passport(value)?),
+            "from" => self.from = parse(key, value)?,
+            "to" => self.to = parse(key, value)?,
+            "date" => self.date = Some(value.to_string()),
+            "time" => self.time = parse(key, value)?,
+            "train" => self.train = parse(key, value)?,
+            "adult_cnt" => self.adult_cnt = parse(key, value)?,
+            "student_cnt" => self.student_cnt = parse(key, value)?,
+            "seat_prefer" => self.seat_prefer = parse(key, value)?,
+            "class_type" => self.class_type = parse(key, value)?,
+            "use_membership" => self.use_membership = parse(key, value)?,
+            _ => return Err(format!("Unknown option {}", key)),
+        }
+        Ok(())
+    }
+}
+
+fn deserialize_passport<'de, D: serde::Deserializer<'de>>(
+    de: D,
+) -> Result<Option<String>, D::Error> {
+    let number = String::deserialize(de)?;
+    parse_passport(&number)
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+/// What a remote booking tells its caller, in order.
+pub enum Update<'a> {
+    /// The step the booking is about to take
+    Reached(&'a Progress),
+    /// The trains found by the search
+    Trains(Vec<Train>),
+    /// Waiting for the answer to a question
+    Question(&'a str),
+    /// Waiting for the security code in the image, or `r` for a new image
+    Captcha(&'a [u8]),
+    /// The last update of a booking
+    Done(&'a Result<BookingResult, String>),
+}
+
+#[cfg(feature = "blocking")]
+type Notify = dyn Fn(Update) + Send + Sync;
+
+/// Hands the questions of a booking over to the caller and waits for the answers.
+#[cfg(feature = "blocking")]
+struct RemotePrompter {
+    notify: Arc<Notify>,
+    answers: Receiver<String>,
+    abandoned: Cell<bool>,
+}
+
+#[cfg(feature = "blocking")]
+impl RemotePrompter {
+    fn ask_caller(&self, update: Update) -> String {
+        (self.notify)(update);
+        match self.answers.recv_timeout(ANSWER_TIMEOUT) {
+            Ok(answer) => answer.trim().to_string(),
+            Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => {
+                self.abandoned.set(true);
+                // Like a scripted prompter running out of answers, without the panic message
+                panic::resume_unwind(Box::new("abandoned"));
+            }
+        }
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl Prompter for RemotePrompter {
+    fn ask(&self, hint: &str) -> String {
+        self.ask_caller(Update::Question(hint))
+    }
+
+    fn security_code(&self, img_data: &[u8]) -> Option<String> {
+        captcha::parse_answer(&self.ask_caller(Update::Captcha(img_data)))
+    }
+
+    // The caller has the lists, e.g. from `Update::Trains`, nothing is printed
+    fn select(&self, hint: &str, items: &[String], default: usize) -> usize {
+        prompt::pick(self, hint, items, default, false)
+    }
+
+    fn search(&self, hint: &str, items: &[String], default: usize) -> usize {
+        prompt::pick(self, hint, items, default, true)
+    }
+
+    fn tell(&self, notice: Notice) {
+        prompt::print(notice)
+    }
+}
+
+/// Start a booking named `id` on its own thread, telling `notify` how it goes.
+/// Answers to the questions are sent through the returned channel.
+#[cfg(feature = "blocking")]
+pub fn spawn(
+    id: String,
+    args: Args,
+    config: Arc<Config>,
+    notify: impl Fn(Update) + Send + Sync + 'static,
+) -> Sender<String> {
+    let (sender, receiver) = mpsc::channel();
+    let notify: Arc<Notify> = Arc::new(notify);
+    // Starting a booking through a frontend is the confirmation
+    let args = Args { yes: true, ..args };
+    thread::spawn(move || {
+        let prompter = RemotePrompter {
+            notify: Arc::clone(&notify),
+            answers: receiver,
+            abandoned: Cell::new(false),
+        };
+        let client = build_client(&config);
+        // A panic, e.g. over an answer out of range, only ends this booking
+        let outcome = panic::catch_unwind(AssertUnwindSafe(|| {
+            book_with_journal(&client, &args, &config, &prompter, &mut |checkpoint| {
+                notify(Update::Reached(checkpoint.progress));
+                if let Progress::Train(stage) = checkpoint.progress {
+                    notify(Update::Trains(stage.trains()));
+                }
+            })
+        }));
+        let outcome = match outcome {
+            Ok(Ok(result)) => {
+                info!("Booking {} done, PNR {}", id, result.pnr);
+                Ok(result)
+            }
+            Ok(Err(err)) => {
+                warn!("Booking {} failed: {}", id, err);
+                Err(err.to_string())
+            }
+            Err(_) if prompter.abandoned.get() => Err("No answer in time".to_string()),
+            Err(_) => Err("The booking stopped unexpectedly".to_string()),
+        };
+        metrics::finished(outcome.is_ok());
+        notify(Update::Done(&outcome));
+    });
+    sender
+}
+
+/// A new booking ID.
+pub fn new_id() -> String {
+    format!("{:016x}", fastrand::u64(..))
+}
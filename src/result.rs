@@ -0,0 +1,26 @@
+//! The booking result as parsed by `thsr_core`, and how the command line shows it.
+
+use chrono::Duration;
+
+use crate::output;
+use crate::time::taipei_now;
+
+pub use thsr_core::result::*;
+
+/// Print the booking result, the PNR standing out, and the payment deadline too when
+/// less than a day is left to pay.
+pub fn show_result(result: &BookingResult) {
+    let now = taipei_now();
+    let pay_soon = (result.time_to_pay(now)).is_some_and(|left| left < Duration::hours(24));
+    let pnr_line = result.pnr_line();
+    output::line("");
+    for line in result.to_string().lines() {
+        if line == pnr_line {
+            output::success(line);
+        } else if pay_soon && line.contains(&result.payment_deadline) {
+            output::alert(line);
+        } else {
+            output::line(line);
+        }
+    }
+}
@@ -0,0 +1,214 @@
+//! Saving the progress of a booking after every step, so `thsr resume` can pick up
+//! an interrupted one where it stopped instead of starting over.
+
+use chrono::{Local, SecondsFormat};
+use scraper::Html;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::backend::BookingBackend;
+use crate::booking_flow::BookingPayload;
+use crate::cli::{Args, ResumeArgs};
+use crate::config::Config;
+use crate::error::Error;
+use crate::exit;
+use crate::http::HttpClient;
+use crate::i18n::tr;
+use crate::output;
+use crate::prompt::{Prompter, StdinPrompter};
+use crate::result::BookingResult;
+use crate::stage::{Progress, S2, S3, Stage};
+use crate::{Checkpoint, build_client, complete_booking, load_config, report, save_cookies};
+
+/// The step a saved booking goes on with.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Step {
+    Train,
+    Ticket,
+}
+
+/// A booking stopped between two steps, as saved to the state file.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BookingState {
+    pub step: Step,
+
+    /// The page the step starts from
+    pub page: String,
+
+    /// The answers of the search, to redo it if the session has expired meanwhile
+    pub payload: BookingPayload,
+
+    pub train: Option<u32>,
+    pub personal_id: Option<String>,
+    pub passport: Option<String>,
+    pub use_membership: Option<bool>,
+
+    /// The cookie jar of the session, see [`HttpClient::export_cookies`]
+    pub cookies: String,
+
+    pub saved_at: String,
+}
+
+impl BookingState {
+    /// `<local data dir>/thsr/booking.json`, e.g. `~/.local/share/thsr/booking.json` on Linux.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_local_dir().map(|dir| dir.join("thsr").join("booking.json"))
+    }
+
+    pub fn load(path: &Path) -> Result<BookingState, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|err| format!("No booking to resume in {}: {}", path.display(), err))?;
+        serde_json::from_str(&content)
+            .map_err(|err| format!("Invalid booking state {}: {}", path.display(), err))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|err| format!("Failed to create {}: {}", dir.display(), err))?;
+        }
+        let content = serde_json::to_string_pretty(self).unwrap();
+        fs::write(path, content)
+            .map_err(|err| format!("Failed to write booking state {}: {}", path.display(), err))
+    }
+
+    /// Go on with the booking through `client`, with the personal ID, passport, membership and
+    /// train of the saved booking instead of those in `args`.
+    pub fn resume(
+        mut self,
+        client: &HttpClient,
+        args: &Args,
+        config: &Config,
+        prompter: &dyn Prompter,
+        journal: &mut dyn FnMut(Checkpoint),
+    ) -> Result<BookingResult, Error> {
+        let args = Args {
+            personal_id: self.personal_id.clone(),
+            passport: self.passport.clone(),
+            use_membership: self.use_membership,
+            train: self
+                .train
+                .map_or_else(|| args.train.clone(), |train| vec![train]),
+            ..args.clone()
+        };
+        if let Err(err_msg) = client.import_cookies(&self.cookies) {
+            warn!("Invalid cookies in the booking state: {}", err_msg);
+        }
+        info!(
+            "Resuming the booking saved at {} with the {} step",
+            self.saved_at,
+            match self.step {
+                Step::Train => "train",
+                Step::Ticket => "ticket",
+            }
+        );
+        complete_booking(
+            client,
+            self.progress(config.backend.backend()),
+            &mut self.payload,
+            &args,
+            config,
+            prompter,
+            journal,
+        )
+    }
+
+    fn progress(&self, backend: &'static dyn BookingBackend) -> Progress {
+        let page = Html::parse_document(&self.page);
+        match self.step {
+            Step::Train => Progress::Train(Stage::<S2>::restore(backend, page)),
+            Step::Ticket => Progress::Ticket(Stage::<S3>::restore(backend, page)),
+        }
+    }
+}
+
+/// Keeps the state file at `path` up to date with the steps of a booking through
+/// `client`, and removes it once booked. Does nothing without a path, or for a dry run.
+pub fn journal<'a>(
+    path: Option<&'a Path>,
+    client: &'a HttpClient,
+    args: &'a Args,
+) -> impl FnMut(Checkpoint) + 'a {
+    move |checkpoint| {
+        let Some(path) = path.filter(|_| !args.dry_run) else {
+            return;
+        };
+        let (step, page) = match checkpoint.progress {
+            Progress::Train(stage) => (Step::Train, stage.document()),
+            Progress::Ticket(stage) => (Step::Ticket, stage.document()),
+            Progress::Booked(_) => {
+                if path.exists()
+                    && let Err(err) = fs::remove_file(path)
+                {
+                    warn!("Failed to remove {}: {}", path.display(), err);
+                }
+                return;
+            }
+        };
+        let state = BookingState {
+            step,
+            page: page.html(),
+            payload: checkpoint.payload.clone(),
+            train: checkpoint.train,
+            personal_id: args.personal_id.clone(),
+            passport: args.passport.clone(),
+            use_membership: args.use_membership,
+            cookies: client.export_cookies(),
+            saved_at: Local::now().to_rfc3339_opts(SecondsFormat::Secs, false),
+        };
+        if let Err(err_msg) = state.save(path) {
+            warn!("{}", err_msg);
+        }
+    }
+}
+
+/// Tell how to go on with a booking that stopped after a step was saved to `path`.
+/// Finished bookings leave no state behind.
+pub fn hint(path: Option<&Path>) {
+    if path.is_some_and(Path::exists) {
+        output::line(tr!(
+            "Run `thsr resume` to pick up the booking where it stopped.",
+            "執行 `thsr resume` 可從中斷的地方繼續訂位。"
+        ));
+    }
+}
+
+/// Go on with the booking saved to the state file.
+pub fn run(resume_args: ResumeArgs) {
+    let ResumeArgs { mut args, state } = resume_args;
+    let Some(path) = state.or_else(BookingState::default_path) else {
+        output::error("No data directory for the booking state, use --state");
+        return;
+    };
+    let state = match BookingState::load(&path) {
+        Ok(state) => state,
+        Err(err_msg) => {
+            output::error(err_msg);
+            return;
+        }
+    };
+
+    let config = match load_config(&mut args) {
+        Ok(config) => config,
+        Err(err_msg) => {
+            output::error(err_msg);
+            exit::fail(exit::Status::Invalid);
+            return;
+        }
+    };
+    let client = build_client(&config);
+    let outcome = state.resume(
+        &client,
+        &args,
+        &config,
+        &StdinPrompter::new(&config.captcha),
+        &mut journal(Some(&path), &client, &args),
+    );
+    save_cookies(&client, &config);
+    report(&config, outcome);
+    hint(Some(&path));
+}
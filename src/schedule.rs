@@ -0,0 +1,173 @@
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate, TimeZone};
+use tracing::{info, warn};
+
+use std::io::Write;
+use std::thread;
+
+use crate::booking_flow::{self, BookingPayload};
+use crate::cli::ScheduleArgs;
+use crate::exit;
+use crate::frontend::Frontend;
+use crate::metrics;
+use crate::ntp;
+use crate::output;
+use crate::prompt::StdinPrompter;
+use crate::resume::{self, BookingState};
+use crate::stage::{Progress, Stage};
+use crate::time::{taipei, taipei_now};
+use crate::{build_client, complete_booking, load_config, report, save_cookies};
+
+/// Tickets go on sale this many days before the travel date, at midnight Taipei time.
+pub const BOOKING_WINDOW_DAYS: i64 = 28;
+
+/// The instant bookings for `travel_date` open.
+pub fn opening_time(travel_date: NaiveDate) -> DateTime<FixedOffset> {
+    let opening_day = travel_date - Duration::days(BOOKING_WINDOW_DAYS);
+    taipei()
+        .from_local_datetime(&opening_day.and_hms_opt(0, 0, 0).unwrap())
+        .unwrap()
+}
+
+/// Local clock corrected by the offset reported by an NTP server.
+struct Clock {
+    offset: Duration,
+}
+
+impl Clock {
+    fn now(&self) -> DateTime<FixedOffset> {
+        taipei_now() + self.offset
+    }
+}
+
+fn format_remaining(remaining: Duration) -> String {
+    let total_ms = remaining.num_milliseconds().max(0);
+    let (days, rest) = (total_ms / 86_400_000, total_ms % 86_400_000);
+    let time = format!(
+        "{:02}:{:02}:{:02}.{}",
+        rest / 3_600_000,
+        rest / 60_000 % 60,
+        rest / 1000 % 60,
+        rest / 100 % 10
+    );
+    if days > 0 {
+        format!("{}d {}", days, time)
+    } else {
+        time
+    }
+}
+
+/// Show a live countdown until `instant`, returning as close to it as possible.
+fn countdown(clock: &Clock, instant: DateTime<FixedOffset>, label: &str) {
+    loop {
+        let remaining = instant - clock.now();
+        if remaining <= Duration::zero() {
+            break;
+        }
+        let mut out = output::writer();
+        let _ = write!(out, "\r{} in {}   ", label, format_remaining(remaining));
+        let _ = out.flush();
+        thread::sleep(remaining.min(Duration::milliseconds(100)).to_std().unwrap());
+    }
+    output::line("");
+}
+
+/// Answer all prompts up front, open the session and solve the security code shortly
+/// before the booking window opens, then submit right at the opening instant.
+pub fn run(schedule_args: ScheduleArgs) {
+    let ScheduleArgs {
+        mut args,
+        target_date,
+        warmup,
+        ntp_server,
+        no_ntp,
+        metrics,
+    } = schedule_args;
+
+    let Some(travel_date) = booking_flow::normalize_date(&target_date)
+        .and_then(|date| NaiveDate::parse_from_str(&date, "%Y/%m/%d").ok())
+    else {
+        output::error(format!("Invalid target date {}", target_date));
+        exit::fail(exit::Status::Invalid);
+        return;
+    };
+    args.date = Some(travel_date.format("%Y/%m/%d").to_string());
+
+    let config = match load_config(&mut args) {
+        Ok(config) => config,
+        Err(err_msg) => {
+            output::error(err_msg);
+            exit::fail(exit::Status::Invalid);
+            return;
+        }
+    };
+
+    if let Some(listen) = metrics
+        && let Err(err_msg) = metrics::serve(listen)
+    {
+        output::error(err_msg);
+        return;
+    }
+
+    // The window isn't open yet, so the date can't be checked against the booking page
+    let stdin = StdinPrompter::new(&config.captcha);
+    let frontend = Frontend::new(&stdin, &args, &config);
+    let mut payload = BookingPayload::default();
+    payload.select_options(&args.options(&config), None, &frontend);
+
+    let clock = Clock {
+        offset: if no_ntp {
+            Duration::zero()
+        } else {
+            match ntp::clock_offset(&ntp_server) {
+                Ok(offset) => {
+                    info!(
+                        "Local clock is off by {:+.3}s according to {}",
+                        -offset.num_milliseconds() as f64 / 1000.0,
+                        ntp_server
+                    );
+                    offset
+                }
+                Err(err_msg) => {
+                    warn!("{}. Using the local clock.", err_msg);
+                    Duration::zero()
+                }
+            }
+        },
+    };
+
+    let opening = opening_time(travel_date);
+    let warmup = Duration::from_std(warmup).unwrap_or_default();
+    output::line(format_args!(
+        "Bookings for {} open at {}",
+        travel_date.format("%Y/%m/%d"),
+        opening.format("%Y/%m/%d %H:%M:%S %:z")
+    ));
+    if opening - warmup > clock.now() {
+        countdown(&clock, opening - warmup, "Opening the session");
+    }
+
+    let client = build_client(&config);
+    let state_path = BookingState::default_path();
+    let outcome = Stage::open(config.backend.backend(), &client, &config.site, &frontend)
+        .and_then(|search| {
+            search.submit(&client, &mut payload, &config.site, &frontend, || {
+                if opening > clock.now() {
+                    countdown(&clock, opening, "Submitting");
+                }
+            })
+        })
+        .and_then(|trains| {
+            complete_booking(
+                &client,
+                Progress::Train(trains),
+                &mut payload,
+                &args,
+                &config,
+                &stdin,
+                &mut resume::journal(state_path.as_deref(), &client, &args),
+            )
+        });
+    save_cookies(&client, &config);
+    report(&config, outcome);
+    resume::hint(state_path.as_deref());
+}
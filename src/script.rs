@@ -0,0 +1,81 @@
+//! `--select-script`, picking the train to book with a [Rhai](https://rhai.rs) script,
+//! for the choices the fixed picks can't make.
+
+use std::path::Path;
+
+use crate::confirm_train_flow::Train;
+use crate::error::Error;
+
+/// The train the script at `path` picks among `trains`, or `None` to ask for one.
+///
+/// The script finds the train list in `trains`, each train a map of `id`, `depart` and
+/// `arrive` as `HH:MM`, `travel_minutes`, `early_bird` and `student` as the percent
+/// paid or `()`, `discount_info` and `seats`. It evaluates to the index of the train
+/// to book, or to `()` to ask.
+#[cfg(feature = "scripting")]
+pub fn select(path: &Path, trains: &[Train]) -> Result<Option<u32>, Error> {
+    use rhai::{Array, Dynamic, Engine, Map, Scope};
+
+    use crate::fare::Discounts;
+
+    let failed = |err: &dyn std::fmt::Display| {
+        Error::Script(format!("Selection script {}: {}", path.display(), err))
+    };
+    let percent = |paid: Option<u32>| paid.map_or(Dynamic::UNIT, |paid| (paid as i64).into());
+    let list: Array = (trains.iter())
+        .map(|train| {
+            let discounts = Discounts::parse(train.discount_info());
+            let mut map = Map::new();
+            map.insert("id".into(), (train.id() as i64).into());
+            map.insert(
+                "depart".into(),
+                train.depart().format("%H:%M").to_string().into(),
+            );
+            map.insert(
+                "arrive".into(),
+                train.arrive().format("%H:%M").to_string().into(),
+            );
+            map.insert(
+                "travel_minutes".into(),
+                (train.travel_minutes() as i64).into(),
+            );
+            map.insert("early_bird".into(), percent(discounts.early_bird));
+            map.insert("student".into(), percent(discounts.student));
+            map.insert("discount_info".into(), train.discount_info().into());
+            map.insert("seats".into(), train.seats().as_str().into());
+            map.into()
+        })
+        .collect();
+
+    let mut engine = Engine::new();
+    // A script stuck in a loop fails instead of hanging the booking
+    engine.set_max_operations(1_000_000);
+    let mut scope = Scope::new();
+    scope.push("trains", list);
+    let picked: Dynamic =
+        (engine.eval_file_with_scope(&mut scope, path.into())).map_err(|err| failed(&err))?;
+    if picked.is_unit() {
+        return Ok(None);
+    }
+    let index =
+        (picked.as_int()).map_err(|kind| failed(&format!("returned a {}, not an index", kind)))?;
+    match usize::try_from(index)
+        .ok()
+        .and_then(|index| trains.get(index))
+    {
+        Some(train) => Ok(Some(train.id())),
+        None => Err(failed(&format!(
+            "returned {}, but only {} trains are listed",
+            index,
+            trains.len()
+        ))),
+    }
+}
+
+#[cfg(not(feature = "scripting"))]
+pub fn select(_path: &Path, _trains: &[Train]) -> Result<Option<u32>, Error> {
+    Err(Error::Script(
+        "Selection scripts are not available, thsr was built without the `scripting` feature"
+            .to_string(),
+    ))
+}
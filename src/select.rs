@@ -0,0 +1,381 @@
+//! `--select`, a one-line expression picking the train to book, between the fixed
+//! picks and a script, e.g. `duration<100 && depart>=18:00 ? earliest : fastest`.
+//!
+//! `COND ? A : B` picks with `A` among the trains meeting `COND`, or with `B` among all
+//! of them when none does. Without `: B`, no train meeting `COND` fails the booking
+//! like a sold out one.
+
+use chrono::{NaiveTime, Timelike};
+
+use std::str::FromStr;
+
+use crate::confirm_train_flow::{Seats, Train};
+use crate::error::Error;
+use crate::fare::Discounts;
+use crate::pick;
+
+/// How a train is picked among those left.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Policy {
+    /// Departing the earliest
+    Earliest,
+    /// Departing the latest
+    Latest,
+    /// The shortest travel time, the earliest of them on a tie
+    Fastest,
+}
+
+/// What a condition compares, each as a number: the times in minutes after midnight,
+/// the discounts as the percent paid, 100 without one, and the seats from 0 when
+/// available to 2 for standing only.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Field {
+    Train,
+    Depart,
+    Arrive,
+    Duration,
+    EarlyBird,
+    Student,
+    Seats,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    Compare(Field, Op, u32),
+    Not(Box<Condition>),
+    And(Box<Condition>, Box<Condition>),
+    Or(Box<Condition>, Box<Condition>),
+}
+
+/// A parsed `--select` expression.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Selection {
+    Pick(Policy),
+    If(Condition, Box<Selection>, Option<Box<Selection>>),
+}
+
+const SEATS: [Seats; 3] = [Seats::Available, Seats::Limited, Seats::StandingOnly];
+
+fn minutes(time: NaiveTime) -> u32 {
+    time.hour() * 60 + time.minute()
+}
+
+impl Field {
+    const NAMES: [(&str, Field); 7] = [
+        ("train", Field::Train),
+        ("depart", Field::Depart),
+        ("arrive", Field::Arrive),
+        ("duration", Field::Duration),
+        ("early_bird", Field::EarlyBird),
+        ("student", Field::Student),
+        ("seats", Field::Seats),
+    ];
+
+    fn of(self, train: &Train) -> u32 {
+        let discounts = || Discounts::parse(train.discount_info());
+        match self {
+            Field::Train => train.id(),
+            Field::Depart => minutes(train.depart()),
+            Field::Arrive => minutes(train.arrive()),
+            Field::Duration => train.travel_minutes(),
+            Field::EarlyBird => discounts().early_bird.unwrap_or(100),
+            Field::Student => discounts().student.unwrap_or(100),
+            Field::Seats => SEATS
+                .iter()
+                .position(|&seats| seats == train.seats())
+                .unwrap() as u32,
+        }
+    }
+
+    /// `value` the way the field is compared, e.g. `18:00` as 1080 for the departure.
+    fn value(self, value: &str) -> Result<u32, String> {
+        let number = || (value.parse()).map_err(|_| format!("{} is not a number", value));
+        match self {
+            Field::Train | Field::EarlyBird | Field::Student => number(),
+            Field::Depart | Field::Arrive => pick::parse_clock(value).map(minutes),
+            Field::Duration => match value.split_once(':') {
+                Some((hours, mins)) => match (hours.parse::<u32>(), mins.parse::<u32>()) {
+                    (Ok(hours), Ok(mins)) if mins < 60 => Ok(hours * 60 + mins),
+                    _ => Err(format!(
+                        "Invalid duration {}, expected minutes or H:MM",
+                        value
+                    )),
+                },
+                None => number(),
+            },
+            Field::Seats => (SEATS.iter())
+                .position(|seats| seats.as_str() == value)
+                .map(|rank| rank as u32)
+                .ok_or_else(|| {
+                    format!(
+                        "Invalid seats {}, expected available, limited or standing_only",
+                        value
+                    )
+                }),
+        }
+    }
+}
+
+impl Op {
+    fn holds(self, left: u32, right: u32) -> bool {
+        match self {
+            Op::Lt => left < right,
+            Op::Le => left <= right,
+            Op::Gt => left > right,
+            Op::Ge => left >= right,
+            Op::Eq => left == right,
+            Op::Ne => left != right,
+        }
+    }
+}
+
+impl Condition {
+    pub fn holds(&self, train: &Train) -> bool {
+        match self {
+            Condition::Compare(field, op, value) => op.holds(field.of(train), *value),
+            Condition::Not(cond) => !cond.holds(train),
+            Condition::And(left, right) => left.holds(train) && right.holds(train),
+            Condition::Or(left, right) => left.holds(train) || right.holds(train),
+        }
+    }
+}
+
+impl Policy {
+    fn pick<'a>(self, trains: &[&'a Train]) -> Option<&'a Train> {
+        let trains = trains.iter().copied();
+        match self {
+            Policy::Earliest => trains.min_by_key(|train| train.depart()),
+            Policy::Latest => trains.max_by_key(|train| train.depart()),
+            Policy::Fastest => trains.min_by_key(|train| (train.travel_minutes(), train.depart())),
+        }
+    }
+}
+
+impl Selection {
+    /// The train to book among `trains`.
+    pub fn train(&self, trains: &[Train]) -> Result<u32, Error> {
+        if trains.is_empty() {
+            return Err(Error::SoldOut("No trains listed".to_string()));
+        }
+        let trains: Vec<&Train> = trains.iter().collect();
+        (self.pick(&trains))
+            .ok_or_else(|| Error::SoldOut("No train listed meets the selection".to_string()))
+    }
+
+    fn pick(&self, trains: &[&Train]) -> Option<u32> {
+        match self {
+            Selection::Pick(policy) => policy.pick(trains).map(Train::id),
+            Selection::If(cond, then, otherwise) => {
+                let meeting: Vec<&Train> = (trains.iter().copied())
+                    .filter(|train| cond.holds(train))
+                    .collect();
+                match (meeting.is_empty(), otherwise) {
+                    (false, _) => then.pick(&meeting),
+                    (true, Some(otherwise)) => otherwise.pick(trains),
+                    (true, None) => None,
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    /// A number or a time, e.g. `100` or `18:00`
+    Value(String),
+    Op(Op),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+    Question,
+    Colon,
+}
+
+fn tokenize(expr: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = expr.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let next = chars.peek().map(|&(_, c)| c);
+        let token = match (c, next) {
+            (c, _) if c.is_whitespace() => continue,
+            (c, _) if c.is_ascii_alphabetic() || c == '_' => {
+                let mut end = start + 1;
+                while let Some(&(idx, c)) = chars.peek()
+                    && (c.is_ascii_alphanumeric() || c == '_')
+                {
+                    end = idx + 1;
+                    chars.next();
+                }
+                Token::Word(expr[start..end].to_ascii_lowercase())
+            }
+            (c, _) if c.is_ascii_digit() => {
+                let mut end = start + 1;
+                // The colon of a time is followed by a digit, unlike that of `? :`
+                while let Some(&(idx, c)) = chars.peek()
+                    && (c.is_ascii_digit()
+                        || c == ':' && expr[idx + 1..].starts_with(|c: char| c.is_ascii_digit()))
+                {
+                    end = idx + 1;
+                    chars.next();
+                }
+                Token::Value(expr[start..end].to_string())
+            }
+            ('<' | '>' | '=' | '!', Some('=')) => {
+                chars.next();
+                Token::Op(match c {
+                    '<' => Op::Le,
+                    '>' => Op::Ge,
+                    '=' => Op::Eq,
+                    _ => Op::Ne,
+                })
+            }
+            ('&', Some('&')) | ('|', Some('|')) => {
+                chars.next();
+                if c == '&' { Token::And } else { Token::Or }
+            }
+            ('<', _) => Token::Op(Op::Lt),
+            ('>', _) => Token::Op(Op::Gt),
+            ('!', _) => Token::Not,
+            ('(', _) => Token::Open,
+            (')', _) => Token::Close,
+            ('?', _) => Token::Question,
+            (':', _) => Token::Colon,
+            _ => return Err(format!("Unexpected {} at {}", c, start + 1)),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+/// A recursive descent over the tokens: a selection is a policy or
+/// `condition ? selection [: selection]`, conditions bind `!` before `&&` before `||`.
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        let found = self.peek() == Some(token);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn selection(&mut self) -> Result<Selection, String> {
+        if let Some(Token::Word(word)) = self.peek() {
+            let policy = match word.as_str() {
+                "earliest" => Some(Policy::Earliest),
+                "latest" => Some(Policy::Latest),
+                "fastest" => Some(Policy::Fastest),
+                _ => None,
+            };
+            if let Some(policy) = policy {
+                self.pos += 1;
+                return Ok(Selection::Pick(policy));
+            }
+        }
+        let cond = self.or()?;
+        if !self.eat(&Token::Question) {
+            return Err("Expected ? and earliest, latest or fastest after the condition".into());
+        }
+        let then = self.selection()?;
+        let otherwise = match self.eat(&Token::Colon) {
+            true => Some(Box::new(self.selection()?)),
+            false => None,
+        };
+        Ok(Selection::If(cond, Box::new(then), otherwise))
+    }
+
+    fn or(&mut self) -> Result<Condition, String> {
+        let mut cond = self.and()?;
+        while self.eat(&Token::Or) {
+            cond = Condition::Or(Box::new(cond), Box::new(self.and()?));
+        }
+        Ok(cond)
+    }
+
+    fn and(&mut self) -> Result<Condition, String> {
+        let mut cond = self.unary()?;
+        while self.eat(&Token::And) {
+            cond = Condition::And(Box::new(cond), Box::new(self.unary()?));
+        }
+        Ok(cond)
+    }
+
+    fn unary(&mut self) -> Result<Condition, String> {
+        if self.eat(&Token::Not) {
+            return Ok(Condition::Not(Box::new(self.unary()?)));
+        }
+        if self.eat(&Token::Open) {
+            let cond = self.or()?;
+            if !self.eat(&Token::Close) {
+                return Err("Expected )".into());
+            }
+            return Ok(cond);
+        }
+        self.compare()
+    }
+
+    fn compare(&mut self) -> Result<Condition, String> {
+        let names = || (Field::NAMES.iter().map(|(name, _)| *name)).collect::<Vec<_>>();
+        let field = match self.next() {
+            Some(Token::Word(word)) => (Field::NAMES.iter())
+                .find(|(name, _)| *name == word)
+                .map(|&(_, field)| field)
+                .ok_or_else(|| {
+                    format!("Unknown field {}, expected {}", word, names().join(", "))
+                })?,
+            _ => return Err(format!("Expected a field: {}", names().join(", "))),
+        };
+        let Some(Token::Op(op)) = self.next() else {
+            return Err("Expected <, <=, >, >=, == or != after the field".into());
+        };
+        let value = match self.next() {
+            Some(Token::Value(value) | Token::Word(value)) => field.value(&value)?,
+            _ => return Err("Expected a value to compare with".into()),
+        };
+        Ok(Condition::Compare(field, op, value))
+    }
+}
+
+impl FromStr for Selection {
+    type Err = String;
+
+    fn from_str(expr: &str) -> Result<Selection, String> {
+        let invalid = |err: String| format!("Invalid selection {}: {}", expr, err);
+        let mut parser = Parser {
+            tokens: tokenize(expr).map_err(invalid)?,
+            pos: 0,
+        };
+        let selection = parser.selection().map_err(invalid)?;
+        match parser.peek() {
+            None => Ok(selection),
+            Some(_) => Err(invalid("Unexpected text after the selection".into())),
+        }
+    }
+}
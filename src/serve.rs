@@ -0,0 +1,228 @@
+//! `thsr serve`: bookings driven through a JSON API instead of the terminal.
+//! Whatever the CLI would ask, the security code included, is published on the
+//! booking for the API caller to answer, see [`crate::remote`].
+
+use serde::{Deserialize, Serialize};
+use tiny_http::{Header, Method, Request, Response, Server};
+use tracing::warn;
+
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+
+use crate::cli::{Args, ServeArgs};
+use crate::config::Config;
+use crate::confirm_train_flow::Train;
+use crate::exit;
+use crate::load_config;
+use crate::metrics;
+use crate::output;
+use crate::progress;
+use crate::remote::{self, BookingRequest, Update};
+use crate::result::BookingResult;
+use crate::schema::STATIONS;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    /// Going through the steps
+    Running,
+    /// Waiting for the security code at `/bookings/{id}/captcha` to be answered
+    Captcha,
+    /// Waiting for the answer to `question`
+    Question,
+    Booked,
+    Failed,
+}
+
+/// A booking as shown by the API.
+#[derive(Serialize, Debug)]
+pub struct Booking {
+    pub id: String,
+    pub status: Status,
+    pub question: Option<String>,
+    /// The trains found by the search
+    pub trains: Vec<Train>,
+    pub result: Option<BookingResult>,
+    pub error: Option<String>,
+
+    #[serde(skip)]
+    captcha: Option<Vec<u8>>,
+}
+
+struct Job {
+    booking: Arc<Mutex<Booking>>,
+    answers: Sender<String>,
+}
+
+/// Start a booking, keeping its state up to date for the API.
+fn start(request: BookingRequest, args: &Args, config: Arc<Config>) -> Job {
+    let id = remote::new_id();
+    let booking = Arc::new(Mutex::new(Booking {
+        id: id.clone(),
+        status: Status::Running,
+        question: None,
+        trains: Vec::new(),
+        result: None,
+        error: None,
+        captcha: None,
+    }));
+    let state = Arc::clone(&booking);
+    let answers = remote::spawn(id, request.apply(args), config, move |update| {
+        let mut booking = state.lock().unwrap();
+        match update {
+            Update::Reached(_) => {}
+            Update::Trains(trains) => booking.trains = trains,
+            Update::Question(hint) => {
+                booking.status = Status::Question;
+                booking.question = Some(hint.to_string());
+            }
+            Update::Captcha(img_data) => {
+                booking.status = Status::Captcha;
+                booking.captcha = Some(img_data.to_vec());
+            }
+            Update::Done(Ok(result)) => {
+                booking.status = Status::Booked;
+                booking.result = Some(result.clone());
+            }
+            Update::Done(Err(err_msg)) => {
+                booking.status = Status::Failed;
+                booking.error = Some(err_msg.clone());
+            }
+        }
+    });
+    Job { booking, answers }
+}
+
+/// Serve the API on the address of `serve_args` until killed.
+pub fn run(serve_args: ServeArgs) {
+    let ServeArgs { mut args, listen } = serve_args;
+    // The bookings run in the background, for the callers of the API
+    progress::disable();
+    let config = match load_config(&mut args) {
+        Ok(config) => config,
+        Err(err_msg) => {
+            output::error(err_msg);
+            exit::fail(exit::Status::Invalid);
+            return;
+        }
+    };
+    let server = match Server::http(listen) {
+        Ok(server) => server,
+        Err(err) => {
+            output::error(format!("Failed to listen on {}: {}", listen, err));
+            return;
+        }
+    };
+    output::line(format_args!(
+        "Serving the booking API at http://{}/",
+        listen
+    ));
+    serve(&server, &args, config);
+}
+
+/// Answer the requests coming to `server`, booking with `args` and `config`
+/// unless a request says otherwise.
+pub fn serve(server: &Server, args: &Args, config: Config) {
+    let config = Arc::new(config);
+    let mut jobs: Vec<Job> = Vec::new();
+    for request in server.incoming_requests() {
+        handle(request, &mut jobs, args, &config);
+    }
+}
+
+fn handle(mut request: Request, jobs: &mut Vec<Job>, args: &Args, config: &Arc<Config>) {
+    let url = request.url().to_string();
+    let (path, _query) = url.split_once('?').unwrap_or((&url, ""));
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    let mut body = String::new();
+    let _ = request.as_reader().read_to_string(&mut body);
+
+    // A booking by its ID, or the PNR it got
+    let find = |key: &str| {
+        jobs.iter().position(|job| {
+            let booking = job.booking.lock().unwrap();
+            booking.id == key || booking.result.as_ref().is_some_and(|r| r.pnr == key)
+        })
+    };
+
+    let response = match (request.method(), segments.as_slice()) {
+        (Method::Get, ["stations"]) => json(200, &STATIONS),
+        (Method::Get, ["metrics"]) => {
+            Response::from_string(metrics::render()).with_header(metrics::content_type())
+        }
+        (Method::Post, ["bookings"]) => match serde_json::from_str::<BookingRequest>(&body) {
+            Ok(booking_request) => {
+                let job = start(booking_request, args, Arc::clone(config));
+                let response = json(201, &*job.booking.lock().unwrap());
+                jobs.push(job);
+                response
+            }
+            Err(err) => error(400, &format!("Invalid booking: {}", err)),
+        },
+        (Method::Get, ["bookings", key]) => match find(key) {
+            Some(idx) => json(200, &*jobs[idx].booking.lock().unwrap()),
+            None => error(404, "No such booking"),
+        },
+        (Method::Get, ["bookings", key, "trains"]) => match find(key) {
+            Some(idx) => json(200, &jobs[idx].booking.lock().unwrap().trains),
+            None => error(404, "No such booking"),
+        },
+        (Method::Get, ["bookings", key, "captcha"]) => match find(key) {
+            Some(idx) => match jobs[idx].booking.lock().unwrap().captcha.clone() {
+                Some(img_data) => Response::from_data(img_data)
+                    .with_header(header("Content-Type", "image/jpeg"))
+                    .with_header(header("Cache-Control", "no-store")),
+                None => error(404, "No security code to answer"),
+            },
+            None => error(404, "No such booking"),
+        },
+        (Method::Post, ["bookings", key, "answer"]) => match find(key) {
+            Some(idx) => answer(&jobs[idx], &body),
+            None => error(404, "No such booking"),
+        },
+        _ => error(404, "Not found"),
+    };
+    if let Err(err) = request.respond(response) {
+        warn!("Failed to answer the API caller: {}", err);
+    }
+}
+
+/// Pass `{"answer": "..."}` on to the booking waiting for it.
+/// For the security code, `r` asks for a new image.
+fn answer(job: &Job, body: &str) -> Response<Cursor<Vec<u8>>> {
+    #[derive(Deserialize)]
+    struct Answer {
+        answer: String,
+    }
+
+    let answer = match serde_json::from_str::<Answer>(body) {
+        Ok(answer) => answer.answer,
+        Err(err) => return error(400, &format!("Invalid answer: {}", err)),
+    };
+    let mut booking = job.booking.lock().unwrap();
+    if !matches!(booking.status, Status::Captcha | Status::Question) {
+        return error(409, "The booking isn't waiting for an answer");
+    }
+    // Not asked twice while the booking thread takes the answer
+    booking.status = Status::Running;
+    booking.question = None;
+    booking.captcha = None;
+    let _ = job.answers.send(answer);
+    json(202, &*booking)
+}
+
+pub(crate) fn json(status: u16, value: &impl Serialize) -> Response<Cursor<Vec<u8>>> {
+    Response::from_string(serde_json::to_string(value).unwrap())
+        .with_status_code(status)
+        .with_header(header("Content-Type", "application/json"))
+}
+
+pub(crate) fn error(status: u16, message: &str) -> Response<Cursor<Vec<u8>>> {
+    json(status, &HashMap::from([("error", message)]))
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name, value).unwrap()
+}
@@ -0,0 +1,59 @@
+//! `thsr station`, telling about the stations, e.g. to find out which one is closer.
+
+use crate::cli::{StationAction, StationArgs};
+use crate::exit;
+use crate::i18n::tr;
+use crate::output;
+use crate::schema::{Station, StationInfo};
+
+/// Print what is known about `station`.
+pub fn info(station: &StationInfo) {
+    println!(
+        "{} ({}, ID {})",
+        station,
+        station.code,
+        station.station.id()
+    );
+    println!("{}", tr!("Address: {}", "地址：{}", station.address));
+    let (lat, lon) = station.location;
+    let map = format!(
+        "https://www.openstreetmap.org/?mlat={:.4}&mlon={:.4}#map=16/{:.4}/{:.4}",
+        lat, lon, lat, lon
+    );
+    println!(
+        "{}",
+        tr!(
+            "Location: {:.4}, {:.4}, {}",
+            "位置：{:.4}, {:.4}，{}",
+            lat,
+            lon,
+            map
+        )
+    );
+    let transfers = match station.transfers {
+        [] => tr!("none", "無"),
+        transfers => transfers.join(", "),
+    };
+    println!("{}", tr!("Transfers: {}", "轉乘：{}", transfers));
+    println!(
+        "{}",
+        tr!(
+            "First and last trains: {} and {}, on a regular day",
+            "首末班車：{} 與 {}（一般日）",
+            station.first_train,
+            station.last_train
+        )
+    );
+}
+
+pub fn run(station_args: StationArgs) {
+    match station_args.action {
+        StationAction::Info { name } => match name.parse::<Station>() {
+            Ok(station) => info(station.info()),
+            Err(err_msg) => {
+                output::error(err_msg);
+                exit::fail(exit::Status::Invalid);
+            }
+        },
+    }
+}
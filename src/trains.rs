@@ -0,0 +1,144 @@
+//! `thsr trains`, listing the trains of a route without booking. A search only lists
+//! the trains of one time slot, so `--sweep` searches from several and merges them.
+
+use tracing::info;
+
+use crate::booking_flow::BookingPayload;
+use crate::cli::{Args, TrainsArgs};
+use crate::config::Config;
+use crate::confirm_train_flow::Train;
+use crate::error::Error;
+use crate::exit;
+use crate::export;
+use crate::frontend::Frontend;
+use crate::http::{RateLimiter, Transport};
+use crate::i18n::tr;
+use crate::output;
+use crate::prompt::{Prompter, StdinPrompter};
+use crate::schema::SlotRange;
+use crate::stage::Stage;
+use crate::{build_client, explain, load_config};
+
+/// The trains listed by the searches from every slot of `sweep`, or from the time of
+/// `args` without one, each train once and in the order of departure. A slot without
+/// trains to sell is skipped.
+pub fn search(
+    client: &dyn Transport,
+    args: &Args,
+    sweep: Option<SlotRange>,
+    config: &Config,
+    prompter: &dyn Prompter,
+) -> Result<Vec<Train>, Error> {
+    let prompter = &Frontend::new(prompter, args, config);
+    let search = Stage::open(config.backend.backend(), client, &config.site, prompter)?;
+    let mut payload = BookingPayload::default();
+    let page = search.page();
+    let args = Args {
+        time: sweep.map(|sweep| sweep.first).or(args.time),
+        ..args.clone()
+    };
+    payload.select_options(
+        &args.options(config),
+        Some((&page.start_date, &page.end_date)),
+        prompter,
+    );
+    let slots = match sweep {
+        Some(sweep) => sweep.slots().to_vec(),
+        None => vec![payload.outbound_time],
+    };
+
+    let mut trains: Vec<Train> = Vec::new();
+    // The session opened for the options does the first search
+    let mut opened = Some(search);
+    for slot in slots {
+        let search = match opened.take() {
+            Some(search) => search,
+            None => Stage::open(config.backend.backend(), client, &config.site, prompter)?,
+        };
+        info!("Searching the trains from {}", slot);
+        payload.outbound_time = slot;
+        match search.submit(client, &mut payload, &config.site, prompter, || {}) {
+            Ok(stage) => {
+                for train in stage.trains() {
+                    // Slots close together list many of the same trains
+                    if !trains.iter().any(|listed| listed.id() == train.id()) {
+                        trains.push(train);
+                    }
+                }
+            }
+            Err(Error::SoldOut(msg)) => info!("No trains from {}: {}", slot, msg),
+            Err(err) => return Err(err),
+        }
+    }
+    trains.sort_by_key(|train| train.depart());
+    Ok(trains)
+}
+
+fn show(trains: &[Train]) {
+    if trains.is_empty() {
+        output::status(tr!("No trains listed", "查無車次"));
+    }
+    for train in trains {
+        let mut line = format!(
+            "{:>4} {}~{} {:>3} {}",
+            train.id(),
+            train.depart().format("%H:%M"),
+            train.arrive().format("%H:%M"),
+            train.travel_time(),
+            train.discount_info()
+        );
+        if let Some(hint) = train.seats().hint() {
+            line = format!("{} [{}]", line.trim_end(), hint);
+        }
+        println!("{}", line.trim_end());
+    }
+}
+
+pub fn run(trains_args: TrainsArgs) {
+    let TrainsArgs {
+        mut args,
+        sweep,
+        json,
+    } = trains_args;
+    // The tickets don't change the trains listed, so they aren't asked for
+    if args.adult_cnt.is_none() && args.student_cnt.is_none() {
+        args.adult_cnt = Some(1);
+    }
+    args.seat_prefer.get_or_insert(0);
+    args.class_type.get_or_insert(0);
+
+    let config = match load_config(&mut args) {
+        Ok(config) => config,
+        Err(err_msg) => {
+            output::error(err_msg);
+            exit::fail(exit::Status::Invalid);
+            return;
+        }
+    };
+
+    let client = build_client(&config).rate_limit(RateLimiter::new(config.rate_limit.clone()));
+    let trains = match search(
+        &client,
+        &args,
+        sweep,
+        &config,
+        &StdinPrompter::new(&config.captcha),
+    ) {
+        Ok(trains) => trains,
+        Err(err) => {
+            output::error(&err);
+            explain(&err);
+            exit::fail(exit::Status::of(&err));
+            return;
+        }
+    };
+    if let Some(path) = &args.trains_out
+        && let Err(err_msg) = export::write_trains(path, &trains)
+    {
+        output::warning(err_msg);
+    }
+    match json {
+        true => println!("{}", serde_json::to_string_pretty(&trains).unwrap()),
+        false => show(&trains),
+    }
+}
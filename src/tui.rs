@@ -0,0 +1,739 @@
+//! `thsr tui`: booking in a full-screen terminal interface.
+//!
+//! The route, date and tickets are picked in a form. The booking then runs like one
+//! from the APIs, on its own thread through [`remote::spawn`], and the interface
+//! shows the trains found, the security code image and the questions it asks.
+
+use image::imageops::FilterType;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Cell, List, ListState, Paragraph, Row, Table, TableState, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::Duration;
+
+use crate::booking_flow::normalize_date;
+use crate::cli::{Args, TuiArgs};
+use crate::config::Config;
+use crate::confirm_train_flow::Train;
+use crate::exit;
+use crate::load_config;
+use crate::notify::{self, Event as NotifyEvent};
+use crate::output;
+use crate::progress;
+use crate::remote::{self, Update};
+use crate::result::{BookingResult, show_result};
+use crate::schema::{STATIONS, Station, TIME_SLOTS};
+use crate::stage::Progress;
+
+/// How many of the latest log lines are kept for the log pane.
+const LOG_LINES: usize = 200;
+
+/// The diagnostics logged while the interface is up, which can't go to stderr.
+static LOG: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+/// Keeps what is logged for the log pane.
+pub struct LogWriter;
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut log = LOG.lock().unwrap();
+        for line in String::from_utf8_lossy(buf).lines() {
+            if !line.trim().is_empty() {
+                log.push_back(line.to_string());
+            }
+        }
+        while log.len() > LOG_LINES {
+            log.pop_front();
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Where the diagnostics go in `thsr tui`, for [`crate::log::init_with`].
+pub fn log_writer() -> LogWriter {
+    LogWriter
+}
+
+/// Book in the full-screen interface, with `tui_args` filling in the form.
+pub fn run(tui_args: TuiArgs) {
+    let TuiArgs { mut args } = tui_args;
+    // The interface shows the steps itself
+    progress::disable();
+    let config = match load_config(&mut args) {
+        Ok(config) => Arc::new(config),
+        Err(err_msg) => {
+            output::error(err_msg);
+            exit::fail(exit::Status::Invalid);
+            return;
+        }
+    };
+
+    let mut terminal = ratatui::init();
+    let mut app = App::new(args, Arc::clone(&config));
+    let outcome = app.run(&mut terminal);
+    ratatui::restore();
+
+    // The result stays on the terminal once the interface is gone
+    match outcome {
+        Ok(Some(Ok(result))) => {
+            show_result(&result);
+            notify::notify(&config, &NotifyEvent::Booked(&result));
+        }
+        Ok(Some(Err(err_msg))) => {
+            output::error(&err_msg);
+            notify::notify(&config, &NotifyEvent::Failed(&err_msg));
+        }
+        // Left before the booking was done
+        Ok(None) => {}
+        Err(err) => output::error(err),
+    }
+}
+
+/// The fields of the form, in order.
+#[derive(Clone, Copy, PartialEq)]
+enum Field {
+    From,
+    To,
+    Date,
+    Time,
+    Adults,
+    Students,
+    Class,
+    Seat,
+}
+
+const FIELDS: [Field; 8] = [
+    Field::From,
+    Field::To,
+    Field::Date,
+    Field::Time,
+    Field::Adults,
+    Field::Students,
+    Field::Class,
+    Field::Seat,
+];
+
+const CLASSES: [&str; 2] = ["standard", "business"];
+const SEATS: [&str; 3] = ["any", "window", "aisle"];
+
+/// The choices of the booking, as 0-based indexes into the lists.
+struct Form {
+    field: usize,
+    from: usize,
+    to: usize,
+    /// Empty for the latest bookable date
+    date: String,
+    time: usize,
+    adults: u8,
+    students: u8,
+    class_type: usize,
+    seat_prefer: usize,
+    error: Option<String>,
+}
+
+impl Form {
+    /// A form with the options of `args`, and the defaults of the command line for the rest.
+    fn new(args: &Args) -> Form {
+        Form {
+            field: 0,
+            from: args.from.unwrap_or(Station::Taipei).index(),
+            to: args.to.unwrap_or(Station::Zuoying).index(),
+            date: args.date.clone().unwrap_or_default(),
+            time: args.time.unwrap_or_default().index(),
+            adults: (args.adult_cnt)
+                .unwrap_or(if args.student_cnt.is_some() { 0 } else { 1 })
+                .min(10),
+            students: args.student_cnt.unwrap_or(0).min(10),
+            class_type: args
+                .class_type
+                .filter(|&idx| idx < CLASSES.len())
+                .unwrap_or(0),
+            seat_prefer: args
+                .seat_prefer
+                .filter(|&idx| idx < SEATS.len())
+                .unwrap_or(0),
+            error: None,
+        }
+    }
+
+    fn current(&self) -> Field {
+        FIELDS[self.field]
+    }
+
+    /// Step the value of the current field by `delta`, wrapping around the lists.
+    fn change(&mut self, delta: isize) {
+        fn step(value: usize, delta: isize, len: usize) -> usize {
+            (value as isize + delta).rem_euclid(len as isize) as usize
+        }
+        match self.current() {
+            Field::From => self.from = step(self.from, delta, STATIONS.len()),
+            Field::To => self.to = step(self.to, delta, STATIONS.len()),
+            Field::Date => {}
+            Field::Time => self.time = step(self.time, delta, TIME_SLOTS.len()),
+            Field::Adults => self.adults = step(self.adults.into(), delta, 11) as u8,
+            Field::Students => self.students = step(self.students.into(), delta, 11) as u8,
+            Field::Class => self.class_type = step(self.class_type, delta, CLASSES.len()),
+            Field::Seat => self.seat_prefer = step(self.seat_prefer, delta, SEATS.len()),
+        }
+    }
+
+    fn value(&self, field: Field) -> String {
+        match field {
+            Field::From => station(self.from),
+            Field::To => station(self.to),
+            Field::Date if self.date.is_empty() => "latest bookable".to_string(),
+            Field::Date => self.date.clone(),
+            Field::Time => TIME_SLOTS[self.time].to_string(),
+            Field::Adults => self.adults.to_string(),
+            Field::Students => self.students.to_string(),
+            Field::Class => CLASSES[self.class_type].to_string(),
+            Field::Seat => SEATS[self.seat_prefer].to_string(),
+        }
+    }
+
+    /// Check the choices, writing the date the way the booking site takes it.
+    fn validate(&mut self) -> Result<(), String> {
+        if self.from == self.to {
+            return Err("The departure and arrival stations are the same".to_string());
+        }
+        if !self.date.is_empty() {
+            self.date = normalize_date(&self.date)
+                .ok_or_else(|| "Please enter a date like 2025/06/29".to_string())?;
+        }
+        if self.adults + self.students == 0 {
+            return Err("Please book at least one ticket".to_string());
+        }
+        Ok(())
+    }
+
+    /// `args` with the choices of the form.
+    fn args(&self, args: &Args) -> Args {
+        Args {
+            from: Station::from_index(self.from),
+            to: Station::from_index(self.to),
+            date: (!self.date.is_empty()).then(|| self.date.clone()),
+            time: TIME_SLOTS.get(self.time).copied(),
+            adult_cnt: Some(self.adults),
+            student_cnt: Some(self.students),
+            class_type: Some(self.class_type),
+            seat_prefer: Some(self.seat_prefer),
+            ..args.clone()
+        }
+    }
+}
+
+fn field_name(field: Field) -> &'static str {
+    match field {
+        Field::From => "From",
+        Field::To => "To",
+        Field::Date => "Date",
+        Field::Time => "Time",
+        Field::Adults => "Adults",
+        Field::Students => "Students",
+        Field::Class => "Class",
+        Field::Seat => "Seat",
+    }
+}
+
+fn station(idx: usize) -> String {
+    STATIONS[idx].to_string()
+}
+
+/// An [`Update`] of the booking thread, owned to be sent over to the interface.
+enum BookingEvent {
+    Step(&'static str),
+    Trains(Vec<Train>),
+    Question(String),
+    Captcha(Vec<u8>),
+    Done(Box<Result<BookingResult, String>>),
+}
+
+const STEPS: [&str; 4] = ["search", "train", "ticket", "booked"];
+
+/// A booking running on its own thread.
+struct Booking {
+    events: Receiver<BookingEvent>,
+    answers: Sender<String>,
+    step: &'static str,
+    trains: Vec<Train>,
+    table: TableState,
+    question: Option<String>,
+    captcha: Option<Vec<u8>>,
+    answer: String,
+    done: Option<Result<BookingResult, String>>,
+}
+
+impl Booking {
+    fn start(args: Args, config: Arc<Config>) -> Booking {
+        let (sender, events) = mpsc::channel();
+        let answers = remote::spawn(remote::new_id(), args, config, move |update| {
+            let event = match update {
+                Update::Reached(progress) => BookingEvent::Step(match progress {
+                    Progress::Train(_) => "train",
+                    Progress::Ticket(_) => "ticket",
+                    Progress::Booked(_) => "booked",
+                }),
+                Update::Trains(trains) => BookingEvent::Trains(trains),
+                Update::Question(hint) => BookingEvent::Question(hint.to_string()),
+                Update::Captcha(img_data) => BookingEvent::Captcha(img_data.to_vec()),
+                Update::Done(outcome) => BookingEvent::Done(Box::new(outcome.clone())),
+            };
+            // Nobody to tell once the interface is closed
+            let _ = sender.send(event);
+        });
+        Booking {
+            events,
+            answers,
+            step: STEPS[0],
+            trains: Vec::new(),
+            table: TableState::default().with_selected(0),
+            question: None,
+            captcha: None,
+            answer: String::new(),
+            done: None,
+        }
+    }
+
+    fn apply(&mut self, event: BookingEvent) {
+        match event {
+            BookingEvent::Step(step) => self.step = step,
+            BookingEvent::Trains(trains) => self.trains = trains,
+            BookingEvent::Question(hint) => self.question = Some(hint),
+            BookingEvent::Captcha(img_data) => self.captcha = Some(img_data),
+            BookingEvent::Done(outcome) => {
+                self.question = None;
+                self.captcha = None;
+                self.done = Some(*outcome);
+            }
+        }
+    }
+
+    /// Whether the question is which train to take, picked from the table.
+    fn picks_train(&self) -> bool {
+        self.step == "train" && self.question.is_some() && !self.trains.is_empty()
+    }
+
+    fn waiting(&self) -> bool {
+        self.question.is_some() || self.captcha.is_some()
+    }
+
+    fn send(&mut self, answer: String) {
+        self.question = None;
+        self.captcha = None;
+        self.answer.clear();
+        // A booking gone quiet has already sent `Done`
+        let _ = self.answers.send(answer);
+    }
+}
+
+enum Screen {
+    Form,
+    Review,
+    Booking,
+}
+
+struct App {
+    args: Args,
+    config: Arc<Config>,
+    screen: Screen,
+    form: Form,
+    booking: Option<Booking>,
+    quit: bool,
+}
+
+impl App {
+    fn new(args: Args, config: Arc<Config>) -> App {
+        App {
+            form: Form::new(&args),
+            args,
+            config,
+            screen: Screen::Form,
+            booking: None,
+            quit: false,
+        }
+    }
+
+    /// Run until the user leaves, returning how the booking went, if it was done.
+    fn run(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+    ) -> io::Result<Option<Result<BookingResult, String>>> {
+        while !self.quit {
+            let mut updated = false;
+            if let Some(booking) = &mut self.booking {
+                while let Ok(event) = booking.events.try_recv() {
+                    booking.apply(event);
+                    updated = true;
+                }
+            }
+            // The flow may print on the terminal too, draw everything over again
+            if updated {
+                terminal.clear()?;
+            }
+            terminal.draw(|frame| self.draw(frame))?;
+
+            if event::poll(Duration::from_millis(100))?
+                && let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+            {
+                self.handle(key);
+            }
+        }
+        Ok(self.booking.take().and_then(|booking| booking.done))
+    }
+
+    fn handle(&mut self, key: KeyEvent) {
+        match self.screen {
+            Screen::Form => self.handle_form(key),
+            Screen::Review => match key.code {
+                KeyCode::Enter => {
+                    let args = self.form.args(&self.args);
+                    self.booking = Some(Booking::start(args, Arc::clone(&self.config)));
+                    self.screen = Screen::Booking;
+                }
+                KeyCode::Esc | KeyCode::Backspace => self.screen = Screen::Form,
+                KeyCode::Char('q') => self.quit = true,
+                _ => {}
+            },
+            Screen::Booking => self.handle_booking(key),
+        }
+    }
+
+    fn handle_form(&mut self, key: KeyEvent) {
+        let form = &mut self.form;
+        form.error = None;
+        match key.code {
+            KeyCode::Esc => self.quit = true,
+            KeyCode::Char('q') if form.current() != Field::Date => self.quit = true,
+            KeyCode::Up | KeyCode::BackTab => {
+                form.field = (form.field + FIELDS.len() - 1) % FIELDS.len()
+            }
+            KeyCode::Down | KeyCode::Tab => form.field = (form.field + 1) % FIELDS.len(),
+            KeyCode::Left => form.change(-1),
+            KeyCode::Right => form.change(1),
+            KeyCode::Char(char)
+                if form.current() == Field::Date && (char.is_ascii_digit() || char == '/') =>
+            {
+                form.date.push(char)
+            }
+            KeyCode::Backspace if form.current() == Field::Date => {
+                form.date.pop();
+            }
+            KeyCode::Enter => match form.validate() {
+                Ok(()) => self.screen = Screen::Review,
+                Err(err_msg) => form.error = Some(err_msg),
+            },
+            _ => {}
+        }
+    }
+
+    fn handle_booking(&mut self, key: KeyEvent) {
+        let Some(booking) = &mut self.booking else {
+            return;
+        };
+        if booking.done.is_some() {
+            if matches!(key.code, KeyCode::Enter | KeyCode::Esc | KeyCode::Char('q')) {
+                self.quit = true;
+            }
+            return;
+        }
+        match key.code {
+            // Dropping the answers gives the booking up
+            KeyCode::Esc => self.quit = true,
+            KeyCode::Up if booking.picks_train() => booking.table.select_previous(),
+            KeyCode::Down if booking.picks_train() => booking.table.select_next(),
+            KeyCode::Enter if booking.picks_train() && booking.answer.is_empty() => {
+                let pick = booking
+                    .table
+                    .selected()
+                    .unwrap_or(0)
+                    .min(booking.trains.len() - 1);
+                booking.send((pick + 1).to_string());
+            }
+            KeyCode::Enter if booking.waiting() => {
+                let answer = booking.answer.clone();
+                booking.send(answer);
+            }
+            KeyCode::Char(char) if booking.waiting() => booking.answer.push(char),
+            KeyCode::Backspace => {
+                booking.answer.pop();
+            }
+            _ => {}
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, log, help] = Layout::vertical([
+            Constraint::Min(0),
+            Constraint::Length(7),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let keys = match self.screen {
+            Screen::Form => self.draw_form(frame, main),
+            Screen::Review => self.draw_review(frame, main),
+            Screen::Booking => self.draw_booking(frame, main),
+        };
+        draw_log(frame, log);
+        frame.render_widget(Paragraph::new(keys).dark_gray(), help);
+    }
+
+    fn draw_form(&self, frame: &mut Frame, area: Rect) -> &'static str {
+        let form = &self.form;
+        let [fields, side] =
+            Layout::horizontal([Constraint::Length(40), Constraint::Min(0)]).areas(area);
+
+        let mut lines: Vec<Line> = (FIELDS.iter().enumerate())
+            .map(|(idx, &field)| {
+                let line = Line::from(vec![
+                    Span::raw(format!(" {:<10}", field_name(field))),
+                    Span::raw(form.value(field)),
+                ]);
+                match idx == form.field {
+                    true => line.reversed(),
+                    false => line,
+                }
+            })
+            .collect();
+        if let Some(err_msg) = &form.error {
+            lines.push(Line::default());
+            lines.push(Line::from(format!(" {}", err_msg)).red());
+        }
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(" Booking ")),
+            fields,
+        );
+
+        let (title, items, selected) = match form.current() {
+            Field::From | Field::To => (
+                " Stations ",
+                (0..STATIONS.len()).map(station).collect(),
+                match form.current() {
+                    Field::From => form.from,
+                    _ => form.to,
+                },
+            ),
+            Field::Time => (
+                " Departure times ",
+                TIME_SLOTS.iter().map(ToString::to_string).collect(),
+                form.time,
+            ),
+            _ => (" ", Vec::new(), 0),
+        };
+        let block = Block::bordered().title(title);
+        if items.is_empty() {
+            let text = match form.current() {
+                Field::Date => {
+                    "Type the date like 2025/06/29, or leave it empty for the latest date open for booking."
+                }
+                _ => "Change the value with ←/→.",
+            };
+            frame.render_widget(
+                Paragraph::new(text).wrap(Wrap { trim: true }).block(block),
+                side,
+            );
+        } else {
+            let mut state = ListState::default().with_selected(Some(selected));
+            frame.render_stateful_widget(
+                List::new(items)
+                    .block(block)
+                    .highlight_style(Style::new().reversed()),
+                side,
+                &mut state,
+            );
+        }
+        "↑/↓ field  ←/→ change  Enter review  Esc quit"
+    }
+
+    fn draw_review(&self, frame: &mut Frame, area: Rect) -> &'static str {
+        let mut lines: Vec<Line> = (FIELDS.iter())
+            .map(|&field| {
+                Line::from(format!(
+                    " {:<10}{}",
+                    field_name(field),
+                    self.form.value(field)
+                ))
+            })
+            .collect();
+        if !self.args.party.is_empty() {
+            lines.push(Line::from(format!(
+                " {:<10}{}",
+                "Passengers",
+                self.args.passengers.join(", ")
+            )));
+        }
+        if !self.args.train.is_empty() {
+            let trains: Vec<String> = self.args.train.iter().map(u32::to_string).collect();
+            lines.push(Line::from(format!(" {:<10}{}", "Train", trains.join(", "))));
+        }
+        lines.push(Line::default());
+        lines.push(Line::from(" Book these tickets?").bold());
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(" Confirm ")),
+            area,
+        );
+        "Enter book  Esc back  q quit"
+    }
+
+    fn draw_booking(&mut self, frame: &mut Frame, area: Rect) -> &'static str {
+        let Some(booking) = &mut self.booking else {
+            return "";
+        };
+        let [steps, main, input] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(3),
+        ])
+        .areas(area);
+
+        let mut spans = Vec::new();
+        for (idx, step) in STEPS.iter().enumerate() {
+            if idx > 0 {
+                spans.push(Span::raw(" › "));
+            }
+            spans.push(match *step == booking.step {
+                true => Span::raw(*step).bold().green(),
+                false => Span::raw(*step).dark_gray(),
+            });
+        }
+        frame.render_widget(Line::from(spans), steps);
+
+        if let Some(outcome) = &booking.done {
+            let (title, text) = match outcome {
+                Ok(result) => (" Booked ", result.to_string()),
+                Err(err_msg) => (" Failed ", format!("Error: {}", err_msg)),
+            };
+            frame.render_widget(
+                Paragraph::new(text).block(Block::bordered().title(title)),
+                main,
+            );
+            return "Enter quit";
+        }
+
+        let [trains, side] =
+            Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)])
+                .areas(main);
+        draw_trains(frame, trains, booking);
+        match &booking.captcha {
+            Some(img_data) => {
+                let block = Block::bordered().title(" Security code ");
+                let inner = block.inner(side);
+                frame.render_widget(block, side);
+                frame.render_widget(Paragraph::new(captcha_lines(img_data, inner)), inner);
+            }
+            None => frame.render_widget(Block::bordered().title(" Security code "), side),
+        }
+
+        let hint = match (&booking.question, &booking.captcha) {
+            (Some(hint), _) => hint.clone(),
+            (None, Some(_)) => "Security code, or r for a new image:".to_string(),
+            (None, None) => "Waiting for the booking site…".to_string(),
+        };
+        frame.render_widget(
+            Paragraph::new(booking.answer.as_str()).block(Block::bordered().title(hint)),
+            input,
+        );
+        match booking.picks_train() {
+            true => "↑/↓ train  Enter book it  Esc give up",
+            false => "Enter answer  Esc give up",
+        }
+    }
+}
+
+fn draw_trains(frame: &mut Frame, area: Rect, booking: &mut Booking) {
+    let rows = (booking.trains.iter().enumerate()).map(|(idx, train)| {
+        Row::new(vec![
+            Cell::from((idx + 1).to_string()),
+            Cell::from(train.id().to_string()),
+            Cell::from(train.depart().format("%H:%M").to_string()),
+            Cell::from(train.arrive().format("%H:%M").to_string()),
+            Cell::from(train.travel_time()),
+            Cell::from(train.seats().hint().unwrap_or_default()).red(),
+            Cell::from(train.discount_info().to_string()).yellow(),
+        ])
+    });
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(3),
+            Constraint::Length(6),
+            Constraint::Length(7),
+            Constraint::Length(7),
+            Constraint::Length(7),
+            Constraint::Length(16),
+            Constraint::Min(0),
+        ],
+    )
+    .header(
+        Row::new([
+            "No.",
+            "Train",
+            "Depart",
+            "Arrive",
+            "Time",
+            "Seats",
+            "Discounts",
+        ])
+        .bold(),
+    )
+    .block(Block::bordered().title(" Trains "));
+    match booking.picks_train() {
+        true => frame.render_stateful_widget(
+            table.row_highlight_style(Style::new().reversed()),
+            area,
+            &mut booking.table,
+        ),
+        false => frame.render_widget(table, area),
+    }
+}
+
+fn draw_log(frame: &mut Frame, area: Rect) {
+    let block = Block::bordered().title(" Log ");
+    let height = usize::from(block.inner(area).height);
+    let log = LOG.lock().unwrap();
+    let lines: Vec<Line> = (log.iter().skip(log.len().saturating_sub(height)))
+        .map(|line| Line::from(line.as_str()))
+        .collect();
+    frame.render_widget(Paragraph::new(lines).dark_gray().block(block), area);
+}
+
+/// The security code image in `area`, two pixels to a cell as half blocks.
+fn captcha_lines(img_data: &[u8], area: Rect) -> Vec<Line<'static>> {
+    let Ok(image) = image::load_from_memory(img_data) else {
+        return vec![Line::from("The image can't be shown")];
+    };
+    let image = image
+        .resize(
+            area.width.into(),
+            u32::from(area.height) * 2,
+            FilterType::Triangle,
+        )
+        .to_rgb8();
+    let rgb = |pixel: &image::Rgb<u8>| Color::Rgb(pixel[0], pixel[1], pixel[2]);
+    (0..image.height())
+        .step_by(2)
+        .map(|y| {
+            let cells: Vec<Span> = (0..image.width())
+                .map(|x| {
+                    let top = image.get_pixel(x, y);
+                    let bottom = image.get_pixel(x, (y + 1).min(image.height() - 1));
+                    Span::styled("▀", Style::new().fg(rgb(top)).bg(rgb(bottom)))
+                })
+                .collect();
+            Line::from(cells)
+        })
+        .collect()
+}
@@ -0,0 +1,174 @@
+use chrono::{NaiveTime, TimeDelta};
+use tracing::{info, info_span, warn};
+
+use std::thread;
+use std::time::Duration;
+
+use crate::booking_flow::BookingPayload;
+use crate::cli::WatchArgs;
+use crate::error::Error;
+use crate::exit;
+use crate::frontend::Frontend;
+use crate::http::{RateLimit, RateLimiter};
+use crate::i18n::tr;
+use crate::metrics;
+use crate::output;
+use crate::pick::{self, TrainPick};
+use crate::prompt::StdinPrompter;
+use crate::resume::{self, BookingState};
+use crate::stage::{Progress, Stage};
+use crate::time;
+use crate::{build_client, complete_booking, load_config, maintenance_end, report};
+
+/// How close to the announced end of a maintenance the cool-down of the rate limit is
+/// waited instead, the notice being up a little longer than it says now and then.
+const MAINTENANCE_MARGIN: TimeDelta = TimeDelta::minutes(5);
+
+/// How long to pause after `err` at `now`, Taipei time: until the end of a maintenance
+/// or for as long as the site asks, and for the cool-down of `limit` when it doesn't
+/// tell or the maintenance should be over by now.
+pub fn cool_down(err: &Error, limit: &RateLimit, now: NaiveTime) -> Duration {
+    match err {
+        Error::Throttled(Some(wait)) => *wait,
+        Error::Maintenance(message) => match maintenance_end(message) {
+            Some(end) => {
+                let mut left = end - now;
+                // Ending after midnight, rather than hours ago
+                if left < -TimeDelta::hours(12) {
+                    left += TimeDelta::days(1);
+                }
+                match left > MAINTENANCE_MARGIN {
+                    true => Duration::from_secs(left.num_seconds() as u64 + 60),
+                    false => limit.cool_down,
+                }
+            }
+            None => limit.cool_down,
+        },
+        _ => limit.cool_down,
+    }
+}
+
+/// Query the route on an interval until a matching train is listed, then book it.
+pub fn run(watch_args: WatchArgs) {
+    let WatchArgs {
+        mut args,
+        every,
+        metrics,
+    } = watch_args;
+    if args.from.is_none() || args.to.is_none() || args.date.is_none() || args.time.is_none() {
+        output::error("watch mode needs --from, --to, --date and --time");
+        exit::fail(exit::Status::Invalid);
+        return;
+    }
+
+    // Use the defaults for the remaining options instead of prompting every round
+    if args.adult_cnt.is_none() && args.student_cnt.is_none() {
+        args.adult_cnt = Some(1);
+    }
+    args.seat_prefer.get_or_insert(0);
+    args.class_type.get_or_insert(0);
+    // Without a way to pick, the first train listed is booked
+    if args.train.is_empty() && args.select.is_none() && args.select_script.is_none() {
+        args.pick.get_or_insert(TrainPick::First);
+    }
+
+    let config = match load_config(&mut args) {
+        Ok(config) => config,
+        Err(err_msg) => {
+            output::error(err_msg);
+            exit::fail(exit::Status::Invalid);
+            return;
+        }
+    };
+
+    if let Some(listen) = metrics
+        && let Err(err_msg) = metrics::serve(listen)
+    {
+        output::error(err_msg);
+        return;
+    }
+
+    let stdin = StdinPrompter::new(&config.captcha);
+    let frontend = Frontend::new(&stdin, &args, &config);
+    let mut payload = BookingPayload::default();
+    payload.select_options(&args.options(&config), None, &frontend);
+
+    // Shared by the sessions of all rounds
+    let limiter = RateLimiter::new(config.rate_limit.clone());
+    let mut round = 1;
+    let (client, stage) = loop {
+        let _span = info_span!("watch", round).entered();
+        info!("Watching for available trains");
+
+        // Start every round with a fresh session
+        let client = build_client(&config).rate_limit(limiter.clone());
+        let searched = (Stage::open(config.backend.backend(), &client, &config.site, &frontend))
+            .and_then(|search| {
+                search.submit(&client, &mut payload, &config.site, &frontend, || {})
+            });
+        match searched {
+            Ok(stage) => {
+                let trains = stage.trains();
+                match args.train[..] {
+                    [train] if trains.iter().any(|t| t.id() == train) => {
+                        break (client, stage);
+                    }
+                    [train] => info!("Train {} is not available yet", train),
+                    // Picked again the same way from the same list when booking
+                    _ => match pick::select(&trains, &args) {
+                        Ok(_) => break (client, stage),
+                        Err(err @ Error::SoldOut(_)) => info!("{}", err),
+                        Err(err) => {
+                            report(&config, Err(err));
+                            return;
+                        }
+                    },
+                }
+            }
+            Err(err @ Error::SoldOut(_)) => info!("No availability yet: {}", err),
+            // Searching again soon would only make it worse
+            Err(err @ (Error::Maintenance(_) | Error::Throttled(_))) => {
+                let wait = cool_down(&err, &config.rate_limit, time::taipei_now().time());
+                output::warning(tr!(
+                    "{}. Waiting {} before searching again",
+                    "{}。{} 後再查詢",
+                    err,
+                    humantime::format_duration(wait)
+                ));
+                thread::sleep(wait);
+                round += 1;
+                continue;
+            }
+            // Worth another round with a new session and security code
+            Err(
+                err @ (Error::WrongSecurityCode(_) | Error::SessionExpired | Error::Network(_)),
+            ) => {
+                warn!("{}", err)
+            }
+            // Invalid options won't fix themselves by waiting
+            Err(err) => {
+                report(&config, Err(err));
+                return;
+            }
+        }
+
+        info!("Retrying in {}", humantime::format_duration(every));
+        thread::sleep(every);
+        round += 1;
+    };
+
+    let state_path = BookingState::default_path();
+    report(
+        &config,
+        complete_booking(
+            &client,
+            Progress::Train(stage),
+            &mut payload,
+            &args,
+            &config,
+            &stdin,
+            &mut resume::journal(state_path.as_deref(), &client, &args),
+        ),
+    );
+    resume::hint(state_path.as_deref());
+}
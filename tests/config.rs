@@ -0,0 +1,228 @@
+use age::armor::{ArmoredWriter, Format};
+use age::secrecy::ExposeSecret;
+use clap::Parser;
+use reqwest::header::{HeaderMap, HeaderValue};
+
+use std::io::Write;
+
+use thsr::backend::BackendKind;
+use thsr::captcha::SolverKind;
+use thsr::cli::{Cli, Command};
+use thsr::config::Config;
+use thsr::last::LastBooking;
+use thsr::pick::TrainPick;
+use thsr::plugin;
+use thsr::preset::{self, Preset};
+use thsr::schema::{Station, TimeSlot};
+use thsr::site::SiteLocale;
+
+#[test]
+fn changes_the_headers() {
+    let config: Config = toml::from_str(
+        r#"
+        [headers]
+        user_agent = "Mozilla/5.0 (X11; Linux x86_64; rv:140.0) Gecko/20100101 Firefox/140.0"
+        remove = ["Sec-Fetch-Mode"]
+
+        [headers.set]
+        "Accept-Language" = "en-US,en;q=0.5"
+        "DNT" = "1"
+        "#,
+    )
+    .unwrap();
+
+    let mut headers = HeaderMap::new();
+    headers.insert("User-Agent", HeaderValue::from_static("Firefox/137.0"));
+    headers.insert("Accept-Language", HeaderValue::from_static("zh-TW"));
+    headers.insert("Sec-Fetch-Mode", HeaderValue::from_static("no-cors"));
+    config.headers.apply(&mut headers);
+
+    assert_eq!(
+        headers["user-agent"],
+        "Mozilla/5.0 (X11; Linux x86_64; rv:140.0) Gecko/20100101 Firefox/140.0"
+    );
+    assert_eq!(headers["accept-language"], "en-US,en;q=0.5");
+    assert_eq!(headers["dnt"], "1");
+    assert!(!headers.contains_key("sec-fetch-mode"));
+}
+
+#[test]
+fn rejects_invalid_headers() {
+    let outcome = toml::from_str::<Config>("[headers.set]\n\"Bad Name\" = \"1\"\n");
+    assert!(outcome.is_err());
+    let outcome = toml::from_str::<Config>("[headers]\nuser_agent = \"line\\nbreak\"\n");
+    assert!(outcome.is_err());
+}
+
+#[test]
+fn selects_the_backend() {
+    let config: Config = toml::from_str("backend = \"web\"\n").unwrap();
+    assert_eq!(config.backend, BackendKind::Web);
+    assert!(toml::from_str::<Config>("backend = \"app\"\n").is_err());
+}
+
+#[test]
+fn decrypts_the_encrypted_section() {
+    let identity = age::x25519::Identity::generate();
+    let recipient = identity.to_public();
+    let mut armored = vec![];
+    let armor = ArmoredWriter::wrap_output(&mut armored, Format::AsciiArmor).unwrap();
+    let mut writer = age::Encryptor::with_recipients(std::iter::once(&recipient as _))
+        .unwrap()
+        .wrap_output(armor)
+        .unwrap();
+    writer
+        .write_all(b"[captcha]\napi_key = \"0123abcd\"\n\n[passengers.me]\nid = \"A123456789\"\n")
+        .unwrap();
+    writer.finish().unwrap().finish().unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.toml");
+    let content = format!(
+        "encrypted = \"\"\"\n{}\"\"\"\n\n[captcha]\nsolver = \"service\"\n",
+        String::from_utf8(armored).unwrap()
+    );
+    std::fs::write(&path, content).unwrap();
+    let key = dir.path().join("key.txt");
+    std::fs::write(&key, identity.to_string().expose_secret()).unwrap();
+
+    let config = Config::load(Some(&path), Some(&key)).unwrap();
+    assert_eq!(config.captcha.solver, SolverKind::Service);
+    assert_eq!(config.captcha.api_key.as_deref(), Some("0123abcd"));
+    assert_eq!(config.passengers["me"].id.expose_secret(), "A123456789");
+
+    let err_msg = Config::load(Some(&path), None).unwrap_err();
+    assert!(err_msg.contains("--config-key"));
+}
+
+#[test]
+fn checks_the_phone_number() {
+    let phone = |number: &str| {
+        toml::from_str::<Config>(&format!("[contact]\nphone = \"{}\"\n", number))
+            .map(|config| config.contact.phone.unwrap())
+    };
+    assert_eq!(phone("+886 912-345-678").unwrap(), "0912345678");
+    assert!(phone("02 2345 6789").is_err());
+    assert!(phone("091234567").is_err());
+}
+
+#[test]
+fn checks_the_email_address() {
+    let config: Config = toml::from_str("[contact]\nemail = \"me@example.com\"\n").unwrap();
+    assert_eq!(config.contact.email.as_deref(), Some("me@example.com"));
+    assert!(toml::from_str::<Config>("[contact]\nemail = \"me@localhost\"\n").is_err());
+    assert!(toml::from_str::<Config>("[contact]\nemail = \"example.com\"\n").is_err());
+}
+
+#[test]
+fn english_booking_site() {
+    let config: Config = toml::from_str(
+        r#"
+        [site]
+        locale = "en"
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(config.site.locale, SiteLocale::En);
+    assert_eq!(
+        config.site.booking_page(),
+        "https://irs.thsrc.com.tw/IMINT/?locale=en"
+    );
+}
+
+#[test]
+fn reuses_the_last_booking() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("last.json");
+    let last = LastBooking {
+        from: Station::Taipei,
+        to: Station::Zuoying,
+        time: TimeSlot::from_id(10),
+        adult_cnt: 1,
+        student_cnt: 1,
+        passengers: Vec::new(),
+        seat_prefer: 1,
+        class_type: 0,
+    };
+    last.save(&path).unwrap();
+    assert_eq!(LastBooking::load(&path).unwrap(), last);
+
+    let mut args = Cli::parse_from(["thsr", "--last", "--to", "hsinchu"]).args;
+    last.apply(&mut args);
+    assert_eq!(
+        (args.from, args.to, args.time),
+        (
+            Some(Station::Taipei),
+            Some(Station::Hsinchu),
+            TimeSlot::from_id(10)
+        )
+    );
+    assert_eq!((args.adult_cnt, args.student_cnt), (Some(1), Some(1)));
+
+    // The tickets are taken as a whole
+    let mut args = Cli::parse_from(["thsr", "--last", "--adult-cnt", "3"]).args;
+    last.apply(&mut args);
+    assert_eq!((args.adult_cnt, args.student_cnt), (Some(3), None));
+}
+
+#[test]
+fn manages_presets() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.toml");
+    std::fs::write(
+        &path,
+        "# Contact details\n[contact]\nphone = \"0912345678\"\n",
+    )
+    .unwrap();
+
+    let home = Preset {
+        from: Some(Station::Taipei),
+        to: Some(Station::Zuoying),
+        time: TimeSlot::from_id(26),
+        adult_cnt: Some(2),
+        pick: Some(TrainPick::Fastest),
+        ..Default::default()
+    };
+    preset::add(&path, "weekend-home", &home).unwrap();
+    preset::add(&path, "work", &Preset::default()).unwrap();
+    assert_eq!(preset::list(&path).unwrap()["weekend-home"], home);
+    assert!(preset::remove(&path, "work").unwrap());
+    assert!(!preset::remove(&path, "work").unwrap());
+
+    let content = std::fs::read_to_string(&path).unwrap();
+    assert!(content.starts_with("# Contact details\n"));
+    let config = Config::load(Some(&path), None).unwrap();
+    assert_eq!(config.presets.len(), 1);
+
+    let mut args = Cli::parse_from(["thsr", "--preset", "weekend-home", "--time", "19:30"]).args;
+    config.presets["weekend-home"].apply(&mut args);
+    assert_eq!(
+        (args.from, args.to, args.time),
+        (
+            Some(Station::Taipei),
+            Some(Station::Zuoying),
+            TimeSlot::from_id(30)
+        )
+    );
+    assert_eq!(args.adult_cnt, Some(2));
+    assert_eq!(args.pick, Some(TrainPick::Fastest));
+}
+
+#[test]
+fn finds_plugins_on_the_path() {
+    let cli = Cli::try_parse_from(["thsr", "export-ics", "--out", "trips.ics"]).unwrap();
+    let Some(Command::External(args)) = cli.command else {
+        panic!("expected a plugin, got {:?}", cli.command);
+    };
+    assert_eq!(args, ["export-ics", "--out", "trips.ics"]);
+
+    let (empty, dir) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+    let plugin = dir
+        .path()
+        .join(format!("thsr-export-ics{}", std::env::consts::EXE_SUFFIX));
+    std::fs::write(&plugin, "").unwrap();
+    let path = std::env::join_paths([empty.path(), dir.path()]).unwrap();
+    assert_eq!(plugin::find("export-ics", &path), Some(plugin));
+    assert_eq!(plugin::find("export-csv", &path), None);
+}
@@ -0,0 +1,154 @@
+#![cfg(feature = "blocking")]
+
+mod mock;
+
+use clap::Parser;
+use serde_json::json;
+
+use std::path::Path;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use thsr::cli::Cli;
+use thsr::config::Config;
+use thsr::daemon::{JobSpec, Queue, load_jobs, work};
+use thsr::history::{self, Entry};
+use thsr::prompt::ScriptedPrompter;
+use thsr::remind;
+use thsr::result::{BookingResult, parse_result};
+
+use mock::{MockSite, SECURITY_CODE, Scenario};
+
+/// Work through `queue` against `mock` until `count` jobs are recorded in `history`.
+fn run_jobs(
+    mock: &MockSite,
+    queue: Arc<Queue>,
+    history: &Path,
+    prompter: impl FnOnce() -> ScriptedPrompter + Send + 'static,
+    count: usize,
+) -> Vec<Entry> {
+    let config = Config {
+        site: mock.site(),
+        ..Default::default()
+    };
+    let path = history.to_path_buf();
+    thread::spawn(move || work(&queue, &config, Some(&path), &prompter()));
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        let entries = history::load(history).unwrap();
+        if entries.len() >= count || Instant::now() > deadline {
+            return entries;
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+#[test]
+fn runs_the_jobs_of_a_job_file() {
+    let mock = MockSite::start(Scenario::default());
+    let dir = tempfile::tempdir().unwrap();
+    let jobs = dir.path().join("jobs.toml");
+    std::fs::write(
+        &jobs,
+        r#"
+[[job]]
+at = "2025/05/01 00:00"
+
+[job.booking]
+from = 2
+to = 12
+date = "2025/06/29"
+time = 10
+train = 1505
+adult_cnt = 2
+"#,
+    )
+    .unwrap();
+
+    let queue = Arc::new(Queue::default());
+    let args = Cli::parse_from(["thsr"]).args;
+    assert_eq!(load_jobs(&queue, &jobs, &args), Ok(1));
+    let id = queue.jobs()[0].id.clone();
+
+    // The IDs of the two early bird passengers
+    let history = dir.path().join("history.jsonl");
+    let prompter = || ScriptedPrompter::new(["", "B223456789"], [SECURITY_CODE]);
+    let entries = run_jobs(&mock, Arc::clone(&queue), &history, prompter, 1);
+
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].job, id);
+    assert_eq!(entries[0].attempts, 1);
+    assert_eq!(entries[0].result.as_ref().unwrap().pnr, "08123456");
+    assert!(queue.jobs().is_empty());
+}
+
+#[test]
+fn retries_a_sold_out_job() {
+    let mock = MockSite::start(Scenario {
+        sold_out: true,
+        ..Default::default()
+    });
+    let dir = tempfile::tempdir().unwrap();
+    let queue = Arc::new(Queue::default());
+    let spec: JobSpec = serde_json::from_value(json!({
+        "booking": { "from": 2, "to": 12, "date": "2025/06/29", "time": 10 },
+        "retry": { "attempts": 2, "delay": "10ms" },
+    }))
+    .unwrap();
+    queue.push(spec, &Cli::parse_from(["thsr"]).args).unwrap();
+
+    let history = dir.path().join("history.jsonl");
+    let prompter = || ScriptedPrompter::new(Vec::<String>::new(), [SECURITY_CODE; 2]);
+    let entries = run_jobs(&mock, queue, &history, prompter, 1);
+
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].attempts, 2);
+    assert!(entries[0].result.is_none());
+    assert_eq!(mock.forms("S1").len(), 2);
+}
+
+#[test]
+fn rejects_jobs_without_a_route() {
+    let queue = Queue::default();
+    let spec: JobSpec = serde_json::from_value(json!({ "booking": { "from": 2 } })).unwrap();
+    let outcome = queue.push(spec, &Cli::parse_from(["thsr"]).args);
+    assert_eq!(
+        outcome.unwrap_err(),
+        "A job needs from, to, date and time".to_string()
+    );
+}
+
+#[test]
+fn reminds_of_the_bookings_to_pay() {
+    let result = parse_result(&scraper::Html::parse_document(include_str!(
+        "fixtures/result.html"
+    )));
+    let paid_late = BookingResult {
+        pnr: "08000000".to_string(),
+        payment_deadline: "2025/06/01 12:00".to_string(),
+        ..result.clone()
+    };
+    let paid_later = BookingResult {
+        pnr: "08999999".to_string(),
+        payment_deadline: "2025/06/10 12:00".to_string(),
+        ..result.clone()
+    };
+    let entries = [
+        Entry::new("thsr", 1, Ok(&paid_later)),
+        Entry::new("thsr", 1, Ok(&result)),
+        Entry::new("thsr", 1, Ok(&paid_late)),
+        Entry::new("job", 3, Err("Sold out".to_string())),
+    ];
+
+    // Three hours before the deadline of the fixture, the other ones passed or far off
+    let now = result.payment_due().unwrap() - chrono::Duration::hours(3);
+    let due = remind::due(&entries, now, chrono::Duration::hours(24));
+    assert_eq!(due.len(), 1);
+    assert_eq!(due[0].0.pnr, "08123456");
+    assert_eq!(
+        remind::reminder(due[0].0, due[0].1),
+        "PNR 08123456, 台北 → 左營 2025/06/29 06:30: pay TWD 2,980 before 2025/06/03 23:59, within 3 hours 0 minutes"
+    );
+}
@@ -0,0 +1,815 @@
+#![cfg(feature = "blocking")]
+
+mod mock;
+
+use clap::Parser;
+use reqwest::header::HeaderMap;
+
+use std::io;
+use std::time::{Duration, Instant};
+
+use thsr::booking_flow::{BookingPayload, fetch_page};
+use thsr::cli::{Args, Cli};
+use thsr::config::Config;
+use thsr::error::Error;
+use thsr::group;
+use thsr::http::{HttpClient, RateLimit, RateLimiter, RetryPolicy};
+use thsr::notify;
+use thsr::passenger;
+use thsr::prompt::{ScriptedPrompter, StreamPrompter};
+use thsr::resume::{self, BookingState, Step};
+use thsr::stage::Stage;
+use thsr::{book, book_with_journal, doctor, trains};
+
+use mock::{CAPTCHA_IMAGE, MockSite, SECURITY_CODE, Scenario};
+
+fn args() -> Args {
+    Cli::parse_from([
+        "thsr",
+        "--from",
+        "2",
+        "--to",
+        "12",
+        "--date",
+        "2025/06/29",
+        "--time",
+        "10",
+        "--adult-cnt",
+        "2",
+        "--seat-prefer",
+        "1",
+        "--class-type",
+        "0",
+        // Without asking to confirm the summary before booking
+        "--yes",
+    ])
+    .args
+}
+
+fn config(mock: &MockSite) -> Config {
+    Config {
+        site: mock.site(),
+        ..Default::default()
+    }
+}
+
+fn client() -> HttpClient {
+    HttpClient::new(HeaderMap::new(), RetryPolicy::default())
+}
+
+#[test]
+fn books_through_all_steps() {
+    let mock = MockSite::start(Scenario::default());
+    // The second train, then the IDs of the two early bird passengers
+    let prompter = ScriptedPrompter::new(["2", "", "B223456789"], [SECURITY_CODE]);
+
+    let result = book(&client(), &args(), &config(&mock), &prompter).unwrap();
+    assert_eq!(result.pnr, "08123456");
+    // TWD 2,980 with membership
+    assert_eq!(result.tgo_points, Some(149));
+
+    let search = &mock.forms("S1")[0];
+    assert_eq!(search["selectStartStation"], "2");
+    assert_eq!(search["selectDestinationStation"], "12");
+    assert_eq!(search["toTimeInputField"], "2025/06/29");
+    assert_eq!(search["homeCaptcha:securityCode"], SECURITY_CODE);
+    assert_eq!(search["seatCon:seatRadioGroup"], "1");
+    assert_eq!(
+        mock.forms("S2")[0]["TrainQueryDataViewPanel:TrainGroup"],
+        "radio20"
+    );
+    assert_eq!(mock.forms("S3")[0]["dummyId"], "S125544509");
+}
+
+#[test]
+fn dry_run_stops_before_booking() {
+    let mock = MockSite::start(Scenario::default());
+    let prompter = ScriptedPrompter::new(["2"], [SECURITY_CODE]);
+    let args = Args {
+        dry_run: true,
+        ..args()
+    };
+
+    let outcome = book(&client(), &args, &config(&mock), &prompter);
+    assert_eq!(outcome.unwrap_err(), Error::DryRun);
+    assert_eq!(mock.forms("S2").len(), 1);
+    assert!(mock.forms("S3").is_empty());
+}
+
+#[test]
+fn asks_to_confirm_the_booking() {
+    let args = Args {
+        yes: false,
+        ..args()
+    };
+
+    let mock = MockSite::start(Scenario::default());
+    let prompter = ScriptedPrompter::new(["2", "n"], [SECURITY_CODE]);
+    let outcome = book(&client(), &args, &config(&mock), &prompter);
+    assert_eq!(outcome.unwrap_err(), Error::Cancelled);
+    assert!(mock.forms("S3").is_empty());
+    assert_eq!(prompter.asked()[1], "Book these tickets? (y/n):");
+
+    let mock = MockSite::start(Scenario::default());
+    let prompter = ScriptedPrompter::new(["2", "maybe", "y", "", "B223456789"], [SECURITY_CODE]);
+    book(&client(), &args, &config(&mock), &prompter).unwrap();
+    assert_eq!(mock.forms("S3").len(), 1);
+}
+
+#[test]
+fn answers_from_a_stream() {
+    let dir = tempfile::tempdir().unwrap();
+    let image = dir.path().join("code.jpg");
+    let mock = MockSite::start(Scenario::default());
+    let mut config = config(&mock);
+    config.captcha.out = Some(image.clone());
+
+    let answers = format!(
+        "# options\npersonal_id=A123456789\ntrain=803\n\ncaptcha={}\nanswer=\nanswer=B223456789\n",
+        SECURITY_CODE
+    );
+    let prompter = StreamPrompter::new(io::Cursor::new(answers)).with_captcha(&config.captcha);
+    let args = prompter.options().unwrap().apply(&args());
+    assert_eq!(args.train, [803]);
+
+    let result = book(&client(), &args, &config, &prompter).unwrap();
+    assert_eq!(result.pnr, "08123456");
+    assert!(image.exists());
+
+    let prompter = StreamPrompter::new(io::Cursor::new("from=Taipei\nto=Kaohsiung\n"));
+    assert_eq!(prompter.options().unwrap_err(), "Invalid to: Kaohsiung");
+}
+
+#[test]
+fn retries_a_wrong_security_code() {
+    let mock = MockSite::start(Scenario::default());
+    let prompter = ScriptedPrompter::new(["1", "", "B223456789"], ["XXXX", SECURITY_CODE]);
+
+    book(&client(), &args(), &config(&mock), &prompter).unwrap();
+    assert_eq!(mock.page_fetches(), 2);
+    assert_eq!(mock.forms("S1").len(), 2);
+}
+
+#[test]
+fn reports_sold_out() {
+    let mock = MockSite::start(Scenario {
+        sold_out: true,
+        ..Default::default()
+    });
+    let prompter = ScriptedPrompter::new(Vec::<String>::new(), [SECURITY_CODE]);
+
+    let outcome = book(&client(), &args(), &config(&mock), &prompter);
+    assert!(matches!(outcome, Err(Error::SoldOut(_))));
+    assert!(mock.forms("S2").is_empty());
+}
+
+#[test]
+fn reports_maintenance_and_throttling() {
+    let mock = MockSite::start(Scenario {
+        maintenance: true,
+        ..Default::default()
+    });
+    let prompter = ScriptedPrompter::new(Vec::<String>::new(), Vec::<String>::new());
+    let outcome = book(&client(), &args(), &config(&mock), &prompter);
+    assert_eq!(
+        outcome.unwrap_err().to_string(),
+        "The booking site is under maintenance until 06:00"
+    );
+
+    let mock = MockSite::start(Scenario {
+        throttled: true,
+        ..Default::default()
+    });
+    let outcome = book(&client(), &args(), &config(&mock), &prompter);
+    assert_eq!(
+        outcome.unwrap_err(),
+        Error::Throttled(Some(Duration::from_secs(120)))
+    );
+    // Without hammering the site with retries
+    assert_eq!(mock.page_fetches(), 1);
+}
+
+#[test]
+fn checks_the_booking_site() {
+    let mock = MockSite::start(Scenario::default());
+    let checks = doctor::site_checks(&client(), &config(&mock));
+    assert_eq!(checks.len(), 3);
+    assert!(checks.iter().all(doctor::Check::passed));
+
+    let mock = MockSite::start(Scenario {
+        maintenance: true,
+        ..Default::default()
+    });
+    let checks = doctor::site_checks(&client(), &config(&mock));
+    let failed: Vec<_> = (checks.iter())
+        .filter_map(|check| check.outcome.as_ref().err())
+        .collect();
+    assert_eq!(
+        failed,
+        [
+            "The booking site is under maintenance until 06:00",
+            "Not checked, the booking page didn't open"
+        ]
+    );
+}
+
+#[test]
+fn searches_nearby_slots_when_sold_out() {
+    let mock = MockSite::start(Scenario {
+        sold_out_searches: 2,
+        ..Default::default()
+    });
+    let prompter = ScriptedPrompter::new(["2", "", "B223456789"], [SECURITY_CODE; 3]);
+    let nearby = Args {
+        nearby_slots: Some(1),
+        ..args()
+    };
+
+    book(&client(), &nearby, &config(&mock), &prompter).unwrap();
+    // 09:30 was asked for, then 09:00 and 10:00 are the nearest
+    let slots: Vec<String> = (mock.forms("S1").iter())
+        .map(|search| search["toTimeTable"].clone())
+        .collect();
+    assert_eq!(slots, ["930A", "900A", "1000A"]);
+
+    // Without nearby slots, the first sold out search is the end
+    let mock = MockSite::start(Scenario {
+        sold_out_searches: 1,
+        ..Default::default()
+    });
+    let prompter = ScriptedPrompter::new(Vec::<String>::new(), [SECURITY_CODE]);
+    let outcome = book(&client(), &args(), &config(&mock), &prompter);
+    assert!(matches!(outcome, Err(Error::SoldOut(_))));
+}
+
+#[test]
+fn books_the_first_of_several_trains_listed() {
+    let mock = MockSite::start(Scenario::default());
+    let prompter = ScriptedPrompter::new(["", "B223456789"], [SECURITY_CODE]);
+    let preference = Cli::parse_from(["thsr", "--train", "1234,0609,1505"]).args;
+    let preference = Args {
+        train: preference.train,
+        ..args()
+    };
+
+    // 1234 isn't listed and 609 has standing room only
+    let result = book(&client(), &preference, &config(&mock), &prompter).unwrap();
+    assert_eq!(result.train, Some(1505));
+}
+
+#[test]
+fn tries_each_date_of_a_range() {
+    let mock = MockSite::start(Scenario {
+        sold_out_searches: 2,
+        ..Default::default()
+    });
+    let prompter = ScriptedPrompter::new(["2", "", "B223456789"], [SECURITY_CODE; 3]);
+    let range = Args {
+        date: Some("2025/06/27..2025/06/30".to_string()),
+        ..args()
+    };
+
+    let result = book(&client(), &range, &config(&mock), &prompter).unwrap();
+    assert_eq!(result.pnr, "08123456");
+    // The booking window ends on 06/29
+    let dates: Vec<String> = (mock.forms("S1").iter())
+        .map(|search| search["toTimeInputField"].clone())
+        .collect();
+    assert_eq!(dates, ["2025/06/27", "2025/06/28", "2025/06/29"]);
+
+    // A train not listed on any date of the range
+    let mock = MockSite::start(Scenario::default());
+    let unlisted = Args {
+        train: vec![999],
+        ..range.clone()
+    };
+    let prompter = ScriptedPrompter::new(Vec::<String>::new(), [SECURITY_CODE; 3]);
+    let outcome = book(&client(), &unlisted, &config(&mock), &prompter);
+    assert_eq!(outcome.unwrap_err(), Error::TrainUnavailable(999));
+    assert_eq!(mock.forms("S1").len(), 3);
+
+    // No date of the range is within the booking window
+    let later = Args {
+        date: Some("2025/07/01..2025/07/03".to_string()),
+        ..args()
+    };
+    let outcome = book(&client(), &later, &config(&mock), &prompter);
+    assert!(matches!(outcome, Err(Error::Rejected(_))));
+    assert_eq!(mock.forms("S1").len(), 3);
+}
+
+#[test]
+fn sweeps_the_time_slots_for_trains() {
+    let mock = MockSite::start(Scenario {
+        sold_out_searches: 1,
+        ..Default::default()
+    });
+    let prompter = ScriptedPrompter::new(Vec::<String>::new(), [SECURITY_CODE; 3]);
+    let sweep = "09:30-10:30".parse().unwrap();
+
+    let trains =
+        trains::search(&client(), &args(), Some(sweep), &config(&mock), &prompter).unwrap();
+    let slots: Vec<String> = (mock.forms("S1").iter())
+        .map(|search| search["toTimeTable"].clone())
+        .collect();
+    assert_eq!(slots, ["930A", "1000A", "1030A"]);
+    // The two searches listing trains listed the same ones, kept once in departure order
+    let ids: Vec<u32> = trains.iter().map(|train| train.id()).collect();
+    assert_eq!(ids, [803, 1505, 609]);
+}
+
+#[test]
+fn restarts_an_expired_session_with_the_same_answers() {
+    let mock = MockSite::start(Scenario {
+        expire_on_train: true,
+        ..Default::default()
+    });
+    let prompter = ScriptedPrompter::new(["3", "", "B223456789"], [SECURITY_CODE, SECURITY_CODE]);
+
+    book(&client(), &args(), &config(&mock), &prompter).unwrap();
+    let searches = mock.forms("S1");
+    assert_eq!(searches.len(), 2);
+    assert_eq!(
+        searches[0]["toTimeInputField"],
+        searches[1]["toTimeInputField"]
+    );
+    // The train picked before the restart is booked without asking again
+    let trains = mock.forms("S2");
+    assert_eq!(trains.len(), 2);
+    assert!(
+        trains
+            .iter()
+            .all(|form| form["TrainQueryDataViewPanel:TrainGroup"] == "radio22")
+    );
+    assert_eq!(prompter.asked().len(), 3);
+}
+
+#[test]
+fn takes_the_passenger_ids_from_a_file() {
+    let mock = MockSite::start(Scenario::default());
+    let file = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(file.path(), "# the Lins\nA123456789\n\nB223456789\n").unwrap();
+    let mut args = Args {
+        passenger_file: Some(file.path().to_path_buf()),
+        ..args()
+    };
+    passenger::read_ids(&mut args).unwrap();
+    assert_eq!(args.passenger_ids, ["A123456789", "B223456789"]);
+
+    // Only the train is asked
+    let prompter = ScriptedPrompter::new(["2"], [SECURITY_CODE]);
+    book(&client(), &args, &config(&mock), &prompter).unwrap();
+    assert_eq!(prompter.asked().len(), 1);
+    let ticket = &mock.forms("S3")[0];
+    assert_eq!(
+        ticket["TicketPassengerInfoInputPanel:passengerDataView:1:passengerDataView2:passengerDataIdNumber"],
+        "B223456789"
+    );
+}
+
+#[test]
+fn reviews_the_passengers_before_submitting() {
+    let mock = MockSite::start(Scenario::default());
+    let args = Args {
+        review_passengers: true,
+        ..args()
+    };
+    let prompter = ScriptedPrompter::new(["2", "", "B223456789", "n"], [SECURITY_CODE]);
+
+    let outcome = book(&client(), &args, &config(&mock), &prompter);
+    assert!(matches!(outcome, Err(Error::Cancelled)));
+    assert!(mock.forms("S3").is_empty());
+}
+
+#[cfg(unix)]
+#[test]
+fn runs_the_success_command_with_the_booking() {
+    let mock = MockSite::start(Scenario::default());
+    let prompter = ScriptedPrompter::new(["2", "", "B223456789"], [SECURITY_CODE]);
+    let result = book(&client(), &args(), &config(&mock), &prompter).unwrap();
+    assert!(result.train.is_some());
+
+    let dir = tempfile::tempdir().unwrap();
+    let out = dir.path().join("booked.txt");
+    let command = format!(
+        "echo \"$THSR_PNR $THSR_TRAIN $THSR_PRICE\" > {}",
+        out.display()
+    );
+    notify::run_hook(&command, notify::hook_env(&result)).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&out).unwrap().trim(),
+        format!("08123456 {} TWD 2,980", result.train.unwrap())
+    );
+    assert!(notify::run_hook("exit 3", Vec::new()).is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn asks_the_confirm_command_to_approve() {
+    let mock = MockSite::start(Scenario::default());
+    let dir = tempfile::tempdir().unwrap();
+    let out = dir.path().join("summary.txt");
+    let approving = Config {
+        on_confirm: Some(format!(
+            "echo \"$THSR_TRAIN $THSR_FROM\" > {}",
+            out.display()
+        )),
+        ..config(&mock)
+    };
+    let prompter = ScriptedPrompter::new(["2", "", "B223456789"], [SECURITY_CODE]);
+    let result = book(&client(), &args(), &approving, &prompter).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&out).unwrap().trim(),
+        format!("{} Taipei", result.train.unwrap())
+    );
+
+    let mock = MockSite::start(Scenario::default());
+    let refusing = Config {
+        on_confirm: Some("exit 1".to_string()),
+        ..config(&mock)
+    };
+    let prompter = ScriptedPrompter::new(["2"], [SECURITY_CODE]);
+    let outcome = book(&client(), &args(), &refusing, &prompter);
+    assert!(matches!(outcome, Err(Error::Cancelled)));
+    assert!(mock.forms("S3").is_empty());
+}
+
+#[test]
+fn captures_traffic_without_ids() {
+    let mock = MockSite::start(Scenario::default());
+    let prompter = ScriptedPrompter::new(["1", "", "B223456789"], [SECURITY_CODE]);
+    let client = client().capture_har();
+
+    book(&client, &args(), &config(&mock), &prompter).unwrap();
+    let file = tempfile::NamedTempFile::new().unwrap();
+    client.har().unwrap().save(file.path()).unwrap();
+
+    let har: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(file.path()).unwrap()).unwrap();
+    let entries = har["log"]["entries"].as_array().unwrap();
+    let methods: Vec<&str> = entries
+        .iter()
+        .map(|entry| entry["request"]["method"].as_str().unwrap())
+        .collect();
+    assert_eq!(methods, ["GET", "GET", "POST", "POST", "POST"]);
+    assert!(
+        entries
+            .iter()
+            .all(|entry| entry["response"]["status"] == 200)
+    );
+    assert!(
+        entries[2]["request"]["headers"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|header| header["name"] == "content-type")
+    );
+
+    let text = har.to_string();
+    assert!(!text.contains("MOCKSESSION"));
+    assert!(!text.contains("S125544509"));
+    assert!(!text.contains("B223456789"));
+}
+
+#[test]
+fn resumes_the_session_from_the_cookie_jar() {
+    let mock = MockSite::start(Scenario::default());
+    let dir = tempfile::tempdir().unwrap();
+    let jar = dir.path().join("cookies.json");
+
+    let first = client();
+    assert_eq!(
+        fetch_page(&first, &mock.site(), &ScriptedPrompter::default())
+            .unwrap()
+            .jid,
+        "MOCKSESSION"
+    );
+    first.save_cookies(&jar).unwrap();
+
+    let second = client();
+    second.load_cookies(&jar).unwrap();
+    assert_eq!(
+        fetch_page(&second, &mock.site(), &ScriptedPrompter::default())
+            .unwrap()
+            .jid,
+        "MOCKSESSION"
+    );
+    assert_eq!(mock.resumed_sessions(), 1);
+}
+
+#[test]
+fn downloads_the_security_code_with_the_page() {
+    let mock = MockSite::start(Scenario::default());
+    let page = fetch_page(&client(), &mock.site(), &ScriptedPrompter::default()).unwrap();
+    assert_eq!(page.security_code_img.as_deref(), Some(CAPTCHA_IMAGE));
+    assert_eq!(mock.captcha_fetches(), 1);
+
+    // Not downloaded again when the code is asked for
+    let prompter = ScriptedPrompter::new(["2", "", "B223456789"], [SECURITY_CODE]);
+    book(&client(), &args(), &config(&mock), &prompter).unwrap();
+    assert_eq!(mock.captcha_fetches(), 2);
+}
+
+#[test]
+fn paces_the_requests() {
+    let mock = MockSite::start(Scenario::default());
+    let limiter = RateLimiter::new(RateLimit {
+        per_minute: 600,
+        min_delay: Duration::ZERO,
+        jitter: 0.0,
+        ..Default::default()
+    });
+    // Clients sharing the limiter pace their requests together
+    let (first, second) = (
+        client().rate_limit(limiter.clone()),
+        client().rate_limit(limiter),
+    );
+
+    let started = Instant::now();
+    fetch_page(&first, &mock.site(), &ScriptedPrompter::default()).unwrap();
+    fetch_page(&second, &mock.site(), &ScriptedPrompter::default()).unwrap();
+    // Two pages and their security codes, 100ms apart
+    assert!(started.elapsed() >= Duration::from_millis(300));
+}
+
+#[test]
+fn posts_the_tickets_once_when_the_answer_times_out() {
+    let mock = MockSite::start(Scenario {
+        slow_ticket: Some(Duration::from_millis(1500)),
+        ..Default::default()
+    });
+    let client = HttpClient::new(
+        HeaderMap::new(),
+        RetryPolicy {
+            timeout: Duration::from_millis(500),
+            backoff: Duration::from_millis(10),
+            ..Default::default()
+        },
+    );
+    let prompter = ScriptedPrompter::new(["2", "", "B223456789"], [SECURITY_CODE]);
+    let outcome = book(&client, &args(), &config(&mock), &prompter);
+    assert!(matches!(outcome, Err(Error::Network(_))));
+    // The booking may have gone through, so it isn't posted again
+    assert_eq!(mock.forms("S3").len(), 1);
+}
+
+#[test]
+fn resumes_an_interrupted_booking_at_the_ticket_step() {
+    let mock = MockSite::start(Scenario {
+        fail_ticket: true,
+        ..Default::default()
+    });
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("booking.json");
+    let (args, config) = (args(), config(&mock));
+
+    let first = client();
+    let prompter = ScriptedPrompter::new(["2", "", "B223456789"], [SECURITY_CODE]);
+    let outcome = book_with_journal(
+        &first,
+        &args,
+        &config,
+        &prompter,
+        &mut resume::journal(Some(&path), &first, &args),
+    );
+    assert!(matches!(outcome, Err(Error::Network(_))));
+    let state = BookingState::load(&path).unwrap();
+    assert_eq!(state.step, Step::Ticket);
+    assert_eq!(state.train, Some(1505));
+
+    // A new process, with nothing but the state file
+    let second = client();
+    let prompter = ScriptedPrompter::new(["", "B223456789"], Vec::<String>::new());
+    let result = state
+        .resume(
+            &second,
+            &args,
+            &config,
+            &prompter,
+            &mut resume::journal(Some(&path), &second, &args),
+        )
+        .unwrap();
+    assert_eq!(result.pnr, "08123456");
+    assert_eq!(mock.page_fetches(), 1);
+    assert_eq!(mock.forms("S2").len(), 1);
+    assert_eq!(mock.forms("S3").len(), 2);
+    assert!(!path.exists());
+}
+
+#[test]
+fn walks_through_the_stages() {
+    let mock = MockSite::start(Scenario::default());
+    let (client, args, config) = (client(), args(), config(&mock));
+    let prompter = ScriptedPrompter::new(["", "B223456789"], [SECURITY_CODE]);
+
+    let options = args.options(&config);
+    let search = Stage::open(config.backend.backend(), &client, &config.site, &prompter).unwrap();
+    let mut payload = BookingPayload::default();
+    payload.select_options(&options, None, &prompter);
+    let trains = search
+        .submit(&client, &mut payload, &config.site, &prompter, || {})
+        .unwrap();
+    assert_eq!(trains.trains()[0].id(), 803);
+
+    let mut train = Some(609);
+    let tickets = trains
+        .confirm_train(&client, &payload, &mut train, &config.site, &prompter)
+        .unwrap();
+    let booked = tickets
+        .confirm_ticket(&client, &options, &config.site, &prompter)
+        .unwrap();
+    assert_eq!(booked.result().pnr, "08123456");
+    assert_eq!(
+        mock.forms("S2")[0]["TrainQueryDataViewPanel:TrainGroup"],
+        "radio22"
+    );
+}
+
+#[test]
+fn books_a_large_group_as_several_bookings() {
+    let mock = MockSite::start(Scenario::default());
+    let args = Args {
+        adult_cnt: Some(12),
+        student_cnt: Some(3),
+        ..args()
+    };
+    let parts = group::split(&Args {
+        passenger_ids: (0..15).map(|idx| format!("A1234567{:02}", idx)).collect(),
+        ..args.clone()
+    });
+    assert_eq!(parts[0].passenger_ids.len(), 10);
+    assert_eq!(parts[1].passenger_ids[0], "A123456710");
+
+    let parts = group::split(&args);
+    assert_eq!(parts.len(), 2);
+    assert_eq!(
+        (parts[0].adult_cnt, parts[0].student_cnt),
+        (Some(10), Some(0))
+    );
+    assert_eq!(
+        (parts[1].adult_cnt, parts[1].student_cnt),
+        (Some(2), Some(3))
+    );
+
+    // The train is only asked for the first booking, the early bird passengers for both
+    let prompter = ScriptedPrompter::new(
+        ["2", "", "B223456789", "", "B223456789"],
+        [SECURITY_CODE, SECURITY_CODE],
+    );
+    let outcomes = group::book(&client(), &args, &config(&mock), &prompter, &mut |_| {});
+    assert_eq!(outcomes.len(), 2);
+    assert!(outcomes.iter().all(Result::is_ok));
+
+    let searches = mock.forms("S1");
+    assert_eq!(searches[0]["ticketPanel:rows:0:ticketAmount"], "10F");
+    assert_eq!(searches[1]["ticketPanel:rows:0:ticketAmount"], "2F");
+    assert_eq!(searches[1]["ticketPanel:rows:4:ticketAmount"], "3P");
+    for train in mock.forms("S2") {
+        assert_eq!(train["TrainQueryDataViewPanel:TrainGroup"], "radio20");
+    }
+}
+
+#[test]
+fn books_for_passenger_profiles() {
+    let mock = MockSite::start(Scenario {
+        ticket_rows: true,
+        ..Default::default()
+    });
+    let config = Config {
+        passengers: toml::from_str(
+            r#"
+            mom = { id = "A223456789", phone = "0912345678", email = "mom@example.com", membership = false }
+            kid = { id = "B123456789", type = "child" }
+            "#,
+        )
+        .unwrap(),
+        ..config(&mock)
+    };
+    let mut party = Args {
+        passengers: vec!["mom".to_string(), "kid".to_string()],
+        ..args()
+    };
+    passenger::pick(&mut party, &config).unwrap();
+    assert_eq!(party.personal_id.as_deref(), Some("A223456789"));
+
+    // Only the train is asked, the early bird IDs come from the profiles
+    let prompter = ScriptedPrompter::new(["2"], [SECURITY_CODE]);
+    book(&client(), &party, &config, &prompter).unwrap();
+
+    let search = &mock.forms("S1")[0];
+    assert_eq!(search["ticketPanel:rows:0:ticketAmount"], "1F");
+    assert_eq!(search["ticketPanel:rows:1:ticketAmount"], "1H");
+    let tickets = &mock.forms("S3")[0];
+    assert_eq!(tickets["dummyId"], "A223456789");
+    assert_eq!(tickets["dummyPhone"], "0912345678");
+    assert_eq!(tickets["email"], "mom@example.com");
+    assert_eq!(
+        tickets["TicketPassengerInfoInputPanel:passengerDataView:1:passengerDataView2:passengerDataIdNumber"],
+        "B123456789"
+    );
+
+    let mut unknown = Args {
+        passengers: vec!["dad".to_string()],
+        ..args()
+    };
+    assert!(passenger::pick(&mut unknown, &config).is_err());
+}
+
+#[test]
+fn books_with_a_passport() {
+    let mock = MockSite::start(Scenario::default());
+    let cli = Cli::try_parse_from(["thsr", "--passport", "x12345678"]).unwrap();
+    let visitor = Args {
+        passport: cli.args.passport,
+        ..args()
+    };
+
+    // The second train, then the second early bird passenger, a visitor as well
+    let prompter = ScriptedPrompter::new(["2", "", "E98765432"], [SECURITY_CODE]);
+    book(&client(), &visitor, &config(&mock), &prompter).unwrap();
+
+    let tickets = &mock.forms("S3")[0];
+    assert_eq!(tickets["dummyId"], "X12345678");
+    assert_eq!(tickets["idInputRadio"], "1");
+    assert!(!tickets.contains_key(
+        "TicketMemberSystemInputPanel:TakerMemberSystemDataView:memberSystemRadioGroup:memberShipNumber"
+    ));
+    let choice = |passenger: usize| {
+        &tickets[&format!(
+            "TicketPassengerInfoInputPanel:passengerDataView:{passenger}:passengerDataView2:passengerDataInputChoice"
+        )]
+    };
+    assert_eq!(choice(0), "1");
+    assert_eq!(choice(1), "1");
+
+    assert!(Cli::try_parse_from(["thsr", "--passport", "X1-234"]).is_err());
+}
+
+#[test]
+fn checks_the_membership_number_before_searching() {
+    let mock = MockSite::start(Scenario::default());
+    let member = Args {
+        personal_id: Some("A123456788".to_string()),
+        ..args()
+    };
+    let outcome = book(
+        &client(),
+        &member,
+        &config(&mock),
+        &ScriptedPrompter::default(),
+    );
+    assert!(matches!(outcome, Err(Error::InvalidId(_))));
+    assert!(mock.forms("S1").is_empty());
+}
+
+#[test]
+fn validates_the_options_up_front() {
+    assert_eq!(args().validate(), Ok(()));
+
+    let invalid = |extra: &[&str]| {
+        let cli = Cli::try_parse_from([&["thsr"], extra].concat()).unwrap();
+        cli.args.validate().unwrap_err()
+    };
+    let unknown = Cli::try_parse_from(["thsr", "--from", "13"]).unwrap_err();
+    assert!(unknown.to_string().contains("no station 13"));
+    let unknown = Cli::try_parse_from(["thsr", "--time", "0"]).unwrap_err();
+    assert!(unknown.to_string().contains("no time 0"));
+    assert!(invalid(&["--from", "2", "--to", "台北"]).contains("both Taipei"));
+    assert!(invalid(&["--date", "2025/02/30"]).contains("not a date"));
+    assert!(invalid(&["--date", "2025/06/03..2025/06/01"]).contains("end before"));
+    assert!(invalid(&["--adult-cnt", "0", "--student-cnt", "0"]).contains("At least one"));
+    assert!(invalid(&["--personal-id", "A123456788"]).contains("not a valid Taiwan ID"));
+    assert!(invalid(&["--passenger-ids", "A123456789,no way"]).contains("neither"));
+
+    // Visitors' passport numbers have no checksum
+    let visitor = Args {
+        personal_id: Some("A123456788".to_string()),
+        passport: Some("X12345678".to_string()),
+        ..args()
+    };
+    assert_eq!(visitor.validate(), Ok(()));
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn fetches_the_booking_page_asynchronously() {
+    use thsr::booking_flow::parse_reloaded_img_src;
+    use thsr::build_async_client;
+
+    let mock = MockSite::start(Scenario::default());
+    let config = config(&mock);
+    let client = build_async_client(&config);
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+
+    let img_data = runtime.block_on(async {
+        let page = client.get(&config.site.booking_page()).await.unwrap();
+        let src = parse_reloaded_img_src(&page.text()).unwrap();
+        client.get(&config.site.url(&src)).await.unwrap().body
+    });
+    assert_eq!(img_data.as_ref(), CAPTCHA_IMAGE);
+    let session = client.cookie(&config.site.booking_page(), "JSESSIONID");
+    assert!(session.is_some());
+}
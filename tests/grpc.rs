@@ -0,0 +1,82 @@
+#![cfg(feature = "grpc")]
+
+mod mock;
+
+use clap::Parser;
+use tokio::net::TcpListener;
+use tokio::runtime::Runtime;
+
+use thsr::cli::Cli;
+use thsr::config::Config;
+use thsr::grpc::proto::booking_client::BookingClient;
+use thsr::grpc::proto::{AnswerRequest, BookRequest, Step, event};
+use thsr::grpc::{Service, serve};
+
+use mock::{MockSite, SECURITY_CODE, Scenario};
+
+#[test]
+fn books_over_grpc() {
+    let mock = MockSite::start(Scenario::default());
+    let config = Config {
+        site: mock.site(),
+        ..Default::default()
+    };
+    let service = Service::new(Cli::parse_from(["thsr"]).args, config);
+
+    Runtime::new().unwrap().block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, service));
+
+        let mut client = BookingClient::connect(format!("http://{}", addr))
+            .await
+            .unwrap();
+        let request = BookRequest {
+            from: Some(2),
+            to: Some(12),
+            date: Some("2025/06/29".to_string()),
+            time: Some(10),
+            adult_cnt: Some(2),
+            seat_prefer: Some(1),
+            class_type: Some(0),
+            ..Default::default()
+        };
+        let mut events = client.book(request).await.unwrap().into_inner();
+
+        // The security code, the second train, then the IDs of the two early bird passengers
+        let mut answers = [SECURITY_CODE, "2", "", "B223456789"].into_iter();
+        let mut steps = Vec::new();
+        let mut trains = Vec::new();
+        let result = loop {
+            let event = events.message().await.unwrap().expect("Stream ended early");
+            match event.kind.unwrap() {
+                event::Kind::Step(step) => steps.push(Step::try_from(step).unwrap()),
+                event::Kind::Trains(list) => trains = list.trains,
+                event::Kind::Question(_) | event::Kind::Captcha(_) => {
+                    let answer = AnswerRequest {
+                        booking_id: event.booking_id,
+                        answer: answers.next().expect("Asked too often").to_string(),
+                    };
+                    client.answer(answer).await.unwrap();
+                }
+                event::Kind::Result(result) => break result,
+                event::Kind::Error(err_msg) => panic!("Booking failed: {}", err_msg),
+            }
+        };
+        assert_eq!(result.pnr, "08123456");
+        assert_eq!(steps, [Step::Train, Step::Ticket, Step::Booked]);
+        assert_eq!(trains[1].id, 1505);
+        assert!(events.message().await.unwrap().is_none());
+
+        let unknown = AnswerRequest {
+            booking_id: "nope".to_string(),
+            answer: String::new(),
+        };
+        let status = client.answer(unknown).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+    });
+    assert_eq!(
+        mock.forms("S2")[0]["TrainQueryDataViewPanel:TrainGroup"],
+        "radio20"
+    );
+}
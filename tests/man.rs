@@ -0,0 +1,27 @@
+use std::fs;
+
+use thsr::man;
+
+#[test]
+fn manual_pages() {
+    let mut page = Vec::new();
+    man::render(&mut page).unwrap();
+    let page = String::from_utf8(page).unwrap();
+    assert!(page.contains(".SH OPTIONS"));
+    assert!(page.contains(r"\-\-site\-locale"));
+    assert!(page.contains("thsr\\-watch(1)"));
+    assert!(page.contains(".SH \"INTERACTIVE USE\""));
+    assert!(page.contains("THSR_CONFIG_PASSPHRASE"));
+    assert!(page.contains(".SH \"EXIT STATUS\""));
+
+    let dir = tempfile::tempdir().unwrap();
+    man::generate_to(dir.path()).unwrap();
+    let mut pages: Vec<String> = fs::read_dir(dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    pages.sort();
+    assert!(pages.contains(&"thsr.1".to_string()));
+    assert!(pages.contains(&"thsr-resume.1".to_string()));
+    assert!(!pages.contains(&"thsr-help.1".to_string()));
+}
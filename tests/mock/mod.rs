@@ -0,0 +1,259 @@
+//! A stand-in for the booking site, serving the fixture pages through the three
+//! Wicket form steps so the flows can run end to end without booking anything.
+
+// Each test file uses its own part of the helpers
+#![allow(dead_code)]
+
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use thsr::site::Site;
+
+pub const SECURITY_CODE: &str = "ABCD";
+const SESSION: &str = "MOCKSESSION";
+
+const BOOKING_PAGE: &str = include_str!("../fixtures/booking_page.html");
+const TRAIN_LIST: &str = include_str!("../fixtures/train_list.html");
+const CONFIRM_TICKET: &str = include_str!("../fixtures/confirm_ticket_early_bird.html");
+const RESULT: &str = include_str!("../fixtures/result.html");
+const ERROR_SECURITY_CODE: &str = include_str!("../fixtures/error_security_code.html");
+const ERROR_SOLD_OUT: &str = include_str!("../fixtures/error_sold_out.html");
+const SESSION_EXPIRED: &str = include_str!("../fixtures/session_expired.html");
+const MAINTENANCE: &str = include_str!("../fixtures/maintenance.html");
+pub const CAPTCHA_IMAGE: &[u8] = &[0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10, 0xFF, 0xD9];
+
+/// How the mock site should misbehave.
+#[derive(Default, Clone)]
+pub struct Scenario {
+    /// Answer the search with "sold out"
+    pub sold_out: bool,
+    /// Answer this many searches with "sold out" before listing the trains
+    pub sold_out_searches: usize,
+    /// Let the session expire the first time a train is confirmed
+    pub expire_on_train: bool,
+    /// Drop the first confirmation of the tickets
+    pub fail_ticket: bool,
+    /// Answer the first confirmation of the tickets only after this long, e.g. after
+    /// the client gave up waiting
+    pub slow_ticket: Option<Duration>,
+    /// Show the maintenance notice in place of the booking page
+    pub maintenance: bool,
+    /// Turn every request away with 429 Too Many Requests
+    pub throttled: bool,
+    /// List a passenger row on the ticket form for every ticket searched, of its type,
+    /// in place of the two early bird adults
+    pub ticket_rows: bool,
+}
+
+#[derive(Default)]
+struct State {
+    scenario: Scenario,
+    page_fetches: usize,
+    resumed_sessions: usize,
+    captcha_fetches: usize,
+    forms: Vec<(String, HashMap<String, String>)>,
+}
+
+pub struct MockSite {
+    server: Arc<Server>,
+    state: Arc<Mutex<State>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl MockSite {
+    pub fn start(scenario: Scenario) -> MockSite {
+        let server = Arc::new(Server::http("127.0.0.1:0").unwrap());
+        let state = Arc::new(Mutex::new(State {
+            scenario,
+            ..Default::default()
+        }));
+
+        let handle = {
+            let server = Arc::clone(&server);
+            let state = Arc::clone(&state);
+            thread::spawn(move || {
+                for request in server.incoming_requests() {
+                    handle(request, &state);
+                }
+            })
+        };
+        MockSite {
+            server,
+            state,
+            handle: Some(handle),
+        }
+    }
+
+    pub fn site(&self) -> Site {
+        Site::new(format!("http://{}", self.server.server_addr()))
+    }
+
+    /// How often a new session was opened on the booking page.
+    pub fn page_fetches(&self) -> usize {
+        self.state.lock().unwrap().page_fetches
+    }
+
+    /// How often the security code image was downloaded.
+    pub fn captcha_fetches(&self) -> usize {
+        self.state.lock().unwrap().captcha_fetches
+    }
+
+    /// How often the booking page was opened within the existing session.
+    pub fn resumed_sessions(&self) -> usize {
+        self.state.lock().unwrap().resumed_sessions
+    }
+
+    /// The posted forms, by step: `S1` search, `S2` train, `S3` ticket.
+    pub fn forms(&self, step: &str) -> Vec<HashMap<String, String>> {
+        self.state
+            .lock()
+            .unwrap()
+            .forms
+            .iter()
+            .filter(|(posted, _)| posted == step)
+            .map(|(_, form)| form.clone())
+            .collect()
+    }
+}
+
+impl Drop for MockSite {
+    fn drop(&mut self) {
+        self.server.unblock();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn handle(mut request: Request, state: &Mutex<State>) {
+    let url = request.url().to_string();
+    let mut body = String::new();
+    let _ = request.as_reader().read_to_string(&mut body);
+    let form: HashMap<String, String> = serde_urlencoded::from_str(&body).unwrap_or_default();
+
+    let session_cookie = format!("JSESSIONID={}", SESSION);
+    let has_session = request.headers().iter().any(|header| {
+        header.field.equiv("Cookie") && header.value.as_str().contains(&session_cookie)
+    });
+
+    let mut state = state.lock().unwrap();
+    let mut delay = None;
+    let response = match (request.method(), url.as_str()) {
+        _ if state.scenario.throttled => {
+            state.page_fetches += 1;
+            Response::from_string("Too Many Requests")
+                .with_status_code(429)
+                .with_header(header("Retry-After", "120"))
+        }
+        (Method::Get, "/IMINT/?locale=tw") if state.scenario.maintenance => {
+            state.page_fetches += 1;
+            html(MAINTENANCE)
+        }
+        // Like Wicket, leave the session ID out of the addresses once the cookie works
+        (Method::Get, "/IMINT/?locale=tw") if has_session => {
+            state.page_fetches += 1;
+            state.resumed_sessions += 1;
+            html(&BOOKING_PAGE.replace(";jsessionid=0123456789ABCDEF", ""))
+        }
+        (Method::Get, "/IMINT/?locale=tw") => {
+            state.page_fetches += 1;
+            html(&BOOKING_PAGE.replace("0123456789ABCDEF", SESSION)).with_header(header(
+                "Set-Cookie",
+                &format!("{}; Path=/IMINT", session_cookie),
+            ))
+        }
+        (Method::Get, url) if url.contains("homeCaptcha:passCode") => {
+            state.captcha_fetches += 1;
+            Response::from_data(CAPTCHA_IMAGE).with_header(header("Content-Type", "image/jpeg"))
+        }
+        (Method::Post, url) if url.contains("BookingS1Form::IFormSubmitListener") => {
+            state.forms.push(("S1".to_string(), form.clone()));
+            if !url.contains(SESSION) {
+                html(SESSION_EXPIRED)
+            } else if form.get("homeCaptcha:securityCode").map(String::as_str)
+                != Some(SECURITY_CODE)
+            {
+                html(ERROR_SECURITY_CODE)
+            } else if state.scenario.sold_out {
+                html(ERROR_SOLD_OUT)
+            } else if state.scenario.sold_out_searches > 0 {
+                state.scenario.sold_out_searches -= 1;
+                html(ERROR_SOLD_OUT)
+            } else {
+                html(TRAIN_LIST)
+            }
+        }
+        (Method::Post, url) if url.contains("BookingS2Form::IFormSubmitListener") => {
+            state.forms.push(("S2".to_string(), form));
+            if state.scenario.expire_on_train {
+                state.scenario.expire_on_train = false;
+                html(SESSION_EXPIRED)
+            } else if state.scenario.ticket_rows {
+                html(&ticket_rows(&state.forms))
+            } else {
+                html(CONFIRM_TICKET)
+            }
+        }
+        (Method::Post, url) if url.contains("BookingS3Form::IFormSubmitListener") => {
+            state.forms.push(("S3".to_string(), form));
+            delay = state.scenario.slow_ticket.take();
+            if state.scenario.fail_ticket {
+                state.scenario.fail_ticket = false;
+                Response::from_string("Not found").with_status_code(404)
+            } else {
+                html(RESULT)
+            }
+        }
+        _ => Response::from_string("Not found").with_status_code(404),
+    };
+    drop(state);
+    if let Some(delay) = delay {
+        thread::sleep(delay);
+    }
+    let _ = request.respond(response);
+}
+
+/// The ticket form with a passenger row for every ticket of the last search, e.g. two
+/// rows of `F` for `2F`.
+fn ticket_rows(forms: &[(String, HashMap<String, String>)]) -> String {
+    let Some((_, search)) = forms.iter().rfind(|(step, _)| step == "S1") else {
+        return CONFIRM_TICKET.to_string();
+    };
+    let types = (0..)
+        .map_while(|row| search.get(&format!("ticketPanel:rows:{}:ticketAmount", row)))
+        .flat_map(|amount| {
+            let (count, code) = amount.split_at(amount.len() - 1);
+            std::iter::repeat_n(code, count.parse().unwrap_or(0))
+        });
+    let rows: String = types
+        .enumerate()
+        .map(|(row, code)| {
+            format!(
+                "    <input type=\"hidden\" name=\"TicketPassengerInfoInputPanel:passengerDataView:{}:passengerDataView2:passengerDataTypeName\" value=\"{}\">\n",
+                row, code
+            )
+        })
+        .collect();
+    let start = CONFIRM_TICKET
+        .find("  <div class=\"passenger\">\n")
+        .unwrap();
+    let end = start + CONFIRM_TICKET[start..].find("  </div>").unwrap();
+    format!(
+        "{}  <div class=\"passenger\">\n{}{}",
+        &CONFIRM_TICKET[..start],
+        rows,
+        &CONFIRM_TICKET[end..]
+    )
+}
+
+fn html(page: &str) -> Response<std::io::Cursor<Vec<u8>>> {
+    Response::from_string(page).with_header(header("Content-Type", "text/html; charset=utf-8"))
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name, value).unwrap()
+}
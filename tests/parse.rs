@@ -0,0 +1,949 @@
+#![cfg(feature = "blocking")]
+
+use chrono::{Duration, NaiveDate, NaiveTime};
+use clap::Parser;
+use scraper::Html;
+use secrecy::{ExposeSecret, SecretString};
+
+use std::collections::HashMap;
+use std::time::Duration as StdDuration;
+
+use thsr::alert::{Alert, AlertKind, Severity};
+use thsr::booking_flow::{
+    BookingPayload, parse_avail_start_end_date, parse_reloaded_img_src, parse_search_by,
+    parse_security_code_img_url, parse_security_code_reload_url, parse_session_id,
+    parse_types_of_trip_value,
+};
+use thsr::cli::Cli;
+use thsr::confirm_ticket_flow::{
+    parse_total_price, passenger_lines, process_membership, process_passenger_info,
+};
+use thsr::confirm_train_flow::{ConfirmTrainPayload, Seats, parse_alerts, parse_trains};
+use thsr::error::Error;
+use thsr::events::Event;
+use thsr::exit::Status;
+use thsr::export;
+use thsr::fare::{self, Discounts};
+use thsr::http::RateLimit;
+use thsr::i18n::Lang;
+use thsr::ntp;
+use thsr::passenger::{self, Passenger, PassengerType};
+use thsr::pick::{self, TrainPick};
+use thsr::prompt::ScriptedPrompter;
+use thsr::result::{
+    SeatAssignment, parse_page_date, parse_page_date_on, parse_result, parse_seats,
+};
+use thsr::schema::{Station, TIME_SLOTS, TicketType, TimeSlot};
+use thsr::select::Selection;
+use thsr::selectors;
+use thsr::site::Site;
+use thsr::tgo;
+use thsr::time::{describe_span, taipei_now};
+use thsr::{is_session_expired, maintenance_end, parse_error, parse_maintenance, watch};
+
+fn fixture(html: &str) -> Html {
+    Html::parse_document(html)
+}
+
+const BOOKING_PAGE: &str = include_str!("fixtures/booking_page.html");
+const CAPTCHA_RELOAD: &str = include_str!("fixtures/captcha_reload.xml");
+const TRAIN_LIST: &str = include_str!("fixtures/train_list.html");
+const TRAIN_LIST_NO_DISCOUNT: &str = include_str!("fixtures/train_list_no_discount.html");
+const CONFIRM_TICKET_EARLY_BIRD: &str = include_str!("fixtures/confirm_ticket_early_bird.html");
+const CONFIRM_TICKET_MIXED: &str = include_str!("fixtures/confirm_ticket_mixed.html");
+const RESULT: &str = include_str!("fixtures/result.html");
+const ERROR_SECURITY_CODE: &str = include_str!("fixtures/error_security_code.html");
+const ERROR_SOLD_OUT: &str = include_str!("fixtures/error_sold_out.html");
+const SESSION_EXPIRED: &str = include_str!("fixtures/session_expired.html");
+const MAINTENANCE: &str = include_str!("fixtures/maintenance.html");
+
+#[test]
+fn compiles_the_selectors() {
+    selectors::check();
+}
+
+#[test]
+fn booking_page() {
+    let page = fixture(BOOKING_PAGE);
+
+    assert_eq!(
+        parse_avail_start_end_date(&page),
+        ("2025/06/01".to_string(), "2025/06/29".to_string())
+    );
+    assert_eq!(parse_types_of_trip_value(&page), 0);
+    assert_eq!(parse_search_by(&page), "radio31");
+    assert_eq!(parse_session_id(&page).as_deref(), Some("0123456789ABCDEF"));
+    assert_eq!(
+        parse_security_code_img_url(&page, &Site::default()),
+        "https://irs.thsrc.com.tw/IMINT/?wicket:interface=:0:BookingS1Form:homeCaptcha:passCode::IResourceListener&wicket:antiCache=1748736000000"
+    );
+    assert_eq!(
+        parse_security_code_reload_url(&page, &Site::default()).as_deref(),
+        Some(
+            "https://irs.thsrc.com.tw/IMINT/?wicket:interface=:0:BookingS1Form:homeCaptcha:reCodeLink::IBehaviorListener&wicket:behaviorId=0&random="
+        )
+    );
+}
+
+#[test]
+fn booking_page_is_not_an_error() {
+    let page = fixture(BOOKING_PAGE);
+    assert_eq!(parse_error(&page), None);
+}
+
+#[test]
+fn reloaded_captcha() {
+    assert_eq!(
+        parse_reloaded_img_src(CAPTCHA_RELOAD).as_deref(),
+        Some(
+            "/IMINT/?wicket:interface=:0:BookingS1Form:homeCaptcha:passCode::IResourceListener&wicket:antiCache=1748736012345"
+        )
+    );
+    assert_eq!(
+        parse_reloaded_img_src("<ajax-response></ajax-response>"),
+        None
+    );
+}
+
+#[test]
+fn train_list() {
+    let page = fixture(TRAIN_LIST);
+
+    assert_eq!(
+        parse_alerts(&page),
+        vec![
+            Alert::new("本查詢結果僅顯示尚有座位之車次。"),
+            Alert::new("早鳥優惠座位有限，售完為止。"),
+        ]
+    );
+
+    let trains = parse_trains(&page);
+    let summary: Vec<_> = trains
+        .iter()
+        .map(|train| {
+            (
+                train.id(),
+                train.depart().format("%H:%M").to_string(),
+                train.arrive().format("%H:%M").to_string(),
+                train.travel_minutes(),
+                train.discount_info(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (
+                803,
+                "06:30".to_string(),
+                "08:15".to_string(),
+                105,
+                "(早鳥65折)"
+            ),
+            (
+                1505,
+                "07:00".to_string(),
+                "09:20".to_string(),
+                140,
+                "(早鳥9折, 大學生5折)"
+            ),
+            (609, "07:15".to_string(), "09:00".to_string(), 105, ""),
+        ]
+    );
+    assert_eq!(trains[1].travel_time(), "2:20");
+    assert_eq!(
+        serde_json::to_value(&trains[0]).unwrap(),
+        serde_json::json!({
+            "id": 803,
+            "depart": "06:30",
+            "arrive": "08:15",
+            "travel_minutes": 105,
+            "discount_info": "(早鳥65折)",
+            "seats": "available"
+        })
+    );
+    let seats: Vec<Seats> = trains.iter().map(|train| train.seats()).collect();
+    assert_eq!(
+        seats,
+        [Seats::Available, Seats::Limited, Seats::StandingOnly]
+    );
+    assert_eq!(parse_error(&page), None);
+    assert!(!is_session_expired(&page));
+}
+
+#[test]
+fn exports_the_trains() {
+    let trains = parse_trains(&fixture(TRAIN_LIST));
+    let csv = export::csv(&trains);
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(
+        lines[0],
+        "id,depart,arrive,travel_minutes,discount_info,seats"
+    );
+    assert_eq!(
+        lines[2],
+        "1505,07:00,09:20,140,\"(早鳥9折, 大學生5折)\",limited"
+    );
+    assert_eq!(lines.len(), 4);
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("trains.json");
+    export::write_trains(&path, &trains).unwrap();
+    let json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(json[2]["travel_minutes"], 105);
+}
+
+#[test]
+fn train_list_without_discounts() {
+    let page = fixture(TRAIN_LIST_NO_DISCOUNT);
+
+    let trains = parse_trains(&page);
+    assert_eq!(
+        trains.iter().map(|train| train.id()).collect::<Vec<_>>(),
+        vec![123, 661]
+    );
+    assert!(trains.iter().all(|train| train.discount_info().is_empty()));
+}
+
+#[test]
+fn membership() {
+    let page = fixture(CONFIRM_TICKET_EARLY_BIRD);
+
+    let (radio, extra) = process_membership(&page, "A123456789", &Some(true));
+    assert_eq!(radio, "radio56");
+    let extra = extra.unwrap();
+    // Kept out of logs
+    assert!(!format!("{:?}", extra).contains("A123456789"));
+    let extra = extra.expose_secret();
+    assert!(extra.contains("memberShipNumber=A123456789"));
+    assert!(extra.contains("memberSystemShipCheckBox=on"));
+
+    let (radio, extra) = process_membership(&page, "A123456789", &Some(false));
+    assert_eq!(radio, "radio60");
+    assert!(extra.is_none());
+}
+
+#[test]
+fn early_bird_passengers() {
+    let page = fixture(CONFIRM_TICKET_EARLY_BIRD);
+    // Keep the default ID for the first passenger, enter one for the second
+    let prompter = ScriptedPrompter::new(["", "B223456789"], Vec::<String>::new());
+
+    let fields = process_passenger_info(&page, "A123456789", &[], &[], &prompter).unwrap();
+    let field = |passenger: usize, name: &str| {
+        fields[&format!(
+            "TicketPassengerInfoInputPanel:passengerDataView:{passenger}:passengerDataView2:{name}"
+        )]
+            .expose_secret()
+            .to_string()
+    };
+    assert_eq!(field(0, "passengerDataIdNumber"), "A123456789");
+    assert_eq!(field(1, "passengerDataIdNumber"), "B223456789");
+    assert_eq!(field(0, "passengerDataTypeName"), "F");
+    assert_eq!(field(1, "passengerDataTypeName"), "F");
+    assert_eq!(prompter.asked().len(), 2);
+}
+
+/// The ID number filled in for the passenger of `row`.
+fn field_of(fields: &HashMap<String, SecretString>, row: usize) -> String {
+    fields[&format!(
+        "TicketPassengerInfoInputPanel:passengerDataView:{row}:passengerDataView2:passengerDataIdNumber"
+    )]
+    .expose_secret()
+    .to_string()
+}
+
+#[test]
+fn passengers_of_any_ticket_type() {
+    let page = fixture(CONFIRM_TICKET_MIXED);
+    let passenger = |id: &str, kind| Passenger {
+        id: id.into(),
+        kind,
+        last_name: None,
+        first_name: None,
+        phone: None,
+        email: None,
+        membership: None,
+    };
+    // The elder is listed before the disabled passenger, the form has them the other way
+    let party = [
+        passenger("A123456789", PassengerType::Adult),
+        Passenger {
+            last_name: Some("林".to_string()),
+            first_name: Some("美玲".to_string()),
+            ..passenger("E123456789", PassengerType::Elder)
+        },
+        passenger("W123456789", PassengerType::Disabled),
+    ];
+    let prompter = ScriptedPrompter::default();
+
+    let fields = process_passenger_info(&page, "A123456789", &party, &[], &prompter).unwrap();
+    let field = |passenger: usize, name: &str| {
+        fields[&format!(
+            "TicketPassengerInfoInputPanel:passengerDataView:{passenger}:passengerDataView2:{name}"
+        )]
+            .expose_secret()
+            .to_string()
+    };
+    assert_eq!(field(1, "passengerDataTypeName"), "W");
+    assert_eq!(field(1, "passengerDataIdNumber"), "W123456789");
+    assert_eq!(field(2, "passengerDataTypeName"), "E");
+    assert_eq!(field(2, "passengerDataIdNumber"), "E123456789");
+    assert_eq!(field(2, "passengerDataLastName"), "林");
+    assert_eq!(field(2, "passengerDataFirstName"), "美玲");
+    assert_eq!(field(1, "passengerDataLastName"), "");
+    assert!(prompter.asked().is_empty());
+    assert_eq!(
+        passenger_lines(&page, &fields),
+        [
+            "1. adults: (no name), A1******89",
+            "2. disabled: (no name), W1******89",
+            "3. elders: 林 美玲, E1******89",
+        ]
+    );
+
+    // Without profiles every ID is asked for
+    let prompter = ScriptedPrompter::new(["", "B223456789", "C123456789"], Vec::<String>::new());
+    let fields = process_passenger_info(&page, "A123456789", &[], &[], &prompter).unwrap();
+    assert_eq!(fields.len(), 15);
+    assert_eq!(prompter.asked().len(), 3);
+
+    // The IDs given take the place of the first ones asked
+    let ids = ["A123456789".to_string(), "B223456789".to_string()];
+    let prompter = ScriptedPrompter::new(["C123456789"], Vec::<String>::new());
+    let fields = process_passenger_info(&page, "A123456789", &[], &ids, &prompter).unwrap();
+    assert_eq!(field_of(&fields, 1), "B223456789");
+    assert_eq!(field_of(&fields, 2), "C123456789");
+    assert_eq!(prompter.asked().len(), 1);
+
+    // A profile only fills in a row of its ticket type, the others are asked for
+    let party = [passenger("W123456789", PassengerType::Disabled)];
+    let prompter = ScriptedPrompter::new(["A123456789", "E123456789"], Vec::<String>::new());
+    let fields = process_passenger_info(&page, "A123456789", &party, &[], &prompter).unwrap();
+    assert_eq!(field_of(&fields, 0), "A123456789");
+    assert_eq!(field_of(&fields, 1), "W123456789");
+    assert_eq!(field_of(&fields, 2), "E123456789");
+    assert_eq!(prompter.asked().len(), 2);
+
+    // Rows not numbered from 0 on are all listed
+    let page =
+        fixture(&CONFIRM_TICKET_MIXED.replace("passengerDataView:0:", "passengerDataView:5:"));
+    let fields = process_passenger_info(&page, "A123456789", &party, &ids, &prompter).unwrap();
+    assert_eq!(
+        passenger_lines(&page, &fields),
+        [
+            "1. adults: (no name), A1******89",
+            "2. disabled: (no name), W1******89",
+            "3. elders: (no name), B2******89",
+        ]
+    );
+}
+
+#[test]
+fn ticket_form_price() {
+    assert_eq!(
+        parse_total_price(&fixture(CONFIRM_TICKET_EARLY_BIRD)).as_deref(),
+        Some("TWD 2,980")
+    );
+    assert_eq!(parse_total_price(&fixture(TRAIN_LIST)), None);
+}
+
+#[test]
+fn no_early_bird_without_discount() {
+    let page = fixture(RESULT);
+    let prompter = ScriptedPrompter::default();
+
+    assert!(process_passenger_info(&page, "A123456789", &[], &[], &prompter).is_none());
+    assert!(prompter.asked().is_empty());
+}
+
+#[test]
+fn booking_result() {
+    let result = parse_result(&fixture(RESULT));
+
+    assert_eq!(result.pnr, "08123456");
+    assert_eq!(result.price, "TWD 2,980");
+    assert_eq!(result.payment_deadline, "2025/06/03 23:59");
+    assert_eq!(result.date, "2025/06/29");
+    assert_eq!(result.depart_time, "06:30");
+    assert_eq!(result.arrive_time, "08:15");
+    assert_eq!(result.from, "台北");
+    assert_eq!(result.to, "左營");
+    assert_eq!(result.class, "標準車廂");
+    assert_eq!(result.passenger_count, "全票 2 張");
+    assert_eq!(
+        result.seats,
+        [
+            SeatAssignment::parse("7車12A", Some("全票".to_string())),
+            SeatAssignment::parse("7車12B", Some("全票".to_string())),
+        ]
+    );
+    assert_eq!(result.seats[0].car, Some(7));
+    assert_eq!(result.seats[0].seat, "12A");
+    assert_eq!(result.seat_list(), "Car 7 12A, Car 7 12B");
+
+    let due = result.payment_due().unwrap();
+    assert_eq!(due.to_rfc3339(), "2025-06-03T23:59:00+08:00");
+    let left = result
+        .time_to_pay(due - Duration::minutes(3 * 24 * 60 + 185))
+        .unwrap();
+    assert_eq!(describe_span(left), "3 days 3 hours");
+    assert_eq!(describe_span(Duration::minutes(200)), "3 hours 20 minutes");
+    assert_eq!(describe_span(Duration::minutes(45)), "45 minutes");
+
+    let ics = result.to_ics().unwrap();
+    assert!(ics.contains("DTSTART:20250628T223000Z"));
+    assert!(ics.contains("DTEND:20250629T001500Z"));
+}
+
+#[test]
+fn seats_by_ticket_type() {
+    let page = fixture(
+        r#"<div class="uk-accordion">
+          <div class="uk-accordion-content"><span>全票 1 張</span></div>
+          <div class="seat-label"><span>7車12A</span></div>
+          <div class="uk-accordion-content"><span>敬老票 2 張</span></div>
+          <div class="seat-label"><span>7車12B</span><span>8車1C</span></div>
+        </div>"#,
+    );
+    let seats = parse_seats(&page);
+    let summary: Vec<_> = (seats.iter())
+        .map(|seat| (seat.car, seat.seat.as_str(), seat.ticket_type.as_deref()))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            (Some(7), "12A", Some("全票")),
+            (Some(7), "12B", Some("敬老票")),
+            (Some(8), "1C", Some("敬老票")),
+        ]
+    );
+
+    assert_eq!(SeatAssignment::parse("Car 11 3D", None).car, Some(11));
+    // Seats of bookings saved as labels
+    let saved: SeatAssignment = serde_json::from_str(r#""7車12A""#).unwrap();
+    assert_eq!(saved, SeatAssignment::parse("7車12A", None));
+}
+
+#[test]
+fn page_dates() {
+    assert_eq!(
+        parse_page_date("2025/06/01 (日)"),
+        NaiveDate::from_ymd_opt(2025, 6, 1)
+    );
+    assert_eq!(parse_page_date("no date"), None);
+
+    // Without a year, the next such date from today, across the end of the year
+    let today = NaiveDate::from_ymd_opt(2025, 12, 20).unwrap();
+    assert_eq!(
+        parse_page_date_on("01/05 (一)", today),
+        NaiveDate::from_ymd_opt(2026, 1, 5)
+    );
+    assert_eq!(
+        parse_page_date_on("12/20", today),
+        NaiveDate::from_ymd_opt(2025, 12, 20)
+    );
+    assert_eq!(
+        parse_page_date_on("12/31", today),
+        NaiveDate::from_ymd_opt(2025, 12, 31)
+    );
+    assert_eq!(
+        parse_page_date_on("2025/01/05", today),
+        NaiveDate::from_ymd_opt(2025, 1, 5)
+    );
+}
+
+#[test]
+fn wrong_security_code() {
+    let page = fixture(ERROR_SECURITY_CODE);
+
+    assert!(matches!(
+        parse_error(&page),
+        Some(Error::WrongSecurityCode(message)) if message.starts_with("檢測碼輸入錯誤")
+    ));
+}
+
+#[test]
+fn sold_out() {
+    let page = fixture(ERROR_SOLD_OUT);
+
+    assert!(matches!(parse_error(&page), Some(Error::SoldOut(_))));
+}
+
+#[test]
+fn english_messages() {
+    let feedback = |message: &str| {
+        fixture(&format!(
+            r#"<ul><li><span class="feedbackPanelERROR">{}</span></li></ul>"#,
+            message
+        ))
+    };
+
+    assert!(matches!(
+        parse_error(&feedback("Incorrect security code, please try again.")),
+        Some(Error::WrongSecurityCode(_))
+    ));
+    assert!(matches!(
+        parse_error(&feedback("Tickets for the selected train are Sold Out.")),
+        Some(Error::SoldOut(_))
+    ));
+    assert!(is_session_expired(&fixture(
+        "<p>Your session has expired, please start again.</p>"
+    )));
+}
+
+#[test]
+fn session_expired() {
+    let page = fixture(SESSION_EXPIRED);
+
+    assert_eq!(parse_error(&page), None);
+    assert!(is_session_expired(&page));
+}
+
+#[test]
+fn maintenance_notice() {
+    assert_eq!(
+        parse_maintenance(&fixture(MAINTENANCE)),
+        Some(Error::Maintenance(
+            "The booking site is under maintenance until 06:00".to_string()
+        ))
+    );
+    // Pages with a form may tell about a maintenance to come
+    assert_eq!(parse_maintenance(&fixture(BOOKING_PAGE)), None);
+    assert_eq!(parse_maintenance(&fixture(SESSION_EXPIRED)), None);
+
+    let end = |text: &str| maintenance_end(text).map(|end| end.format("%H:%M").to_string());
+    assert_eq!(end("維護時間 23:30 至 05:30").as_deref(), Some("05:30"));
+    assert_eq!(
+        end("Down for maintenance until 6:00").as_deref(),
+        Some("06:00")
+    );
+    assert_eq!(end("系統維護中"), None);
+}
+
+#[test]
+fn cools_down_until_the_maintenance_ends() {
+    let limit = RateLimit::default();
+    let err = Error::Maintenance("The booking site is under maintenance until 06:00".to_string());
+    let at = |time: &str| {
+        watch::cool_down(
+            &err,
+            &limit,
+            NaiveTime::parse_from_str(time, "%H:%M:%S").unwrap(),
+        )
+    };
+    // A minute past the end on top
+    assert_eq!(at("05:00:00"), StdDuration::from_secs(3660));
+    assert_eq!(at("23:00:00"), StdDuration::from_secs(7 * 3600 + 60));
+    // Still up just after the end, or about to end
+    assert_eq!(at("06:00:10"), limit.cool_down);
+    assert_eq!(at("05:58:00"), limit.cool_down);
+    assert_eq!(
+        watch::cool_down(
+            &Error::Throttled(Some(StdDuration::from_secs(120))),
+            &limit,
+            NaiveTime::MIN
+        ),
+        StdDuration::from_secs(120)
+    );
+}
+
+#[test]
+fn tgo_membership() {
+    assert!(tgo::is_membership_number("A123456789"));
+    assert!(tgo::is_membership_number("S125544509"));
+    assert!(!tgo::is_membership_number("A123456788"));
+    assert!(!tgo::is_membership_number("X12345678"));
+    assert!(passenger::is_valid_national_id("a123456789"));
+    assert!(!passenger::is_valid_national_id("A12345678Z"));
+    assert_eq!(tgo::points("TWD 2,980"), Some(149));
+    assert_eq!(tgo::points("TWD"), None);
+}
+
+#[test]
+fn estimates_the_fares() {
+    assert_eq!(fare::adult_fare(Station::Taipei, Station::Zuoying), 1490);
+    assert_eq!(fare::adult_fare(Station::Taipei, Station::Nangang), 40);
+    let discounts = Discounts::parse("(早鳥65折, 大學生5折)");
+    assert_eq!(
+        discounts,
+        Discounts {
+            early_bird: Some(65),
+            student: Some(50)
+        }
+    );
+    assert_eq!(
+        Discounts::parse("(Early Bird 10% off)").early_bird,
+        Some(90)
+    );
+    assert_eq!(Discounts::parse(""), Discounts::default());
+    let fare = |ticket_type| fare::ticket_fare(1330, &ticket_type, &discounts);
+    assert_eq!(fare(TicketType::Adult), (865, Some(65)));
+    assert_eq!(fare(TicketType::College), (665, Some(50)));
+    assert_eq!(fare(TicketType::Elder), (665, None));
+    assert_eq!(fare::ntd(1490), "NT$1,490");
+
+    // One adult from Taipei to Zuoying, in the standard car
+    let trains = parse_trains(&fixture(TRAIN_LIST));
+    let mut search = BookingPayload::default();
+    let estimate = fare::estimate(&search, &trains[0]).unwrap();
+    assert_eq!(estimate, "adults NT$1,490 → NT$970 (35% off)");
+    assert!(
+        fare::estimate(&search, &trains[2])
+            .unwrap()
+            .ends_with(" NT$1,490")
+    );
+    search.class_type = 1;
+    assert_eq!(fare::estimate(&search, &trains[0]), None);
+}
+
+#[test]
+fn asks_again_for_an_invalid_train() {
+    let trains = parse_trains(&fixture(TRAIN_LIST_NO_DISCOUNT));
+    let prompter = ScriptedPrompter::new(["3", "abc", "2"], Vec::<String>::new());
+
+    let mut payload = ConfirmTrainPayload::default();
+    let search = BookingPayload::default();
+    let train = payload.select_available_trains(&trains, None, &search, &prompter);
+    assert_eq!(train, Ok(661));
+    assert_eq!(prompter.asked().len(), 3);
+}
+
+#[test]
+fn picks_stations_by_name() {
+    // By Chinese name, by part of the English one after an ambiguous try, and by number
+    let prompter = ScriptedPrompter::new(["台中", "ta", "tain", "3"], Vec::<String>::new());
+    let mut payload = BookingPayload::default();
+    payload.select_start_station(&None, &None, &prompter);
+    assert_eq!(payload.start_station, Station::Taichung);
+    payload.select_dest_station(&None, &prompter);
+    assert_eq!(payload.dest_station, Station::Tainan);
+    payload.select_start_station(&None, &None, &prompter);
+    assert_eq!(payload.start_station, Station::Banqiao);
+}
+
+#[test]
+fn asks_again_for_the_same_station() {
+    // Taichung for both ends, then Tainan
+    let prompter = ScriptedPrompter::new(["7", "台中", "11"], Vec::<String>::new());
+    let mut payload = BookingPayload::default();
+    payload.select_start_station(&None, &None, &prompter);
+    payload.select_dest_station(&None, &prompter);
+    assert_eq!(
+        (payload.start_station, payload.dest_station),
+        (Station::Taichung, Station::Tainan)
+    );
+    assert_eq!(prompter.asked().len(), 3);
+
+    // Taipei given as the arrival: the departure defaults to the next station instead
+    let prompter = ScriptedPrompter::new(["2", ""], Vec::<String>::new());
+    payload.select_start_station(&None, &Some(Station::Taipei), &prompter);
+    assert_eq!(payload.start_station, Station::Banqiao);
+}
+
+#[test]
+fn language_of_the_locale() {
+    assert_eq!(Lang::from_locale("zh_TW.UTF-8"), Lang::Zh);
+    assert_eq!(Lang::from_locale("zh"), Lang::Zh);
+    assert_eq!(Lang::from_locale("en_US.UTF-8"), Lang::En);
+    assert_eq!(Lang::from_locale("C"), Lang::En);
+}
+
+#[test]
+fn exit_status_of_errors() {
+    let status = |message: &str| Status::of(&Error::from_feedback(message.to_string())).code();
+    assert_eq!(status("檢測碼輸入錯誤"), 3);
+    assert_eq!(status("您所選擇的車次已售完"), 4);
+    assert_eq!(status("身分證字號錯誤"), 2);
+    assert_eq!(status("Something else"), 1);
+    assert_eq!(
+        Status::of(&Error::Network("timed out".to_string())).code(),
+        5
+    );
+    assert_eq!(Status::of(&Error::SessionExpired).code(), 6);
+    assert_eq!(Status::of(&Error::Cancelled).code(), 7);
+    assert_eq!(Status::of(&Error::DryRun), Status::Success);
+}
+
+#[test]
+fn explains_the_site_messages() {
+    let explanation = |html: &str| parse_error(&fixture(html)).unwrap().explanation();
+    assert!(
+        explanation(ERROR_SECURITY_CODE)
+            .unwrap()
+            .starts_with("The security code was entered wrong")
+    );
+    assert!(
+        explanation(ERROR_SOLD_OUT)
+            .unwrap()
+            .starts_with("No seats are left")
+    );
+    let explanation = |message: &str| Error::from_feedback(message.to_string()).explanation();
+    // The membership, not the ID itself, was refused
+    assert!(
+        explanation("會員身分證字號錯誤")
+            .unwrap()
+            .starts_with("The TGo membership")
+    );
+    assert_eq!(explanation("Something else"), None);
+    assert_eq!(Error::SessionExpired.explanation(), None);
+}
+
+#[test]
+fn progress_events() {
+    let json = |event: &Event| serde_json::to_value(event).unwrap();
+    assert_eq!(
+        json(&Event::TrainSelected { train: 803 }),
+        serde_json::json!({"event": "train_selected", "train": 803})
+    );
+    assert_eq!(
+        json(&Event::error(&Error::SessionExpired)),
+        serde_json::json!({"event": "error", "message": Error::SessionExpired.to_string(), "status": 6})
+    );
+    assert_eq!(
+        json(&Event::Alert {
+            alert: &Alert::new("因颱風影響，部分列車停駛。")
+        }),
+        serde_json::json!({"event": "alert", "kind": "disruption", "severity": "critical", "text": "因颱風影響，部分列車停駛。"})
+    );
+}
+
+#[test]
+fn classifies_alerts() {
+    let classify = |text: &str| {
+        let alert = Alert::new(text);
+        (alert.kind, alert.severity)
+    };
+    assert_eq!(
+        classify("Trains are delayed due to a signal failure."),
+        (AlertKind::Disruption, Severity::Critical)
+    );
+    assert_eq!(
+        classify("中央氣象署已發布颱風警報，請留意行車資訊。"),
+        (AlertKind::Weather, Severity::Warning)
+    );
+    assert_eq!(
+        classify("早鳥優惠座位有限，售完為止。"),
+        (AlertKind::Discount, Severity::Info)
+    );
+    assert_eq!(
+        classify("本查詢結果僅顯示尚有座位之車次。"),
+        (AlertKind::Notice, Severity::Info)
+    );
+}
+
+#[test]
+fn picks_a_train() {
+    let trains = parse_trains(&fixture(TRAIN_LIST));
+    let before = |time: &str| Some(pick::parse_clock(time).unwrap());
+    assert_eq!(pick::train(&trains, TrainPick::Ask, None), Ok(None));
+    assert_eq!(pick::train(&trains, TrainPick::First, None), Ok(Some(803)));
+    // 1505 takes 2:20, 609 1:45
+    assert_eq!(
+        pick::train(&trains[1..], TrainPick::First, None),
+        Ok(Some(1505))
+    );
+    assert_eq!(
+        pick::train(&trains[1..], TrainPick::Fastest, None),
+        Ok(Some(609))
+    );
+    assert_eq!(
+        pick::train(&trains[1..], TrainPick::Fastest, before("07:10")),
+        Ok(Some(1505))
+    );
+    assert!(matches!(
+        pick::train(&trains, TrainPick::First, before("06:00")),
+        Err(Error::SoldOut(_))
+    ));
+}
+
+#[test]
+fn picks_the_first_preferred_train_with_seats() {
+    let trains = parse_trains(&fixture(TRAIN_LIST));
+    // 1234 isn't listed, 609 has standing room only
+    assert_eq!(pick::preferred(&trains, &[1234, 1505, 803]), Ok(1505));
+    assert_eq!(pick::preferred(&trains, &[609, 803]), Ok(803));
+    assert!(matches!(
+        pick::preferred(&trains, &[609, 1234]),
+        Err(Error::SoldOut(_))
+    ));
+}
+
+#[test]
+fn selects_the_train_the_options_pick() {
+    let trains = parse_trains(&fixture(TRAIN_LIST));
+    let select = |options: &[&str]| {
+        let cli = Cli::parse_from([&["thsr"], options].concat());
+        pick::select(&trains, &cli.args)
+    };
+    assert_eq!(select(&[]), Ok(None));
+    assert_eq!(select(&["--pick", "fastest"]), Ok(Some(803)));
+    assert_eq!(
+        select(&["--pick", "first", "--depart-before", "07:10"]),
+        Ok(Some(803))
+    );
+    assert_eq!(select(&["--select", "latest"]), Ok(Some(609)));
+    assert_eq!(select(&["--train", "609,1505"]), Ok(Some(1505)));
+    assert!(matches!(
+        select(&["--pick", "first", "--depart-before", "06:00"]),
+        Err(Error::SoldOut(_))
+    ));
+}
+
+#[test]
+fn picks_a_train_with_an_expression() {
+    let trains = parse_trains(&fixture(TRAIN_LIST));
+    let select = |expr: &str| expr.parse::<Selection>().unwrap().train(&trains);
+    // 803 06:30 1:45 65折, 1505 07:00 2:20 9折 and 5折 for students, 609 07:15 1:45
+    assert_eq!(select("latest"), Ok(609));
+    assert_eq!(
+        select("duration<100 && depart>=07:00 ? earliest : fastest"),
+        Ok(803)
+    );
+    assert_eq!(
+        select("duration<=1:45 && depart>=07:00 ? earliest : fastest"),
+        Ok(609)
+    );
+    assert_eq!(select("early_bird<100 ? latest"), Ok(1505));
+    assert_eq!(
+        select("!(student<100 || seats==standing_only) ? fastest"),
+        Ok(803)
+    );
+    assert_eq!(
+        select("arrive>09:00 ? depart<07:00 ? earliest : latest : fastest"),
+        Ok(1505)
+    );
+    assert!(matches!(
+        select("train==1234 ? earliest"),
+        Err(Error::SoldOut(_))
+    ));
+
+    for invalid in [
+        "",
+        "slowest",
+        "depart>=18:00",
+        "depart>=6pm ? earliest",
+        "speed<100 ? fastest",
+        "seats==none ? fastest",
+        "(duration<100 ? fastest",
+        "fastest earliest",
+    ] {
+        assert!(invalid.parse::<Selection>().is_err(), "{}", invalid);
+    }
+}
+
+#[cfg(feature = "scripting")]
+#[test]
+fn picks_a_train_with_a_script() {
+    let trains = parse_trains(&fixture(TRAIN_LIST));
+    let dir = tempfile::tempdir().unwrap();
+    let select = |script: &str| {
+        let path = dir.path().join("policy.rhai");
+        std::fs::write(&path, script).unwrap();
+        thsr::script::select(&path, &trains)
+    };
+    // The fastest train from `from` with an early bird discount, else the earliest
+    let policy = |from: &str| {
+        format!(
+            r#"
+            let best = ();
+            for (train, idx) in trains {{
+                if train.depart >= "{}" && train.early_bird != () {{
+                    if best == () || train.travel_minutes < trains[best].travel_minutes {{
+                        best = idx;
+                    }}
+                }}
+            }}
+            if best == () {{ 0 }} else {{ best }}
+            "#,
+            from
+        )
+    };
+    assert_eq!(select(&policy("07:00")), Ok(Some(1505)));
+    assert_eq!(select(&policy("07:10")), Ok(Some(803)));
+    assert_eq!(select("trains.len() - 1"), Ok(Some(609)));
+    assert_eq!(select("()"), Ok(None));
+    assert!(matches!(select("3"), Err(Error::Script(_))));
+    assert!(matches!(select("\"fastest\""), Err(Error::Script(_))));
+    assert!(matches!(select("loop {}"), Err(Error::Script(_))));
+}
+
+#[test]
+fn finds_stations() {
+    let find = |name: &str| name.parse::<Station>().ok();
+    assert_eq!(find("臺北"), Some(Station::Taipei));
+    assert_eq!(find(" zuoying "), Some(Station::Zuoying));
+    assert_eq!(find("TNN"), Some(Station::Tainan));
+    assert_eq!(find("7"), Some(Station::Taichung));
+    assert_eq!(find("13"), None);
+    assert_eq!(find("Kaohsiung"), None);
+    assert_eq!(Station::Taichung.info().code, "TAC");
+    assert_eq!(
+        Station::from_index(Station::Zuoying.index()),
+        Some(Station::Zuoying)
+    );
+
+    // Written as the ID the booking form takes, read from an ID or a name
+    assert_eq!(serde_json::to_string(&Station::Taipei).unwrap(), "2");
+    let read = |json: &str| serde_json::from_str::<Station>(json).ok();
+    assert_eq!(read("12"), Some(Station::Zuoying));
+    assert_eq!(read("\"板橋\""), Some(Station::Banqiao));
+    assert_eq!(read("0"), None);
+}
+
+#[test]
+fn time_slots() {
+    let codes: Vec<String> = TIME_SLOTS.iter().map(|slot| slot.code()).collect();
+    assert_eq!(codes[..3], ["1201A", "1230A", "600A"]);
+    assert_eq!(codes[14..17], ["1200N", "1230P", "100P"]);
+    assert_eq!(codes[37], "1130P");
+
+    let slot = |time: &str| time.parse::<TimeSlot>().ok();
+    assert_eq!(slot("10"), TimeSlot::from_code("930A"));
+    assert_eq!(slot("12:30").map(TimeSlot::id), Some(16));
+    assert_eq!(
+        slot("1201a").map(|slot| slot.to_string()),
+        Some("00:01".to_string())
+    );
+    assert_eq!(slot("12:15"), None);
+    assert_eq!(slot("39"), None);
+    let noon = NaiveTime::from_hms_opt(12, 0, 0).unwrap();
+    assert_eq!(TimeSlot::from_time(noon).map(TimeSlot::time), Some(noon));
+
+    // The slot the time falls in still lists trains departing later
+    let at = |time: &str| TimeSlot::upcoming(NaiveTime::parse_from_str(time, "%H:%M").unwrap());
+    assert_eq!(at("09:45")[0].to_string(), "09:30");
+    assert_eq!(at("00:00").len(), TIME_SLOTS.len());
+    assert_eq!(at("23:59").len(), 1);
+}
+
+#[test]
+fn moves_a_passed_time_on() {
+    let prompter = ScriptedPrompter::default();
+    let mut payload = BookingPayload::default();
+    let first = TimeSlot::from_id(1);
+    payload.outbound_date = "2099/01/01".to_string();
+    payload.select_time(&first, &prompter);
+    assert_eq!(Some(payload.outbound_time), first);
+
+    // Today in Taiwan, the first train of the day has left unless it is just past midnight
+    let before = taipei_now();
+    payload.outbound_date = before.format("%Y/%m/%d").to_string();
+    payload.select_time(&first, &prompter);
+    let after = taipei_now();
+    assert!(payload.outbound_time >= TimeSlot::upcoming(before.time())[0]);
+    assert!(payload.outbound_time <= TimeSlot::upcoming(after.time())[0]);
+    assert!(prompter.asked().is_empty());
+}
+
+#[test]
+fn reads_the_ntp_server_address() {
+    let addr = |server| ntp::server_addr(server).unwrap().to_string();
+    assert_eq!(addr("192.0.2.1"), "192.0.2.1:123");
+    assert_eq!(addr("192.0.2.1:1123"), "192.0.2.1:1123");
+    assert_eq!(addr("::1"), "[::1]:123");
+    assert_eq!(addr("[::1]"), "[::1]:123");
+    assert_eq!(addr("[::1]:1123"), "[::1]:1123");
+    assert!(ntp::server_addr("localhost:port").is_err());
+}
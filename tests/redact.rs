@@ -0,0 +1,65 @@
+use thsr::redact::{REDACTED, Redactor, token};
+
+#[test]
+fn replaces_personal_data_with_stable_tokens() {
+    let redactor = Redactor::default();
+    let text = redactor.text("ID A123456789, ARC AB12345678, phone 0912-345-678 or +886912345678");
+    assert!(!text.contains("A123456789"));
+    assert!(!text.contains("AB12345678"));
+    assert!(!text.contains("0912-345-678"));
+    assert!(!text.contains("+886912345678"));
+    assert!(text.starts_with(&format!("ID {}, ARC ", token("A123456789"))));
+    assert_eq!(
+        text,
+        redactor.text("ID A123456789, ARC AB12345678, phone 0912-345-678 or +886912345678")
+    );
+    assert_ne!(token("A123456789"), token("B223456789"));
+}
+
+#[test]
+fn tokens_stay_the_same_from_run_to_run() {
+    assert_eq!(token("A123456789"), "REDACTED-5d4e614e");
+}
+
+#[test]
+fn keeps_longer_numbers_and_words() {
+    let redactor = Redactor::default();
+    for text in [
+        "PNR 08123456",
+        "XA123456789",
+        "A1234567890",
+        "091234567890",
+        "train 0803",
+    ] {
+        assert_eq!(redactor.text(text), text);
+    }
+}
+
+#[test]
+fn redacts_given_secrets() {
+    let redactor = Redactor::default().secret("X1234567");
+    assert_eq!(
+        redactor.text("passport X1234567"),
+        format!("passport {}", token("X1234567"))
+    );
+}
+
+#[test]
+fn redacts_personal_form_fields() {
+    let redactor = Redactor::default();
+    let form = redactor.form(
+        "dummyId=E987654321&dummyPhone=0223456789&email=me%40example.com\
+         &TicketMemberSystemInputPanel%3ATakerMemberSystemDataView%3AmemberSystemRadioGroup%3AmemberShipNumber=12345\
+         &seatCon%3AseatRadioGroup=0",
+    );
+    let fields: Vec<(&str, &str)> = form
+        .split('&')
+        .map(|field| field.split_once('=').unwrap())
+        .collect();
+    assert!(
+        fields[..4]
+            .iter()
+            .all(|(_, value)| value.starts_with(REDACTED))
+    );
+    assert_eq!(fields[4], ("seatCon%3AseatRadioGroup", "0"));
+}
@@ -0,0 +1,83 @@
+use clap::Parser;
+
+use std::path::Path;
+
+use thsr::book;
+use thsr::cassette::{Cassette, Recorder, Replayer};
+use thsr::cli::{Args, Cli};
+use thsr::config::Config;
+use thsr::error::Error;
+use thsr::prompt::ScriptedPrompter;
+use thsr::redact::REDACTED;
+
+const SESSION: &str = "tests/fixtures/booking_session.json";
+
+fn args() -> Args {
+    Cli::parse_from([
+        "thsr",
+        "--from",
+        "2",
+        "--to",
+        "12",
+        "--date",
+        "2025/06/29",
+        "--time",
+        "10",
+        "--adult-cnt",
+        "2",
+        "--seat-prefer",
+        "0",
+        "--class-type",
+        "0",
+        "--train",
+        "803",
+        "--yes",
+    ])
+    .args
+}
+
+/// The security code, then the IDs of the two early bird passengers.
+fn prompter() -> ScriptedPrompter {
+    ScriptedPrompter::new(["", "B223456789"], ["ABCD"])
+}
+
+#[test]
+fn replays_a_booking() {
+    let replayer = Replayer::load(Path::new(SESSION)).unwrap();
+
+    let result = book(&replayer, &args(), &Config::default(), &prompter()).unwrap();
+    assert_eq!(result.pnr, "08123456");
+    assert_eq!(result.seat_list(), "Car 7 12A, Car 7 12B");
+    assert!(replayer.is_done());
+}
+
+#[test]
+fn records_without_ids() {
+    let replayer = Replayer::load(Path::new(SESSION)).unwrap();
+    let recorder = Recorder::new(replayer).redact("password");
+
+    book(&recorder, &args(), &Config::default(), &prompter()).unwrap();
+    let cassette = recorder.into_cassette();
+    assert_eq!(cassette.interactions.len(), 5);
+
+    let forms: Vec<&str> = cassette
+        .interactions
+        .iter()
+        .filter_map(|interaction| interaction.form.as_deref())
+        .collect();
+    assert!(forms[0].contains("homeCaptcha%3AsecurityCode=ABCD"));
+    assert!(forms[1].contains("TrainQueryDataViewPanel%3ATrainGroup=radio18"));
+    assert!(forms[2].contains(&format!("dummyId={}", REDACTED)));
+    assert!(forms.iter().all(|form| !form.contains("S125544509")));
+    assert!(forms.iter().all(|form| !form.contains("B223456789")));
+}
+
+#[test]
+fn stops_at_the_end_of_the_cassette() {
+    let mut cassette = Cassette::load(Path::new(SESSION)).unwrap();
+    cassette.interactions.truncate(3);
+    let replayer = Replayer::new(cassette);
+
+    let outcome = book(&replayer, &args(), &Config::default(), &prompter());
+    assert!(matches!(outcome, Err(Error::Network(_))));
+}
@@ -0,0 +1,137 @@
+#![cfg(feature = "blocking")]
+
+mod mock;
+
+use clap::Parser;
+use reqwest::blocking::Client;
+use serde_json::{Value, json};
+use tiny_http::Server;
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use thsr::cli::Cli;
+use thsr::config::Config;
+use thsr::serve::serve;
+
+use mock::{MockSite, SECURITY_CODE, Scenario};
+
+/// Serve the API for `mock` on a free port, returning its address.
+fn start(mock: &MockSite) -> String {
+    let server = Server::http("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", server.server_addr());
+    let config = Config {
+        site: mock.site(),
+        ..Default::default()
+    };
+    thread::spawn(move || serve(&server, &Cli::parse_from(["thsr"]).args, config));
+    url
+}
+
+fn get(client: &Client, url: &str) -> Value {
+    serde_json::from_str(&client.get(url).send().unwrap().text().unwrap()).unwrap()
+}
+
+fn post(client: &Client, url: &str, body: Value) -> (u16, Value) {
+    let response = client.post(url).body(body.to_string()).send().unwrap();
+    let status = response.status().as_u16();
+    (
+        status,
+        serde_json::from_str(&response.text().unwrap()).unwrap(),
+    )
+}
+
+/// Poll the booking at `url` until it no longer runs.
+fn settle(client: &Client, url: &str) -> Value {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        let booking = get(client, url);
+        if booking["status"] != "running" || Instant::now() > deadline {
+            return booking;
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+#[test]
+fn books_through_the_api() {
+    let mock = MockSite::start(Scenario::default());
+    let api = start(&mock);
+    let client = Client::new();
+
+    let (status, booking) = post(
+        &client,
+        &format!("{}/bookings", api),
+        json!({
+            "from": 2,
+            "to": 12,
+            "date": "2025/06/29",
+            "time": 10,
+            "adult_cnt": 2,
+            "seat_prefer": 1,
+            "class_type": 0,
+        }),
+    );
+    assert_eq!(status, 201);
+    let id = booking["id"].as_str().unwrap().to_string();
+    let url = format!("{}/bookings/{}", api, id);
+
+    let booking = settle(&client, &url);
+    assert_eq!(booking["status"], "captcha");
+    let image = client.get(format!("{}/captcha", url)).send().unwrap();
+    assert_eq!(image.headers()["content-type"], "image/jpeg");
+
+    let answer = |text: &str| {
+        let body = json!({ "answer": text });
+        assert_eq!(post(&client, &format!("{}/answer", url), body).0, 202);
+        settle(&client, &url)
+    };
+    let booking = answer(SECURITY_CODE);
+    assert_eq!(booking["question"], "Select a train (default: 1):");
+    assert_eq!(booking["trains"][1]["id"], 1505);
+
+    // The second train, then the IDs of the two early bird passengers
+    let mut booking = answer("2");
+    for passenger in ["", "B223456789"] {
+        assert_eq!(booking["status"], "question", "{}", booking);
+        booking = answer(passenger);
+    }
+    assert_eq!(booking["status"], "booked", "{}", booking);
+    assert_eq!(booking["result"]["pnr"], "08123456");
+    assert_eq!(
+        mock.forms("S2")[0]["TrainQueryDataViewPanel:TrainGroup"],
+        "radio20"
+    );
+
+    let by_pnr = get(&client, &format!("{}/bookings/08123456", api));
+    assert_eq!(by_pnr["id"], id.as_str());
+
+    let stations = get(&client, &format!("{}/stations", api));
+    assert_eq!(stations[11]["code"], "ZUY");
+    assert_eq!(stations[11]["name_zh"], "左營");
+    assert_eq!(stations[11]["location"], json!([22.6871, 120.3079]));
+
+    let metrics = client
+        .get(format!("{}/metrics", api))
+        .send()
+        .unwrap()
+        .text()
+        .unwrap();
+    assert!(metrics.contains("thsr_bookings_total{outcome=\"booked\"}"));
+    assert!(metrics.contains("thsr_step_duration_seconds_count{step=\"ticket\"}"));
+    assert!(metrics.contains("thsr_search_attempts_total"));
+}
+
+#[test]
+fn rejects_unknown_options() {
+    let mock = MockSite::start(Scenario::default());
+    let api = start(&mock);
+
+    let (status, body) = post(
+        &Client::new(),
+        &format!("{}/bookings", api),
+        json!({ "form": 2 }),
+    );
+    assert_eq!(status, 400);
+    assert!(body["error"].as_str().unwrap().contains("form"));
+}
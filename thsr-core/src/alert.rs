@@ -0,0 +1,78 @@
+//! The alerts the booking site lists above the trains, sorted out by what they are
+//! about so that a suspended service stands out from the discount notes.
+
+use serde::Serialize;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    /// Trains stopped, delayed or running irregularly
+    Disruption,
+    /// Typhoons, earthquakes and heavy rain, which may stop the trains
+    Weather,
+    Discount,
+    Notice,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, PartialOrd)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Info,
+    Warning,
+    Critical,
+}
+
+/// Words telling what an alert is about, in Chinese and English, the first kind
+/// matching winning.
+const KEYWORDS: [(AlertKind, &[&str]); 3] = [
+    (
+        AlertKind::Disruption,
+        &[
+            "停駛", "停運", "延誤", "誤點", "暫停", "中斷", "異常", "suspend", "delay", "disrupt",
+            "cancel",
+        ],
+    ),
+    (
+        AlertKind::Weather,
+        &[
+            "颱風",
+            "地震",
+            "豪雨",
+            "typhoon",
+            "earthquake",
+            "heavy rain",
+        ],
+    ),
+    (
+        AlertKind::Discount,
+        &["優惠", "早鳥", "折", "discount", "early bird", "% off"],
+    ),
+];
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Alert {
+    pub kind: AlertKind,
+    pub severity: Severity,
+    pub text: String,
+}
+
+impl Alert {
+    /// The alert `text`, classified by its words.
+    pub fn new(text: impl Into<String>) -> Alert {
+        let text: String = text.into();
+        let lower = text.to_lowercase();
+        let kind = (KEYWORDS.iter())
+            .find(|(_, words)| words.iter().any(|word| lower.contains(word)))
+            .map_or(AlertKind::Notice, |(kind, _)| *kind);
+        let severity = match kind {
+            AlertKind::Disruption => Severity::Critical,
+            AlertKind::Weather => Severity::Warning,
+            AlertKind::Discount | AlertKind::Notice => Severity::Info,
+        };
+        Alert {
+            kind,
+            severity,
+            text,
+        }
+    }
+}
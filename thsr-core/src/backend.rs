@@ -0,0 +1,189 @@
+//! The ways of talking to the booking system. Each backend knows the addresses and
+//! the shape of the requests of one interface; the [`Stage`](crate::stage::Stage)s
+//! of a booking take care of the order of the steps.
+
+use scraper::Html;
+use serde::Deserialize;
+use tracing::info;
+
+use crate::booking::BookingOptions;
+use crate::booking_flow::{self, BookingPage, BookingPayload};
+use crate::confirm_ticket_flow;
+use crate::confirm_train_flow::{self, ConfirmTrainPayload, Train};
+use crate::error::Error;
+use crate::http::Transport;
+use crate::i18n::tr;
+use crate::page::{is_session_expired, parse_error, parse_maintenance};
+use crate::prompt::{self, Notice, Prompter};
+use crate::result::{BookingResult, parse_result};
+use crate::site::Site;
+
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    /// Fill in the forms of the booking website
+    #[default]
+    Web,
+}
+
+impl BackendKind {
+    pub fn backend(self) -> &'static dyn BookingBackend {
+        match self {
+            BackendKind::Web => &Web,
+        }
+    }
+}
+
+/// The steps of a booking, as done through one interface of the booking system.
+/// The pages passed between the steps are the ones returned by the same backend.
+pub trait BookingBackend: Sync {
+    /// Open a new session on the search form.
+    fn open(
+        &self,
+        client: &dyn Transport,
+        site: &Site,
+        prompter: &dyn Prompter,
+    ) -> Result<BookingPage, Error>;
+
+    /// Enter the security code and search with the selections of `payload`,
+    /// returning the train list. `before_submit` runs right before the search is sent.
+    fn search(
+        &self,
+        client: &dyn Transport,
+        page: BookingPage,
+        payload: &mut BookingPayload,
+        site: &Site,
+        prompter: &dyn Prompter,
+        before_submit: &mut dyn FnMut(),
+    ) -> Result<Html, Error>;
+
+    /// The trains listed on the train list.
+    fn trains(&self, page: &Html) -> Vec<Train>;
+
+    /// Pick `train`, or ask for one among the trains found searching with `payload`,
+    /// storing the pick back into `train`. Returns the ticket form.
+    fn confirm_train(
+        &self,
+        client: &dyn Transport,
+        page: Html,
+        payload: &BookingPayload,
+        train: &mut Option<u32>,
+        site: &Site,
+        prompter: &dyn Prompter,
+    ) -> Result<Html, Error>;
+
+    /// Enter the passengers on the ticket form and book, returning the result page.
+    fn confirm_ticket(
+        &self,
+        client: &dyn Transport,
+        page: Html,
+        options: &BookingOptions,
+        site: &Site,
+        prompter: &dyn Prompter,
+    ) -> Result<Html, Error>;
+
+    /// The total price shown on the ticket form, if it shows one.
+    fn price(&self, page: &Html) -> Option<String>;
+
+    fn result(&self, page: &Html) -> BookingResult;
+}
+
+/// The booking website, a Wicket application posting one form per step.
+pub struct Web;
+
+impl BookingBackend for Web {
+    fn open(
+        &self,
+        client: &dyn Transport,
+        site: &Site,
+        prompter: &dyn Prompter,
+    ) -> Result<BookingPage, Error> {
+        booking_flow::fetch_page(client, site, prompter)
+    }
+
+    fn search(
+        &self,
+        client: &dyn Transport,
+        page: BookingPage,
+        payload: &mut BookingPayload,
+        site: &Site,
+        prompter: &dyn Prompter,
+        before_submit: &mut dyn FnMut(),
+    ) -> Result<Html, Error> {
+        booking_flow::solve_and_submit(client, page, payload, site, prompter, before_submit)
+    }
+
+    fn trains(&self, page: &Html) -> Vec<Train> {
+        confirm_train_flow::parse_trains(page)
+    }
+
+    fn confirm_train(
+        &self,
+        client: &dyn Transport,
+        page: Html,
+        search: &BookingPayload,
+        train: &mut Option<u32>,
+        site: &Site,
+        prompter: &dyn Prompter,
+    ) -> Result<Html, Error> {
+        // Parse alerts
+        for alert in confirm_train_flow::parse_alerts(&page) {
+            prompter.tell(Notice::Alert(&alert));
+        }
+
+        // Parse available trains
+        let trains = confirm_train_flow::parse_trains(&page);
+        let mut payload = ConfirmTrainPayload::default();
+        *train = Some(payload.select_available_trains(&trains, *train, search, prompter)?);
+
+        let resp = prompt::wait(
+            prompter,
+            &tr!("Confirming the train", "正在確認車次"),
+            || {
+                client.post_form(
+                    &site.confirm_train(),
+                    serde_urlencoded::to_string(&payload).unwrap(),
+                )
+            },
+        )?;
+        parse_reply(&resp.text())
+    }
+
+    fn confirm_ticket(
+        &self,
+        client: &dyn Transport,
+        page: Html,
+        options: &BookingOptions,
+        site: &Site,
+        prompter: &dyn Prompter,
+    ) -> Result<Html, Error> {
+        let payload = confirm_ticket_flow::ticket_form(&page, options, prompter)?;
+
+        info!("Booking");
+        let resp = prompt::wait(prompter, &tr!("Booking", "正在訂位"), || {
+            client.post_form(&site.confirm_ticket(), payload)
+        })?;
+        parse_reply(&resp.text())
+    }
+
+    fn price(&self, page: &Html) -> Option<String> {
+        confirm_ticket_flow::parse_total_price(page)
+    }
+
+    fn result(&self, page: &Html) -> BookingResult {
+        parse_result(page)
+    }
+}
+
+/// The page replied to a form, unless it tells the form was refused.
+pub(crate) fn parse_reply(body: &str) -> Result<Html, Error> {
+    let html = Html::parse_document(body);
+    if let Some(err) = parse_error(&html).or_else(|| parse_maintenance(&html)) {
+        return Err(err);
+    }
+    if is_session_expired(&html) {
+        return Err(Error::SessionExpired);
+    }
+    Ok(html)
+}
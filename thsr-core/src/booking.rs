@@ -0,0 +1,319 @@
+//! A whole booking, from the search to the booking result, with the options it is
+//! made with. Whatever the options leave open is asked of the [`Prompter`].
+
+use serde::Deserialize;
+use tracing::warn;
+
+use crate::backend::BackendKind;
+use crate::booking_flow::{self, BookingPayload};
+use crate::error::Error;
+use crate::http::Transport;
+use crate::i18n::{self, tr};
+use crate::passenger::{self, Passenger};
+use crate::prompt::{Notice, Prompter};
+use crate::result::BookingResult;
+use crate::schema::{Station, TIME_SLOTS, TimeSlot};
+use crate::site::Site;
+use crate::stage::{Progress, Stage};
+use crate::summary::Summary;
+use crate::tgo;
+use crate::time;
+
+/// What to book, and how. Left out, the trip and tickets are asked for.
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default)]
+pub struct BookingOptions {
+    pub site: Site,
+    pub backend: BackendKind,
+
+    /// A date like `2025/06/29`, or a range of dates like `2025/06/01..2025/06/03`
+    /// tried in order
+    pub date: Option<String>,
+    pub time: Option<TimeSlot>,
+    pub from: Option<Station>,
+    pub to: Option<Station>,
+    /// The train to book, of several the one picked from the train list
+    pub train: Vec<u32>,
+    /// Time slots to search around `time` when none of its trains can be booked
+    pub nearby_slots: Option<usize>,
+
+    pub adult_cnt: Option<u8>,
+    pub student_cnt: Option<u8>,
+    /// The passengers booked for, one ticket of their type each, in place of the counts
+    pub party: Vec<Passenger>,
+    /// The names of the passengers of `party`, for the summary
+    pub passengers: Vec<String>,
+    /// The IDs of the passengers the ticket form asks for, after those of `party`
+    pub passenger_ids: Vec<String>,
+    /// Show the passengers filled in and ask before submitting them
+    pub review_passengers: bool,
+    /// 0: any, 1: window, 2: aisle
+    pub seat_prefer: Option<usize>,
+    /// 0: standard, 1: business
+    pub class_type: Option<usize>,
+
+    pub personal_id: Option<String>,
+    pub passport: Option<String>,
+    pub phone: Option<String>,
+    pub email: Option<String>,
+    pub use_membership: Option<bool>,
+
+    /// Stop before booking, showing what would be booked
+    pub dry_run: bool,
+    /// Book without asking for a confirmation
+    pub yes: bool,
+}
+
+impl BookingOptions {
+    /// Whether the personal ID is used as TGo membership. Never with a passport.
+    pub fn uses_membership(&self) -> bool {
+        self.passport.is_none() && self.use_membership.unwrap_or(true)
+    }
+}
+
+/// Go through all three steps of a booking, asking `prompter` for what `options` leaves open.
+pub fn book(
+    client: &dyn Transport,
+    options: &BookingOptions,
+    prompter: &dyn Prompter,
+) -> Result<BookingResult, Error> {
+    book_with_journal(client, options, prompter, &mut |_| {})
+}
+
+/// Like [`book`], handing every step reached to `journal`, e.g. to save it for resuming.
+pub fn book_with_journal(
+    client: &dyn Transport,
+    options: &BookingOptions,
+    prompter: &dyn Prompter,
+    journal: &mut dyn FnMut(Checkpoint),
+) -> Result<BookingResult, Error> {
+    // Checked before the security code is spent on the search
+    if options.uses_membership()
+        && let Some(personal_id) = &options.personal_id
+        && !passenger::is_valid_national_id(personal_id)
+    {
+        return Err(Error::InvalidId(format!(
+            "{} is not a valid TGo membership number, book with --use-membership false",
+            personal_id
+        )));
+    }
+
+    // First page
+    let backend = options.backend.backend();
+    let mut search = Stage::open(backend, client, &options.site, prompter)?;
+    let mut payload = BookingPayload::default();
+    let page = search.page();
+    // A range of dates is tried in order, those outside the booking window left out
+    let mut dates = (options.date.as_deref())
+        .and_then(booking_flow::parse_dates)
+        .unwrap_or_default();
+    if dates.len() > 1 {
+        dates.retain(|date| *date >= page.start_date && *date <= page.end_date);
+        if dates.is_empty() {
+            return Err(Error::Rejected(format!(
+                "No date of {} can be booked, bookings are open from {} to {}",
+                options.date.as_deref().unwrap_or_default(),
+                page.start_date,
+                page.end_date
+            )));
+        }
+    }
+    let mut dates = dates.into_iter();
+    let first = BookingOptions {
+        date: dates.next().or_else(|| options.date.clone()),
+        ..options.clone()
+    };
+    payload.select_options(&first, Some((&page.start_date, &page.end_date)), prompter);
+    let (asked_date, asked) = (payload.outbound_date.clone(), payload.outbound_time);
+    let radius = options.nearby_slots.unwrap_or(0);
+    let mut nearby = nearby_slots(&payload, radius).into_iter();
+
+    loop {
+        let outcome = (search.submit(client, &mut payload, &options.site, prompter, || {}))
+            .and_then(|trains| {
+                complete_booking(
+                    client,
+                    Progress::Train(trains),
+                    &mut payload,
+                    options,
+                    prompter,
+                    journal,
+                )
+            });
+        match outcome {
+            // The train asked for or picked may run at another time or on another day
+            Err(err @ (Error::SoldOut(_) | Error::TrainUnavailable(_)))
+                if nearby.len() > 0 || dates.len() > 0 =>
+            {
+                let err_msg = err.to_string();
+                match nearby.next() {
+                    Some(slot) => {
+                        prompter.tell(Notice::Warning(&tr!(
+                            "{}, searching from {} instead",
+                            "{}，改查詢 {} 起的車次",
+                            err_msg,
+                            slot
+                        )));
+                        payload.outbound_time = slot;
+                    }
+                    None => {
+                        let date = dates.next().unwrap();
+                        prompter.tell(Notice::Warning(&tr!(
+                            "{}, trying {} instead",
+                            "{}，改查詢 {} 的車次",
+                            err_msg,
+                            date
+                        )));
+                        payload.outbound_date = date;
+                        payload.outbound_time = asked;
+                        nearby = nearby_slots(&payload, radius).into_iter();
+                    }
+                }
+                search = Stage::open(backend, client, &options.site, prompter)?;
+            }
+            outcome => {
+                if outcome.is_ok() && payload.outbound_date != asked_date {
+                    prompter.tell(Notice::Status(&tr!(
+                        "Booked a train on {}, none on {} could be",
+                        "已改訂 {} 的車次，{} 的車次無法訂位",
+                        payload.outbound_date,
+                        asked_date
+                    )));
+                } else if outcome.is_ok() && payload.outbound_time != asked {
+                    prompter.tell(Notice::Status(&tr!(
+                        "Booked a train from {}, none from {} could be",
+                        "已改訂 {} 起的車次，{} 起的車次無法訂位",
+                        payload.outbound_time,
+                        asked
+                    )));
+                }
+                return outcome;
+            }
+        }
+    }
+}
+
+/// The time slots to search when none of the trains from that of `payload` can be
+/// booked, up to `radius` slots away, leaving out those gone today.
+fn nearby_slots(payload: &BookingPayload, radius: usize) -> Vec<TimeSlot> {
+    let now = time::taipei_now();
+    let first = match payload.outbound_date == now.format("%Y/%m/%d").to_string() {
+        true => TimeSlot::upcoming(now.time())[0],
+        false => TIME_SLOTS[0],
+    };
+    (payload.outbound_time.nearby(radius).into_iter())
+        .filter(|slot| *slot >= first)
+        .collect()
+}
+
+/// How far a booking got, along with everything needed to go on from there.
+pub struct Checkpoint<'a> {
+    pub progress: &'a Progress,
+    pub payload: &'a BookingPayload,
+    /// The train picked so far
+    pub train: Option<u32>,
+}
+
+/// Pick the train and confirm the tickets, starting from `progress`.
+/// If the session expires on the way, the search is redone in a new session with
+/// the same answers, so only the security code has to be entered again.
+pub fn complete_booking(
+    client: &dyn Transport,
+    mut progress: Progress,
+    payload: &mut BookingPayload,
+    options: &BookingOptions,
+    prompter: &dyn Prompter,
+    journal: &mut dyn FnMut(Checkpoint),
+) -> Result<BookingResult, Error> {
+    let site = &options.site;
+    // Of several trains, the one to book is known from the train list
+    let mut train = match options.train[..] {
+        [train] => Some(train),
+        _ => None,
+    };
+    loop {
+        journal(Checkpoint {
+            progress: &progress,
+            payload,
+            train,
+        });
+        let next = match progress {
+            // Second Page
+            Progress::Train(stage) => {
+                let trains = stage.trains();
+                prompter.tell(Notice::TrainsFound(&trains));
+                if train.is_none() {
+                    train = prompter.pick_train(&trains)?;
+                }
+                let next = stage.confirm_train(client, payload, &mut train, site, prompter);
+                if let (Ok(_), Some(train)) = (&next, train) {
+                    prompter.tell(Notice::TrainSelected(train));
+                }
+                next.map(Progress::Ticket)
+            }
+            // Final page, which makes the reservation, so it is confirmed first
+            Progress::Ticket(stage) => {
+                let summary = Summary::new(payload, train, options, stage.price());
+                if options.dry_run || !options.yes {
+                    if options.dry_run {
+                        prompter.tell(Notice::Line(&format!(
+                            "{}\n{}",
+                            tr!("-------(Would book)-------", "-------（將訂位）-------"),
+                            summary
+                        )));
+                        return Err(Error::DryRun);
+                    }
+                    prompter.tell(Notice::Line(&format!(
+                        "{}\n{}",
+                        tr!("-------(Booking)-------", "-------（訂位內容）-------"),
+                        summary
+                    )));
+                    if !prompter.confirm(&tr!("Book these tickets?", "確定訂購這些車票？"))
+                    {
+                        return Err(Error::Cancelled);
+                    }
+                }
+                if let Err(err_msg) = prompter.approve(&summary) {
+                    prompter.tell(Notice::Warning(&tr!(
+                        "The booking wasn't approved: {}",
+                        "訂位未獲核准：{}",
+                        err_msg
+                    )));
+                    return Err(Error::Cancelled);
+                }
+                stage
+                    .confirm_ticket(client, options, site, prompter)
+                    .map(Progress::Booked)
+            }
+            Progress::Booked(stage) => {
+                let mut result = stage.result();
+                result.train = train;
+                if options.uses_membership() {
+                    result.tgo_points = tgo::points(&result.price);
+                }
+                return Ok(result);
+            }
+        };
+        progress = match next {
+            Err(Error::SessionExpired) => {
+                warn!(
+                    "{}, starting over with the same answers",
+                    Error::SessionExpired
+                );
+                let search = Stage::open(options.backend.backend(), client, site, prompter)?;
+                Progress::Train(search.submit(client, payload, site, prompter, || {})?)
+            }
+            next => next?,
+        };
+    }
+}
+
+/// Tell what the Chinese message of the booking site in `err` means, in English,
+/// along with the message itself. Those reading Chinese need no explanation.
+pub fn explain(err: &Error, prompter: &dyn Prompter) {
+    if i18n::lang() == i18n::Lang::En
+        && let Some(explanation) = err.explanation()
+    {
+        prompter.tell(Notice::Status(explanation));
+    }
+}
@@ -0,0 +1,721 @@
+//! The first page of a booking: the search form, its session and security code.
+
+use bytes::Bytes;
+use chrono::NaiveDate;
+use scraper::Html;
+use secrecy::SecretString;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use std::thread;
+
+use crate::backend;
+use crate::booking::{BookingOptions, explain};
+use crate::error::Error;
+use crate::http::Transport;
+use crate::i18n::tr;
+use crate::page::parse_maintenance;
+use crate::passenger::Passenger;
+use crate::prompt::{self, Notice, Prompter};
+use crate::redact;
+use crate::schema::{STATIONS, Station, TIME_SLOTS, TicketType, TimeSlot};
+use crate::selectors;
+use crate::site::Site;
+use crate::time;
+
+pub use crate::page::{
+    parse_avail_start_end_date, parse_reloaded_img_src, parse_search_by,
+    parse_security_code_img_url, parse_security_code_reload_url, parse_session_id,
+    parse_types_of_trip_value,
+};
+
+/// Give up after this many rejected security codes in a row.
+const MAX_SECURITY_CODE_ATTEMPTS: u32 = 5;
+
+/// Enter the security code and submit the search. When the code is rejected or the
+/// session expired meanwhile, a new page and code are fetched and the same
+/// selections are submitted again.
+/// `before_submit` runs between entering the code and submitting.
+pub fn solve_and_submit(
+    client: &dyn Transport,
+    mut page: BookingPage,
+    payload: &mut BookingPayload,
+    site: &Site,
+    prompter: &dyn Prompter,
+    mut before_submit: impl FnMut(),
+) -> Result<Html, Error> {
+    let mut attempt = 1;
+    loop {
+        solve_security_code(client, &mut page, payload, site, prompter)?;
+        before_submit();
+        let outcome = submit(client, &page, payload, site, prompter);
+        prompter.tell(Notice::Searched(outcome.as_ref().err()));
+        match outcome {
+            Err(err @ (Error::WrongSecurityCode(_) | Error::SessionExpired))
+                if attempt < MAX_SECURITY_CODE_ATTEMPTS =>
+            {
+                warn!("{}", err);
+                explain(&err, prompter);
+                info!("Fetching a new security code");
+                page = fetch_page(client, site, prompter)?;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// The parts of the booking page needed to submit the search form.
+pub struct BookingPage {
+    pub jid: String,
+    pub security_code_img_url: String,
+    /// The security code image, when it was downloaded along with the page
+    pub security_code_img: Option<Bytes>,
+    pub security_code_reload_url: Option<String>,
+    pub search_by: String,
+    pub types_of_trip: u8,
+    pub start_date: String,
+    pub end_date: String,
+}
+
+/// Open a new session on the booking page.
+pub fn fetch_page(
+    client: &dyn Transport,
+    site: &Site,
+    prompter: &dyn Prompter,
+) -> Result<BookingPage, Error> {
+    info!("Requesting booking page");
+    let response = prompt::wait(
+        prompter,
+        &tr!("Requesting the booking page", "正在取得訂票頁面"),
+        || client.get(&site.booking_page()),
+    )?;
+
+    // The image address is found in the markup right away, so the security code
+    // is downloaded while the page is parsed, a round trip less before the search
+    let text = response.text();
+    let img_url = parse_reloaded_img_src(&text).map(|src| site.url(&src));
+    thread::scope(|scope| {
+        let download = (img_url.clone())
+            .map(|url| scope.spawn(move || client.get(&url).map(|resp| resp.body)));
+
+        // Parse to HTML object
+        let document = Html::parse_document(&text);
+        // The maintenance notice comes in place of the page
+        if document.select(&selectors::DATE_INPUT).next().is_none() {
+            return Err(parse_maintenance(&document).unwrap_or_else(|| {
+                Error::UnexpectedPage("no search form on the booking page".to_string())
+            }));
+        }
+
+        // Parse jsession id. A session kept in the cookie jar goes on without a new cookie.
+        let jid = (response.cookie("JSESSIONID").map(str::to_string))
+            .or_else(|| parse_session_id(&document))
+            .or_else(|| client.cookie(&site.booking_page(), "JSESSIONID"))
+            .ok_or_else(|| Error::UnexpectedPage("no session on the booking page".to_string()))?;
+
+        let (start_date, end_date) = parse_avail_start_end_date(&document);
+        let security_code_img_url = parse_security_code_img_url(&document, site);
+        // Failing, the image is downloaded again when the code is asked for
+        let security_code_img = (download.and_then(|download| download.join().ok()))
+            .and_then(Result::ok)
+            .filter(|_| img_url.as_ref() == Some(&security_code_img_url));
+        prompter.tell(Notice::PageLoaded);
+        Ok(BookingPage {
+            jid,
+            security_code_img_url,
+            security_code_img,
+            security_code_reload_url: parse_security_code_reload_url(&document, site),
+            search_by: parse_search_by(&document),
+            types_of_trip: parse_types_of_trip_value(&document),
+            start_date,
+            end_date,
+        })
+    })
+}
+
+/// Download the security code image of `page` and ask for the code,
+/// reloading the image for as long as the user asks for a new one.
+pub fn solve_security_code(
+    client: &dyn Transport,
+    page: &mut BookingPage,
+    payload: &mut BookingPayload,
+    site: &Site,
+    prompter: &dyn Prompter,
+) -> Result<(), Error> {
+    prompter.tell(Notice::CaptchaRequired);
+    loop {
+        let img_data = match page.security_code_img.take() {
+            Some(img_data) => img_data,
+            None => {
+                prompt::wait(
+                    prompter,
+                    &tr!("Downloading the security code", "正在下載驗證碼"),
+                    || client.get(&page.security_code_img_url),
+                )?
+                .body
+            }
+        };
+        if let Some(code) = prompter.security_code(&img_data) {
+            payload.security_code = code.into();
+            return Ok(());
+        }
+        reload_security_code(client, page, site, prompter)?;
+    }
+}
+
+/// Ask for a new security code image through the reload button of the page.
+/// Pages without the button are replaced by a new session.
+fn reload_security_code(
+    client: &dyn Transport,
+    page: &mut BookingPage,
+    site: &Site,
+    prompter: &dyn Prompter,
+) -> Result<(), Error> {
+    info!("Requesting a new security code");
+    let Some(reload_url) = &page.security_code_reload_url else {
+        *page = fetch_page(client, site, prompter)?;
+        return Ok(());
+    };
+
+    // The button's Ajax reply replaces the image, usually with a new address.
+    // If it can't be found, the old address serves the new image as well.
+    let resp = prompt::wait(
+        prompter,
+        &tr!("Requesting a new security code", "正在更換驗證碼"),
+        || client.get(&format!("{}{}", reload_url, fastrand::u32(..))),
+    )?;
+    if let Some(src) = parse_reloaded_img_src(&resp.text()) {
+        page.security_code_img_url = site.url(&src);
+    }
+    Ok(())
+}
+
+/// Post the filled search form within the session of `page`.
+pub fn submit(
+    client: &dyn Transport,
+    page: &BookingPage,
+    payload: &BookingPayload,
+    site: &Site,
+    prompter: &dyn Prompter,
+) -> Result<Html, Error> {
+    let form = payload.form(&page.search_by, page.types_of_trip);
+
+    // Make the booking request
+    let resp = prompt::wait(
+        prompter,
+        &tr!("Searching for trains", "正在查詢車次"),
+        || client.post_form(&site.submit_form(&page.jid), form),
+    )?;
+
+    backend::parse_reply(&resp.text())
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BookingPayload {
+    #[serde(rename = "selectStartStation")]
+    pub start_station: Station,
+
+    #[serde(rename = "selectDestinationStation")]
+    pub dest_station: Station,
+
+    #[serde(rename = "bookingMethod")]
+    pub search_by: String,
+
+    #[serde(rename = "tripCon:typesoftrip", default)]
+    pub types_of_trip: u8, // 0: one way, 1: round trip
+
+    #[serde(rename = "toTimeInputField")]
+    pub outbound_date: String,
+
+    #[serde(rename = "toTimeTable", serialize_with = "TimeSlot::serialize_code")]
+    pub outbound_time: TimeSlot,
+
+    #[serde(rename = "homeCaptcha:securityCode", serialize_with = "redact::expose")]
+    pub security_code: SecretString,
+
+    #[serde(rename = "seatCon:seatRadioGroup")]
+    pub seat_prefer: usize, // 0: any, 1: window, 2: aisle
+
+    #[serde(rename = "BookingS1Form:hf:0", default)]
+    pub form_mark: String,
+
+    #[serde(rename = "trainCon:trainRadioGroup", default)]
+    pub class_type: u8, // 0: standard, 1: business
+
+    #[serde(rename = "backTimeInputField")]
+    pub inbound_date: Option<String>,
+
+    #[serde(rename = "backTimeTable")]
+    pub inbound_time: Option<String>,
+
+    #[serde(rename = "toTrainIDInputField", default)]
+    pub to_train_id: Option<u8>,
+
+    #[serde(rename = "backTrainIDInputField", default)]
+    pub back_train_id: Option<u8>,
+
+    #[serde(
+        rename = "ticketPanel:rows:0:ticketAmount",
+        default = "default_adult_ticket_num"
+    )]
+    pub adult_ticket_num: String,
+
+    #[serde(
+        rename = "ticketPanel:rows:1:ticketAmount",
+        default = "default_child_ticket_num"
+    )]
+    pub child_ticket_num: String,
+
+    #[serde(
+        rename = "ticketPanel:rows:2:ticketAmount",
+        default = "default_disabled_ticket_num"
+    )]
+    pub disabled_ticket_num: String,
+
+    #[serde(
+        rename = "ticketPanel:rows:3:ticketAmount",
+        default = "default_elder_ticket_num"
+    )]
+    pub elder_ticket_num: String,
+
+    #[serde(
+        rename = "ticketPanel:rows:4:ticketAmount",
+        default = "default_college_ticket_num"
+    )]
+    pub college_ticket_num: String,
+}
+
+pub fn default_adult_ticket_num() -> String {
+    "1F".to_string()
+}
+
+pub fn default_child_ticket_num() -> String {
+    "0H".to_string()
+}
+
+pub fn default_disabled_ticket_num() -> String {
+    "0W".to_string()
+}
+
+pub fn default_elder_ticket_num() -> String {
+    "0E".to_string()
+}
+
+pub fn default_college_ticket_num() -> String {
+    "0P".to_string()
+}
+
+impl Default for BookingPayload {
+    fn default() -> Self {
+        BookingPayload {
+            // MODIFIED: Default start station to Taipei
+            start_station: Station::Taipei,
+            // MODIFIED: Default destination station to Zuoying
+            dest_station: Station::Zuoying,
+            search_by: "1".to_string(),
+            types_of_trip: 0,
+            // NOTE: This date is a temporary placeholder before scraping the real end_date from the booking page
+            outbound_date: "2023/10/01".to_string(),
+            outbound_time: TimeSlot::default(),
+            security_code: "1234".into(),
+            seat_prefer: 0,
+            form_mark: "".to_string(),
+            class_type: 0,
+            inbound_date: None,
+            inbound_time: None,
+            to_train_id: None,
+            back_train_id: None,
+            adult_ticket_num: default_adult_ticket_num(),
+            child_ticket_num: default_child_ticket_num(),
+            disabled_ticket_num: default_disabled_ticket_num(),
+            elder_ticket_num: default_elder_ticket_num(),
+            college_ticket_num: default_college_ticket_num(),
+        }
+    }
+}
+
+impl BookingPayload {
+    /// The urlencoded search form, with the search method `search_by` and the type of
+    /// trip `types_of_trip` of the booking page it is posted from.
+    pub fn form(&self, search_by: &str, types_of_trip: u8) -> String {
+        let payload = BookingPayload {
+            search_by: search_by.to_string(),
+            types_of_trip,
+            ..self.clone()
+        };
+        serde_urlencoded::to_string(&payload).unwrap()
+    }
+
+    /// Fill in everything but the security code from `options`, prompting for what's
+    /// missing. The date is checked against the bookable `window` of the booking page;
+    /// without a window, `options.date` is taken as is.
+    pub fn select_options(
+        &mut self,
+        options: &BookingOptions,
+        window: Option<(&String, &String)>,
+        prompter: &dyn Prompter,
+    ) {
+        self.select_start_station(&options.from, &options.to, prompter);
+        self.select_dest_station(&options.to, prompter);
+
+        match window {
+            Some((start_date, end_date)) => {
+                // MODIFIED: If no date is provided via CLI, set the default to the latest possible date (end_date).
+                if options.date.is_none() {
+                    self.outbound_date = end_date.clone();
+                }
+                self.select_date(start_date, end_date, &options.date, prompter);
+            }
+            None => {
+                // The first date of a range
+                if let Some(date) = (options.date.as_deref())
+                    .and_then(parse_dates)
+                    .and_then(|dates| dates.into_iter().next())
+                {
+                    self.outbound_date = date;
+                }
+            }
+        }
+
+        self.select_time(&options.time, prompter);
+        if !options.party.is_empty() {
+            self.select_party(&options.party);
+        } else {
+            if options.adult_cnt.is_none() && options.student_cnt.is_none() {
+                self.select_ticket_num(TicketType::Adult, &None, prompter);
+            }
+            if options.adult_cnt.is_some() {
+                self.select_ticket_num(TicketType::Adult, &options.adult_cnt, prompter);
+            }
+            if options.student_cnt.is_some() {
+                self.select_ticket_num(TicketType::College, &options.student_cnt, prompter);
+            }
+        }
+        self.select_seat_prefer(&options.seat_prefer, prompter);
+        self.select_class_type(&options.class_type, prompter);
+    }
+
+    /// Pick the departure station, other than the arrival station `to` if it is given.
+    pub fn select_start_station(
+        &mut self,
+        from: &Option<Station>,
+        to: &Option<Station>,
+        prompter: &dyn Prompter,
+    ) {
+        if let Some(from) = from {
+            self.start_station = *from;
+            return;
+        }
+
+        self.start_station = select_station(
+            prompter,
+            &tr!("Please select start station", "請選擇出發站"),
+            Station::Taipei,
+            *to,
+        );
+    }
+
+    /// Pick the arrival station, other than the departure station picked before.
+    pub fn select_dest_station(&mut self, to: &Option<Station>, prompter: &dyn Prompter) {
+        if let Some(to) = to {
+            self.dest_station = *to;
+            return;
+        }
+
+        self.dest_station = select_station(
+            prompter,
+            &tr!("Please select destination station", "請選擇到達站"),
+            Station::Zuoying,
+            Some(self.start_station),
+        );
+    }
+
+    pub fn select_date(
+        &mut self,
+        start_date: &String,
+        end_date: &String,
+        date: &Option<String>,
+        prompter: &dyn Prompter,
+    ) {
+        let input = match date.clone() {
+            Some(date) => date,
+            // Defaults to the latest date
+            None => prompter.input(
+                &tr!(
+                    "Please select a date between {} and {}",
+                    "請選擇 {} 至 {} 之間的日期",
+                    start_date,
+                    end_date
+                ),
+                end_date,
+                &|answer| match normalize_date(answer) {
+                    Some(date) if date.ge(start_date) && date.le(end_date) => Ok(()),
+                    Some(_) => Err(tr!(
+                        "The date must be between {} and {}",
+                        "日期須在 {} 至 {} 之間",
+                        start_date,
+                        end_date
+                    )),
+                    None => Err(tr!(
+                        "Please enter a date like 2025/06/29",
+                        "請輸入日期，例如 2025/06/29"
+                    )),
+                },
+            ),
+        };
+
+        let input = match normalize_date(&input) {
+            Some(date) => date,
+            None => {
+                // MODIFIED: Default to end_date on format error
+                prompter.tell(Notice::Warning(&tr!(
+                    "Invalid date format, defaulting to latest date: {}",
+                    "日期格式錯誤，改用最晚可訂的日期：{}",
+                    end_date
+                )));
+                end_date.clone()
+            }
+        };
+
+        if input.is_empty() {
+            // MODIFIED: Ensure input defaults to end_date if empty
+            self.outbound_date = end_date.clone();
+            return;
+        }
+
+        if input.ge(start_date) && input.le(end_date) {
+            self.outbound_date = input;
+        } else {
+            // MODIFIED: Default to end_date on range error
+            prompter.tell(Notice::Warning(&tr!(
+                "Invalid date or outside booking range, defaulting to latest date: {}",
+                "日期錯誤或不在可訂範圍內，改用最晚可訂的日期：{}",
+                end_date
+            )));
+            self.outbound_date = end_date.to_string();
+        }
+    }
+
+    /// Pick the time to search for trains from. Trains of today that left already
+    /// in Taiwan are not offered, and a time given that has passed moves on to
+    /// the current one.
+    pub fn select_time(&mut self, time: &Option<TimeSlot>, prompter: &dyn Prompter) {
+        let now = time::taipei_now();
+        let slots = match self.outbound_date == now.format("%Y/%m/%d").to_string() {
+            true => TimeSlot::upcoming(now.time()),
+            false => &TIME_SLOTS[..],
+        };
+
+        if let Some(time) = *time {
+            if time < slots[0] {
+                prompter.tell(Notice::Warning(&tr!(
+                    "Trains from {} have left already today, searching from {} instead",
+                    "今天 {} 起的車次已發車，改查詢 {} 起的車次",
+                    time,
+                    slots[0]
+                )));
+            }
+            self.outbound_time = time.max(slots[0]);
+            return;
+        }
+
+        let times: Vec<String> = slots.iter().map(ToString::to_string).collect();
+        let default = TimeSlot::default().max(slots[0]);
+        let pick = prompter.select(
+            &tr!("Select departure time", "請選擇出發時間"),
+            &times,
+            default.index() - slots[0].index(),
+        );
+        self.outbound_time = slots.get(pick).copied().unwrap_or(default);
+    }
+
+    pub fn select_ticket_num(
+        &mut self,
+        ticket_type: TicketType,
+        val: &Option<u8>,
+        prompter: &dyn Prompter,
+    ) {
+        let mut val = match *val {
+            Some(val) => val,
+            None => prompter
+                .input(
+                    &tr!(
+                        "Please select the number (0~10) of tickets for {}",
+                        "請選擇{}的張數（0~10）",
+                        ticket_type.name()
+                    ),
+                    "1",
+                    &|answer| match answer.parse::<u8>() {
+                        Ok(count) if count <= 10 => Ok(()),
+                        _ => Err(tr!(
+                            "Please enter a number from 0 to 10",
+                            "請輸入 0 到 10 的數字"
+                        )),
+                    },
+                )
+                .parse()
+                .unwrap_or(1),
+        };
+
+        if val > 10 {
+            prompter.tell(Notice::Warning(&tr!(
+                "Invalid input, defaulting to 1.",
+                "輸入錯誤，改為 1 張。"
+            )));
+            val = 1;
+        }
+        self.set_ticket_num(ticket_type, val);
+    }
+
+    fn set_ticket_num(&mut self, ticket_type: TicketType, val: u8) {
+        let val = format!("{}{}", val, (ticket_type.clone() as u8) as char);
+        match ticket_type {
+            TicketType::Adult => self.adult_ticket_num = val,
+            TicketType::Child => self.child_ticket_num = val,
+            TicketType::Disabled => self.disabled_ticket_num = val,
+            TicketType::Elder => self.elder_ticket_num = val,
+            TicketType::College => self.college_ticket_num = val,
+        }
+    }
+
+    /// The number of tickets of every type asked for, leaving out the others.
+    pub fn ticket_counts(&self) -> Vec<(TicketType, u8)> {
+        [
+            (TicketType::Adult, &self.adult_ticket_num),
+            (TicketType::Child, &self.child_ticket_num),
+            (TicketType::Disabled, &self.disabled_ticket_num),
+            (TicketType::Elder, &self.elder_ticket_num),
+            (TicketType::College, &self.college_ticket_num),
+        ]
+        .into_iter()
+        .filter_map(|(ticket_type, value)| {
+            let count: u8 = value.trim_end_matches(char::is_alphabetic).parse().ok()?;
+            (count > 0).then_some((ticket_type, count))
+        })
+        .collect()
+    }
+
+    /// One ticket of the matching type for every passenger of `party`.
+    pub fn select_party(&mut self, party: &[Passenger]) {
+        for ticket_type in [
+            TicketType::Adult,
+            TicketType::Child,
+            TicketType::Disabled,
+            TicketType::Elder,
+            TicketType::College,
+        ] {
+            let count = party
+                .iter()
+                .filter(|passenger| passenger.kind.ticket_type() == ticket_type)
+                .count();
+            self.set_ticket_num(ticket_type, count as u8);
+        }
+    }
+
+    pub fn select_seat_prefer(&mut self, prefer: &Option<usize>, prompter: &dyn Prompter) {
+        let input = match *prefer {
+            Some(prefer) => prefer,
+            None => prompter.select(
+                &tr!("Please select seat preference", "請選擇座位偏好"),
+                &[
+                    tr!("any", "無偏好"),
+                    tr!("window", "靠窗"),
+                    tr!("aisle", "靠走道"),
+                ],
+                0,
+            ),
+        };
+
+        if input > 2 {
+            prompter.tell(Notice::Warning(&tr!(
+                "Invalid input, defaulting to any.",
+                "輸入錯誤，改為無偏好。"
+            )));
+            self.seat_prefer = 0;
+        } else {
+            self.seat_prefer = input;
+        }
+    }
+
+    pub fn select_class_type(&mut self, class_type: &Option<usize>, prompter: &dyn Prompter) {
+        let input = match *class_type {
+            Some(class_type) => class_type,
+            None => prompter.select(
+                &tr!("Please select class type", "請選擇車廂種類"),
+                &[tr!("standard", "標準車廂"), tr!("business", "商務車廂")],
+                0,
+            ),
+        };
+
+        if input > 1 {
+            prompter.tell(Notice::Warning(&tr!(
+                "Invalid input, defaulting to standard.",
+                "輸入錯誤，改為標準車廂。"
+            )));
+            self.class_type = 0;
+        } else {
+            self.class_type = input as u8;
+        }
+    }
+}
+
+/// Pick a station by its English or Chinese name. The station `other` is at the
+/// other end of the trip, and asked for again if picked.
+fn select_station(
+    prompter: &dyn Prompter,
+    hint: &str,
+    default: Station,
+    other: Option<Station>,
+) -> Station {
+    let stations: Vec<String> = STATIONS.iter().map(ToString::to_string).collect();
+    let default = match other == Some(default) {
+        true => Station::from_index((default.index() + 1) % stations.len()).unwrap_or(default),
+        false => default,
+    };
+    loop {
+        let pick = prompter.search(hint, &stations, default.index());
+        let pick = Station::from_index(pick).unwrap_or(default);
+        if other != Some(pick) {
+            return pick;
+        }
+        prompter.tell(Notice::Warning(&tr!(
+            "The departure and arrival stations must differ, {} is the other one",
+            "出發站與到達站不可相同，{} 已是另一端的車站",
+            stations[pick.index()]
+        )));
+    }
+}
+
+pub fn normalize_date(input: &str) -> Option<String> {
+    let parts: Vec<&str> = input.split('/').collect();
+    if parts.len() != 3 {
+        return None;
+    }
+
+    let year = parts[0].parse::<u16>().ok()?;
+    let month = parts[1].parse::<u8>().ok()?;
+    let day = parts[2].parse::<u8>().ok()?;
+
+    if year >= 1000 && (1..=12).contains(&month) && (1..=31).contains(&day) {
+        Some(format!("{:04}/{:02}/{:02}", year, month, day))
+    } else {
+        None
+    }
+}
+
+/// The dates of `input`, a date or a range of them like `2025/06/01..2025/06/03`,
+/// normalized like [`normalize_date`]. A range ending before it starts has none.
+pub fn parse_dates(input: &str) -> Option<Vec<String>> {
+    let (first, last) = input.split_once("..").unwrap_or((input, input));
+    let parse = |date: &str| {
+        (normalize_date(date.trim()))
+            .and_then(|date| NaiveDate::parse_from_str(&date, "%Y/%m/%d").ok())
+    };
+    let (first, last) = (parse(first)?, parse(last)?);
+    let dates = (first.iter_days())
+        .take_while(|day| *day <= last)
+        .map(|day| day.format("%Y/%m/%d").to_string())
+        .collect();
+    Some(dates)
+}
@@ -0,0 +1,362 @@
+//! The last page of a booking: the ticket form, with the personal ID, membership and
+//! passengers.
+
+use scraper::Html;
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use std::collections::HashMap;
+
+use crate::booking::BookingOptions;
+use crate::error::Error;
+use crate::i18n::tr;
+use crate::passenger::{self, Passenger};
+use crate::prompt::{Notice, Prompter};
+use crate::redact;
+use crate::schema::TicketType;
+use crate::selectors;
+use crate::summary;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ConfirmTicketPayload {
+    #[serde(rename(serialize = "dummyId"), serialize_with = "redact::expose")]
+    pub personal_id: SecretString,
+
+    #[serde(rename(serialize = "dummyPhone"))]
+    pub phone_num: String,
+
+    #[serde(rename(
+        serialize = "TicketMemberSystemInputPanel:TakerMemberSystemDataView:memberSystemRadioGroup"
+    ))]
+    pub member_radio: String, // 非高鐵會員, 企業會員 / 高鐵會員 / 企業會員統編
+
+    #[serde(rename(serialize = "BookingS3FormSP:hf:0"), default)]
+    form_mark: String,
+
+    #[serde(rename(serialize = "idInputRadio"), default)]
+    id_input_radio: u8, // 0: 身份證字號 / 1: 護照號碼
+
+    #[serde(rename(serialize = "diffOver"), default = "default_diff_over")]
+    diff_over: u8,
+
+    #[serde(rename(serialize = "email"), default)]
+    pub email: String,
+
+    #[serde(rename(serialize = "agree"), default = "default_agree")]
+    agree: String,
+
+    #[serde(rename(serialize = "isGoBackM"), default)]
+    go_back_m: String,
+
+    #[serde(rename(serialize = "backHome"), default)]
+    back_home: String,
+
+    #[serde(rename(serialize = "TgoError"), default)]
+    tgo_error: u8,
+}
+
+fn default_diff_over() -> u8 {
+    1
+}
+
+fn default_agree() -> String {
+    "on".to_string()
+}
+
+impl Default for ConfirmTicketPayload {
+    fn default() -> Self {
+        ConfirmTicketPayload {
+            personal_id: SecretString::default(), // The actual ID is loaded from args.personal_id
+            phone_num: "".to_string(),
+            member_radio: "0".to_string(),
+            form_mark: "".to_string(),
+            id_input_radio: 0,
+            diff_over: default_diff_over(),
+            email: "".to_string(),
+            agree: default_agree(),
+            go_back_m: "".to_string(),
+            back_home: "".to_string(),
+            tgo_error: 1,
+        }
+    }
+}
+
+impl ConfirmTicketPayload {
+    // MODIFIED: Simplified to use the default/CLI argument directly, skipping interactive prompt.
+    pub fn input_personal_id(
+        &mut self,
+        personal_id: &Option<String>,
+        prompter: &dyn Prompter,
+    ) -> SecretString {
+        let id_to_use = personal_id.as_ref().cloned().unwrap_or_else(|| {
+            // This is the emergency fallback if the CLI default failed.
+            prompter.tell(Notice::Warning(&tr!(
+                "Personal ID was empty, using placeholder default A123456789.",
+                "未提供身分證字號，改用預設的 A123456789。"
+            )));
+            "A123456789".to_string()
+        });
+
+        // Only the kind of ID, an ID from a profile or preset isn't masked in the log
+        info!("Using a personal ID");
+        self.personal_id = id_to_use.trim().into();
+        self.personal_id.clone()
+    }
+
+    /// Book with the passport `number` instead of a Taiwan ID.
+    pub fn input_passport(&mut self, number: &str) -> SecretString {
+        info!("Using a passport");
+        self.personal_id = number.into();
+        self.id_input_radio = 1;
+        self.personal_id.clone()
+    }
+}
+
+/// The urlencoded ticket form of the ticket page `page`, filled in from `options`:
+/// the ID or passport of the passenger booking, their contact details, membership
+/// and the IDs of the passengers of the rows, asking `prompter` for those missing.
+pub fn ticket_form(
+    page: &Html,
+    options: &BookingOptions,
+    prompter: &dyn Prompter,
+) -> Result<String, Error> {
+    let mut payload = ConfirmTicketPayload::default();
+
+    // Input personal ID, or passport number
+    let personal_id = match &options.passport {
+        Some(number) => payload.input_passport(number),
+        None => payload.input_personal_id(&options.personal_id, prompter),
+    };
+
+    // Contact details of the passenger booking
+    payload.phone_num = options.phone.clone().unwrap_or_default();
+    payload.email = options.email.clone().unwrap_or_default();
+
+    // Parse membership radio
+    let (radio_value, add_payload) = process_membership(
+        page,
+        personal_id.expose_secret(),
+        &Some(options.uses_membership()),
+    );
+    payload.member_radio = radio_value;
+
+    // IDs of the passengers of discounted tickets
+    let mut payload = serde_urlencoded::to_string(&payload).unwrap();
+    if let Some(additional_payload) = process_passenger_info(
+        page,
+        personal_id.expose_secret(),
+        &options.party,
+        &options.passenger_ids,
+        prompter,
+    ) {
+        if options.review_passengers {
+            prompter.tell(Notice::Line(&format!(
+                "{}\n{}",
+                tr!("-------(Passengers)-------", "-------（乘客資料）-------"),
+                passenger_lines(page, &additional_payload).join("\n")
+            )));
+            if !prompter.confirm(&tr!("Submit these passengers?", "確定送出這些乘客資料？"))
+            {
+                return Err(Error::Cancelled);
+            }
+        }
+        let fields: Vec<(&String, &str)> = (additional_payload.iter())
+            .map(|(name, value)| (name, value.expose_secret()))
+            .collect();
+        let additional_payload = serde_urlencoded::to_string(&fields).unwrap();
+        payload = format!("{}&{}", payload, additional_payload);
+    }
+
+    if let Some(add_payload) = add_payload {
+        payload = format!("{}&{}", payload, add_payload.expose_secret());
+    }
+    Ok(payload)
+}
+
+/// The total price shown on the ticket form, e.g. `TWD 2,980`.
+pub fn parse_total_price(page: &Html) -> Option<String> {
+    let text: String = page
+        .select(&selectors::TOTAL_PRICE)
+        .next()?
+        .text()
+        .collect();
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+pub fn process_membership(
+    page: &Html,
+    membership_id: &str,
+    to_use_membership: &Option<bool>,
+) -> (String, Option<SecretString>) {
+    // MODIFIED: Now defaults to 'true' if the value is missing from the command line,
+    // relying on the cli.rs default and eliminating interactive prompt need.
+    let use_membership = match to_use_membership {
+        Some(v) => *v,
+        None => true, // Default to true if no flag is provided (cleanup change)
+    };
+
+    let membership_selector = match use_membership {
+        true => &selectors::MEMBER_RADIO,
+        false => &selectors::NON_MEMBER_RADIO,
+    };
+    let elem = page.select(membership_selector).next().unwrap();
+    let membership_radio = elem.attr("value").unwrap();
+
+    if use_membership {
+        let payload = vec![
+            (
+                "TicketMemberSystemInputPanel:TakerMemberSystemDataView:memberSystemRadioGroup:memberShipNumber",
+                membership_id.to_string(),
+            ),
+            (
+                "TicketMemberSystemInputPanel:TakerMemberSystemDataView:memberSystemRadioGroup:memberSystemShipCheckBox",
+                "on".to_string(),
+            ),
+        ];
+        let encoded_payload = serde_urlencoded::to_string(&payload).unwrap();
+        return (membership_radio.to_string(), Some(encoded_payload.into()));
+    }
+
+    (membership_radio.to_string(), None)
+}
+
+/// The field `name` of the row `row` of the passenger form.
+fn passenger_field(row: usize, name: &str) -> String {
+    format!("TicketPassengerInfoInputPanel:passengerDataView:{row}:passengerDataView2:{name}")
+}
+
+/// The rows of the passenger form with the ticket type code of each, one for every
+/// ticket the booking site wants the ID of, e.g. early bird, elder or disabled ones.
+pub fn parse_passenger_rows(page: &Html) -> Vec<(usize, String)> {
+    page.select(&selectors::PASSENGER_TYPES)
+        .filter_map(|input| {
+            let row = input.attr("name")?.split(':').nth(2)?.parse().ok()?;
+            Some((row, input.attr("value").unwrap_or_default().to_string()))
+        })
+        .collect()
+}
+
+/// The IDs of the passengers the ticket form asks for, whatever their ticket types.
+/// Every row takes the next passenger of `party` with its ticket type, with their
+/// names, or else the next of `ids`. The IDs are asked for otherwise, the first one
+/// defaulting to `personal_id`. Those not shaped like a Taiwan ID are passed as
+/// passport numbers.
+pub fn process_passenger_info(
+    page: &Html,
+    personal_id: &str,
+    party: &[Passenger],
+    ids: &[String],
+    prompter: &dyn Prompter,
+) -> Option<HashMap<String, SecretString>> {
+    let rows = parse_passenger_rows(page);
+    if rows.is_empty() {
+        return None;
+    }
+
+    let mut unused: Vec<&Passenger> = party.iter().collect();
+    let mut ids = ids.iter();
+    let mut fields = HashMap::new();
+    for (idx, (row, type_code)) in rows.iter().enumerate() {
+        let ticket_type = TicketType::from_code(type_code);
+        let matching =
+            (unused.iter()).position(|passenger| Some(passenger.kind.ticket_type()) == ticket_type);
+        let profile = matching.map(|pos| unused.remove(pos));
+        let given = (profile.map(|passenger| passenger.id.expose_secret().to_string()))
+            .or_else(|| ids.next().cloned());
+        let id = given.unwrap_or_else(|| {
+            let passenger = match &ticket_type {
+                Some(ticket_type) => format!("{} ({})", idx + 1, ticket_type.name()),
+                None => (idx + 1).to_string(),
+            };
+            let default = if idx == 0 { personal_id } else { "" };
+            prompter.secret(
+                &tr!(
+                    "ID number of passenger {}\n(ID change is not allowed after input!)",
+                    "乘客 {} 的身分證字號\n（輸入後無法更改！）",
+                    passenger
+                ),
+                default,
+                &check_id,
+            )
+        });
+        let id = id.trim();
+
+        let (last_name, first_name) = match profile {
+            Some(passenger) => (passenger.last_name.clone(), passenger.first_name.clone()),
+            None => (None, None),
+        };
+        fields.insert(
+            passenger_field(*row, "passengerDataLastName"),
+            last_name.unwrap_or_default().into(),
+        );
+        fields.insert(
+            passenger_field(*row, "passengerDataFirstName"),
+            first_name.unwrap_or_default().into(),
+        );
+        fields.insert(
+            passenger_field(*row, "passengerDataTypeName"),
+            type_code.as_str().into(),
+        );
+        fields.insert(passenger_field(*row, "passengerDataIdNumber"), id.into());
+        fields.insert(
+            passenger_field(*row, "passengerDataInputChoice"),
+            id_input_choice(id).into(),
+        );
+    }
+    Some(fields)
+}
+
+/// The passengers as filled in for the rows of the ticket form `page`, one line
+/// each, e.g. `1. elders: 林 美玲, A1******89`, the IDs masked.
+pub fn passenger_lines(page: &Html, fields: &HashMap<String, SecretString>) -> Vec<String> {
+    (parse_passenger_rows(page).into_iter())
+        .enumerate()
+        .filter_map(|(idx, (row, _))| {
+            let field = |name: &str| {
+                (fields.get(&passenger_field(row, name)))
+                    .map(|value| value.expose_secret().trim().to_string())
+            };
+            let type_code = field("passengerDataTypeName")?;
+            let ticket_type = (TicketType::from_code(&type_code))
+                .map_or(type_code, |ticket_type| ticket_type.name());
+            let name = [
+                field("passengerDataLastName"),
+                field("passengerDataFirstName"),
+            ]
+            .into_iter()
+            .flatten()
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+            let name = match name.is_empty() {
+                true => tr!("(no name)", "（未填姓名）"),
+                false => name,
+            };
+            let id = field("passengerDataIdNumber").unwrap_or_default();
+            Some(format!(
+                "{}. {}: {}, {}",
+                idx + 1,
+                ticket_type,
+                name,
+                summary::mask(&id)
+            ))
+        })
+        .collect()
+}
+
+fn check_id(id: &str) -> Result<(), String> {
+    match id.trim() {
+        "" => Err(tr!("ID should not be empty!", "身分證字號不可空白！")),
+        _ => Ok(()),
+    }
+}
+
+/// How the passenger form takes `id`: 0 for a Taiwan ID, 1 for a passport number.
+fn id_input_choice(id: &str) -> &'static str {
+    match passenger::is_national_id(id) {
+        true => "0",
+        false => "1",
+    }
+}
@@ -0,0 +1,89 @@
+//! The second page of a booking: the trains found by the search, and picking one.
+
+use scraper::Html;
+use serde::{Deserialize, Serialize};
+
+use crate::alert::Alert;
+use crate::booking_flow::BookingPayload;
+use crate::error::Error;
+use crate::fare;
+use crate::i18n::tr;
+use crate::prompt::{Notice, Prompter};
+use crate::selectors;
+
+pub use crate::page::{Seats, Train, parse_discount, parse_seats, parse_trains};
+
+/// The alerts listed above the trains, one per item of the list.
+pub fn parse_alerts(document: &Html) -> Vec<Alert> {
+    document
+        .select(&selectors::ALERTS)
+        .map(|tag| Alert::new(tag.text().collect::<String>().trim()))
+        .collect()
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ConfirmTrainPayload {
+    #[serde(rename(serialize = "TrainQueryDataViewPanel:TrainGroup"), default)]
+    pub selected_train: String,
+
+    #[serde(rename(serialize = "BookingS2Form:hf:0"), default)]
+    pub form_mark: String,
+}
+
+impl Default for ConfirmTrainPayload {
+    fn default() -> Self {
+        ConfirmTrainPayload {
+            selected_train: "".to_string(),
+            form_mark: "".to_string(),
+        }
+    }
+}
+
+impl ConfirmTrainPayload {
+    pub fn select_available_trains(
+        &mut self,
+        trains: &[Train],
+        train: Option<u32>,
+        search: &BookingPayload,
+        prompter: &dyn Prompter,
+    ) -> Result<u32, Error> {
+        if let Some(train) = train {
+            let selected = trains
+                .iter()
+                .find(|t| t.id() == train)
+                .ok_or(Error::TrainUnavailable(train))?;
+            prompter.tell(Notice::Status(&tr!(
+                "Selected train {:>4} {}~{}",
+                "已選擇車次 {:>4} {}~{}",
+                selected.id(),
+                selected.depart().format("%H:%M"),
+                selected.arrive().format("%H:%M")
+            )));
+            self.selected_train = selected.form_value().to_string();
+            return Ok(selected.id());
+        }
+
+        let items: Vec<String> = (trains.iter())
+            .map(|train| {
+                let mut item = format!(
+                    "{:>4} {}~{} {:>3} {}",
+                    train.id(),
+                    train.depart().format("%H:%M"),
+                    train.arrive().format("%H:%M"),
+                    train.travel_time(),
+                    train.discount_info()
+                );
+                if let Some(hint) = train.seats().hint() {
+                    item = format!("{} [{}]", item.trim_end(), hint);
+                }
+                if let Some(fares) = fare::estimate(search, train) {
+                    item = format!("{} | {}", item.trim_end(), fares);
+                }
+                item
+            })
+            .collect();
+        let selection = prompter.select(&tr!("Select a train", "請選擇車次"), &items, 0);
+        self.selected_train = trains[selection].form_value().to_string();
+        Ok(trains[selection].id())
+    }
+}
@@ -0,0 +1,196 @@
+use std::fmt;
+use std::time::Duration;
+
+/// Why a booking step failed.
+///
+/// Messages shown by the booking site are classified so callers can tell a
+/// retryable problem (wrong security code, sold out) from a hopeless one.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// No seats left for the requested trains
+    SoldOut(String),
+    WrongSecurityCode(String),
+    /// The personal ID or passport number was refused
+    InvalidId(String),
+    /// More tickets requested than allowed in a single booking
+    TooManyTickets(String),
+    /// The booking system is down for maintenance
+    Maintenance(String),
+    /// The site turns the requests away as too many, answering 429 or 503, with the
+    /// time it asked to wait if it did
+    Throttled(Option<Duration>),
+    /// Any other message shown by the site
+    Rejected(String),
+    /// The session has been idle for too long
+    SessionExpired,
+    /// The requested train is not in the train list
+    TrainUnavailable(u32),
+    /// The request could not be completed, even after retrying
+    Network(String),
+    /// The page didn't look like expected
+    UnexpectedPage(String),
+    /// Stopped before confirming the tickets, as asked with `--dry-run`
+    DryRun,
+    /// The summary shown before confirming the tickets was turned down
+    Cancelled,
+    /// The script picking the train failed
+    Script(String),
+}
+
+/// English explanations of the messages the Chinese booking site shows the most, with
+/// what to do about them, by a part of the message. The first match wins.
+const EXPLANATIONS: [(&str, &str); 12] = [
+    (
+        "檢測碼",
+        "The security code was entered wrong. thsr fetches a new code and asks again.",
+    ),
+    (
+        "早鳥",
+        "The early bird tickets of this train are gone. Pick another train, or book it at the full fare.",
+    ),
+    (
+        "售完",
+        "No seats are left on the trains of the search. Try another time or date, or wait for returned tickets with thsr watch.",
+    ),
+    (
+        "查無可售車次",
+        "No seats are left on the trains of the search. Try another time or date, or wait for returned tickets with thsr watch.",
+    ),
+    (
+        "開放預訂",
+        "The date isn't open for booking yet. Bookings open 28 days ahead, thsr schedule books as soon as they do.",
+    ),
+    (
+        "會員",
+        "The TGo membership was refused. Check that the ID is a member's, or book without it using -m false.",
+    ),
+    (
+        "身分證字號",
+        "The personal ID was refused. Check it, visitors give their passport number with --passport.",
+    ),
+    (
+        "護照號碼",
+        "The passport number was refused. Check it against the passport.",
+    ),
+    (
+        "張數",
+        "Too many tickets for one booking, which holds at most 10. Larger groups are split into several bookings by thsr.",
+    ),
+    (
+        "10張",
+        "Too many tickets for one booking, which holds at most 10. Larger groups are split into several bookings by thsr.",
+    ),
+    (
+        "維護",
+        "The booking system is down for maintenance. Try again once it is over.",
+    ),
+    (
+        "忙碌",
+        "The booking system is overloaded. Try again in a few minutes.",
+    ),
+];
+
+impl Error {
+    /// Classify the feedback `message` shown by the booking site.
+    pub fn from_feedback(message: String) -> Error {
+        // The English site words them in English, in any case
+        let lowercase = message.to_lowercase();
+        let has = |markers: &[&str]| markers.iter().any(|marker| lowercase.contains(marker));
+
+        if has(&["檢測碼", "security code", "verification code"]) {
+            Error::WrongSecurityCode(message)
+        } else if has(&[
+            "售完",
+            "已無座位",
+            "無可售",
+            "座位已滿",
+            "sold out",
+            "no seats",
+        ]) {
+            Error::SoldOut(message)
+        } else if has(&[
+            "身分證字號",
+            "身份證字號",
+            "證件號碼",
+            "護照號碼",
+            "id number",
+            "passport number",
+        ]) {
+            Error::InvalidId(message)
+        } else if has(&[
+            "張數",
+            "超過10張",
+            "最多10張",
+            "number of tickets",
+            "10 tickets",
+        ]) {
+            Error::TooManyTickets(message)
+        } else if has(&["維護", "暫停服務", "系統忙碌", "maintenance", "busy"]) {
+            Error::Maintenance(message)
+        } else {
+            Error::Rejected(message)
+        }
+    }
+
+    /// The English explanation of the message shown by the booking site, with what to
+    /// do about it, when the message is a known one.
+    pub fn explanation(&self) -> Option<&'static str> {
+        let (Error::SoldOut(message)
+        | Error::WrongSecurityCode(message)
+        | Error::InvalidId(message)
+        | Error::TooManyTickets(message)
+        | Error::Maintenance(message)
+        | Error::Rejected(message)) = self
+        else {
+            return None;
+        };
+        (EXPLANATIONS.iter())
+            .find(|(marker, _)| message.contains(marker))
+            .map(|(_, explanation)| *explanation)
+    }
+
+    /// Whether trying again later may succeed, as opposed to a problem with the options.
+    pub fn is_temporary(&self) -> bool {
+        matches!(
+            self,
+            Error::SoldOut(_)
+                | Error::Maintenance(_)
+                | Error::Throttled(_)
+                | Error::WrongSecurityCode(_)
+                | Error::SessionExpired
+                | Error::Network(_)
+                | Error::TrainUnavailable(_)
+        )
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::SoldOut(message)
+            | Error::WrongSecurityCode(message)
+            | Error::InvalidId(message)
+            | Error::TooManyTickets(message)
+            | Error::Maintenance(message)
+            | Error::Rejected(message)
+            | Error::Script(message) => write!(f, "{}", message),
+            Error::Throttled(None) => write!(
+                f,
+                "The booking site turns the requests away, too many were sent. Wait a while before trying again"
+            ),
+            Error::Throttled(Some(wait)) => write!(
+                f,
+                "The booking site turns the requests away, too many were sent. It asks to wait {}",
+                humantime::format_duration(*wait)
+            ),
+            Error::SessionExpired => write!(f, "The booking session has expired"),
+            Error::TrainUnavailable(train) => write!(f, "Train {} is not available", train),
+            Error::Network(err_msg) => write!(f, "{}", err_msg),
+            Error::UnexpectedPage(err_msg) => write!(f, "Unexpected page: {}", err_msg),
+            Error::DryRun => write!(f, "Dry run, nothing was booked"),
+            Error::Cancelled => write!(f, "Cancelled, nothing was booked"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
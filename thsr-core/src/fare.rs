@@ -0,0 +1,142 @@
+//! Fare estimates for the trains listed, from the standard car fares between the
+//! stations and the discounts each train offers. The booking site only tells the
+//! price once a train is picked.
+
+use strum::EnumCount;
+
+use crate::booking_flow::BookingPayload;
+use crate::confirm_train_flow::Train;
+use crate::i18n::{self, Lang};
+use crate::schema::{Station, TicketType};
+
+/// The adult fare of the standard car from Nangang to every station, in NT$. The
+/// fare between two other stations is about the difference of theirs.
+const FARES_FROM_NANGANG: [u32; Station::COUNT] =
+    [0, 40, 70, 200, 330, 480, 750, 870, 970, 1120, 1390, 1530];
+
+/// The fare of the shortest trips, e.g. Nangang to Taipei.
+const MIN_FARE: u32 = 40;
+
+/// The adult fare of the standard car between `from` and `to`, in NT$.
+pub fn adult_fare(from: Station, to: Station) -> u32 {
+    let (from, to) = (
+        FARES_FROM_NANGANG[from.index()],
+        FARES_FROM_NANGANG[to.index()],
+    );
+    from.abs_diff(to).max(MIN_FARE)
+}
+
+/// The discounts a train offers, as the percent of the fare paid.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Discounts {
+    pub early_bird: Option<u32>,
+    pub student: Option<u32>,
+}
+
+impl Discounts {
+    /// The discounts of `info`, e.g. `(早鳥65折, 大學生5折)` or `(Early Bird 35% off)`.
+    pub fn parse(info: &str) -> Discounts {
+        let mut discounts = Discounts::default();
+        let info = info.trim_matches(|c| c == '(' || c == ')');
+        for discount in info.split(',').map(str::trim) {
+            let Some(paid) = paid_percent(discount) else {
+                continue;
+            };
+            let lower = discount.to_lowercase();
+            if discount.contains("早鳥") || lower.contains("early") {
+                discounts.early_bird = Some(paid);
+            } else if discount.contains("學生") || lower.contains("student") {
+                discounts.student = Some(paid);
+            }
+        }
+        discounts
+    }
+}
+
+/// The percent paid of a discount written `65折` or `35% off`.
+fn paid_percent(discount: &str) -> Option<u32> {
+    let number = |text: &str| -> Option<u32> {
+        text.trim_start_matches(|c: char| !c.is_ascii_digit())
+            .parse()
+            .ok()
+    };
+    if let Some((text, _)) = discount.split_once('折') {
+        // 9折 is 90%, 65折 is 65%
+        return number(text).map(|paid| if paid < 10 { paid * 10 } else { paid });
+    }
+    let (text, _) = discount.split_once('%')?;
+    number(text).map(|off| 100 - off.min(100))
+}
+
+/// A discount the way the booking site writes it in the language in use.
+fn label(paid: u32) -> String {
+    match i18n::lang() {
+        Lang::En => format!("{}% off", 100 - paid),
+        Lang::Zh if paid.is_multiple_of(10) => format!("{}折", paid / 10),
+        Lang::Zh => format!("{}折", paid),
+    }
+}
+
+/// `fare` at `paid` percent, rounded to NT$5 like the fares of the booking site.
+fn discounted(fare: u32, paid: u32) -> u32 {
+    (fare * paid + 250) / 500 * 5
+}
+
+/// What one `ticket_type` ticket costs on a train with `discounts`, with the percent
+/// paid when a discount of the train applies.
+pub fn ticket_fare(
+    fare: u32,
+    ticket_type: &TicketType,
+    discounts: &Discounts,
+) -> (u32, Option<u32>) {
+    let paid = match ticket_type {
+        TicketType::Adult => discounts.early_bird,
+        TicketType::College => discounts.student,
+        TicketType::Child | TicketType::Disabled | TicketType::Elder => {
+            return (discounted(fare, 50), None);
+        }
+    };
+    match paid {
+        Some(paid) => (discounted(fare, paid), Some(paid)),
+        None => (fare, None),
+    }
+}
+
+/// An amount like the booking site writes it, e.g. `NT$1,490`.
+pub fn ntd(amount: u32) -> String {
+    let digits = amount.to_string();
+    let mut grouped = String::new();
+    for (idx, digit) in digits.chars().enumerate() {
+        if idx > 0 && (digits.len() - idx).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    format!("NT${}", grouped)
+}
+
+/// The fare of every ticket type searched for on `train`, e.g.
+/// `adults NT$1,330 → NT$865 (65折)`. Business class fares aren't known, so
+/// nothing is estimated for them.
+pub fn estimate(payload: &BookingPayload, train: &Train) -> Option<String> {
+    if payload.class_type != 0 {
+        return None;
+    }
+    let fare = adult_fare(payload.start_station, payload.dest_station);
+    let discounts = Discounts::parse(train.discount_info());
+    let parts: Vec<String> = (payload.ticket_counts().iter())
+        .map(
+            |(ticket_type, _)| match ticket_fare(fare, ticket_type, &discounts) {
+                (price, Some(paid)) => format!(
+                    "{} {} → {} ({})",
+                    ticket_type.name(),
+                    ntd(fare),
+                    ntd(price),
+                    label(paid)
+                ),
+                (price, None) => format!("{} {}", ticket_type.name(), ntd(price)),
+            },
+        )
+        .collect();
+    (!parts.is_empty()).then(|| parts.join(", "))
+}
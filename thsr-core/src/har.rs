@@ -0,0 +1,452 @@
+//! Capture of the HTTP traffic in the HAR 1.2 format, which browsers' network tools
+//! can open. Meant for finding out why the site blocks requests, so it keeps every
+//! header, redirect and timing, with cookies and personal data redacted.
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use chrono::{DateTime, SecondsFormat, Utc};
+use reqwest::blocking::Request as HttpRequest;
+use reqwest::header::{CONTENT_TYPE, COOKIE, HeaderMap, SET_COOKIE};
+use reqwest::redirect::Policy;
+use reqwest::{Method, StatusCode, Url, Version};
+use serde::Serialize;
+
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::redact::{Redactor, token};
+
+/// Redirects followed before giving up, as many as the client follows without capture.
+const MAX_REDIRECTS: usize = 20;
+
+#[derive(Serialize)]
+struct Har<'a> {
+    log: Log<'a>,
+}
+
+#[derive(Serialize)]
+struct Log<'a> {
+    version: &'static str,
+    creator: Creator,
+    entries: &'a [Entry],
+}
+
+#[derive(Serialize)]
+struct Creator {
+    name: &'static str,
+    version: &'static str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Entry {
+    started_date_time: String,
+    /// Total time in milliseconds
+    time: f64,
+    request: Request,
+    response: Response,
+    cache: Cache,
+    timings: Timings,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comment: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Request {
+    method: String,
+    url: String,
+    http_version: String,
+    cookies: Vec<NameValue>,
+    headers: Vec<NameValue>,
+    query_string: Vec<NameValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    post_data: Option<PostData>,
+    headers_size: i64,
+    body_size: i64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Response {
+    status: u16,
+    status_text: String,
+    http_version: String,
+    cookies: Vec<NameValue>,
+    headers: Vec<NameValue>,
+    content: Content,
+    #[serde(rename = "redirectURL")]
+    redirect_url: String,
+    headers_size: i64,
+    body_size: i64,
+}
+
+#[derive(Serialize, Clone)]
+struct NameValue {
+    name: String,
+    value: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PostData {
+    mime_type: String,
+    text: String,
+}
+
+#[derive(Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct Content {
+    size: i64,
+    mime_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    encoding: Option<&'static str>,
+}
+
+#[derive(Serialize)]
+struct Cache {}
+
+#[derive(Serialize)]
+struct Timings {
+    send: f64,
+    wait: f64,
+    receive: f64,
+}
+
+/// A redirect the client followed on its own.
+struct Hop {
+    status: StatusCode,
+    from: Url,
+    to: Url,
+}
+
+/// A request on its way, as far as it is known before the answer.
+pub(crate) struct Sent {
+    started: DateTime<Utc>,
+    method: Method,
+    url: Url,
+    headers: Vec<NameValue>,
+    body: Option<String>,
+}
+
+/// The answer to a [`Sent`] request, after following any redirects.
+pub(crate) struct Received<'a> {
+    pub status: StatusCode,
+    pub version: Version,
+    pub url: &'a Url,
+    pub headers: &'a HeaderMap,
+    pub body: &'a [u8],
+    /// Until the headers arrived
+    pub wait: Duration,
+    /// Reading the body
+    pub receive: Duration,
+}
+
+/// The exchanges of a session, collected by [`HttpClient`](crate::http::HttpClient).
+#[derive(Default)]
+pub struct Capture {
+    hops: Arc<Mutex<Vec<Hop>>>,
+    redactor: Mutex<Redactor>,
+    entries: Mutex<Vec<Entry>>,
+}
+
+impl Capture {
+    /// Keep `secret` out of the capture, besides cookies and national IDs.
+    pub fn redact(&self, secret: &str) {
+        self.redactor.lock().unwrap().add(secret);
+    }
+
+    /// Write the exchanges captured so far to `path`.
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let entries = self.entries.lock().unwrap();
+        let har = Har {
+            log: Log {
+                version: "1.2",
+                creator: Creator {
+                    name: "thsr",
+                    version: env!("CARGO_PKG_VERSION"),
+                },
+                entries: &entries,
+            },
+        };
+        let content = serde_json::to_string_pretty(&har).unwrap();
+        fs::write(path, content)
+            .map_err(|err| format!("Failed to write HAR file {}: {}", path.display(), err))
+    }
+
+    /// Follows redirects like the client normally does, noting down every hop.
+    pub(crate) fn redirect_policy(&self) -> Policy {
+        let hops = Arc::clone(&self.hops);
+        Policy::custom(move |attempt| {
+            if attempt.previous().len() > MAX_REDIRECTS {
+                return attempt.error("too many redirects");
+            }
+            if let Some(from) = attempt.previous().last() {
+                let hop = Hop {
+                    status: attempt.status(),
+                    from: from.clone(),
+                    to: attempt.url().clone(),
+                };
+                hops.lock().unwrap().push(hop);
+            }
+            attempt.follow()
+        })
+    }
+
+    /// Note down `request` right before it is sent. The client adds
+    /// `default_headers` to the ones set on the request.
+    pub(crate) fn sent(&self, request: &HttpRequest, default_headers: &HeaderMap) -> Sent {
+        self.hops.lock().unwrap().clear();
+        let mut headers = default_headers.clone();
+        headers.extend(request.headers().clone());
+        Sent {
+            started: Utc::now(),
+            method: request.method().clone(),
+            url: request.url().clone(),
+            headers: self.headers(&headers),
+            body: request.body().and_then(|body| body.as_bytes()).map(|body| {
+                self.redactor
+                    .lock()
+                    .unwrap()
+                    .form(&String::from_utf8_lossy(body))
+            }),
+        }
+    }
+
+    /// Record `sent` along with the redirects it went through and the final answer.
+    pub(crate) fn received(&self, sent: Sent, received: Received) {
+        // Session cookies show up in the URLs afterwards, keep them out there too
+        for value in received.headers.get_all(SET_COOKIE) {
+            if let Some((_, value)) = value
+                .to_str()
+                .unwrap_or_default()
+                .split(';')
+                .next()
+                .and_then(|pair| pair.split_once('='))
+                && !value.is_empty()
+            {
+                self.redact(value);
+            }
+        }
+
+        let hops: Vec<Hop> = self.hops.lock().unwrap().drain(..).collect();
+        let mut method = sent.method.clone();
+        let mut request_headers = Some(sent.headers);
+        let mut body = sent.body;
+        for hop in &hops {
+            let request = self.request(&method, &hop.from, request_headers.take(), body.take());
+            let response = Response {
+                status: hop.status.as_u16(),
+                status_text: reason(hop.status),
+                http_version: String::new(),
+                cookies: Vec::new(),
+                headers: Vec::new(),
+                content: Content::default(),
+                redirect_url: self.scrub(hop.to.as_str()),
+                headers_size: -1,
+                body_size: -1,
+            };
+            self.push(
+                sent.started,
+                Duration::ZERO,
+                Duration::ZERO,
+                request,
+                response,
+            );
+            // Browsers and reqwest turn the method into GET on these
+            if matches!(hop.status.as_u16(), 301..=303) && method != Method::HEAD {
+                method = Method::GET;
+            }
+        }
+
+        let mime_type = received
+            .headers
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        let (text, encoding) = match std::str::from_utf8(received.body) {
+            Ok(text) => (self.scrub(text), None),
+            Err(_) => (STANDARD.encode(received.body), Some("base64")),
+        };
+        let request = self.request(&method, received.url, request_headers, body);
+        let response = Response {
+            status: received.status.as_u16(),
+            status_text: reason(received.status),
+            http_version: format!("{:?}", received.version),
+            cookies: self.cookies(received.headers, true),
+            headers: self.headers(received.headers),
+            content: Content {
+                size: received.body.len() as i64,
+                mime_type,
+                text: Some(text),
+                encoding,
+            },
+            redirect_url: String::new(),
+            headers_size: -1,
+            body_size: received.body.len() as i64,
+        };
+        self.push(
+            sent.started,
+            received.wait,
+            received.receive,
+            request,
+            response,
+        );
+    }
+
+    /// Record `sent` as failed without an answer, e.g. on a timeout.
+    pub(crate) fn failed(&self, sent: Sent, elapsed: Duration, err_msg: &str) {
+        self.hops.lock().unwrap().clear();
+        let request = self.request(&sent.method, &sent.url, Some(sent.headers), sent.body);
+        let response = Response {
+            status: 0,
+            status_text: String::new(),
+            http_version: String::new(),
+            cookies: Vec::new(),
+            headers: Vec::new(),
+            content: Content::default(),
+            redirect_url: String::new(),
+            headers_size: -1,
+            body_size: -1,
+        };
+        self.push(sent.started, elapsed, Duration::ZERO, request, response);
+        if let Some(entry) = self.entries.lock().unwrap().last_mut() {
+            entry.comment = Some(self.scrub(err_msg));
+        }
+    }
+
+    fn push(
+        &self,
+        started: DateTime<Utc>,
+        wait: Duration,
+        receive: Duration,
+        request: Request,
+        response: Response,
+    ) {
+        let millis = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        self.entries.lock().unwrap().push(Entry {
+            started_date_time: started.to_rfc3339_opts(SecondsFormat::Millis, true),
+            time: millis(wait + receive),
+            request,
+            response,
+            cache: Cache {},
+            timings: Timings {
+                send: 0.0,
+                wait: millis(wait),
+                receive: millis(receive),
+            },
+            comment: None,
+        });
+    }
+
+    fn request(
+        &self,
+        method: &Method,
+        url: &Url,
+        headers: Option<Vec<NameValue>>,
+        body: Option<String>,
+    ) -> Request {
+        let headers = headers.unwrap_or_default();
+        let cookies = headers
+            .iter()
+            .filter(|header| header.name.eq_ignore_ascii_case(COOKIE.as_str()))
+            .flat_map(|header| redacted_cookies(&header.value, false))
+            .collect();
+        let mime_type = headers
+            .iter()
+            .find(|header| header.name.eq_ignore_ascii_case(CONTENT_TYPE.as_str()))
+            .map(|header| header.value.clone())
+            .unwrap_or_default();
+        Request {
+            method: method.to_string(),
+            url: self.scrub(url.as_str()),
+            http_version: "HTTP/1.1".to_string(),
+            cookies,
+            headers,
+            query_string: url
+                .query_pairs()
+                .map(|(name, value)| NameValue {
+                    name: name.into_owned(),
+                    value: self.scrub(&value),
+                })
+                .collect(),
+            headers_size: -1,
+            body_size: body.as_ref().map_or(0, |body| body.len() as i64),
+            post_data: body.map(|text| PostData { mime_type, text }),
+        }
+    }
+
+    fn headers(&self, headers: &HeaderMap) -> Vec<NameValue> {
+        headers
+            .iter()
+            .map(|(name, value)| {
+                let value = value.to_str().unwrap_or_default();
+                let value = if name == COOKIE || name == SET_COOKIE {
+                    let is_set_cookie = name == SET_COOKIE;
+                    redact_cookie_header(value, is_set_cookie)
+                } else {
+                    self.scrub(value)
+                };
+                NameValue {
+                    name: name.to_string(),
+                    value,
+                }
+            })
+            .collect()
+    }
+
+    fn cookies(&self, headers: &HeaderMap, is_set_cookie: bool) -> Vec<NameValue> {
+        let name = if is_set_cookie { SET_COOKIE } else { COOKIE };
+        headers
+            .get_all(name)
+            .iter()
+            .flat_map(|value| redacted_cookies(value.to_str().unwrap_or_default(), is_set_cookie))
+            .collect()
+    }
+
+    fn scrub(&self, text: &str) -> String {
+        self.redactor.lock().unwrap().text(text)
+    }
+}
+
+/// The names of the cookies in a `Cookie` or `Set-Cookie` header, with the values redacted.
+fn redacted_cookies(header: &str, is_set_cookie: bool) -> Vec<NameValue> {
+    let pairs: Vec<&str> = if is_set_cookie {
+        header.split(';').take(1).collect()
+    } else {
+        header.split(';').collect()
+    };
+    pairs
+        .into_iter()
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(name, value)| NameValue {
+            name: name.trim().to_string(),
+            value: token(value),
+        })
+        .collect()
+}
+
+/// A `Cookie` or `Set-Cookie` header with the values redacted, but the attributes
+/// such as `Path` kept.
+fn redact_cookie_header(header: &str, is_set_cookie: bool) -> String {
+    header
+        .split(';')
+        .enumerate()
+        .map(|(idx, part)| match part.split_once('=') {
+            Some((name, value)) if idx == 0 || !is_set_cookie => {
+                format!("{}={}", name, token(value))
+            }
+            _ => part.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+fn reason(status: StatusCode) -> String {
+    status.canonical_reason().unwrap_or_default().to_string()
+}
@@ -0,0 +1,301 @@
+use bytes::Bytes;
+use reqwest::{Proxy, StatusCode, Url};
+use serde::Deserialize;
+
+use std::time::Duration;
+
+use crate::error::Error;
+
+#[cfg(any(feature = "blocking", feature = "async"))]
+use {
+    crate::page,
+    reqwest::header::{HeaderMap, RETRY_AFTER},
+    scraper::Html,
+};
+
+#[cfg(feature = "async")]
+mod async_client;
+#[cfg(feature = "blocking")]
+mod client;
+
+#[cfg(feature = "async")]
+pub use async_client::AsyncHttpClient;
+#[cfg(feature = "blocking")]
+pub use client::{HttpClient, RateLimiter};
+
+/// How failed requests are retried. Only transient failures (timeouts, connection
+/// problems and 5xx answers) are retried, anything else fails right away. Form posts
+/// are only sent again when they couldn't connect, since one that reached the site
+/// may have booked already.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetryPolicy {
+    /// Total number of tries per request, including the first one
+    pub attempts: u32,
+
+    /// Delay before the first retry, doubled for every further one
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub backoff: Duration,
+
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub max_backoff: Duration,
+
+    /// Random spread applied to every delay, as a fraction of it (0.2 = ±20%)
+    pub jitter: f64,
+
+    /// Longest wait for the answer to a request, before it counts as failed
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub timeout: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            attempts: 3,
+            backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+            jitter: 0.2,
+            timeout: Duration::from_secs(60),
+        }
+    }
+}
+
+/// How often the modes searching over and over, like `thsr watch`, may send requests,
+/// so that polling doesn't get the address blocked by the site.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimit {
+    /// Most requests in a minute, spread out evenly. 0 for no limit
+    pub per_minute: u32,
+
+    /// Least time between two requests
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub min_delay: Duration,
+
+    /// Random extra time added to every wait, as a fraction of it (0.5 = up to 50% longer)
+    pub jitter: f64,
+
+    /// Pause after the site turns the requests away or is down for maintenance,
+    /// unless it tells how long
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub cool_down: Duration,
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        RateLimit {
+            per_minute: 30,
+            min_delay: Duration::from_secs(1),
+            jitter: 0.5,
+            cool_down: Duration::from_secs(15 * 60),
+        }
+    }
+}
+
+impl RateLimit {
+    /// The time to leave between two requests.
+    #[cfg(feature = "blocking")]
+    fn interval(&self) -> Duration {
+        let spread = match self.per_minute {
+            0 => Duration::ZERO,
+            per_minute => Duration::from_secs(60) / per_minute,
+        };
+        let extra = self.jitter.clamp(0.0, 1.0) * fastrand::f64();
+        spread.max(self.min_delay).mul_f64(1.0 + extra)
+    }
+}
+
+/// Parse a proxy URL: `http://`, `https://`, `socks5://` or `socks5h://` (resolving
+/// names through the proxy), with the credentials in the URL if needed.
+pub fn parse_proxy(url: &str) -> Result<Proxy, String> {
+    let parsed = Url::parse(url).map_err(|err| format!("Invalid proxy {}: {}", url, err))?;
+    if !["http", "https", "socks5", "socks5h"].contains(&parsed.scheme()) {
+        return Err(format!(
+            "Invalid proxy {}: use http://, https://, socks5:// or socks5h://",
+            url
+        ));
+    }
+    Proxy::all(url).map_err(|err| format!("Invalid proxy {}: {}", url, err))
+}
+
+/// Deserialize a proxy URL with [`parse_proxy`], for `#[serde(deserialize_with)]`.
+pub fn deserialize_proxy<'de, D: serde::Deserializer<'de>>(
+    de: D,
+) -> Result<Option<Proxy>, D::Error> {
+    let url = String::deserialize(de)?;
+    parse_proxy(&url)
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+/// Deserialize a duration like `90s` or `5m`, for `#[serde(deserialize_with)]`.
+pub fn deserialize_duration<'de, D: serde::Deserializer<'de>>(de: D) -> Result<Duration, D::Error> {
+    let text = String::deserialize(de)?;
+    humantime::parse_duration(&text).map_err(serde::de::Error::custom)
+}
+
+impl RetryPolicy {
+    #[cfg(any(feature = "blocking", feature = "async"))]
+    fn delay(&self, retry: u32) -> Duration {
+        let base = self
+            .backoff
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+            .min(self.max_backoff);
+        let spread = self.jitter.clamp(0.0, 1.0) * (fastrand::f64() * 2.0 - 1.0);
+        base.mul_f64(1.0 + spread)
+    }
+}
+
+/// A fully read response.
+pub struct HttpResponse {
+    pub status: StatusCode,
+    pub cookies: Vec<(String, String)>,
+    pub body: Bytes,
+}
+
+impl HttpResponse {
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    pub fn cookie(&self, name: &str) -> Option<&str> {
+        self.cookies
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// The requests the booking flows make. Implemented by `HttpClient` for the
+/// real site, and by stand-ins serving canned pages in tests. Shared with a thread
+/// now and then, e.g. to download the security code while the page is parsed.
+pub trait Transport: Sync {
+    fn get(&self, url: &str) -> Result<HttpResponse, Error>;
+
+    /// Post an urlencoded form body.
+    fn post_form(&self, url: &str, body: String) -> Result<HttpResponse, Error>;
+
+    /// The value of the cookie `name` that would be sent to `url`, if the transport keeps cookies.
+    fn cookie(&self, _url: &str, _name: &str) -> Option<String> {
+        None
+    }
+}
+
+impl<T: Transport + ?Sized> Transport for &T {
+    fn get(&self, url: &str) -> Result<HttpResponse, Error> {
+        (**self).get(url)
+    }
+
+    fn post_form(&self, url: &str, body: String) -> Result<HttpResponse, Error> {
+        (**self).post_form(url, body)
+    }
+
+    fn cookie(&self, url: &str, name: &str) -> Option<String> {
+        (**self).cookie(url, name)
+    }
+}
+
+/// Why a request failed, telling whether trying again may help. The items from here
+/// on are shared by the clients.
+#[cfg(any(feature = "blocking", feature = "async"))]
+enum Failure {
+    /// Nothing reached the site, the connection couldn't be made
+    Unsent(String),
+    Transient(String),
+    Permanent(String),
+    /// Turned away by the site, which a quick retry would only make worse
+    Refused(Error),
+}
+
+#[cfg(any(feature = "blocking", feature = "async"))]
+impl Failure {
+    /// Timeouts and connection problems are worth another try.
+    fn of(err: reqwest::Error) -> Failure {
+        if err.is_connect() {
+            Failure::Unsent(err.to_string())
+        } else if err.is_timeout() || err.is_request() || err.is_body() {
+            Failure::Transient(err.to_string())
+        } else {
+            Failure::Permanent(err.to_string())
+        }
+    }
+}
+
+#[cfg(any(feature = "blocking", feature = "async"))]
+impl Failure {
+    /// The message to log before trying `url` again, or the error to fail with.
+    /// Requests that may have reached the site are only sent again if `resend`,
+    /// since a form posted twice can book twice.
+    fn retry(self, url: &str, resend: bool) -> Result<String, Error> {
+        match self {
+            Failure::Unsent(err_msg) => Ok(err_msg),
+            Failure::Transient(err_msg) if resend => Ok(err_msg),
+            Failure::Transient(err_msg) => Err(Error::Network(format!(
+                "Request to {} failed, and isn't sent again as it may have reached the site: {}",
+                url, err_msg
+            ))),
+            Failure::Permanent(err_msg) => Err(Error::Network(err_msg)),
+            Failure::Refused(err) => Err(err),
+        }
+    }
+}
+
+/// The response of `status`, failing for an error status. 5xx answers are worth
+/// another try, but for 429 and 503, telling that the site is overloaded or down for
+/// maintenance.
+#[cfg(any(feature = "blocking", feature = "async"))]
+fn checked(
+    status: StatusCode,
+    headers: &HeaderMap,
+    cookies: Vec<(String, String)>,
+    body: Bytes,
+) -> Result<HttpResponse, Failure> {
+    if status == StatusCode::SERVICE_UNAVAILABLE
+        && let Some(err) =
+            page::parse_maintenance(&Html::parse_document(&String::from_utf8_lossy(&body)))
+    {
+        return Err(Failure::Refused(err));
+    }
+    if status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::SERVICE_UNAVAILABLE {
+        // Only the seconds, the site hasn't been seen sending a date
+        let wait = (headers.get(RETRY_AFTER))
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse().ok())
+            .map(Duration::from_secs);
+        return Err(Failure::Refused(Error::Throttled(wait)));
+    }
+    if status.is_server_error() {
+        return Err(Failure::Transient(format!("server answered {}", status)));
+    }
+    if !status.is_success() {
+        return Err(Failure::Permanent(format!("server answered {}", status)));
+    }
+    Ok(HttpResponse {
+        status,
+        cookies,
+        body,
+    })
+}
+
+/// The value of the cookie `name` of `cookies` that would be sent to `url`.
+#[cfg(any(feature = "blocking", feature = "async"))]
+fn cookie(
+    cookies: &reqwest_cookie_store::CookieStoreMutex,
+    url: &str,
+    name: &str,
+) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    let store = cookies.lock().unwrap();
+    store
+        .get_request_values(&url)
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value.to_string())
+}
+
+#[cfg(any(feature = "blocking", feature = "async"))]
+fn exhausted(url: &str, attempts: u32, err_msg: &str) -> Error {
+    Error::Network(format!(
+        "Request to {} failed after {} attempt(s): {}",
+        url, attempts, err_msg
+    ))
+}
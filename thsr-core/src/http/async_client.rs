@@ -0,0 +1,109 @@
+//! An async client for programs fetching the pages of the booking site from their own
+//! runtime. It sends the same requests as [`HttpClient`](super::HttpClient) and retries
+//! them the same way, without capturing the traffic. The booking flows don't run on it,
+//! they take a blocking [`Transport`](super::Transport).
+
+use reqwest::header::{HeaderMap, HeaderValue};
+use reqwest::{Client, Proxy, RequestBuilder};
+use reqwest_cookie_store::CookieStoreMutex;
+use tracing::{debug, warn};
+
+use std::sync::Arc;
+
+use super::{Failure, HttpResponse, RetryPolicy, checked, cookie, exhausted};
+use crate::error::Error;
+
+/// Async HTTP client of the booking session, retrying transient failures.
+pub struct AsyncHttpClient {
+    client: Client,
+    retry: RetryPolicy,
+    cookies: Arc<CookieStoreMutex>,
+}
+
+impl AsyncHttpClient {
+    /// A client sending `headers` with every request, through `proxy` if given.
+    pub fn new(headers: HeaderMap, retry: RetryPolicy, proxy: Option<Proxy>) -> AsyncHttpClient {
+        let cookies = Arc::default();
+        let mut builder = Client::builder()
+            .default_headers(headers)
+            .cookie_provider(Arc::clone(&cookies))
+            .timeout(retry.timeout);
+        if let Some(proxy) = proxy {
+            builder = builder.proxy(proxy);
+        }
+        AsyncHttpClient {
+            client: builder.build().unwrap(),
+            retry,
+            cookies,
+        }
+    }
+
+    pub async fn get(&self, url: &str) -> Result<HttpResponse, Error> {
+        debug!("GET {}", url);
+        self.send(url, true, || self.client.get(url)).await
+    }
+
+    /// Post an urlencoded form body.
+    pub async fn post_form(&self, url: &str, body: String) -> Result<HttpResponse, Error> {
+        debug!("POST {}", url);
+        self.send(url, false, || {
+            self.client
+                .post(url)
+                .header(
+                    "Content-Type",
+                    HeaderValue::from_static("application/x-www-form-urlencoded"),
+                )
+                .body(body.clone())
+        })
+        .await
+    }
+
+    /// The value of the cookie `name` that would be sent to `url`.
+    pub fn cookie(&self, url: &str, name: &str) -> Option<String> {
+        cookie(&self.cookies, url, name)
+    }
+
+    /// Send `request`, sending it again after a transient failure only if `resend`.
+    async fn send(
+        &self,
+        url: &str,
+        resend: bool,
+        request: impl Fn() -> RequestBuilder,
+    ) -> Result<HttpResponse, Error> {
+        let attempts = self.retry.attempts.max(1);
+        let mut attempt = 1;
+        loop {
+            let err_msg = match try_send(request()).await {
+                Ok(resp) => return Ok(resp),
+                Err(failure) => failure.retry(url, resend)?,
+            };
+            if attempt >= attempts {
+                return Err(exhausted(url, attempt, &err_msg));
+            }
+
+            let delay = self.retry.delay(attempt);
+            warn!(
+                "Request failed ({}), retrying in {:.1}s ({}/{})",
+                err_msg,
+                delay.as_secs_f64(),
+                attempt + 1,
+                attempts
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+async fn try_send(request: RequestBuilder) -> Result<HttpResponse, Failure> {
+    let resp = request.send().await.map_err(Failure::of)?;
+    let status = resp.status();
+    let headers = resp.headers().clone();
+    let cookies = resp
+        .cookies()
+        .map(|cookie| (cookie.name().to_string(), cookie.value().to_string()))
+        .collect();
+    let body = resp.bytes().await.map_err(Failure::of)?;
+    debug!("{} ({} bytes)", status, body.len());
+    checked(status, &headers, cookies, body)
+}
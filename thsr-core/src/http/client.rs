@@ -0,0 +1,279 @@
+//! The blocking client the command line books with.
+
+use reqwest::Proxy;
+use reqwest::blocking::{Client, RequestBuilder};
+use reqwest::header::{HeaderMap, HeaderValue};
+use reqwest_cookie_store::CookieStoreMutex;
+use tracing::{debug, warn};
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::{Failure, HttpResponse, RateLimit, RetryPolicy, Transport, checked, cookie, exhausted};
+use crate::error::Error;
+use crate::har::{Capture, Received};
+
+fn connect(
+    headers: &HeaderMap,
+    timeout: Duration,
+    proxy: Option<&Proxy>,
+    har: Option<&Capture>,
+    cookies: &Arc<CookieStoreMutex>,
+) -> Client {
+    let policy = match har {
+        Some(har) => har.redirect_policy(),
+        None => reqwest::redirect::Policy::limited(20),
+    };
+    let mut builder = Client::builder()
+        .redirect(policy)
+        .default_headers(headers.clone())
+        .cookie_provider(Arc::clone(cookies))
+        .timeout(timeout);
+    if let Some(proxy) = proxy {
+        builder = builder.proxy(proxy.clone());
+    }
+    builder.build().unwrap()
+}
+
+/// Holds the requests back to a [`RateLimit`]. Its clones share the time of the
+/// last request, so the clients of a watch pace their requests together.
+#[derive(Clone)]
+pub struct RateLimiter {
+    limit: RateLimit,
+    last: Arc<Mutex<Option<Instant>>>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: RateLimit) -> RateLimiter {
+        RateLimiter {
+            limit,
+            last: Arc::default(),
+        }
+    }
+
+    /// Wait until the next request may be sent.
+    pub fn wait(&self) {
+        let mut last = self.last.lock().unwrap();
+        if let Some(last) = *last {
+            let ready = last + self.limit.interval();
+            let now = Instant::now();
+            if ready > now {
+                debug!(
+                    "Holding the request back for {:.1}s",
+                    (ready - now).as_secs_f64()
+                );
+                thread::sleep(ready - now);
+            }
+        }
+        *last = Some(Instant::now());
+    }
+}
+
+/// Blocking HTTP client of the booking session, retrying transient failures.
+pub struct HttpClient {
+    client: Client,
+    headers: HeaderMap,
+    retry: RetryPolicy,
+    proxy: Option<Proxy>,
+    har: Option<Capture>,
+    cookies: Arc<CookieStoreMutex>,
+    limiter: Option<RateLimiter>,
+}
+
+impl HttpClient {
+    pub fn new(headers: HeaderMap, retry: RetryPolicy) -> HttpClient {
+        let cookies = Arc::default();
+        HttpClient {
+            client: connect(&headers, retry.timeout, None, None, &cookies),
+            headers,
+            retry,
+            proxy: None,
+            har: None,
+            cookies,
+            limiter: None,
+        }
+    }
+
+    /// Hold every request back to `limiter`, retries included.
+    pub fn rate_limit(mut self, limiter: RateLimiter) -> HttpClient {
+        self.limiter = Some(limiter);
+        self
+    }
+
+    /// Send all requests through `proxy`, instead of the one from the
+    /// `HTTPS_PROXY`/`ALL_PROXY` environment variables.
+    pub fn proxy(mut self, proxy: Proxy) -> HttpClient {
+        self.proxy = Some(proxy);
+        self.reconnect()
+    }
+
+    /// Capture all traffic from now on, see [`HttpClient::har`].
+    pub fn capture_har(mut self) -> HttpClient {
+        self.har = Some(Capture::default());
+        self.reconnect()
+    }
+
+    /// The traffic captured since [`HttpClient::capture_har`].
+    pub fn har(&self) -> Option<&Capture> {
+        self.har.as_ref()
+    }
+
+    /// Replace the cookies with the ones saved to `path` by [`HttpClient::save_cookies`],
+    /// so the server session of an earlier run goes on. A missing file is an empty jar.
+    pub fn load_cookies(&self, path: &Path) -> Result<(), String> {
+        match fs::read_to_string(path) {
+            Ok(jar) => self
+                .import_cookies(&jar)
+                .map_err(|err_msg| format!("Invalid cookie jar {}: {}", path.display(), err_msg)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => self.import_cookies(""),
+            Err(err) => Err(format!(
+                "Failed to read cookie jar {}: {}",
+                path.display(),
+                err
+            )),
+        }
+    }
+
+    /// Write all cookies to `path`, including the session cookies a browser would
+    /// forget when closed.
+    pub fn save_cookies(&self, path: &Path) -> Result<(), String> {
+        fs::write(path, self.export_cookies())
+            .map_err(|err| format!("Failed to write cookie jar {}: {}", path.display(), err))
+    }
+
+    /// All cookies, including the session cookies, in the format of the cookie jar.
+    pub fn export_cookies(&self) -> String {
+        let mut jar = Vec::new();
+        // Writing to memory can't fail
+        let _ = cookie_store::serde::json::save_incl_expired_and_nonpersistent(
+            &self.cookies.lock().unwrap(),
+            &mut jar,
+        );
+        String::from_utf8_lossy(&jar).into_owned()
+    }
+
+    /// Replace the cookies with the ones from [`HttpClient::export_cookies`].
+    pub fn import_cookies(&self, jar: &str) -> Result<(), String> {
+        let store =
+            cookie_store::serde::json::load(jar.as_bytes()).map_err(|err| err.to_string())?;
+        *self.cookies.lock().unwrap() = store;
+        Ok(())
+    }
+
+    fn reconnect(mut self) -> HttpClient {
+        self.client = connect(
+            &self.headers,
+            self.retry.timeout,
+            self.proxy.as_ref(),
+            self.har.as_ref(),
+            &self.cookies,
+        );
+        self
+    }
+
+    /// Send `request`, sending it again after a transient failure only if `resend`.
+    fn send(
+        &self,
+        url: &str,
+        resend: bool,
+        request: impl Fn() -> RequestBuilder,
+    ) -> Result<HttpResponse, Error> {
+        let attempts = self.retry.attempts.max(1);
+        let mut attempt = 1;
+        loop {
+            if let Some(limiter) = &self.limiter {
+                limiter.wait();
+            }
+            let err_msg = match self.try_send(request()) {
+                Ok(resp) => return Ok(resp),
+                Err(failure) => failure.retry(url, resend)?,
+            };
+            if attempt >= attempts {
+                return Err(exhausted(url, attempt, &err_msg));
+            }
+
+            let delay = self.retry.delay(attempt);
+            warn!(
+                "Request failed ({}), retrying in {:.1}s ({}/{})",
+                err_msg,
+                delay.as_secs_f64(),
+                attempt + 1,
+                attempts
+            );
+            thread::sleep(delay);
+            attempt += 1;
+        }
+    }
+
+    fn try_send(&self, request: RequestBuilder) -> Result<HttpResponse, Failure> {
+        let (client, request) = request.build_split();
+        let request = request.map_err(|err| Failure::Permanent(err.to_string()))?;
+        let capture = self
+            .har
+            .as_ref()
+            .map(|har| (har, har.sent(&request, &self.headers)));
+        let started = Instant::now();
+        let resp = match client.execute(request) {
+            Ok(resp) => resp,
+            Err(err) => {
+                if let Some((har, sent)) = capture {
+                    har.failed(sent, started.elapsed(), &err.to_string());
+                }
+                return Err(Failure::of(err));
+            }
+        };
+        let wait = started.elapsed();
+
+        let status = resp.status();
+        let version = resp.version();
+        let url = resp.url().clone();
+        let headers = resp.headers().clone();
+        let cookies = resp
+            .cookies()
+            .map(|cookie| (cookie.name().to_string(), cookie.value().to_string()))
+            .collect();
+        let body = resp.bytes().map_err(Failure::of)?;
+        debug!("{} ({} bytes)", status, body.len());
+        if let Some((har, sent)) = capture {
+            let received = Received {
+                status,
+                version,
+                url: &url,
+                headers: &headers,
+                body: &body,
+                wait,
+                receive: started.elapsed() - wait,
+            };
+            har.received(sent, received);
+        }
+        checked(status, &headers, cookies, body)
+    }
+}
+
+impl Transport for HttpClient {
+    fn get(&self, url: &str) -> Result<HttpResponse, Error> {
+        debug!("GET {}", url);
+        self.send(url, true, || self.client.get(url))
+    }
+
+    fn post_form(&self, url: &str, body: String) -> Result<HttpResponse, Error> {
+        debug!("POST {}", url);
+        self.send(url, false, || {
+            self.client
+                .post(url)
+                .header(
+                    "Content-Type",
+                    HeaderValue::from_static("application/x-www-form-urlencoded"),
+                )
+                .body(body.clone())
+        })
+    }
+
+    fn cookie(&self, url: &str, name: &str) -> Option<String> {
+        cookie(&self.cookies, url, name)
+    }
+}
@@ -0,0 +1,63 @@
+//! The language of the prompts and of what is printed, picked with `--lang` or
+//! taken from the locale. Messages are written in both languages where they are used,
+//! with [`tr!`](crate::i18n::tr).
+
+use std::env;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[repr(u8)]
+pub enum Lang {
+    /// English
+    #[default]
+    En,
+    /// Traditional Chinese
+    Zh,
+}
+
+impl Lang {
+    /// The language of a locale like `zh_TW.UTF-8`, English unless it is Chinese.
+    pub fn from_locale(locale: &str) -> Lang {
+        match locale.to_ascii_lowercase().starts_with("zh") {
+            true => Lang::Zh,
+            false => Lang::En,
+        }
+    }
+}
+
+static LANG: AtomicU8 = AtomicU8::new(Lang::En as u8);
+
+/// Use `lang`, or without one, the language of the locale from `LC_ALL`,
+/// `LC_MESSAGES` or `LANG`.
+pub fn init(lang: Option<Lang>) {
+    let lang = lang.unwrap_or_else(|| {
+        (["LC_ALL", "LC_MESSAGES", "LANG"].iter())
+            .filter_map(|name| env::var(name).ok())
+            .find(|locale| !locale.is_empty())
+            .map(|locale| Lang::from_locale(&locale))
+            .unwrap_or_default()
+    });
+    LANG.store(lang as u8, Ordering::Relaxed);
+}
+
+pub fn lang() -> Lang {
+    match LANG.load(Ordering::Relaxed) {
+        1 => Lang::Zh,
+        _ => Lang::En,
+    }
+}
+
+/// The message in the language in use, formatted like `format!`:
+/// `tr!("Select a train", "請選擇車次")`.
+#[macro_export]
+macro_rules! tr {
+    ($en:literal, $zh:literal $(, $arg:expr)* $(,)?) => {
+        match $crate::i18n::lang() {
+            $crate::i18n::Lang::En => format!($en $(, $arg)*),
+            $crate::i18n::Lang::Zh => format!($zh $(, $arg)*),
+        }
+    };
+}
+
+pub use crate::tr;
@@ -0,0 +1,29 @@
+//! The booking site as thsr sees it: the HTTP session with its retries, the addresses,
+//! selectors and parsers of the pages, the errors they report, and the booking flows
+//! going through them. Nothing here reads or prints anything, the flows ask and tell
+//! through a [`prompt::Prompter`]; the command line and its prompts are in the `thsr` crate.
+
+pub mod alert;
+pub mod backend;
+pub mod booking;
+pub mod booking_flow;
+pub mod confirm_ticket_flow;
+pub mod confirm_train_flow;
+pub mod error;
+pub mod fare;
+#[cfg(feature = "blocking")]
+pub mod har;
+pub mod http;
+pub mod i18n;
+pub mod page;
+pub mod passenger;
+pub mod prompt;
+pub mod redact;
+pub mod result;
+pub mod schema;
+pub mod selectors;
+pub mod site;
+pub mod stage;
+pub mod summary;
+pub mod tgo;
+pub mod time;
@@ -0,0 +1,305 @@
+//! Reading the pages of the booking site: the booking page, the train list and the
+//! error messages any page may show. Everything is parsed from the markup alone, so
+//! a program doing its own requests can read the pages the same way.
+
+use chrono::NaiveTime;
+use scraper::{Html, Selector};
+use serde::Serialize;
+
+use crate::error::Error;
+use crate::i18n::tr;
+use crate::selectors;
+use crate::site::Site;
+
+/// A time of the day written `HH:MM`, e.g. `19:00`.
+pub fn parse_clock(time: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(time.trim(), "%H:%M")
+        .map_err(|_| format!("Invalid time {}, expected HH:MM", time))
+}
+
+/// Whether `page` is the notice shown after the session has been idle for too long.
+/// The site may also send the user back to the first page instead, so check
+/// `parse_error` first: a rejected search form is answered with the first page too.
+pub fn is_session_expired(page: &Html) -> bool {
+    let text: String = page.root_element().text().collect();
+    let lowercase = text.to_lowercase();
+    if ["閒置過久", "連線逾時", "已逾時"]
+        .iter()
+        .any(|marker| text.contains(marker))
+        || ["idle for too long", "timed out", "session has expired"]
+            .iter()
+            .any(|marker| lowercase.contains(marker))
+    {
+        return true;
+    }
+    page.select(&selectors::BOOKING_FORM).next().is_some()
+}
+
+/// The maintenance notice the site shows instead of its pages while the booking system
+/// is down, e.g. every night. Its pages all have a form, the notice has none.
+pub fn parse_maintenance(page: &Html) -> Option<Error> {
+    if page.select(&selectors::FORM).next().is_some() {
+        return None;
+    }
+    let text: String = page.root_element().text().collect();
+    let lowercase = text.to_lowercase();
+    let under_maintenance = ["維護", "暫停服務"]
+        .iter()
+        .any(|marker| text.contains(marker))
+        || ["maintenance", "temporarily unavailable"]
+            .iter()
+            .any(|marker| lowercase.contains(marker));
+    if !under_maintenance {
+        return None;
+    }
+    Some(Error::Maintenance(match maintenance_end(&text) {
+        Some(end) => format!(
+            "The booking site is under maintenance until {}",
+            end.format("%H:%M")
+        ),
+        None => "The booking site is under maintenance".to_string(),
+    }))
+}
+
+/// The time a maintenance ends, the first `HH:MM` after a word like `至` or `until`
+/// in `text`, e.g. 06:00 in `00:00~06:00` or in the message of [`parse_maintenance`].
+pub fn maintenance_end(text: &str) -> Option<NaiveTime> {
+    let lowercase = text.to_lowercase();
+    ["至", "到", "~", "～", "until", " to "]
+        .iter()
+        .filter_map(|marker| lowercase.find(marker).map(|at| at + marker.len()))
+        .filter_map(|from| {
+            let rest = lowercase[from..].trim_start();
+            let end = rest
+                .find(|c: char| !c.is_ascii_digit() && c != ':')
+                .unwrap_or(rest.len());
+            parse_clock(&rest[..end]).ok()
+        })
+        .next()
+}
+
+/// The feedback messages of `page`, classified.
+pub fn parse_error(page: &Html) -> Option<Error> {
+    let errors: Vec<String> = page
+        .select(&selectors::FEEDBACK_ERROR)
+        .filter_map(|element| element.text().next().map(|text| text.trim().to_string()))
+        .collect();
+    if errors.is_empty() {
+        None
+    } else {
+        Some(Error::from_feedback(errors.join("\n")))
+    }
+}
+
+// Booking page
+
+pub fn parse_avail_start_end_date(page: &Html) -> (String, String) {
+    let elem = page.select(&selectors::DATE_INPUT).next().unwrap();
+    let end_date = elem.attr("limit").unwrap();
+    let start_date = elem.attr("date").unwrap();
+    (start_date.to_string(), end_date.to_string())
+}
+
+pub fn parse_types_of_trip_value(page: &Html) -> u8 {
+    let elem = page.select(&selectors::TRIP_TYPE).next().unwrap();
+    let trip_type = elem.select(&selectors::SELECTED).next().unwrap();
+    trip_type.attr("value").unwrap().parse().unwrap()
+}
+
+/// The session ID in the address of the search form.
+pub fn parse_session_id(page: &Html) -> Option<String> {
+    let action = page
+        .select(&selectors::BOOKING_FORM)
+        .next()?
+        .attr("action")?;
+    let (_, rest) = action.split_once(";jsessionid=")?;
+    let jid = rest.split(['?', ';']).next()?;
+    (!jid.is_empty()).then(|| jid.to_string())
+}
+
+pub fn parse_search_by(page: &Html) -> String {
+    let mut candidates = page.select(&selectors::BOOKING_METHOD);
+    let tag = candidates
+        .find(|cand| cand.value().attr("checked").is_some())
+        .unwrap();
+    tag.value().attr("value").unwrap().to_string()
+}
+
+pub fn parse_security_code_img_url(page: &Html, site: &Site) -> String {
+    let elem = page.select(&selectors::SECURITY_CODE_IMG).next().unwrap();
+    let img_url = elem.attr("src").unwrap();
+    site.url(img_url)
+}
+
+/// The reload button calls `wicketAjaxGet('<url>' + Math.random(), ...)`,
+/// the returned URL still needs the random suffix.
+pub fn parse_security_code_reload_url(page: &Html, site: &Site) -> Option<String> {
+    let onclick = (page.select(&selectors::SECURITY_CODE_RELOAD).next()?).attr("onclick")?;
+    let (_, call) = onclick.split_once("wicketAjaxGet('")?;
+    let (url, _) = call.split_once('\'')?;
+    let url = url.replace("&amp;", "&");
+    if url.starts_with('/') {
+        Some(site.url(&url))
+    } else {
+        Some(site.url(&format!("/IMINT/{}", url)))
+    }
+}
+
+/// Find the image address in the markup of the booking page, or in the Ajax reply
+/// of the reload button, without parsing it.
+pub fn parse_reloaded_img_src(reply: &str) -> Option<String> {
+    let (_, img) = reply.split_once("BookingS1Form_homeCaptcha_passCode")?;
+    let (_, src) = img.split_once("src=\"")?;
+    let (src, _) = src.split_once('"')?;
+    Some(src.replace("&amp;", "&"))
+}
+
+// Train list
+
+/// The trains of the train list, leaving out those it doesn't tell enough about.
+pub fn parse_trains(document: &Html) -> Vec<Train> {
+    let avail = document.select(&selectors::TRAIN_ITEM);
+
+    avail
+        .filter_map(|element| {
+            let elem = element.select(&selectors::TRAIN_INPUT).next()?;
+            let clock = |name: &str| parse_clock(elem.attr(name)?).ok();
+
+            let train = Train {
+                id: elem.attr("querycode")?.parse().ok()?,
+                depart: clock("querydeparture")?,
+                arrive: clock("queryarrival")?,
+                travel_minutes: parse_minutes(elem.attr("queryestimatedtime")?)?,
+                discount_info: parse_discount(&element),
+                seats: parse_seats(&element),
+                form_value: elem.attr("value")?.to_string(),
+            };
+            Some(train)
+        })
+        .collect()
+}
+
+/// A travel time written `h:mm` as minutes.
+fn parse_minutes(travel_time: &str) -> Option<u32> {
+    let (hours, minutes) = travel_time.trim().split_once(':')?;
+    Some(hours.parse::<u32>().ok()? * 60 + minutes.parse::<u32>().ok()?)
+}
+
+fn serialize_clock<S: serde::Serializer>(time: &NaiveTime, ser: S) -> Result<S::Ok, S::Error> {
+    ser.collect_str(&time.format("%H:%M"))
+}
+
+/// The discounts of a train, e.g. `(早鳥9折, 大學生5折)`, written straight into one string.
+pub fn parse_discount(item: &scraper::ElementRef) -> String {
+    let mut info = String::new();
+    for selector in [&selectors::EARLY_BIRD, &selectors::STUDENT] {
+        let Some(text) = (item.select(selector).next()).and_then(|tag| tag.text().next()) else {
+            continue;
+        };
+        info.push_str(if info.is_empty() { "(" } else { ", " });
+        info.push_str(text);
+    }
+    if !info.is_empty() {
+        info.push(')');
+    }
+    info
+}
+
+/// How many seats a train has left, as marked on the train list.
+#[derive(Serialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Seats {
+    #[default]
+    Available,
+    Limited,
+    /// Only standing room is left in the reserved cars
+    StandingOnly,
+}
+
+impl Seats {
+    /// The name of the seats in JSON and CSV, e.g. `standing_only`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Seats::Available => "available",
+            Seats::Limited => "limited",
+            Seats::StandingOnly => "standing_only",
+        }
+    }
+
+    /// What to tell about the seats next to the train, nothing when there are enough.
+    pub fn hint(self) -> Option<String> {
+        match self {
+            Seats::Available => None,
+            Seats::Limited => Some(tr!("few seats left", "座位有限")),
+            Seats::StandingOnly => Some(tr!("standing only", "僅剩站位")),
+        }
+    }
+}
+
+pub fn parse_seats(item: &scraper::ElementRef) -> Seats {
+    let marked = |selector: &Selector| item.select(selector).next().is_some();
+    if marked(&selectors::SEATS_STANDING) {
+        Seats::StandingOnly
+    } else if marked(&selectors::SEATS_LIMITED) {
+        Seats::Limited
+    } else {
+        Seats::Available
+    }
+}
+
+/// A train of the train list, written to JSON with its times as `HH:MM`.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Train {
+    id: u32,
+    #[serde(serialize_with = "serialize_clock")]
+    depart: NaiveTime,
+    #[serde(serialize_with = "serialize_clock")]
+    arrive: NaiveTime,
+    travel_minutes: u32,
+    discount_info: String,
+    seats: Seats,
+    #[serde(skip)]
+    form_value: String,
+}
+
+impl Train {
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn depart(&self) -> NaiveTime {
+        self.depart
+    }
+
+    /// The arrival time, before the departure for a train arriving after midnight.
+    pub fn arrive(&self) -> NaiveTime {
+        self.arrive
+    }
+
+    pub fn travel_minutes(&self) -> u32 {
+        self.travel_minutes
+    }
+
+    /// The travel time as shown on the booking site, e.g. `1:45`.
+    pub fn travel_time(&self) -> String {
+        format!(
+            "{}:{:02}",
+            self.travel_minutes / 60,
+            self.travel_minutes % 60
+        )
+    }
+
+    /// Discounts offered on the train, e.g. `(早鳥65折, 大學生5折)`, or empty.
+    pub fn discount_info(&self) -> &str {
+        &self.discount_info
+    }
+
+    pub fn seats(&self) -> Seats {
+        self.seats
+    }
+
+    /// What the train list form sends to book the train.
+    pub fn form_value(&self) -> &str {
+        &self.form_value
+    }
+}
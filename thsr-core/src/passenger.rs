@@ -0,0 +1,160 @@
+//! Passenger profiles, booking with their own IDs and ticket types, and the checks of
+//! the IDs, phone numbers and email addresses passengers book with.
+
+use secrecy::SecretString;
+use serde::Deserialize;
+
+use crate::schema::TicketType;
+
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PassengerType {
+    #[default]
+    Adult,
+    Child,
+    Disabled,
+    Elder,
+    Student,
+}
+
+impl PassengerType {
+    pub fn ticket_type(self) -> TicketType {
+        match self {
+            PassengerType::Adult => TicketType::Adult,
+            PassengerType::Child => TicketType::Child,
+            PassengerType::Disabled => TicketType::Disabled,
+            PassengerType::Elder => TicketType::Elder,
+            PassengerType::Student => TicketType::College,
+        }
+    }
+}
+
+/// A passenger profile, e.g. a `[passengers.<name>]` section of the config file of `thsr`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Passenger {
+    /// Personal ID
+    pub id: SecretString,
+
+    #[serde(default, rename = "type")]
+    pub kind: PassengerType,
+
+    /// Names filled in when the ticket form asks for them, as for discounted tickets
+    #[serde(default)]
+    pub last_name: Option<String>,
+    #[serde(default)]
+    pub first_name: Option<String>,
+
+    #[serde(default, deserialize_with = "deserialize_phone")]
+    pub phone: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_email")]
+    pub email: Option<String>,
+
+    /// Whether to use the ID as membership when this passenger books
+    pub membership: Option<bool>,
+}
+
+/// Whether `id` is shaped like a Taiwan national ID or resident certificate number,
+/// a letter and nine digits, rather than a passport number.
+pub fn is_national_id(id: &str) -> bool {
+    let mut chars = id.chars();
+    chars
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic())
+        && id.len() == 10
+        && chars.all(|char| char.is_ascii_digit())
+}
+
+/// The values of the first letter of a Taiwan ID, A to Z.
+const LETTER_VALUES: [u32; 26] = [
+    10, 11, 12, 13, 14, 15, 16, 17, 34, 18, 19, 20, 21, 22, 35, 23, 24, 25, 26, 27, 28, 29, 32, 30,
+    31, 33,
+];
+
+/// Whether `id` is a valid Taiwan national ID or resident certificate number: shaped
+/// like one, see [`is_national_id`], with the right check digit.
+pub fn is_valid_national_id(id: &str) -> bool {
+    if !is_national_id(id) {
+        return false;
+    }
+    let bytes = id.as_bytes();
+    let letter = LETTER_VALUES[usize::from(bytes[0].to_ascii_uppercase() - b'A')];
+    let digits = bytes[1..].iter().map(|digit| u32::from(digit - b'0'));
+    // The last digit weighs 1, like the tens of the letter
+    let weighted: u32 = (digits.zip([8, 7, 6, 5, 4, 3, 2, 1, 1]))
+        .map(|(digit, weight)| digit * weight)
+        .sum();
+    (letter / 10 + letter % 10 * 9 + weighted).is_multiple_of(10)
+}
+
+/// A passport number for `--passport`: 5 to 20 letters and digits, in upper case.
+pub fn parse_passport(number: &str) -> Result<String, String> {
+    let number = number.trim().to_ascii_uppercase();
+    if !(5..=20).contains(&number.len()) || !number.chars().all(|char| char.is_ascii_alphanumeric())
+    {
+        return Err("a passport number has 5 to 20 letters and digits".to_string());
+    }
+    Ok(number)
+}
+
+/// A Taiwan mobile number for `--phone`, e.g. `0912-345-678` or `+886 912 345 678`,
+/// written as `0912345678`.
+pub fn parse_phone(number: &str) -> Result<String, String> {
+    let digits: String = (number.chars())
+        .filter(|char| !matches!(char, ' ' | '-'))
+        .collect();
+    let digits = match digits.strip_prefix("+886") {
+        Some(rest) => format!("0{}", rest),
+        None => digits,
+    };
+    if digits.len() != 10
+        || !digits.starts_with("09")
+        || !digits.chars().all(|char| char.is_ascii_digit())
+    {
+        return Err(format!(
+            "{} is not a Taiwan mobile number like 0912345678",
+            number
+        ));
+    }
+    Ok(digits)
+}
+
+/// A phone number checked with [`parse_phone`].
+pub fn deserialize_phone<'de, D: serde::Deserializer<'de>>(
+    de: D,
+) -> Result<Option<String>, D::Error> {
+    let number = String::deserialize(de)?;
+    parse_phone(&number)
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+/// An email address for `--email`, checked only for the shape `name@domain.tld`.
+pub fn parse_email(address: &str) -> Result<String, String> {
+    let address = address.trim();
+    let valid = match address.split_once('@') {
+        Some((name, domain)) => {
+            !name.is_empty()
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+                && !address.contains(char::is_whitespace)
+                && !domain.contains('@')
+        }
+        None => false,
+    };
+    match valid {
+        true => Ok(address.to_string()),
+        false => Err(format!("{} is not an email address", address)),
+    }
+}
+
+/// An email address checked with [`parse_email`].
+pub fn deserialize_email<'de, D: serde::Deserializer<'de>>(
+    de: D,
+) -> Result<Option<String>, D::Error> {
+    let address = String::deserialize(de)?;
+    parse_email(&address)
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
@@ -0,0 +1,242 @@
+//! What a booking asks and tells along the way. The flows never read or print
+//! anything themselves: the questions, the security codes and the news of each step
+//! go to a [`Prompter`], the terminal, a remote caller or a script.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crate::alert::Alert;
+use crate::error::Error;
+use crate::i18n::tr;
+use crate::page::Train;
+use crate::summary::Summary;
+
+/// Checks an answer, telling what is wrong with it.
+pub type Check<'a> = &'a dyn Fn(&str) -> Result<(), String>;
+
+/// What a booking tells along the way, for the prompter to show or count.
+#[derive(Debug, Clone, Copy)]
+pub enum Notice<'a> {
+    /// A line to show as is, e.g. an item of a list or the summary of the booking
+    Line(&'a str),
+    Status(&'a str),
+    Warning(&'a str),
+    /// An alert of the booking site above the trains
+    Alert(&'a Alert),
+    /// A new session was opened on the booking page
+    PageLoaded,
+    /// The security code is waiting to be solved
+    CaptchaRequired,
+    /// A search was submitted, refused with the error if it was
+    Searched(Option<&'a Error>),
+    /// A step of the booking, `search`, `train` or `ticket`, is over
+    StepDone {
+        step: &'a str,
+        took: Duration,
+        error: Option<&'a Error>,
+    },
+    TrainsFound(&'a [Train]),
+    TrainSelected(u32),
+}
+
+/// Source of the answers to everything the flows need to ask, and where they tell
+/// how the booking goes.
+pub trait Prompter {
+    /// Ask `hint` and return the answer, trimmed. An empty answer takes the default.
+    fn ask(&self, hint: &str) -> String;
+
+    /// Ask for the security code in `img_data`, or `None` to get a new image.
+    fn security_code(&self, img_data: &[u8]) -> Option<String>;
+
+    /// Ask to pick one of `items`, returning its index. An empty answer picks `default`.
+    fn select(&self, hint: &str, items: &[String], default: usize) -> usize {
+        tell_items(self, items);
+        pick(self, hint, items, default, false)
+    }
+
+    /// Like [`Prompter::select`], for long lists: an answer may also be part of the
+    /// item, as long as it matches a single one.
+    fn search(&self, hint: &str, items: &[String], default: usize) -> usize {
+        tell_items(self, items);
+        pick(self, hint, items, default, true)
+    }
+
+    /// Ask `hint` until `check` accepts the answer. An empty answer takes `default`.
+    fn input(&self, hint: &str, default: &str, check: Check) -> String {
+        input_checked(self, hint, default, check)
+    }
+
+    /// Like [`Prompter::input`], for personal IDs, which are hidden where possible.
+    fn secret(&self, hint: &str, default: &str, check: Check) -> String {
+        self.input(hint, default, check)
+    }
+
+    /// Ask the yes or no question `hint` until it is answered.
+    fn confirm(&self, hint: &str) -> bool {
+        confirm_answered(self, hint)
+    }
+
+    /// Hear what the booking tells. Nothing is shown unless overridden.
+    fn tell(&self, _notice: Notice) {}
+
+    /// Run `work`, the request of `step`, e.g. `Booking`, while the booking site is
+    /// waited for. `work` must be run exactly once.
+    fn wait(&self, _step: &str, work: &mut dyn FnMut()) {
+        work()
+    }
+
+    /// The train to book of `trains` without asking, if there are rules to pick it.
+    fn pick_train(&self, _trains: &[Train]) -> Result<Option<u32>, Error> {
+        Ok(None)
+    }
+
+    /// Approve the booking of `summary` before it is made, or tell why it isn't.
+    fn approve(&self, _summary: &Summary) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Run `work` through [`Prompter::wait`], returning what it returns.
+pub fn wait<T>(prompter: &dyn Prompter, step: &str, work: impl FnOnce() -> T) -> T {
+    let mut work = Some(work);
+    let mut outcome = None;
+    prompter.wait(step, &mut || outcome = work.take().map(|work| work()));
+    outcome.expect("Prompter::wait didn't run the request")
+}
+
+/// Tell `items` as a numbered list.
+fn tell_items<P: Prompter + ?Sized>(prompter: &P, items: &[String]) {
+    for (idx, item) in items.iter().enumerate() {
+        prompter.tell(Notice::Line(&format!("{:>2}. {}", idx + 1, item)));
+    }
+}
+
+/// Ask for the number of one of `items` until a valid one is given, or with
+/// `by_name`, for part of it, as long as it matches a single item.
+pub fn pick<P: Prompter + ?Sized>(
+    prompter: &P,
+    hint: &str,
+    items: &[String],
+    default: usize,
+    by_name: bool,
+) -> usize {
+    let hint = tr!("{} (default: {}):", "{}（預設：{}）：", hint, default + 1);
+    loop {
+        let answer = prompter.ask(&hint);
+        if answer.is_empty() {
+            return default;
+        }
+        if let Ok(pick) = answer.parse::<usize>()
+            && (1..=items.len()).contains(&pick)
+        {
+            return pick - 1;
+        }
+        if !by_name {
+            prompter.tell(Notice::Warning(&tr!(
+                "Please enter a number from 1 to {}",
+                "請輸入 1 到 {} 的數字",
+                items.len()
+            )));
+            continue;
+        }
+        let answer = answer.to_lowercase();
+        let matches: Vec<usize> = (0..items.len())
+            .filter(|&idx| items[idx].to_lowercase().contains(&answer))
+            .collect();
+        match matches.as_slice() {
+            [pick] => return *pick,
+            [] => prompter.tell(Notice::Warning(&tr!(
+                "Nothing matches {}",
+                "沒有符合 {} 的項目",
+                answer
+            ))),
+            _ => prompter.tell(Notice::Warning(&tr!(
+                "{} matches several, type more of it",
+                "{} 符合多個項目，請再多輸入一些",
+                answer
+            ))),
+        }
+    }
+}
+
+/// Ask `hint` line by line until `check` accepts the answer, see [`Prompter::input`].
+pub fn input_checked<P: Prompter + ?Sized>(
+    prompter: &P,
+    hint: &str,
+    default: &str,
+    check: Check,
+) -> String {
+    let hint = match default {
+        "" => tr!("{}:", "{}：", hint),
+        default => tr!("{} (default: {}):", "{}（預設：{}）：", hint, default),
+    };
+    loop {
+        let answer = match prompter.ask(&hint) {
+            answer if answer.is_empty() => default.to_string(),
+            answer => answer,
+        };
+        match check(&answer) {
+            Ok(()) => return answer,
+            Err(err_msg) => prompter.tell(Notice::Warning(&err_msg)),
+        }
+    }
+}
+
+/// Ask the yes or no question `hint` line by line, see [`Prompter::confirm`].
+pub fn confirm_answered<P: Prompter + ?Sized>(prompter: &P, hint: &str) -> bool {
+    let hint = tr!("{} (y/n):", "{}（y/n）：", hint);
+    loop {
+        match prompter.ask(&hint).to_lowercase().as_str() {
+            "y" | "yes" | "是" => return true,
+            "n" | "no" | "否" => return false,
+            _ => prompter.tell(Notice::Warning(&tr!(
+                "Please answer y or n",
+                "請回答 y 或 n"
+            ))),
+        }
+    }
+}
+
+/// Gives prepared answers in order, for tests and programs embedding the flows.
+/// An empty answer takes the default, running out of answers panics.
+#[derive(Default)]
+pub struct ScriptedPrompter {
+    answers: RefCell<VecDeque<String>>,
+    codes: RefCell<VecDeque<String>>,
+    asked: RefCell<Vec<String>>,
+}
+
+impl ScriptedPrompter {
+    pub fn new<A, C>(answers: A, codes: C) -> Self
+    where
+        A: IntoIterator,
+        A::Item: Into<String>,
+        C: IntoIterator,
+        C::Item: Into<String>,
+    {
+        ScriptedPrompter {
+            answers: RefCell::new(answers.into_iter().map(Into::into).collect()),
+            codes: RefCell::new(codes.into_iter().map(Into::into).collect()),
+            asked: RefCell::default(),
+        }
+    }
+
+    /// The questions asked so far.
+    pub fn asked(&self) -> Vec<String> {
+        self.asked.borrow().clone()
+    }
+}
+
+impl Prompter for ScriptedPrompter {
+    fn ask(&self, hint: &str) -> String {
+        self.asked.borrow_mut().push(hint.to_string());
+        let answer = self.answers.borrow_mut().pop_front();
+        answer.unwrap_or_else(|| panic!("ScriptedPrompter ran out of answers at {:?}", hint))
+    }
+
+    fn security_code(&self, _img_data: &[u8]) -> Option<String> {
+        let code = self.codes.borrow_mut().pop_front();
+        Some(code.expect("ScriptedPrompter ran out of security codes"))
+    }
+}
@@ -0,0 +1,162 @@
+//! Keeps personal data out of everything written for debugging: logs, cassettes and HAR files.
+//!
+//! Personal IDs, membership numbers and phone numbers are replaced by tokens such as
+//! `REDACTED-3f09a1c2`. A value gets the same token everywhere and in every run, so
+//! logs, cassettes and HAR files can be matched up with each other. The tokens are
+//! hashed with a salt, fixed unless [`set_salt`] gives one of your own so that the
+//! tokens can't be traced back to the values by someone who doesn't know it.
+
+use secrecy::{ExposeSecret, SecretString};
+
+use std::sync::OnceLock;
+
+/// Starts every token standing in for a redacted value.
+pub const REDACTED: &str = "REDACTED";
+
+/// Form fields holding personal data, matched by the end of their name.
+const SENSITIVE_FIELDS: &[&str] = &[
+    "dummyId",
+    "dummyPhone",
+    "email",
+    "memberShipNumber",
+    "passengerDataIdNumber",
+    "passengerDataLastName",
+    "passengerDataFirstName",
+];
+
+/// Mixed into the tokens unless [`set_salt`] is called.
+const DEFAULT_SALT: u64 = 0x7468_7372_2d72_6564;
+
+static SALT: OnceLock<u64> = OnceLock::new();
+
+/// Hash the tokens with `salt` rather than the fixed one, before anything is redacted.
+/// The first salt set stays.
+pub fn set_salt(salt: &str) {
+    let _ = SALT.set(fnv1a(DEFAULT_SALT, salt.as_bytes()));
+}
+
+/// Replaces the values it was given, and everything shaped like a national ID,
+/// resident certificate number or mobile phone number.
+#[derive(Default, Clone, Debug)]
+pub struct Redactor {
+    secrets: Vec<String>,
+}
+
+impl Redactor {
+    /// Keep `secret` out as well, e.g. a passport number that doesn't look like an ID.
+    pub fn secret(mut self, secret: &str) -> Self {
+        self.add(secret);
+        self
+    }
+
+    pub fn add(&mut self, secret: &str) {
+        let secret = secret.trim();
+        if !secret.is_empty() && !self.secrets.iter().any(|known| known == secret) {
+            self.secrets.push(secret.to_string());
+        }
+    }
+
+    pub fn text(&self, text: &str) -> String {
+        let text = self.secrets.iter().fold(text.to_string(), |text, secret| {
+            text.replace(secret.as_str(), &token(secret))
+        });
+        redact_patterns(&text)
+    }
+
+    /// Redact an urlencoded form body, including every field known to hold personal data.
+    pub fn form(&self, body: &str) -> String {
+        let fields: Vec<String> = body
+            .split('&')
+            .map(|field| match field.split_once('=') {
+                Some((name, value)) if !value.is_empty() && is_sensitive(name) => {
+                    format!("{}={}", name, token(value))
+                }
+                _ => field.to_string(),
+            })
+            .collect();
+        self.text(&fields.join("&"))
+    }
+}
+
+fn is_sensitive(name: &str) -> bool {
+    let name = name.replace("%3A", ":").replace("%3a", ":");
+    SENSITIVE_FIELDS.iter().any(|field| name.ends_with(field))
+}
+
+/// The token standing in for `value`.
+pub fn token(value: &str) -> String {
+    let hash = fnv1a(*SALT.get().unwrap_or(&DEFAULT_SALT), value.as_bytes());
+    format!("{}-{:08x}", REDACTED, hash as u32)
+}
+
+/// FNV-1a of `bytes`, seeded with `salt`.
+fn fnv1a(salt: u64, bytes: &[u8]) -> u64 {
+    (salt.to_le_bytes().iter())
+        .chain(bytes)
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+/// Replace everything shaped like personal data, which catches the passenger IDs
+/// entered at the prompts as well.
+fn redact_patterns(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let is_word = |idx: usize| chars.get(idx).is_some_and(|c| c.is_ascii_alphanumeric());
+
+    let mut redacted = String::with_capacity(text.len());
+    let mut idx = 0;
+    while idx < chars.len() {
+        let len = if idx == 0 || !is_word(idx - 1) {
+            personal_data_len(&chars[idx..]).filter(|len| !is_word(idx + len))
+        } else {
+            None
+        };
+        match len {
+            Some(len) => {
+                let value: String = chars[idx..idx + len].iter().collect();
+                redacted.push_str(&token(&value));
+                idx += len;
+            }
+            None => {
+                redacted.push(chars[idx]);
+                idx += 1;
+            }
+        }
+    }
+    redacted
+}
+
+/// The length of the personal data `chars` starts with, if any.
+fn personal_data_len(chars: &[char]) -> Option<usize> {
+    let matches = |pattern: &str| {
+        chars.len() >= pattern.len()
+            && pattern.chars().zip(chars).all(|(kind, c)| match kind {
+                'A' => c.is_ascii_uppercase(),
+                'B' => ('A'..='D').contains(c),
+                '#' => c.is_ascii_digit(),
+                _ => kind == *c,
+            })
+    };
+    [
+        // National ID and the newer resident certificates
+        "A#########",
+        // Older resident certificates
+        "AB########",
+        // Mobile phone numbers
+        "09########",
+        "09##-###-###",
+        "+8869########",
+    ]
+    .iter()
+    .find(|pattern| matches(pattern))
+    .map(|pattern| pattern.len())
+}
+
+/// Serialize `secret` as is, for the forms that have to carry it.
+pub fn expose<S: serde::Serializer>(
+    secret: &SecretString,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(secret.expose_secret())
+}
@@ -0,0 +1,356 @@
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use scraper::{CaseSensitivity, Html, Selector};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use std::fmt;
+
+use crate::i18n::tr;
+use crate::selectors;
+use crate::time::{self, taipei, taipei_now, taipei_today};
+
+/// Details of a completed booking, scraped from the final result page.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BookingResult {
+    pub pnr: String,
+    pub price: String,
+    pub payment_deadline: String,
+    pub date: String,
+    pub depart_time: String,
+    pub arrive_time: String,
+    pub from: String,
+    pub to: String,
+    pub class: String,
+    pub passenger_count: String,
+    pub seats: Vec<SeatAssignment>,
+
+    /// The train booked, when it is known from the train list
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub train: Option<u32>,
+
+    /// TGo points the booking is expected to earn, when booked with membership
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tgo_points: Option<u32>,
+}
+
+/// A seat of the booking, with its car and the ticket it was booked for.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SeatAssignment {
+    /// The car number, `None` when the label couldn't be read
+    pub car: Option<u8>,
+    /// The seat in the car, e.g. `12A`, or the whole label when the car couldn't be read
+    pub seat: String,
+    /// The ticket type as the page writes it, e.g. `全票` or `Adult`
+    pub ticket_type: Option<String>,
+}
+
+impl SeatAssignment {
+    /// The seat labelled `label` on the page, e.g. `7車12A` or `Car 7 12A`.
+    pub fn parse(label: &str, ticket_type: Option<String>) -> SeatAssignment {
+        let label = label.trim();
+        let parts = match label.split_once('車') {
+            Some((car, seat)) => Some((car.trim(), seat.trim())),
+            None => (label.strip_prefix("Car"))
+                .map(str::trim_start)
+                .and_then(|rest| rest.split_once(|c: char| !c.is_ascii_digit()))
+                .map(|(car, seat)| (car, seat.trim_matches(|c: char| !c.is_ascii_alphanumeric()))),
+        };
+        let car_seat = parts.and_then(|(car, seat)| Some((car.parse().ok()?, seat)));
+        match car_seat {
+            Some((car, seat)) if !seat.is_empty() => SeatAssignment {
+                car: Some(car),
+                seat: seat.to_string(),
+                ticket_type,
+            },
+            _ => SeatAssignment {
+                car: None,
+                seat: label.to_string(),
+                ticket_type,
+            },
+        }
+    }
+}
+
+impl fmt::Display for SeatAssignment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.car {
+            Some(car) => write!(f, "{}", tr!("Car {} {}", "{}車{}", car, self.seat)),
+            None => write!(f, "{}", self.seat),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for SeatAssignment {
+    fn deserialize<D: Deserializer<'de>>(de: D) -> Result<SeatAssignment, D::Error> {
+        // Bookings saved before the seats had their cars and ticket types hold the labels
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Saved {
+            Label(String),
+            Seat {
+                car: Option<u8>,
+                seat: String,
+                #[serde(default)]
+                ticket_type: Option<String>,
+            },
+        }
+        Ok(match Saved::deserialize(de)? {
+            Saved::Label(label) => SeatAssignment::parse(&label, None),
+            Saved::Seat {
+                car,
+                seat,
+                ticket_type,
+            } => SeatAssignment {
+                car,
+                seat,
+                ticket_type,
+            },
+        })
+    }
+}
+
+/// The ticket type of a group of the page, e.g. `全票` of `全票 2 張` or `Adult` of `Adult x 2`.
+fn ticket_type_of(text: &str) -> Option<String> {
+    let name = (text.split(|c: char| c.is_ascii_digit()).next())
+        .unwrap_or_default()
+        .trim()
+        .trim_end_matches(['x', '×'])
+        .trim();
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// The seats of the page with their ticket types. The page lists the tickets by
+/// type, each group followed by the seats booked for it.
+pub fn parse_seats(page: &Html) -> Vec<SeatAssignment> {
+    let mut ticket_type = None;
+    let mut seats = Vec::new();
+    for group in page.select(&selectors::TICKETS_AND_SEATS) {
+        let mut labels = (group.select(&selectors::SPAN))
+            .filter_map(|tag| tag.text().next().map(|text| text.to_string()));
+        let is_seats = (group.value()).has_class("seat-label", CaseSensitivity::CaseSensitive);
+        match is_seats {
+            true => {
+                seats.extend(labels.map(|label| SeatAssignment::parse(&label, ticket_type.clone())))
+            }
+            false => ticket_type = labels.next().as_deref().and_then(ticket_type_of),
+        }
+    }
+    seats
+}
+
+fn first_text(page: &Html, selector: &Selector) -> String {
+    let tag = page.select(selector).next().unwrap();
+    tag.text().next().unwrap().to_string()
+}
+
+pub fn parse_result(page: &Html) -> BookingResult {
+    BookingResult {
+        pnr: first_text(page, &selectors::PNR),
+        price: first_text(page, &selectors::RESULT_PRICE),
+        payment_deadline: first_text(page, &selectors::PAYMENT_DEADLINE),
+        date: first_text(page, &selectors::RESULT_DATE),
+        depart_time: first_text(page, &selectors::DEPART_TIME),
+        arrive_time: first_text(page, &selectors::ARRIVE_TIME),
+        from: first_text(page, &selectors::DEPART_STATION),
+        to: first_text(page, &selectors::ARRIVE_STATION),
+        class: first_text(page, &selectors::CLASS),
+        passenger_count: first_text(page, &selectors::TICKET_GROUPS),
+        seats: parse_seats(page),
+        train: None,
+        tgo_points: None,
+    }
+}
+
+impl fmt::Display for BookingResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{}",
+            tr!(
+                "Please use the following PNR code for payment and picking up the ticket:",
+                "請使用以下訂位代號付款及取票："
+            )
+        )?;
+        writeln!(f, "{}", self.pnr_line())?;
+        writeln!(f, "{}", self.payment_line(taipei_now()))?;
+        if let Some(points) = self.tgo_points {
+            writeln!(
+                f,
+                "{}",
+                tr!("TGo points: about {}", "TGo 點數：約 {} 點", points)
+            )?;
+        }
+        writeln!(
+            f,
+            "{}",
+            tr!(
+                "-------(Ticket Information)-------",
+                "-------（車票資訊）-------"
+            )
+        )?;
+        writeln!(f, "{:>7}{}", tr!("Date: ", "日期："), self.date)?;
+        writeln!(
+            f,
+            "{:>7}{}~{}",
+            tr!("Time: ", "時間："),
+            self.depart_time,
+            self.arrive_time
+        )?;
+        writeln!(f, "{:>7}{}", tr!("From: ", "起站："), self.from)?;
+        writeln!(f, "{:>7}{}", tr!("To: ", "迄站："), self.to)?;
+        writeln!(
+            f,
+            "{}",
+            tr!(
+                "Class: {}{}",
+                "車廂：{}{}",
+                self.class,
+                self.passenger_count
+            )
+        )?;
+        write!(f, "{}", tr!("Seats: {}", "座位：{}", self.seat_list()))
+    }
+}
+
+impl BookingResult {
+    /// The line of the PNR in the printed result.
+    pub fn pnr_line(&self) -> String {
+        tr!("PNR Code: {}", "訂位代號：{}", self.pnr)
+    }
+
+    /// The payment deadline, read as Taipei time. A deadline without a time is the
+    /// end of that day.
+    pub fn payment_due(&self) -> Option<DateTime<FixedOffset>> {
+        let deadline = self.payment_deadline.trim();
+        let due = NaiveDateTime::parse_from_str(deadline, "%Y/%m/%d %H:%M")
+            .ok()
+            .or_else(|| parse_page_date(deadline)?.and_hms_opt(23, 59, 0))?;
+        taipei().from_local_datetime(&due).single()
+    }
+
+    /// The time left at `now` to pay, negative once the deadline has passed.
+    pub fn time_to_pay(&self, now: DateTime<FixedOffset>) -> Option<Duration> {
+        self.payment_due().map(|due| due - now)
+    }
+
+    /// The price and the payment deadline, with the time left to pay at `now`.
+    fn payment_line(&self, now: DateTime<FixedOffset>) -> String {
+        let left = match self.time_to_pay(now) {
+            Some(left) if left > Duration::zero() => {
+                tr!(", within {}", "，剩餘 {}", time::describe_span(left))
+            }
+            Some(_) => tr!(", the deadline has passed", "，已超過付款期限"),
+            None => String::new(),
+        };
+        tr!(
+            "Price: {}. Please pay before {}{}",
+            "票價：{}，請於 {} 前付款{}",
+            self.price,
+            self.payment_deadline,
+            left
+        )
+    }
+
+    /// The seats, with the ticket type of each when the booking mixes several.
+    pub fn seat_list(&self) -> String {
+        let first_type = self.seats.first().map(|seat| &seat.ticket_type);
+        let mixed = (self.seats.iter()).any(|seat| Some(&seat.ticket_type) != first_type);
+        let seats: Vec<String> = (self.seats.iter())
+            .map(|seat| match (&seat.ticket_type, mixed) {
+                (Some(ticket_type), true) => format!("{} ({})", seat, ticket_type),
+                _ => seat.to_string(),
+            })
+            .collect();
+        seats.join(", ")
+    }
+
+    /// Departure and arrival instants, interpreting the page values as Taipei local time.
+    fn schedule(&self) -> Option<(chrono::DateTime<Utc>, chrono::DateTime<Utc>)> {
+        let date = parse_page_date(&self.date)?;
+        let depart = NaiveTime::parse_from_str(self.depart_time.trim(), "%H:%M").ok()?;
+        let arrive = NaiveTime::parse_from_str(self.arrive_time.trim(), "%H:%M").ok()?;
+
+        let depart_at = taipei()
+            .from_local_datetime(&date.and_time(depart))
+            .single()?;
+        let mut arrive_at = taipei()
+            .from_local_datetime(&date.and_time(arrive))
+            .single()?;
+        // Late trains may arrive after midnight
+        if arrive_at < depart_at {
+            arrive_at += Duration::days(1);
+        }
+        Some((depart_at.with_timezone(&Utc), arrive_at.with_timezone(&Utc)))
+    }
+
+    /// Render the booking as an iCalendar event. Returns `None` if the
+    /// date or time on the result page could not be understood.
+    pub fn to_ics(&self) -> Option<String> {
+        let (depart_at, arrive_at) = self.schedule()?;
+        let fmt = "%Y%m%dT%H%M%SZ";
+        let lines = [
+            "BEGIN:VCALENDAR".to_string(),
+            "VERSION:2.0".to_string(),
+            "PRODID:-//thsr//booking//EN".to_string(),
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:{}@thsr", self.pnr),
+            format!("DTSTAMP:{}", Utc::now().format(fmt)),
+            format!("DTSTART:{}", depart_at.format(fmt)),
+            format!("DTEND:{}", arrive_at.format(fmt)),
+            format!(
+                "SUMMARY:THSR {} → {}",
+                ics_escape(&self.from),
+                ics_escape(&self.to)
+            ),
+            format!("LOCATION:{}", ics_escape(&self.from)),
+            format!(
+                "DESCRIPTION:PNR: {}\\nClass: {}{}\\nSeats: {}\\nPrice: {}",
+                ics_escape(&self.pnr),
+                ics_escape(&self.class),
+                ics_escape(&self.passenger_count),
+                ics_escape(&self.seat_list()),
+                ics_escape(&self.price),
+            ),
+            "END:VEVENT".to_string(),
+            "END:VCALENDAR".to_string(),
+        ];
+        Some(lines.join("\r\n") + "\r\n")
+    }
+}
+
+fn ics_escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Extract a date from text like `2025/06/01 (日)`. When the year is
+/// missing (`06/01`), the date is the next one from today, see [`parse_page_date_on`].
+pub fn parse_page_date(text: &str) -> Option<NaiveDate> {
+    parse_page_date_on(text, taipei_today())
+}
+
+/// Extract a date from text like [`parse_page_date`], read on `today`: a date
+/// without a year is the first one on or after `today`, so a January trip
+/// booked in December falls in the next year.
+pub fn parse_page_date_on(text: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let nums: Vec<u32> = text
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|part| !part.is_empty())
+        .filter_map(|part| part.parse().ok())
+        .collect();
+
+    match nums.as_slice() {
+        [year, month, day, ..] if *year >= 1000 => {
+            NaiveDate::from_ymd_opt(*year as i32, *month, *day)
+        }
+        [month, day, ..] => {
+            let year = chrono::Datelike::year(&today);
+            match NaiveDate::from_ymd_opt(year, *month, *day) {
+                Some(date) if date >= today => Some(date),
+                _ => NaiveDate::from_ymd_opt(year + 1, *month, *day),
+            }
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,527 @@
+use chrono::{NaiveTime, Timelike};
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use strum::{EnumCount, IntoEnumIterator};
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::i18n::tr;
+
+/// A station of the line, numbered from north to south like on the booking form.
+#[derive(
+    strum::Display,
+    strum::EnumCount,
+    strum::EnumIter,
+    strum::FromRepr,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Ord,
+)]
+#[repr(u8)]
+pub enum Station {
+    Nangang = 1,
+    Taipei,
+    Banqiao,
+    Taoyuan,
+    Hsinchu,
+    Miaoli,
+    Taichung,
+    Changhua,
+    Yunlin,
+    Chiayi,
+    Tainan,
+    Zuoying,
+}
+
+impl Station {
+    /// The value of the station on the booking form, also its ID on the command line.
+    pub fn id(self) -> u8 {
+        self as u8
+    }
+
+    /// The position of the station in [`STATIONS`], counting from 0.
+    pub fn index(self) -> usize {
+        usize::from(self.id()) - 1
+    }
+
+    /// The station at `idx` in [`STATIONS`], counting from 0.
+    pub fn from_index(idx: usize) -> Option<Station> {
+        Station::iter().nth(idx)
+    }
+
+    pub fn info(self) -> &'static StationInfo {
+        &STATIONS[self.index()]
+    }
+}
+
+impl FromStr for Station {
+    type Err = String;
+
+    /// The station of the ID, code, English or Chinese name `name`, in any case.
+    fn from_str(name: &str) -> Result<Station, String> {
+        let name = name.trim().replace('臺', "台");
+        if let Ok(id) = name.parse() {
+            return Station::from_repr(id).ok_or_else(|| {
+                format!(
+                    "There is no station {}, the stations are numbered 1 to {}, see --list-station",
+                    id,
+                    Station::COUNT
+                )
+            });
+        }
+        (STATIONS.iter())
+            .find(|station| {
+                station.code.eq_ignore_ascii_case(&name)
+                    || station.name.eq_ignore_ascii_case(&name)
+                    || station.name_zh == name
+            })
+            .map(|info| info.station)
+            .ok_or_else(|| format!("There is no station {}, see --list-station", name))
+    }
+}
+
+/// Written as its ID, the way the booking form takes it.
+impl Serialize for Station {
+    fn serialize<S: Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+        ser.serialize_u8(self.id())
+    }
+}
+
+/// Read from its ID, code or name.
+impl<'de> Deserialize<'de> for Station {
+    fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Station, D::Error> {
+        struct StationVisitor;
+
+        impl Visitor<'_> for StationVisitor {
+            type Value = Station;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "a station ID, code or name")
+            }
+
+            fn visit_u64<E: de::Error>(self, id: u64) -> Result<Station, E> {
+                (u8::try_from(id).ok())
+                    .and_then(Station::from_repr)
+                    .ok_or_else(|| E::custom(format!("There is no station {}", id)))
+            }
+
+            fn visit_i64<E: de::Error>(self, id: i64) -> Result<Station, E> {
+                match u64::try_from(id) {
+                    Ok(id) => self.visit_u64(id),
+                    Err(_) => Err(E::custom(format!("There is no station {}", id))),
+                }
+            }
+
+            fn visit_str<E: de::Error>(self, name: &str) -> Result<Station, E> {
+                name.parse().map_err(E::custom)
+            }
+        }
+
+        de.deserialize_any(StationVisitor)
+    }
+}
+
+/// A station of the line.
+#[derive(Serialize, Debug)]
+pub struct StationInfo {
+    #[serde(rename = "id")]
+    pub station: Station,
+    /// The THSR station code, e.g. `TPE`
+    pub code: &'static str,
+    pub name: &'static str,
+    /// The Chinese name, as shown on the booking site
+    pub name_zh: &'static str,
+    pub address: &'static str,
+    /// Latitude and longitude of the entrance
+    pub location: (f64, f64),
+    /// Railway and metro lines to change to at the station, or close by
+    pub transfers: &'static [&'static str],
+    /// First and last departure of a regular day, either way. The timetable of the
+    /// date may differ
+    pub first_train: &'static str,
+    pub last_train: &'static str,
+}
+
+/// The stations from north to south, the order of their IDs.
+pub static STATIONS: [StationInfo; Station::COUNT] = [
+    StationInfo {
+        station: Station::Nangang,
+        code: "NAG",
+        name: "Nangang",
+        name_zh: "南港",
+        address: "No. 313, Sec. 1, Nangang Rd., Nangang Dist., Taipei City",
+        location: (25.0531, 121.6071),
+        transfers: &["TRA", "Taipei Metro Bannan line", "Taipei Metro Wenhu line"],
+        first_train: "06:00",
+        last_train: "23:35",
+    },
+    StationInfo {
+        station: Station::Taipei,
+        code: "TPE",
+        name: "Taipei",
+        name_zh: "台北",
+        address: "No. 3, Beiping W. Rd., Zhongzheng Dist., Taipei City",
+        location: (25.0478, 121.5170),
+        transfers: &[
+            "TRA",
+            "Taipei Metro Tamsui-Xinyi line",
+            "Taipei Metro Bannan line",
+            "Taoyuan Airport MRT",
+        ],
+        first_train: "06:10",
+        last_train: "23:45",
+    },
+    StationInfo {
+        station: Station::Banqiao,
+        code: "BAQ",
+        name: "Banqiao",
+        name_zh: "板橋",
+        address: "No. 7, Xianmin Blvd., Banqiao Dist., New Taipei City",
+        location: (25.0142, 121.4638),
+        transfers: &["TRA", "Taipei Metro Bannan line", "Circular line"],
+        first_train: "06:20",
+        last_train: "23:55",
+    },
+    StationInfo {
+        station: Station::Taoyuan,
+        code: "TAY",
+        name: "Taoyuan",
+        name_zh: "桃園",
+        address: "No. 6, Sec. 1, Gaotie N. Rd., Zhongli Dist., Taoyuan City",
+        location: (25.0128, 121.2150),
+        transfers: &["Taoyuan Airport MRT"],
+        first_train: "06:15",
+        last_train: "23:50",
+    },
+    StationInfo {
+        station: Station::Hsinchu,
+        code: "HSC",
+        name: "Hsinchu",
+        name_zh: "新竹",
+        address: "No. 6, Xinggao 1st Rd., Zhubei City, Hsinchu County",
+        location: (24.8080, 121.0402),
+        transfers: &["TRA Liujia line"],
+        first_train: "06:05",
+        last_train: "23:40",
+    },
+    StationInfo {
+        station: Station::Miaoli,
+        code: "MIL",
+        name: "Miaoli",
+        name_zh: "苗栗",
+        address: "No. 268, Gaotie 3rd Rd., Houlong Township, Miaoli County",
+        location: (24.6054, 120.8253),
+        transfers: &["TRA (Fengfu)"],
+        first_train: "06:20",
+        last_train: "23:25",
+    },
+    StationInfo {
+        station: Station::Taichung,
+        code: "TAC",
+        name: "Taichung",
+        name_zh: "台中",
+        address: "No. 8, Zhanqu 2nd Rd., Wuri Dist., Taichung City",
+        location: (24.1120, 120.6157),
+        transfers: &["TRA (Xinwuri)", "Taichung MRT Green line"],
+        first_train: "06:00",
+        last_train: "23:50",
+    },
+    StationInfo {
+        station: Station::Changhua,
+        code: "CHH",
+        name: "Changhua",
+        name_zh: "彰化",
+        address: "No. 99, Zhanqu Rd., Tianzhong Township, Changhua County",
+        location: (23.8742, 120.5745),
+        transfers: &[],
+        first_train: "06:25",
+        last_train: "23:15",
+    },
+    StationInfo {
+        station: Station::Yunlin,
+        code: "YUL",
+        name: "Yunlin",
+        name_zh: "雲林",
+        address: "No. 301, Gaotie 2nd Rd., Huwei Township, Yunlin County",
+        location: (23.7364, 120.4165),
+        transfers: &[],
+        first_train: "06:15",
+        last_train: "23:25",
+    },
+    StationInfo {
+        station: Station::Chiayi,
+        code: "CHY",
+        name: "Chiayi",
+        name_zh: "嘉義",
+        address: "No. 168, Gaotie W. Rd., Taibao City, Chiayi County",
+        location: (23.4596, 120.3239),
+        transfers: &[],
+        first_train: "06:05",
+        last_train: "23:40",
+    },
+    StationInfo {
+        station: Station::Tainan,
+        code: "TNN",
+        name: "Tainan",
+        name_zh: "台南",
+        address: "No. 100, Guiren Blvd., Guiren Dist., Tainan City",
+        location: (22.9250, 120.2857),
+        transfers: &["TRA Shalun line"],
+        first_train: "06:00",
+        last_train: "23:45",
+    },
+    StationInfo {
+        station: Station::Zuoying,
+        code: "ZUY",
+        name: "Zuoying",
+        name_zh: "左營",
+        address: "No. 105, Gaotie Rd., Zuoying Dist., Kaohsiung City",
+        location: (22.6871, 120.3079),
+        transfers: &["TRA (Xinzuoying)", "Kaohsiung MRT Red line"],
+        first_train: "05:55",
+        last_train: "22:45",
+    },
+];
+
+impl fmt::Display for StationInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.name, self.name_zh)
+    }
+}
+
+/// A time the booking form searches for trains from, shown on the 24-hour clock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TimeSlot(NaiveTime);
+
+/// The times of the booking form in order: just past midnight, half past midnight,
+/// then every half hour from 06:00 to 23:30. Their IDs count from 1.
+pub static TIME_SLOTS: [TimeSlot; 38] = {
+    let mut slots = [TimeSlot(NaiveTime::MIN); 38];
+    slots[0] = TimeSlot::at(0, 1);
+    slots[1] = TimeSlot::at(0, 30);
+    let mut idx = 2;
+    while idx < slots.len() {
+        let minutes = 6 * 60 + 30 * (idx as u32 - 2);
+        slots[idx] = TimeSlot::at(minutes / 60, minutes % 60);
+        idx += 1;
+    }
+    slots
+};
+
+impl TimeSlot {
+    const fn at(hour: u32, minute: u32) -> TimeSlot {
+        TimeSlot(NaiveTime::from_hms_opt(hour, minute, 0).unwrap())
+    }
+
+    /// The time of the ID `id`, counting from 1.
+    pub fn from_id(id: usize) -> Option<TimeSlot> {
+        TIME_SLOTS.get(id.checked_sub(1)?).copied()
+    }
+
+    /// The ID of the time on the command line, counting from 1.
+    pub fn id(self) -> usize {
+        self.index() + 1
+    }
+
+    /// The position of the time in [`TIME_SLOTS`], counting from 0.
+    pub fn index(self) -> usize {
+        TIME_SLOTS.partition_point(|slot| *slot < self)
+    }
+
+    /// The times up to `radius` slots away, the nearest first, and the earlier first
+    /// of two as near.
+    pub fn nearby(self, radius: usize) -> Vec<TimeSlot> {
+        let index = self.index();
+        (1..=radius)
+            .flat_map(|distance| [index.checked_sub(distance), Some(index + distance)])
+            .filter_map(|index| TIME_SLOTS.get(index?).copied())
+            .collect()
+    }
+
+    /// The time `time` if the booking form has it.
+    pub fn from_time(time: NaiveTime) -> Option<TimeSlot> {
+        TIME_SLOTS.iter().copied().find(|slot| slot.0 == time)
+    }
+
+    pub fn time(self) -> NaiveTime {
+        self.0
+    }
+
+    /// The time written the way the booking form takes it, e.g. `1230P` for 12:30,
+    /// `1200N` for noon and `1201A` for 00:01.
+    pub fn code(self) -> String {
+        let (hour, minute) = (self.0.hour(), self.0.minute());
+        let clock_hour = match hour % 12 {
+            0 => 12,
+            hour => hour,
+        };
+        let suffix = match (hour, minute) {
+            (12, 0) => 'N',
+            (0..12, _) => 'A',
+            _ => 'P',
+        };
+        format!("{}{:02}{}", clock_hour, minute, suffix)
+    }
+
+    /// The times still worth searching from at `now` on the day of travel: the one
+    /// `now` falls in and those after it.
+    pub fn upcoming(now: NaiveTime) -> &'static [TimeSlot] {
+        let passed = TIME_SLOTS.partition_point(|slot| slot.0 <= now);
+        &TIME_SLOTS[passed.saturating_sub(1)..]
+    }
+
+    /// The time written `code` on the booking form.
+    pub fn from_code(code: &str) -> Option<TimeSlot> {
+        TIME_SLOTS.iter().copied().find(|slot| slot.code() == code)
+    }
+
+    /// Written the way the booking form takes it, for the fields sent to it.
+    pub fn serialize_code<S: Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+        ser.serialize_str(&self.code())
+    }
+}
+
+/// The time asked for when none is given, 09:30.
+impl Default for TimeSlot {
+    fn default() -> TimeSlot {
+        TIME_SLOTS[9]
+    }
+}
+
+impl fmt::Display for TimeSlot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.format("%H:%M"))
+    }
+}
+
+impl FromStr for TimeSlot {
+    type Err = String;
+
+    /// The time of the ID, the `HH:MM` time or the booking form code `time`.
+    fn from_str(time: &str) -> Result<TimeSlot, String> {
+        let time = time.trim();
+        if let Ok(id) = time.parse() {
+            return TimeSlot::from_id(id).ok_or_else(|| {
+                format!(
+                    "There is no time {}, the times are numbered 1 to {}, see --list-time-table",
+                    id,
+                    TIME_SLOTS.len()
+                )
+            });
+        }
+        (NaiveTime::parse_from_str(time, "%H:%M").ok())
+            .and_then(TimeSlot::from_time)
+            .or_else(|| TimeSlot::from_code(&time.to_ascii_uppercase()))
+            .ok_or_else(|| format!("There is no time {}, see --list-time-table", time))
+    }
+}
+
+/// Written `HH:MM`.
+impl Serialize for TimeSlot {
+    fn serialize<S: Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+        ser.collect_str(self)
+    }
+}
+
+/// Read from its ID, its `HH:MM` time or its booking form code.
+impl<'de> Deserialize<'de> for TimeSlot {
+    fn deserialize<D: Deserializer<'de>>(de: D) -> Result<TimeSlot, D::Error> {
+        struct TimeSlotVisitor;
+
+        impl Visitor<'_> for TimeSlotVisitor {
+            type Value = TimeSlot;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "a time ID or HH:MM")
+            }
+
+            fn visit_u64<E: de::Error>(self, id: u64) -> Result<TimeSlot, E> {
+                (usize::try_from(id).ok())
+                    .and_then(TimeSlot::from_id)
+                    .ok_or_else(|| E::custom(format!("There is no time {}", id)))
+            }
+
+            fn visit_i64<E: de::Error>(self, id: i64) -> Result<TimeSlot, E> {
+                match u64::try_from(id) {
+                    Ok(id) => self.visit_u64(id),
+                    Err(_) => Err(E::custom(format!("There is no time {}", id))),
+                }
+            }
+
+            fn visit_str<E: de::Error>(self, time: &str) -> Result<TimeSlot, E> {
+                time.parse().map_err(E::custom)
+            }
+        }
+
+        de.deserialize_any(TimeSlotVisitor)
+    }
+}
+
+/// The time slots from `first` to `last`, both included, written `08:00-12:00`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SlotRange {
+    pub first: TimeSlot,
+    pub last: TimeSlot,
+}
+
+impl SlotRange {
+    pub fn slots(self) -> &'static [TimeSlot] {
+        &TIME_SLOTS[self.first.index()..=self.last.index()]
+    }
+}
+
+impl FromStr for SlotRange {
+    type Err = String;
+
+    /// Two times as taken by [`TimeSlot`], separated by `-`.
+    fn from_str(range: &str) -> Result<SlotRange, String> {
+        let Some((first, last)) = range.split_once('-') else {
+            return Err(format!(
+                "Invalid time range {}, expected e.g. 08:00-12:00",
+                range
+            ));
+        };
+        let (first, last): (TimeSlot, TimeSlot) = (first.parse()?, last.parse()?);
+        if first > last {
+            return Err(format!("The time range {} ends before it starts", range));
+        }
+        Ok(SlotRange { first, last })
+    }
+}
+
+#[repr(u8)]
+#[derive(strum::FromRepr, Debug, Clone, PartialEq)]
+pub enum TicketType {
+    Adult = 70,    // F
+    Child = 72,    // H
+    Disabled = 87, // W
+    Elder = 69,    // E
+    College = 80,  // P
+}
+
+impl TicketType {
+    /// The ticket type of a code of the booking site, e.g. `E` for elders.
+    pub fn from_code(code: &str) -> Option<TicketType> {
+        match code.as_bytes() {
+            [code] => TicketType::from_repr(*code),
+            _ => None,
+        }
+    }
+
+    /// The name of the ticket type in the language in use.
+    pub fn name(&self) -> String {
+        match self {
+            TicketType::Adult => tr!("adults", "全票"),
+            TicketType::Child => tr!("children", "孩童票"),
+            TicketType::Disabled => tr!("disabled", "愛心票"),
+            TicketType::Elder => tr!("elders", "敬老票"),
+            TicketType::College => tr!("college students", "大學生優惠票"),
+        }
+    }
+}
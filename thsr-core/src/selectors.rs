@@ -0,0 +1,67 @@
+//! The CSS selectors of the booking site's pages, compiled once. [`check`] compiles
+//! them all at startup, so a malformed one fails right away instead of mid-booking.
+
+use scraper::Selector;
+
+use std::sync::LazyLock;
+
+macro_rules! selectors {
+    ($($(#[$doc:meta])* $name:ident = $css:expr;)*) => {
+        $(
+            $(#[$doc])*
+            pub static $name: LazyLock<Selector> = LazyLock::new(|| Selector::parse($css).unwrap());
+        )*
+
+        /// Compile all the selectors, panicking on the first malformed one.
+        pub fn check() {
+            $(LazyLock::force(&$name);)*
+        }
+    };
+}
+
+selectors! {
+    /// Any element marked as selected, e.g. the option of a `<select>`
+    SELECTED = "[selected='selected']";
+    /// The error messages of any page
+    FEEDBACK_ERROR = "span.feedbackPanelERROR";
+    /// Any form, which all pages have but the maintenance notice
+    FORM = "form";
+
+    // Booking page
+    BOOKING_FORM = "#BookingS1Form";
+    DATE_INPUT = "#toTimeInputField";
+    TRIP_TYPE = "#BookingS1Form_tripCon_typesoftrip";
+    BOOKING_METHOD = "input[name='bookingMethod']";
+    SECURITY_CODE_IMG = "#BookingS1Form_homeCaptcha_passCode";
+    SECURITY_CODE_RELOAD = "#BookingS1Form_homeCaptcha_reCodeLink";
+
+    // Train list
+    ALERTS = "ul.alert-body > li";
+    TRAIN_ITEM = "label.result-item";
+    TRAIN_INPUT = "input";
+    EARLY_BIRD = "p.early-bird span";
+    STUDENT = "p.student span";
+    SEATS_STANDING = "p.seat-standing";
+    SEATS_LIMITED = "p.seat-limited";
+
+    // Ticket form
+    TOTAL_PRICE = "#TotalPrice";
+    MEMBER_RADIO = "#memberSystemRadio1";
+    NON_MEMBER_RADIO = "#memberSystemRadio3";
+    PASSENGER_TYPES = "input[name^='TicketPassengerInfoInputPanel:passengerDataView:'][name$=':passengerDataTypeName']";
+
+    // Result page
+    PNR = "p.pnr-code span";
+    RESULT_PRICE = "#setTrainTotalPriceValue";
+    PAYMENT_DEADLINE = "span.status-unpaid span:nth-child(3)";
+    RESULT_DATE = "span.date span";
+    DEPART_TIME = "#setTrainDeparture0";
+    ARRIVE_TIME = "#setTrainArrival0";
+    DEPART_STATION = "p.departure-stn span";
+    ARRIVE_STATION = "p.arrival-stn span";
+    CLASS = "p.info-data span";
+    /// The ticket types and their counts, also the first line of the seats
+    TICKET_GROUPS = "div.uk-accordion-content span";
+    TICKETS_AND_SEATS = "div.uk-accordion-content, div.seat-label";
+    SPAN = "span";
+}
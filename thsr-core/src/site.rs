@@ -0,0 +1,82 @@
+use serde::Deserialize;
+
+/// Where the booking system lives. Every URL the flows use is derived from the base URL,
+/// so they can be pointed at a mock server, or follow the site if it moves.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Site {
+    pub base_url: String,
+    pub locale: SiteLocale,
+}
+
+/// The language of the booking site. Both serve the same forms, with the alerts,
+/// error messages and station names in their own language.
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SiteLocale {
+    /// Traditional Chinese
+    #[default]
+    Tw,
+    /// English
+    En,
+}
+
+impl SiteLocale {
+    /// The `locale` parameter of the booking page.
+    pub fn param(self) -> &'static str {
+        match self {
+            SiteLocale::Tw => "tw",
+            SiteLocale::En => "en",
+        }
+    }
+
+    /// The `Accept-Language` header a browser set to the locale would send.
+    pub fn accept_language(self) -> &'static str {
+        match self {
+            SiteLocale::Tw => "zh-TW,zh;q=0.8,en-US;q=0.5,en;q=0.3",
+            SiteLocale::En => "en-US,en;q=0.8,zh-TW;q=0.5,zh;q=0.3",
+        }
+    }
+}
+
+impl Default for Site {
+    fn default() -> Self {
+        Site::new("https://irs.thsrc.com.tw")
+    }
+}
+
+impl Site {
+    pub fn new(base_url: impl Into<String>) -> Site {
+        let base_url: String = base_url.into();
+        Site {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            locale: SiteLocale::default(),
+        }
+    }
+
+    /// `path` on the site, e.g. `/IMINT/`.
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url.trim_end_matches('/'), path)
+    }
+
+    pub fn booking_page(&self) -> String {
+        self.url(&format!("/IMINT/?locale={}", self.locale.param()))
+    }
+
+    /// The search form of the first page, within session `jid`.
+    pub fn submit_form(&self, jid: &str) -> String {
+        self.url(&format!(
+            "/IMINT/;jsessionid={}?wicket:interface=:0:BookingS1Form::IFormSubmitListener",
+            jid
+        ))
+    }
+
+    pub fn confirm_train(&self) -> String {
+        self.url("/IMINT/?wicket:interface=:1:BookingS2Form::IFormSubmitListener")
+    }
+
+    pub fn confirm_ticket(&self) -> String {
+        self.url("/IMINT/?wicket:interface=:2:BookingS3Form::IFormSubmitListener")
+    }
+}
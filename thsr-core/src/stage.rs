@@ -0,0 +1,203 @@
+//! The booking as a state machine: each page of the booking site is a [`Stage`],
+//! and the only way to the next page is the transition of the current one, which
+//! consumes it. Steps can't be taken out of order, or twice on the same page.
+//!
+//! ```text
+//! Stage<S1> --submit--> Stage<S2> --confirm_train--> Stage<S3> --confirm_ticket--> Stage<Booked>
+//! ```
+//!
+//! The requests of each step are made by the [`BookingBackend`] the booking was opened with.
+
+use scraper::Html;
+use tracing::info_span;
+
+use std::time::Instant;
+
+use crate::backend::BookingBackend;
+use crate::booking::BookingOptions;
+use crate::booking_flow::{BookingPage, BookingPayload};
+use crate::confirm_train_flow::Train;
+use crate::error::Error;
+use crate::http::Transport;
+use crate::prompt::{Notice, Prompter};
+use crate::result::BookingResult;
+use crate::site::Site;
+
+/// The search form, in a new session.
+pub struct S1(BookingPage);
+
+/// The list of trains found by the search.
+pub struct S2(Html);
+
+/// The ticket form for the passengers of the picked train.
+pub struct S3(Html);
+
+/// The page with the booking result.
+pub struct Booked(Html);
+
+/// A page of the booking, `S` telling which one.
+pub struct Stage<S> {
+    backend: &'static dyn BookingBackend,
+    state: S,
+}
+
+impl Stage<S1> {
+    /// Open a new session on the booking page.
+    pub fn open(
+        backend: &'static dyn BookingBackend,
+        client: &dyn Transport,
+        site: &Site,
+        prompter: &dyn Prompter,
+    ) -> Result<Stage<S1>, Error> {
+        Ok(Stage {
+            backend,
+            state: S1(backend.open(client, site, prompter)?),
+        })
+    }
+
+    pub fn page(&self) -> &BookingPage {
+        &self.state.0
+    }
+
+    /// Enter the security code and search with the selections of `payload`,
+    /// opening new sessions while the code is rejected or the session expires.
+    /// `before_submit` runs between entering the code and submitting.
+    pub fn submit(
+        self,
+        client: &dyn Transport,
+        payload: &mut BookingPayload,
+        site: &Site,
+        prompter: &dyn Prompter,
+        mut before_submit: impl FnMut(),
+    ) -> Result<Stage<S2>, Error> {
+        let _span = info_span!("search").entered();
+        let backend = self.backend;
+        let document = timed("search", prompter, || {
+            backend.search(
+                client,
+                self.state.0,
+                payload,
+                site,
+                prompter,
+                &mut before_submit,
+            )
+        })?;
+        Ok(Stage {
+            backend,
+            state: S2(document),
+        })
+    }
+}
+
+impl Stage<S2> {
+    /// Pick up from a train list saved earlier.
+    pub fn restore(backend: &'static dyn BookingBackend, document: Html) -> Stage<S2> {
+        Stage {
+            backend,
+            state: S2(document),
+        }
+    }
+
+    pub fn document(&self) -> &Html {
+        &self.state.0
+    }
+
+    pub fn trains(&self) -> Vec<Train> {
+        self.backend.trains(&self.state.0)
+    }
+
+    /// Book `train`, or the one picked at the prompt, which is stored back into
+    /// `train` so a restarted session books the same train.
+    pub fn confirm_train(
+        self,
+        client: &dyn Transport,
+        payload: &BookingPayload,
+        train: &mut Option<u32>,
+        site: &Site,
+        prompter: &dyn Prompter,
+    ) -> Result<Stage<S3>, Error> {
+        let _span = info_span!("train").entered();
+        let backend = self.backend;
+        let document = timed("train", prompter, || {
+            backend.confirm_train(client, self.state.0, payload, train, site, prompter)
+        })?;
+        Ok(Stage {
+            backend,
+            state: S3(document),
+        })
+    }
+}
+
+impl Stage<S3> {
+    /// Pick up from a ticket form saved earlier.
+    pub fn restore(backend: &'static dyn BookingBackend, document: Html) -> Stage<S3> {
+        Stage {
+            backend,
+            state: S3(document),
+        }
+    }
+
+    pub fn document(&self) -> &Html {
+        &self.state.0
+    }
+
+    /// The total price shown on the ticket form, if it shows one.
+    pub fn price(&self) -> Option<String> {
+        self.backend.price(&self.state.0)
+    }
+
+    /// Enter the passengers and confirm the booking.
+    pub fn confirm_ticket(
+        self,
+        client: &dyn Transport,
+        options: &BookingOptions,
+        site: &Site,
+        prompter: &dyn Prompter,
+    ) -> Result<Stage<Booked>, Error> {
+        let _span = info_span!("ticket").entered();
+        let backend = self.backend;
+        let document = timed("ticket", prompter, || {
+            backend.confirm_ticket(client, self.state.0, options, site, prompter)
+        })?;
+        Ok(Stage {
+            backend,
+            state: Booked(document),
+        })
+    }
+}
+
+impl Stage<Booked> {
+    pub fn document(&self) -> &Html {
+        &self.state.0
+    }
+
+    pub fn result(&self) -> BookingResult {
+        self.backend.result(&self.state.0)
+    }
+}
+
+/// A stage after the search, for code handling whichever one was reached.
+pub enum Progress {
+    /// Picking the train from the train list
+    Train(Stage<S2>),
+    /// Entering the passengers on the ticket page
+    Ticket(Stage<S3>),
+    /// Done, the page shows the booking result
+    Booked(Stage<Booked>),
+}
+
+/// Run the booking step `step`, telling `prompter` how long it took and how it went.
+fn timed<T>(
+    step: &str,
+    prompter: &dyn Prompter,
+    run: impl FnOnce() -> Result<T, Error>,
+) -> Result<T, Error> {
+    let started = Instant::now();
+    let outcome = run();
+    prompter.tell(Notice::StepDone {
+        step,
+        took: started.elapsed(),
+        error: outcome.as_ref().err(),
+    });
+    outcome
+}
@@ -0,0 +1,170 @@
+//! What a booking is about to book, once the train is picked and before the
+//! tickets are confirmed.
+
+use std::fmt;
+
+use crate::booking::BookingOptions;
+use crate::booking_flow::BookingPayload;
+use crate::i18n::tr;
+use crate::schema::{Station, TicketType, TimeSlot};
+
+#[derive(Debug, Clone)]
+pub struct Summary {
+    pub train: Option<u32>,
+    pub date: String,
+    pub time: TimeSlot,
+    pub from: Station,
+    pub to: Station,
+    /// The number of tickets of every type booked, leaving out the others
+    pub tickets: Vec<(TicketType, u8)>,
+    pub class_type: u8,
+    pub seat_prefer: usize,
+    /// The personal ID or passport number booking, masked
+    pub booked_by: String,
+    pub membership: bool,
+    /// The names of the passenger profiles booked for
+    pub passengers: Vec<String>,
+    /// The total price shown on the ticket form, if it shows one
+    pub price: Option<String>,
+}
+
+impl Summary {
+    /// The booking of `payload` on `train`, for the passengers of `options`.
+    pub fn new(
+        payload: &BookingPayload,
+        train: Option<u32>,
+        options: &BookingOptions,
+        price: Option<String>,
+    ) -> Summary {
+        let booked_by = options.passport.as_ref().or(options.personal_id.as_ref());
+        Summary {
+            train,
+            date: payload.outbound_date.clone(),
+            time: payload.outbound_time,
+            from: payload.start_station,
+            to: payload.dest_station,
+            tickets: payload.ticket_counts(),
+            class_type: payload.class_type,
+            seat_prefer: payload.seat_prefer,
+            booked_by: booked_by.map(|id| mask(id)).unwrap_or_default(),
+            membership: options.uses_membership(),
+            passengers: options.passengers.clone(),
+            price,
+        }
+    }
+}
+
+impl Summary {
+    /// The booking as the environment of the `on_confirm` command, the whole summary
+    /// as printed in `THSR_SUMMARY`.
+    pub fn hook_env(&self) -> Vec<(&'static str, String)> {
+        let tickets: Vec<String> = (self.tickets.iter())
+            .map(|(ticket_type, count)| format!("{} {}", count, ticket_type.name()))
+            .collect();
+        vec![
+            (
+                "THSR_TRAIN",
+                self.train
+                    .map(|train| train.to_string())
+                    .unwrap_or_default(),
+            ),
+            ("THSR_DATE", self.date.clone()),
+            ("THSR_TIME", self.time.to_string()),
+            ("THSR_FROM", self.from.to_string()),
+            ("THSR_TO", self.to.to_string()),
+            ("THSR_TICKETS", tickets.join(", ")),
+            ("THSR_PASSENGERS", self.passengers.join(",")),
+            ("THSR_BOOKED_BY", self.booked_by.clone()),
+            ("THSR_PRICE", self.price.clone().unwrap_or_default()),
+            ("THSR_SUMMARY", self.to_string()),
+        ]
+    }
+}
+
+/// `id` with all but its first two and last two characters hidden.
+pub fn mask(id: &str) -> String {
+    let chars: Vec<char> = id.chars().collect();
+    if chars.len() <= 4 {
+        return "*".repeat(chars.len());
+    }
+    let hidden = "*".repeat(chars.len() - 4);
+    format!(
+        "{}{}{}",
+        chars[..2].iter().collect::<String>(),
+        hidden,
+        chars[chars.len() - 2..].iter().collect::<String>()
+    )
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(train) = self.train {
+            writeln!(f, "{}", tr!("Train: {}", "車次：{}", train))?;
+        }
+        writeln!(
+            f,
+            "{}",
+            tr!(
+                "Date: {}, trains from {}",
+                "日期：{}，{} 起的車次",
+                self.date,
+                self.time
+            )
+        )?;
+        writeln!(
+            f,
+            "{}",
+            tr!(
+                "Route: {} → {}",
+                "路線：{} → {}",
+                self.from.info(),
+                self.to.info()
+            )
+        )?;
+        let tickets: Vec<String> = (self.tickets.iter())
+            .map(|(ticket_type, count)| format!("{} {}", count, ticket_type.name()))
+            .collect();
+        writeln!(f, "{}", tr!("Tickets: {}", "車票：{}", tickets.join(", ")))?;
+        if !self.passengers.is_empty() {
+            writeln!(
+                f,
+                "{}",
+                tr!("Passengers: {}", "乘客：{}", self.passengers.join(", "))
+            )?;
+        }
+        let class = match self.class_type {
+            1 => tr!("business", "商務車廂"),
+            _ => tr!("standard", "標準車廂"),
+        };
+        let seat = match self.seat_prefer {
+            1 => tr!("window", "靠窗"),
+            2 => tr!("aisle", "靠走道"),
+            _ => tr!("any", "無偏好"),
+        };
+        writeln!(
+            f,
+            "{}",
+            tr!("Class: {}, seat: {}", "車廂：{}，座位：{}", class, seat)
+        )?;
+        match self.membership {
+            true => writeln!(
+                f,
+                "{}",
+                tr!(
+                    "Booked by: {}, with TGo membership",
+                    "訂票人：{}，使用 TGo 會員",
+                    self.booked_by
+                )
+            )?,
+            false => writeln!(f, "{}", tr!("Booked by: {}", "訂票人：{}", self.booked_by))?,
+        }
+        match &self.price {
+            Some(price) => write!(f, "{}", tr!("Price: {}", "票價：{}", price)),
+            None => write!(
+                f,
+                "{}",
+                tr!("Price: shown once booked", "票價：訂位完成後顯示")
+            ),
+        }
+    }
+}
@@ -0,0 +1,21 @@
+//! TGo, THSR's membership programme: the membership number is the personal ID, and
+//! bookings made with it earn points.
+
+/// Fare in NT$ earning one TGo point.
+pub const FARE_PER_POINT: u32 = 20;
+
+use crate::passenger;
+
+/// Whether `number` is a valid membership number, which is a valid Taiwan ID, see
+/// [`passenger::is_valid_national_id`].
+pub fn is_membership_number(number: &str) -> bool {
+    passenger::is_valid_national_id(number)
+}
+
+/// The points a booking of `price`, e.g. `TWD 2,980`, is expected to earn. The site
+/// credits them after the trip, so this is an estimate.
+pub fn points(price: &str) -> Option<u32> {
+    let fare: String = price.chars().filter(char::is_ascii_digit).collect();
+    let fare: u32 = fare.parse().ok()?;
+    Some(fare / FARE_PER_POINT)
+}
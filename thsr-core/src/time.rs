@@ -0,0 +1,32 @@
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate, Utc};
+
+use crate::i18n::tr;
+
+/// Taiwan has no daylight saving time, so a fixed offset is enough.
+pub fn taipei() -> FixedOffset {
+    FixedOffset::east_opt(8 * 3600).unwrap()
+}
+
+pub fn taipei_now() -> DateTime<FixedOffset> {
+    Utc::now().with_timezone(&taipei())
+}
+
+/// The date in Taiwan, which the booking site counts in whatever the local timezone.
+pub fn taipei_today() -> NaiveDate {
+    taipei_now().date_naive()
+}
+
+/// A span of time the way people say it, e.g. `2 days 3 hours`, or the minutes when
+/// less than an hour is left.
+pub fn describe_span(span: Duration) -> String {
+    let (days, hours, minutes) = (
+        span.num_days(),
+        span.num_hours() % 24,
+        span.num_minutes() % 60,
+    );
+    match (days, hours) {
+        (0, 0) => tr!("{} minutes", "{} 分鐘", minutes.max(0)),
+        (0, _) => tr!("{} hours {} minutes", "{} 小時 {} 分鐘", hours, minutes),
+        _ => tr!("{} days {} hours", "{} 天 {} 小時", days, hours),
+    }
+}
@@ -0,0 +1,47 @@
+/* C bindings of thsr-core, see thsr-ffi/src/lib.rs for the details.
+ *
+ * Strings go in and out as UTF-8, results as JSON. NULL is returned for an
+ * argument that isn't UTF-8 or a page that isn't the expected one. Free every
+ * string returned with thsr_string_free. */
+
+#ifndef THSR_H
+#define THSR_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* session_id, start_date, end_date, types_of_trip, search_by,
+ * security_code_url and security_code_reload_url of the booking page. */
+char *thsr_parse_booking_page(const char *html, const char *base_url);
+
+/* The trains of the train list: id, depart, arrive, travel_minutes,
+ * discount_info and seats. */
+char *thsr_parse_trains(const char *html);
+
+/* The urlencoded form body booking train_id of the train list. */
+char *thsr_train_form(const char *html, uint32_t train_id);
+
+/* The urlencoded search form of the booking page, filled in from the JSON
+ * booking options and the solved security code. */
+char *thsr_search_form(const char *html, const char *options, const char *security_code);
+
+/* The urlencoded ticket form of the ticket page, filled in from the JSON
+ * booking options. */
+char *thsr_ticket_form(const char *html, const char *options);
+
+/* The error shown on the page as {"kind", "message", "explanation"}, or null. */
+char *thsr_parse_error(const char *html);
+
+/* 1 when the page tells the session expired, 0 otherwise, -1 for a bad argument. */
+int32_t thsr_is_session_expired(const char *html);
+
+void thsr_string_free(char *string);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,224 @@
+//! C bindings of thsr-core, for apps doing the requests themselves, e.g. on a phone:
+//! they read the pages of the booking site and build the forms posted to it, and the
+//! app downloads and solves the security code. See `include/thsr.h`.
+//!
+//! Everything goes in and out as UTF-8 C strings, the results as JSON. A function
+//! returns null for an argument that isn't UTF-8 or a page that isn't the expected
+//! one. Every string returned is freed with [`thsr_string_free`].
+
+use scraper::Html;
+use serde_json::{Value, json};
+use thsr_core::booking::BookingOptions;
+use thsr_core::booking_flow::BookingPayload;
+use thsr_core::confirm_ticket_flow;
+use thsr_core::error::Error;
+use thsr_core::page;
+use thsr_core::prompt::ScriptedPrompter;
+use thsr_core::site::Site;
+
+use std::ffi::{CStr, CString, c_char};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+/// The string at `arg`, or `None` for null or not UTF-8.
+///
+/// # Safety
+/// `arg` is null or points to a C string.
+unsafe fn text<'a>(arg: *const c_char) -> Option<&'a str> {
+    if arg.is_null() {
+        return None;
+    }
+    unsafe { CStr::from_ptr(arg) }.to_str().ok()
+}
+
+/// The result of `read` as a C string, null when it fails or panics, as the parsers
+/// do on a page missing what they look for.
+fn returned(read: impl FnOnce() -> Option<String>) -> *mut c_char {
+    match panic::catch_unwind(AssertUnwindSafe(read)) {
+        Ok(Some(result)) => CString::new(result).map_or(ptr::null_mut(), CString::into_raw),
+        _ => ptr::null_mut(),
+    }
+}
+
+/// The name of the kind of `err`, e.g. `wrong_security_code`.
+fn kind(err: &Error) -> &'static str {
+    match err {
+        Error::SoldOut(_) => "sold_out",
+        Error::WrongSecurityCode(_) => "wrong_security_code",
+        Error::InvalidId(_) => "invalid_id",
+        Error::TooManyTickets(_) => "too_many_tickets",
+        Error::Maintenance(_) => "maintenance",
+        Error::Throttled(_) => "throttled",
+        Error::Rejected(_) => "rejected",
+        Error::SessionExpired => "session_expired",
+        Error::TrainUnavailable(_) => "train_unavailable",
+        Error::Network(_) => "network",
+        Error::UnexpectedPage(_) => "unexpected_page",
+        Error::DryRun => "dry_run",
+        Error::Cancelled => "cancelled",
+        Error::Script(_) => "script",
+    }
+}
+
+/// Read the booking page `html` of the site at `base_url`: `session_id`, the
+/// `start_date` and `end_date` open for booking, `types_of_trip`, `search_by`,
+/// `security_code_url` and `security_code_reload_url`, which still needs a random
+/// number appended.
+///
+/// # Safety
+/// `html` and `base_url` are null or point to C strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn thsr_parse_booking_page(
+    html: *const c_char,
+    base_url: *const c_char,
+) -> *mut c_char {
+    let (html, base_url) = unsafe { (text(html), text(base_url)) };
+    returned(|| {
+        let page = Html::parse_document(html?);
+        let site = Site::new(base_url?);
+        let (start_date, end_date) = page::parse_avail_start_end_date(&page);
+        let parsed = json!({
+            "session_id": page::parse_session_id(&page),
+            "start_date": start_date,
+            "end_date": end_date,
+            "types_of_trip": page::parse_types_of_trip_value(&page),
+            "search_by": page::parse_search_by(&page),
+            "security_code_url": page::parse_security_code_img_url(&page, &site),
+            "security_code_reload_url": page::parse_security_code_reload_url(&page, &site),
+        });
+        Some(parsed.to_string())
+    })
+}
+
+/// Read the trains of the train list `html`, each with its `id`, `depart` and `arrive`
+/// as `HH:MM`, `travel_minutes`, `discount_info` and `seats`.
+///
+/// # Safety
+/// `html` is null or points to a C string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn thsr_parse_trains(html: *const c_char) -> *mut c_char {
+    let html = unsafe { text(html) };
+    returned(|| {
+        let trains = page::parse_trains(&Html::parse_document(html?));
+        serde_json::to_string(&trains).ok()
+    })
+}
+
+/// The form body booking the train `train_id` of the train list `html`, to post
+/// urlencoded to the train list form. Null when the train isn't listed.
+///
+/// # Safety
+/// `html` is null or points to a C string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn thsr_train_form(html: *const c_char, train_id: u32) -> *mut c_char {
+    let html = unsafe { text(html) };
+    returned(|| {
+        let trains = page::parse_trains(&Html::parse_document(html?));
+        let train = trains.iter().find(|train| train.id() == train_id)?;
+        serde_urlencoded::to_string([
+            ("TrainQueryDataViewPanel:TrainGroup", train.form_value()),
+            ("BookingS2Form:hf:0", ""),
+        ])
+        .ok()
+    })
+}
+
+/// The urlencoded search form of the booking page `html`, to post to its form, filled
+/// in from the JSON booking `options`, e.g. `{"from": "Taipei", "to": "Zuoying",
+/// "date": "2025/06/29", "time": "10:00", "adult_cnt": 1}`, and the `security_code`
+/// solved by the app. Null when the options leave something to ask, e.g. without a
+/// time, or give a date the page doesn't book.
+///
+/// # Safety
+/// `html`, `options` and `security_code` are null or point to C strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn thsr_search_form(
+    html: *const c_char,
+    options: *const c_char,
+    security_code: *const c_char,
+) -> *mut c_char {
+    let (html, options, security_code) =
+        unsafe { (text(html), text(options), text(security_code)) };
+    returned(|| {
+        let page = Html::parse_document(html?);
+        let options: BookingOptions = serde_json::from_str(options?).ok()?;
+        let (start_date, end_date) = page::parse_avail_start_end_date(&page);
+        let mut payload = BookingPayload::default();
+        // Without answers, the first question panics
+        let prompter = ScriptedPrompter::default();
+        payload.select_options(&options, Some((&start_date, &end_date)), &prompter);
+        payload.security_code = security_code?.into();
+        Some(payload.form(
+            &page::parse_search_by(&page),
+            page::parse_types_of_trip_value(&page),
+        ))
+    })
+}
+
+/// The urlencoded ticket form of the ticket page `html`, to post to the ticket form,
+/// filled in from the JSON booking `options`: the `personal_id` or `passport`, `phone`,
+/// `email`, `use_membership`, and the passengers of the rows from `party` and
+/// `passenger_ids`. Null when an ID of a row is missing, or with `review_passengers`.
+///
+/// # Safety
+/// `html` and `options` are null or point to C strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn thsr_ticket_form(
+    html: *const c_char,
+    options: *const c_char,
+) -> *mut c_char {
+    let (html, options) = unsafe { (text(html), text(options)) };
+    returned(|| {
+        let page = Html::parse_document(html?);
+        let options: BookingOptions = serde_json::from_str(options?).ok()?;
+        confirm_ticket_flow::ticket_form(&page, &options, &ScriptedPrompter::default()).ok()
+    })
+}
+
+/// The error shown on any page `html`, or the maintenance notice shown in place of
+/// the pages, as its `kind`, e.g. `wrong_security_code` or `sold_out`, its `message` and the English `explanation` of a known message, or
+/// `null`. `null` in JSON without an error.
+///
+/// # Safety
+/// `html` is null or points to a C string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn thsr_parse_error(html: *const c_char) -> *mut c_char {
+    let html = unsafe { text(html) };
+    returned(|| {
+        let html = Html::parse_document(html?);
+        let parsed = match page::parse_error(&html).or_else(|| page::parse_maintenance(&html)) {
+            Some(err) => json!({
+                "kind": kind(&err),
+                "message": err.to_string(),
+                "explanation": err.explanation(),
+            }),
+            None => Value::Null,
+        };
+        Some(parsed.to_string())
+    })
+}
+
+/// 1 when `html`, answering a search or a train form, tells that the session has
+/// been idle for too long, 0 otherwise, -1 for an argument that isn't a string.
+/// Check [`thsr_parse_error`] first, a rejected form sends the booking page back too.
+///
+/// # Safety
+/// `html` is null or points to a C string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn thsr_is_session_expired(html: *const c_char) -> i32 {
+    match unsafe { text(html) } {
+        Some(html) => page::is_session_expired(&Html::parse_document(html)) as i32,
+        None => -1,
+    }
+}
+
+/// Free a string returned by the functions of this library. Null is ignored.
+///
+/// # Safety
+/// `string` is null or was returned by this library, and is not used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn thsr_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(unsafe { CString::from_raw(string) });
+    }
+}
@@ -0,0 +1,139 @@
+use serde_json::Value;
+use thsr_ffi::{
+    thsr_is_session_expired, thsr_parse_booking_page, thsr_parse_error, thsr_parse_trains,
+    thsr_search_form, thsr_string_free, thsr_ticket_form, thsr_train_form,
+};
+
+use std::collections::HashMap;
+
+use std::ffi::{CStr, CString, c_char};
+use std::ptr;
+
+const BOOKING_PAGE: &str = include_str!("../../tests/fixtures/booking_page.html");
+const TRAIN_LIST: &str = include_str!("../../tests/fixtures/train_list.html");
+const CONFIRM_TICKET: &str = include_str!("../../tests/fixtures/confirm_ticket_early_bird.html");
+const ERROR_SOLD_OUT: &str = include_str!("../../tests/fixtures/error_sold_out.html");
+const SESSION_EXPIRED: &str = include_str!("../../tests/fixtures/session_expired.html");
+
+/// The string returned by a binding, freed.
+fn taken(returned: *mut c_char) -> Option<String> {
+    if returned.is_null() {
+        return None;
+    }
+    let text = unsafe { CStr::from_ptr(returned) }
+        .to_str()
+        .unwrap()
+        .to_string();
+    unsafe { thsr_string_free(returned) };
+    Some(text)
+}
+
+fn json(returned: *mut c_char) -> Value {
+    serde_json::from_str(&taken(returned).unwrap()).unwrap()
+}
+
+/// The fields of the urlencoded form returned by a binding.
+fn form(returned: *mut c_char) -> HashMap<String, String> {
+    serde_urlencoded::from_str(&taken(returned).unwrap()).unwrap()
+}
+
+#[test]
+fn reads_the_pages_through_the_bindings() {
+    let booking_page = CString::new(BOOKING_PAGE).unwrap();
+    let base_url = CString::new("https://irs.thsrc.com.tw").unwrap();
+    let page = json(unsafe { thsr_parse_booking_page(booking_page.as_ptr(), base_url.as_ptr()) });
+    assert!(page["session_id"].is_string());
+    assert!(
+        page["security_code_url"]
+            .as_str()
+            .unwrap()
+            .starts_with("https://irs.thsrc.com.tw/IMINT/")
+    );
+
+    let train_list = CString::new(TRAIN_LIST).unwrap();
+    let trains = json(unsafe { thsr_parse_trains(train_list.as_ptr()) });
+    assert_eq!(trains[0]["id"], 803);
+    assert_eq!(trains[0]["depart"], "06:30");
+    let form = taken(unsafe { thsr_train_form(train_list.as_ptr(), 1505) });
+    assert_eq!(
+        form.as_deref(),
+        Some("TrainQueryDataViewPanel%3ATrainGroup=radio20&BookingS2Form%3Ahf%3A0=")
+    );
+    assert_eq!(
+        taken(unsafe { thsr_train_form(train_list.as_ptr(), 1) }),
+        None
+    );
+
+    let sold_out = CString::new(ERROR_SOLD_OUT).unwrap();
+    let error = json(unsafe { thsr_parse_error(sold_out.as_ptr()) });
+    assert_eq!(error["kind"], "sold_out");
+    assert!(error["explanation"].is_string());
+    assert_eq!(
+        json(unsafe { thsr_parse_error(train_list.as_ptr()) }),
+        Value::Null
+    );
+    let expired = CString::new(SESSION_EXPIRED).unwrap();
+    assert_eq!(unsafe { thsr_is_session_expired(expired.as_ptr()) }, 1);
+    assert_eq!(unsafe { thsr_is_session_expired(train_list.as_ptr()) }, 0);
+
+    // The train list is no booking page, and null is no page at all
+    assert_eq!(
+        taken(unsafe { thsr_parse_booking_page(train_list.as_ptr(), base_url.as_ptr()) }),
+        None
+    );
+    assert_eq!(taken(unsafe { thsr_parse_trains(ptr::null()) }), None);
+}
+
+#[test]
+fn builds_the_search_form_from_the_options() {
+    let booking_page = CString::new(BOOKING_PAGE).unwrap();
+    let options = CString::new(
+        r#"{"from": "Taipei", "to": "Zuoying", "date": "2025/06/20", "time": "10:00",
+            "adult_cnt": 2, "seat_prefer": 1, "class_type": 0}"#,
+    )
+    .unwrap();
+    let code = CString::new("ABCD").unwrap();
+    let search =
+        form(unsafe { thsr_search_form(booking_page.as_ptr(), options.as_ptr(), code.as_ptr()) });
+    assert_eq!(search["selectStartStation"], "2");
+    assert_eq!(search["selectDestinationStation"], "12");
+    assert_eq!(search["toTimeInputField"], "2025/06/20");
+    assert_eq!(search["ticketPanel:rows:0:ticketAmount"], "2F");
+    assert_eq!(search["seatCon:seatRadioGroup"], "1");
+    assert_eq!(search["homeCaptcha:securityCode"], "ABCD");
+
+    // Without a time, the time would have to be asked
+    let asking = CString::new(r#"{"from": "Taipei", "to": "Zuoying", "adult_cnt": 1}"#).unwrap();
+    assert_eq!(
+        taken(unsafe { thsr_search_form(booking_page.as_ptr(), asking.as_ptr(), code.as_ptr()) }),
+        None
+    );
+}
+
+#[test]
+fn builds_the_ticket_form_from_the_options() {
+    let ticket_page = CString::new(CONFIRM_TICKET).unwrap();
+    let options = CString::new(
+        r#"{"personal_id": "A123456789", "phone": "0912345678", "use_membership": false,
+            "passenger_ids": ["A123456789", "B223456789"]}"#,
+    )
+    .unwrap();
+    let tickets = form(unsafe { thsr_ticket_form(ticket_page.as_ptr(), options.as_ptr()) });
+    assert_eq!(tickets["dummyId"], "A123456789");
+    assert_eq!(tickets["dummyPhone"], "0912345678");
+    let id = |row| {
+        &tickets[&format!(
+            "TicketPassengerInfoInputPanel:passengerDataView:{}:passengerDataView2:passengerDataIdNumber",
+            row
+        )]
+    };
+    assert_eq!(id(0), "A123456789");
+    assert_eq!(id(1), "B223456789");
+
+    // The IDs of the rows would have to be asked
+    let asking = CString::new(r#"{"personal_id": "A123456789"}"#).unwrap();
+    assert_eq!(
+        taken(unsafe { thsr_ticket_form(ticket_page.as_ptr(), asking.as_ptr()) }),
+        None
+    );
+}